@@ -0,0 +1,121 @@
+//! Which recipes reference a recipe
+//!
+//! References are ingredients like `@./Pizza Dough{}`. Finding the recipes
+//! that use one needs to parse the whole collection, so [`Backlinks`] is built
+//! once and then queried.
+
+use std::collections::HashMap;
+
+use camino::{Utf8Path, Utf8PathBuf};
+use cooklang::{CooklangParser, Modifiers, ScalableRecipe};
+
+use crate::{Error, FsIndex, RecipeEntry};
+
+/// Reverse map of the recipe references of a collection
+#[derive(Debug, Default, Clone)]
+pub struct Backlinks {
+    /// Referenced recipe to the recipes that reference it, all paths in disk
+    referenced_by: HashMap<Utf8PathBuf, Vec<Utf8PathBuf>>,
+}
+
+impl Backlinks {
+    /// Parses every recipe in the index
+    ///
+    /// Recipes that can't be read or parsed are skipped with a warning, and
+    /// references that don't resolve are ignored.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn build(index: &FsIndex, parser: &CooklangParser) -> Self {
+        let mut backlinks = Self::default();
+        for entry in index.iter() {
+            backlinks.add(index, parser, &entry);
+        }
+        backlinks
+    }
+
+    /// Recipes that reference a recipe, sorted by path
+    ///
+    /// The recipe is a query like in [`FsIndex::get`].
+    pub fn referenced_by(&self, index: &FsIndex, recipe: &str) -> Result<Vec<RecipeEntry>, Error> {
+        let target = index.get(recipe)?;
+        Ok(self.referenced_by_path(target.path()))
+    }
+
+    /// Like [`Self::referenced_by`] with the path in disk of the recipe
+    pub fn referenced_by_path(&self, path: &Utf8Path) -> Vec<RecipeEntry> {
+        self.referenced_by
+            .get(path)
+            .into_iter()
+            .flatten()
+            .map(RecipeEntry::new)
+            .collect()
+    }
+
+    /// Parses a recipe again after it changed, or removes it if it's no longer
+    /// in the index
+    pub fn update(&mut self, index: &FsIndex, parser: &CooklangParser, path: &Utf8Path) {
+        self.remove(path);
+        if index.cache.contains_path(path) {
+            self.add(index, parser, &RecipeEntry::new(path));
+        }
+    }
+
+    /// Forgets the references from a recipe
+    pub fn remove(&mut self, path: &Utf8Path) {
+        self.referenced_by.retain(|_, sources| {
+            sources.retain(|s| s != path);
+            !sources.is_empty()
+        });
+    }
+
+    fn add(&mut self, index: &FsIndex, parser: &CooklangParser, entry: &RecipeEntry) {
+        let parsed = match entry.read() {
+            Ok(content) => content.parse(parser),
+            Err(e) => {
+                tracing::warn!("Can't read '{}': {e}", entry.path());
+                return;
+            }
+        };
+        let Some(recipe) = parsed.output() else {
+            tracing::warn!("Can't parse '{}'", entry.path());
+            return;
+        };
+        for name in recipe_refs(recipe) {
+            let Ok(target) = index.resolve_relative(entry.path(), name) else {
+                continue;
+            };
+            let sources = self
+                .referenced_by
+                .entry(target.path().to_path_buf())
+                .or_default();
+            if let Err(i) = sources.binary_search_by(|s| s.as_path().cmp(entry.path())) {
+                sources.insert(i, entry.path().to_path_buf());
+            }
+        }
+    }
+}
+
+/// Names of the recipes referenced in a recipe, as written
+fn recipe_refs(recipe: &ScalableRecipe) -> impl Iterator<Item = &str> {
+    recipe
+        .ingredients
+        .iter()
+        .filter(|igr| igr.modifiers().contains(Modifiers::RECIPE))
+        .map(|igr| igr.name.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refs() {
+        let parser = CooklangParser::new(cooklang::Extensions::all(), cooklang::Converter::empty());
+        let recipe = parser
+            .parse("Spread @./Bases/Pizza Dough{} with @tomato{}.")
+            .into_output()
+            .unwrap();
+        let refs = recipe_refs(&recipe).collect::<Vec<_>>();
+        assert_eq!(refs.len(), 1);
+        assert!(refs[0].ends_with("Pizza Dough"));
+    }
+}
@@ -0,0 +1,203 @@
+use std::cmp::Ordering;
+
+use camino::Utf8Path;
+use serde::{Deserialize, Serialize};
+
+use crate::names::strip_accents;
+
+/// How recipe names and paths are sorted
+///
+/// Paths are compared component by component, so the recipes in a dir stay
+/// together. Every collation breaks ties with the binary order, so the result
+/// never depends on the order of the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Collation {
+    /// Order of the bytes, `Zucchini` before `apple`
+    #[default]
+    Binary,
+    /// Ignore case and compare numbers by value, `Recipe 2` before `Recipe 10`
+    Natural,
+    /// Like [`Self::Natural`], but also ignore accents, so `Crêpes` sorts
+    /// with `Crepes` and not after `Zucchini`
+    Unicode,
+}
+
+impl Collation {
+    /// Compares two names
+    pub fn cmp(self, a: &str, b: &str) -> Ordering {
+        match self {
+            Collation::Binary => a.cmp(b),
+            Collation::Natural => natural_cmp(a, b).then_with(|| a.cmp(b)),
+            Collation::Unicode => natural_cmp(&strip_accents(a), &strip_accents(b))
+                .then_with(|| natural_cmp(a, b))
+                .then_with(|| a.cmp(b)),
+        }
+    }
+
+    /// Compares two paths
+    pub fn cmp_paths(self, a: &Utf8Path, b: &Utf8Path) -> Ordering {
+        let mut a = a.components();
+        let mut b = b.components();
+        loop {
+            match (a.next(), b.next()) {
+                (Some(x), Some(y)) => match self.cmp(x.as_str(), y.as_str()) {
+                    Ordering::Equal => continue,
+                    ord => return ord,
+                },
+                (None, Some(_)) => return Ordering::Less,
+                (Some(_), None) => return Ordering::Greater,
+                (None, None) => return Ordering::Equal,
+            }
+        }
+    }
+
+    /// Sorts items by the path returned by `key`
+    pub fn sort_by_path<T>(self, items: &mut [T], key: impl Fn(&T) -> &Utf8Path) {
+        items.sort_by(|a, b| self.cmp_paths(key(a), key(b)));
+    }
+
+    /// Sorts items by the name returned by `key`
+    pub fn sort_by_name<T>(self, items: &mut [T], key: impl Fn(&T) -> &str) {
+        items.sort_by(|a, b| self.cmp(key(a), key(b)));
+    }
+}
+
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = Chunks(a);
+    let mut b = Chunks(b);
+    loop {
+        let ord = match (a.next(), b.next()) {
+            (Some(Chunk::Number(x)), Some(Chunk::Number(y))) => {
+                let x = x.trim_start_matches('0');
+                let y = y.trim_start_matches('0');
+                // same number of digits compare like text
+                x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+            }
+            (Some(Chunk::Text(x)), Some(Chunk::Text(y))) => x
+                .chars()
+                .flat_map(char::to_lowercase)
+                .cmp(y.chars().flat_map(char::to_lowercase)),
+            // numbers first, like digits before letters in ASCII
+            (Some(Chunk::Number(_)), Some(Chunk::Text(_))) => Ordering::Less,
+            (Some(Chunk::Text(_)), Some(Chunk::Number(_))) => Ordering::Greater,
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (None, None) => return Ordering::Equal,
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+}
+
+enum Chunk<'a> {
+    Number(&'a str),
+    Text(&'a str),
+}
+
+/// Splits a text in runs of digits and runs of other chars
+struct Chunks<'a>(&'a str);
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = Chunk<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = self.0.chars().next()?;
+        let is_digit = first.is_ascii_digit();
+        let end = self
+            .0
+            .find(|c: char| c.is_ascii_digit() != is_digit)
+            .unwrap_or(self.0.len());
+        let (chunk, rest) = self.0.split_at(end);
+        self.0 = rest;
+        Some(if is_digit {
+            Chunk::Number(chunk)
+        } else {
+            Chunk::Text(chunk)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(collation: Collation, names: &[&'static str]) -> Vec<&'static str> {
+        let mut names = names.to_vec();
+        collation.sort_by_name(&mut names, |n| *n);
+        names
+    }
+
+    #[test]
+    fn natural() {
+        let names = [
+            "Recipe 10",
+            "recipe 3",
+            "Recipe 2",
+            "Apple pie",
+            "Recipe 02",
+        ];
+        assert_eq!(
+            sorted(Collation::Natural, &names),
+            [
+                "Apple pie",
+                "Recipe 02",
+                "Recipe 2",
+                "recipe 3",
+                "Recipe 10"
+            ]
+        );
+        assert_eq!(
+            sorted(Collation::Binary, &names),
+            [
+                "Apple pie",
+                "Recipe 02",
+                "Recipe 10",
+                "Recipe 2",
+                "recipe 3"
+            ]
+        );
+    }
+
+    #[test]
+    fn unicode() {
+        let names = [
+            "Zucchini",
+            "Crêpes",
+            "crepes 10",
+            "Crepes",
+            "crepes 9",
+            "Éclair",
+        ];
+        assert_eq!(
+            sorted(Collation::Unicode, &names),
+            [
+                "Crepes",
+                "Crêpes",
+                "crepes 9",
+                "crepes 10",
+                "Éclair",
+                "Zucchini"
+            ]
+        );
+        assert_eq!(
+            sorted(Collation::Natural, &names),
+            [
+                "Crepes",
+                "crepes 9",
+                "crepes 10",
+                "Crêpes",
+                "Zucchini",
+                "Éclair"
+            ]
+        );
+    }
+
+    #[test]
+    fn paths() {
+        let mut paths = vec!["b.cook", "a/z.cook", "a.cook", "a/b/c.cook"];
+        Collation::Binary.sort_by_path(&mut paths, |p| Utf8Path::new(p));
+        assert_eq!(paths, ["a/b/c.cook", "a/z.cook", "a.cook", "b.cook"]);
+    }
+}
@@ -0,0 +1,72 @@
+//! Creating new recipe files
+
+use std::fs;
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{
+    check_relative_path, normalize_separators, safe_write, Error, RecipeEntry, WriteOptions,
+    MARKDOWN_EXTENSION,
+};
+
+/// Max number tried in a suffix before giving up
+const MAX_SUFFIX: u32 = 1000;
+
+/// What to do when creating a recipe with the name of an existing one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnCollision {
+    /// Fail with [`Error::AlreadyExists`]
+    #[default]
+    Fail,
+    /// Add a number to the name, like `Pasta (2)`
+    Suffix,
+}
+
+/// Writes a new `.cook` recipe
+///
+/// The name is relative to `base_path`, with `/` or `\` separated dirs that
+/// are created if missing. The `.cook` extension is optional. A recipe with
+/// the same name in the same dir, `.cook` or Markdown, is a collision.
+///
+/// This doesn't lock the collection, that is up to the caller.
+pub fn create_recipe(
+    base_path: &Utf8Path,
+    name: &str,
+    content: &str,
+    on_collision: OnCollision,
+    options: WriteOptions,
+) -> Result<RecipeEntry, Error> {
+    let name = normalize_separators(name);
+    let name = name.strip_suffix(".cook").unwrap_or(&name);
+    check_relative_path(name)?;
+
+    let mut path = recipe_path(base_path, name, None);
+    if is_taken(&path) {
+        if on_collision == OnCollision::Fail {
+            return Err(Error::AlreadyExists(name.to_string()));
+        }
+        path = (2..=MAX_SUFFIX)
+            .map(|n| recipe_path(base_path, name, Some(n)))
+            .find(|p| !is_taken(p))
+            .ok_or_else(|| Error::AlreadyExists(name.to_string()))?;
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    safe_write(&path, content, options)?;
+    Ok(RecipeEntry::new(path))
+}
+
+fn recipe_path(base_path: &Utf8Path, name: &str, suffix: Option<u32>) -> Utf8PathBuf {
+    let file = match suffix {
+        Some(n) => format!("{name} ({n}).cook"),
+        None => format!("{name}.cook"),
+    };
+    base_path.join(file)
+}
+
+/// Checks the path and its Markdown sibling, which would have the same name
+fn is_taken(path: &Utf8Path) -> bool {
+    path.exists() || path.with_extension(MARKDOWN_EXTENSION).exists()
+}
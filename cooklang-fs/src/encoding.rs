@@ -0,0 +1,90 @@
+use serde::Serialize;
+
+/// Text encoding of a recipe file
+///
+/// Recipes should be UTF-8, but files imported from other programs may not be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// ISO-8859-1, used as fallback when the file is not valid UTF-8
+    Latin1,
+}
+
+impl std::fmt::Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Latin1 => "ISO-8859-1",
+        })
+    }
+}
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// Decodes the bytes of a file into text
+///
+/// UTF-16 is only detected with a BOM. If there is no BOM and the content is
+/// not valid UTF-8, it's read as latin-1, which never fails.
+pub fn decode(bytes: Vec<u8>) -> (String, Encoding) {
+    if let Some(rest) = bytes.strip_prefix(UTF8_BOM) {
+        return (String::from_utf8_lossy(rest).into_owned(), Encoding::Utf8);
+    }
+    if let Some(rest) = bytes.strip_prefix(UTF16LE_BOM) {
+        return (decode_utf16(rest, u16::from_le_bytes), Encoding::Utf16Le);
+    }
+    if let Some(rest) = bytes.strip_prefix(UTF16BE_BOM) {
+        return (decode_utf16(rest, u16::from_be_bytes), Encoding::Utf16Be);
+    }
+    match String::from_utf8(bytes) {
+        Ok(text) => (text, Encoding::Utf8),
+        Err(err) => {
+            let text = err.into_bytes().into_iter().map(char::from).collect();
+            (text, Encoding::Latin1)
+        }
+    }
+}
+
+fn decode_utf16(bytes: &[u8], f: fn([u8; 2]) -> u16) -> String {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|c| f([c[0], c[1]]))
+        .collect::<Vec<_>>();
+    String::from_utf16_lossy(&units)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_encoding() {
+        assert_eq!(
+            decode("Añade @sal".into()),
+            ("Añade @sal".to_string(), Encoding::Utf8)
+        );
+        assert_eq!(
+            decode(b"\xEF\xBB\xBF@sal".to_vec()),
+            ("@sal".to_string(), Encoding::Utf8)
+        );
+        assert_eq!(
+            decode(b"A\xF1ade @sal".to_vec()),
+            ("Añade @sal".to_string(), Encoding::Latin1)
+        );
+        assert_eq!(
+            decode(b"\xFF\xFE@\x00s\x00".to_vec()),
+            ("@s".to_string(), Encoding::Utf16Le)
+        );
+        assert_eq!(
+            decode(b"\xFE\xFF\x00@\x00s".to_vec()),
+            ("@s".to_string(), Encoding::Utf16Be)
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Hash of the content of a recipe
+///
+/// Use it to detect changes instead of modification times, which have low
+/// resolution in some file systems and are not preserved by every sync tool.
+///
+/// It's a SHA-256 of the text, so it's stable between runs and versions and
+/// can be stored.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ContentHash([u8; 32]);
+
+impl ContentHash {
+    pub fn of(text: &str) -> Self {
+        Self::of_bytes(text.as_bytes())
+    }
+
+    /// Hash of what a recipe means, ignoring whitespace and comments
+    ///
+    /// Reformatting, adding comments or blank lines between paragraphs give
+    /// the same hash, so it can be used to skip parsing or rendering a recipe
+    /// again. The front matter is only trimmed, in YAML indentation matters.
+    ///
+    /// Don't use it to detect conflicts, two different texts can have it.
+    pub fn of_recipe(text: &str) -> Self {
+        Self::of(&normalize_recipe(text))
+    }
+
+    /// Like [`Self::of`] for content that is not text
+    pub fn of_bytes(bytes: &[u8]) -> Self {
+        Self(Sha256::digest(bytes).into())
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for ContentHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for b in self.0 {
+            write!(f, "{b:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for ContentHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ContentHash({self})")
+    }
+}
+
+/// Front matter without blank lines and comments, then a line per paragraph
+/// with its words separated by a space and without comments
+fn normalize_recipe(text: &str) -> String {
+    let mut out = String::new();
+    let mut lines = text.lines().peekable();
+    if lines.peek().is_some_and(|l| l.trim_end() == "---") {
+        lines.next();
+        out.push_str("---\n");
+        for line in lines.by_ref() {
+            let line = line.trim_end();
+            if line == "---" {
+                break;
+            }
+            if !line.is_empty() && !line.trim_start().starts_with('#') {
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+        out.push_str("---\n");
+    }
+
+    let body = lines.collect::<Vec<_>>().join("\n");
+    let body = strip_block_comments(&body);
+    let mut paragraph = String::new();
+    let mut flush = |paragraph: &mut String| {
+        if !paragraph.is_empty() {
+            out.push_str(paragraph);
+            out.push('\n');
+            paragraph.clear();
+        }
+    };
+    for line in body.lines() {
+        if line.trim().is_empty() {
+            flush(&mut paragraph);
+            continue;
+        }
+        let code = line.split_once("--").map_or(line, |(code, _)| code);
+        for word in code.split_whitespace() {
+            if !paragraph.is_empty() {
+                paragraph.push(' ');
+            }
+            paragraph.push_str(word);
+        }
+    }
+    flush(&mut paragraph);
+    out
+}
+
+/// Replaces `[- ... -]` comments with a space
+fn strip_block_comments(text: &str) -> std::borrow::Cow<'_, str> {
+    if !text.contains("[-") {
+        return text.into();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("[-") {
+        out.push_str(&rest[..start]);
+        out.push(' ');
+        rest = match rest[start..].find("-]") {
+            Some(end) => &rest[start + end + 2..],
+            None => "",
+        };
+    }
+    out.push_str(rest);
+    out.into()
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid content hash, expected 64 hex digits")]
+pub struct InvalidHash;
+
+impl std::str::FromStr for ContentHash {
+    type Err = InvalidHash;
+
+    /// Parses the hex string of [`Display`](std::fmt::Display)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 64 || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(InvalidHash);
+        }
+        let mut bytes = [0; 32];
+        for (b, i) in bytes.iter_mut().zip((0..s.len()).step_by(2)) {
+            *b = u8::from_str_radix(&s[i..i + 2], 16).map_err(|_| InvalidHash)?;
+        }
+        Ok(Self(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex() {
+        let hash = ContentHash::of("Boil @water{}.");
+        assert_eq!(hash.to_string().parse::<ContentHash>().unwrap(), hash);
+        assert!("abc".parse::<ContentHash>().is_err());
+        assert!("zz".repeat(32).parse::<ContentHash>().is_err());
+    }
+
+    #[test]
+    fn recipe() {
+        let hash = ContentHash::of_recipe(">> servings: 2\n\nBoil @water{1%l}.\nAdd @salt.\n");
+        let same = [
+            ">> servings: 2\n\n\nBoil  @water{1%l}. -- a lot\n  Add @salt.  \r\n",
+            ">> servings: 2\n\nBoil @water{1%l}. [- cold\nwater -] Add @salt.",
+        ];
+        for text in same {
+            assert_eq!(ContentHash::of_recipe(text), hash, "{text}");
+        }
+        let different = [
+            ">> servings: 4\n\nBoil @water{1%l}.\nAdd @salt.\n",
+            ">> servings: 2\n\nBoil @water{1%l}.\n\nAdd @salt.\n",
+        ];
+        for text in different {
+            assert_ne!(ContentHash::of_recipe(text), hash, "{text}");
+        }
+
+        let yaml = "---\ntags:\n  - easy\n---\nBoil.";
+        assert_eq!(
+            ContentHash::of_recipe(yaml),
+            ContentHash::of_recipe("---\n# comment\ntags:\n  - easy\n\n---\n\nBoil.\n")
+        );
+        assert_ne!(
+            ContentHash::of_recipe(yaml),
+            ContentHash::of_recipe("---\ntags:\n- easy\n---\nBoil.")
+        );
+    }
+}
@@ -0,0 +1,238 @@
+//! Adding and removing recipe images
+//!
+//! Images are copied next to the recipe with the names [`recipe_images`]
+//! finds: `<recipe>.<ext>` for the main image and `<recipe>.<step>.<ext>` or
+//! `<recipe>.<section>.<step>.<ext>` for a step.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use cooklang::{quantity::QuantityValue, CooklangParser};
+
+use crate::{
+    check_recipe_images, recipe_images, safe_write, walker::Walker, Error, Image, ImageIndexes,
+    NonUtf8Policy, RecipeEntry, RecipeImageError, WriteOptions, IMAGE_EXTENSIONS,
+};
+
+/// Copies an image for a step of the recipe at `path`
+///
+/// The section and step start at 0 and are checked against the parsed
+/// recipe. An image of the same step with another extension is replaced.
+pub fn add_image<D, V: QuantityValue>(
+    path: &Utf8Path,
+    recipe: &cooklang::Recipe<D, V>,
+    section: u16,
+    step: u16,
+    source: &Utf8Path,
+) -> Result<Image, Error> {
+    let indexes = ImageIndexes::new(section, step);
+    let image = Image {
+        path: image_path(path, Some(&indexes), source)?,
+        indexes: Some(indexes),
+    };
+    if let Err(mut errors) = check_recipe_images(std::slice::from_ref(&image), recipe) {
+        return Err(errors.remove(0).into());
+    }
+    write_image(path, image, source)
+}
+
+/// Copies the main image of the recipe at `path`, replacing the current one
+pub fn set_main_image(path: &Utf8Path, source: &Utf8Path) -> Result<Image, Error> {
+    let image = Image {
+        path: image_path(path, None, source)?,
+        indexes: None,
+    };
+    write_image(path, image, source)
+}
+
+/// Removes an image of the recipe at `path`, `None` for the main image
+///
+/// Returns the removed images, empty if there was none.
+pub fn remove_image(path: &Utf8Path, indexes: Option<&ImageIndexes>) -> Result<Vec<Image>, Error> {
+    let images = recipe_images(path)
+        .into_iter()
+        .filter(|i| i.indexes.as_ref() == indexes)
+        .collect::<Vec<_>>();
+    for image in &images {
+        fs::remove_file(&image.path)?;
+    }
+    Ok(images)
+}
+
+fn write_image(path: &Utf8Path, image: Image, source: &Utf8Path) -> Result<Image, Error> {
+    let content = fs::read(source)?;
+    safe_write(&image.path, content, WriteOptions::default())?;
+    for old in recipe_images(path) {
+        if old.indexes == image.indexes && old.path != image.path {
+            fs::remove_file(&old.path)?;
+        }
+    }
+    Ok(image)
+}
+
+/// An image that does not belong to a recipe, from [`orphaned_images`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrphanedImage {
+    pub path: Utf8PathBuf,
+    /// The recipe it's named after, if it exists but doesn't have the
+    /// section or step of the image
+    pub recipe: Option<Utf8PathBuf>,
+}
+
+/// Finds the images in `dir` and its subdirs that don't belong to a recipe
+///
+/// An image is orphaned when no recipe in the same dir has its name, or when
+/// it's for a section or step the recipe doesn't have. Recipes that can't be
+/// parsed are not checked.
+pub fn orphaned_images(
+    dir: impl AsRef<std::path::Path>,
+    parser: &CooklangParser,
+) -> Result<Vec<OrphanedImage>, Error> {
+    let dir: &Utf8Path = dir
+        .as_ref()
+        .try_into()
+        .map_err(|e: camino::FromPathError| e.into_io_error())?;
+    let mut walker = Walker::new(dir, usize::MAX);
+    walker.set_markdown(true);
+    walker.set_non_utf8_policy(NonUtf8Policy::Skip);
+    let mut recipes = HashMap::new();
+    let mut images = Vec::new();
+    for entry in walker {
+        let entry = entry?;
+        if entry.is_recipe_file() {
+            recipes.insert(entry.path().with_extension(""), entry.into_path());
+        } else if entry.is_image() {
+            images.push(entry.into_path());
+        }
+    }
+
+    let mut orphaned = Vec::new();
+    let mut step_images = BTreeMap::<&Utf8Path, Vec<Image>>::new();
+    for path in images {
+        match image_recipe(&path, &recipes) {
+            None => orphaned.push(OrphanedImage { path, recipe: None }),
+            Some((recipe, image)) if image.indexes.is_some() => {
+                step_images.entry(recipe).or_default().push(image);
+            }
+            Some(_) => {}
+        }
+    }
+    for (recipe, images) in step_images {
+        let parsed = match RecipeEntry::new(recipe).read() {
+            Ok(content) => content.parse(parser),
+            Err(e) => {
+                tracing::warn!("Can't read '{recipe}': {e}");
+                continue;
+            }
+        };
+        let Some(parsed) = parsed.output() else {
+            tracing::debug!("not checking the images of '{recipe}', it has errors");
+            continue;
+        };
+        for error in check_recipe_images(&images, parsed)
+            .err()
+            .unwrap_or_default()
+        {
+            let (RecipeImageError::MissingSection { image, .. }
+            | RecipeImageError::MissingStep { image, .. }) = error;
+            orphaned.push(OrphanedImage {
+                path: image,
+                recipe: Some(recipe.to_path_buf()),
+            });
+        }
+    }
+    orphaned.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+    Ok(orphaned)
+}
+
+/// Finds the recipe an image is named after
+///
+/// `recipes` maps the paths of the recipes without the extension to the
+/// paths with it.
+pub(crate) fn image_recipe<'a>(
+    image: &Utf8Path,
+    recipes: &'a HashMap<Utf8PathBuf, Utf8PathBuf>,
+) -> Option<(&'a Utf8Path, Image)> {
+    // `<recipe>.<ext>`, `<recipe>.<step>.<ext>` or `<recipe>.<section>.<step>.<ext>`
+    let mut name = image.with_extension("");
+    for _ in 0..3 {
+        if let Some(recipe) = recipes.get(&name) {
+            let image = name
+                .file_name()
+                .and_then(|n| Image::from_path(n, image.to_path_buf()));
+            if let Some(image) = image {
+                return Some((recipe, image));
+            }
+        }
+        if !name.extension().is_some_and(|e| e.parse::<u16>().is_ok()) {
+            break;
+        }
+        name.set_extension("");
+    }
+    None
+}
+
+/// Path of an image of a recipe, with the extension of `source`
+fn image_path(
+    recipe: &Utf8Path,
+    indexes: Option<&ImageIndexes>,
+    source: &Utf8Path,
+) -> Result<Utf8PathBuf, Error> {
+    let ext = source
+        .extension()
+        .map(str::to_lowercase)
+        .filter(|ext| IMAGE_EXTENSIONS.contains(&ext.as_str()))
+        .ok_or_else(|| Error::InvalidName(source.to_string()))?;
+    let name = recipe
+        .file_stem()
+        .ok_or_else(|| Error::InvalidName(recipe.to_string()))?;
+    let file_name = match indexes {
+        None => format!("{name}.{ext}"),
+        Some(i) if i.section() == 0 => format!("{name}.{}.{ext}", i.step()),
+        Some(i) => format!("{name}.{}.{}.{ext}", i.section(), i.step()),
+    };
+    Ok(recipe.with_file_name(file_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_names() {
+        let recipe = Utf8Path::new("base/Pasta.cook");
+        let path = |indexes: Option<ImageIndexes>, source: &str| {
+            image_path(recipe, indexes.as_ref(), Utf8Path::new(source))
+        };
+        assert_eq!(path(None, "photo.JPG").unwrap(), "base/Pasta.jpg");
+        let step = |section, step| Some(ImageIndexes::new(section, step));
+        assert_eq!(path(step(0, 3), "a.png").unwrap(), "base/Pasta.3.png");
+        assert_eq!(path(step(1, 3), "a.png").unwrap(), "base/Pasta.1.3.png");
+        assert!(path(None, "notes.txt").is_err());
+
+        // they are found again
+        let image = Image::from_path("Pasta", path(step(1, 3), "a.png").unwrap()).unwrap();
+        assert_eq!((image.section(), image.step()), (Some(1), Some(3)));
+    }
+
+    #[test]
+    fn recipe_of_image() {
+        let recipes = ["base/Pasta.cook", "base/Soups/Leek.md"]
+            .into_iter()
+            .map(|p| (Utf8Path::new(p).with_extension(""), Utf8PathBuf::from(p)))
+            .collect::<HashMap<_, _>>();
+        let recipe =
+            |image: &str| image_recipe(Utf8Path::new(image), &recipes).map(|(r, _)| r.as_str());
+        assert_eq!(recipe("base/Pasta.jpg"), Some("base/Pasta.cook"));
+        assert_eq!(recipe("base/Pasta.2.png"), Some("base/Pasta.cook"));
+        assert_eq!(recipe("base/Pasta.1.2.jpeg"), Some("base/Pasta.cook"));
+        assert_eq!(recipe("base/Soups/Leek.jpg"), Some("base/Soups/Leek.md"));
+        assert_eq!(recipe("base/Leek.jpg"), None);
+        assert_eq!(recipe("base/Pasta.cover.jpg"), None);
+        assert_eq!(recipe("base/Pasta.1.2.3.jpg"), None);
+        assert_eq!(recipe("base/Pizza.jpg"), None);
+    }
+}
@@ -6,18 +6,71 @@
 //! It implements an index into the file system to efficiently resolve recipes
 //! from a path. The index can be lazy or eager. Both created with
 //! [`new_index`].
-
+//!
+//! Files should be modified with [`safe_write`] to avoid corrupting them.
+//!
+//! The logic to match names and images is in [`matching`], which does not
+//! touch the file system. [`StoreIndex`] uses it with any [`RecipeStore`].
+
+mod backlinks;
+mod collation;
+mod create;
+mod encoding;
+mod hash;
+mod images;
+mod lock;
+mod markdown;
+pub mod matching;
+mod metadata;
+mod names;
+mod readonly;
+mod rename;
+mod shared;
+mod sidecar;
+mod stats;
+mod store;
+pub mod sync;
+pub mod transaction;
+mod trash;
+mod tree;
 mod walker;
+#[cfg(feature = "watch")]
+mod watch;
+mod write;
 
-use std::{cell::RefCell, collections::HashMap};
+use std::cell::RefCell;
 
-use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+use camino::{Utf8Path, Utf8PathBuf};
 use cooklang::quantity::QuantityValue;
+use matching::{into_name_path, recipe_path, NameIndex as Cache};
 use once_cell::sync::OnceCell;
-use serde::Serialize;
-
-pub use walker::DirEntry;
+use serde::{Deserialize, Serialize};
+
+pub use backlinks::Backlinks;
+pub use collation::Collation;
+pub use create::{create_recipe, OnCollision};
+pub use encoding::Encoding;
+pub use hash::{ContentHash, InvalidHash};
+pub use images::{add_image, orphaned_images, remove_image, set_main_image, OrphanedImage};
+pub use lock::{CollectionLock, Locked, LOCK_FILE};
+pub use markdown::MARKDOWN_EXTENSION;
+pub use matching::{Image, ImageIndexes, NameMatching, RecipeGlob, IMAGE_EXTENSIONS};
+pub use metadata::{MetadataIndex, RecipeMetadata};
+pub use names::{
+    check_file_name, check_relative_path, fold_case, normalize_separators, strip_accents,
+};
+pub use readonly::{is_readonly, READONLY_FILE, READONLY_KEY};
+pub use shared::SharedFsIndex;
+pub use sidecar::{recipe_sidecars, Nutrition, Sidecar, NOTES_KIND, NUTRITION_KIND};
+pub use stats::CollectionStats;
+pub use store::{FsStore, InMemoryStore, RecipeStore, StoreIndex};
+pub use trash::{Trash, TrashedFile};
+pub use tree::RecipeTree;
 use walker::Walker;
+pub use walker::{map_par, DirEntry, NonUtf8Policy, IGNORE_FILE};
+#[cfg(feature = "watch")]
+pub use watch::{Change, WatchedIndex};
+pub use write::{backup_path, safe_write, WriteOptions, BACKUP_EXTENSION};
 
 pub fn new_index(
     base_path: impl AsRef<std::path::Path>,
@@ -29,6 +82,7 @@ pub fn new_index(
 pub struct FsIndexBuilder {
     base_path: Utf8PathBuf,
     walker: Walker,
+    cache: Cache,
 }
 
 impl FsIndexBuilder {
@@ -42,6 +96,7 @@ impl FsIndexBuilder {
         Ok(Self {
             base_path: base_path.to_path_buf(),
             walker,
+            cache: Cache::default(),
         })
     }
 
@@ -61,6 +116,66 @@ impl FsIndexBuilder {
         self
     }
 
+    /// Sets what to do with non UTF-8 paths found while indexing
+    ///
+    /// By default it's an error. The skipped ones are available with
+    /// [`FsIndex::skipped_paths`].
+    pub fn non_utf8(mut self, policy: NonUtf8Policy) -> Self {
+        self.walker.set_non_utf8_policy(policy);
+        self
+    }
+
+    /// Indexes Markdown files with a cooklang code block as recipes
+    ///
+    /// The recipe is the code block and the YAML front matter is the metadata.
+    /// Disabled by default.
+    pub fn markdown(mut self, enabled: bool) -> Self {
+        self.walker.set_markdown(enabled);
+        self
+    }
+
+    /// Follows symbolic links to files and dirs
+    ///
+    /// Links to something inside the base path or already reached through
+    /// another link are skipped, so a collection can link shared dirs without
+    /// duplicate recipes or cycles. Disabled by default.
+    pub fn follow_links(mut self, follow: bool) -> Self {
+        self.walker.set_follow_links(follow);
+        self
+    }
+
+    /// Gets the file types of the entries of each dir in a few threads
+    ///
+    /// Faster in network file systems, where it may need a round trip for
+    /// each file. Disabled by default.
+    pub fn batch_stats(mut self, batch: bool) -> Self {
+        self.walker.set_batch_stats(batch);
+        self
+    }
+
+    /// Sets how recipe names are compared with queries
+    ///
+    /// By default it ignores case.
+    pub fn name_matching(mut self, matching: NameMatching) -> Self {
+        self.cache.set_matching(matching);
+        self
+    }
+
+    /// Adds alternate names for recipes
+    ///
+    /// The paths are relative to the base path, with the extension. A query
+    /// that does not match any recipe is compared with the aliases, ignoring
+    /// case like the names.
+    pub fn aliases<'a>(
+        mut self,
+        aliases: impl IntoIterator<Item = (&'a str, &'a Utf8Path)>,
+    ) -> Self {
+        for (alias, path) in aliases {
+            self.cache.add_alias(alias, &self.base_path.join(path));
+        }
+        self
+    }
+
     /// Create a new [lazy index](`LazyFsIndex`)
     ///
     /// The structure this creates is not completely thread safe, see
@@ -69,31 +184,212 @@ impl FsIndexBuilder {
         LazyFsIndex {
             base_path: self.base_path,
             walker: RefCell::new(self.walker),
-            cache: RefCell::new(Cache::default()),
+            cache: RefCell::new(self.cache),
         }
     }
 
     /// Create a new [complete index](`FsIndex`)
-    pub fn indexed(mut self) -> Result<FsIndex, Error> {
-        let mut cache = Cache::default();
-        index_all(&mut cache, &mut self.walker)?;
+    pub fn indexed(self) -> Result<FsIndex, Error> {
+        let mut cache = self.cache;
+        let dirs = index_fresh(&mut cache, &self.walker)?;
         Ok(FsIndex {
             base_path: self.base_path,
             cache,
+            walker: self.walker,
+            dirs: Some(dirs),
         })
     }
+
+    /// Walks the collection to count what is in it
+    ///
+    /// Tags are not counted, see [`Self::stats_with_tags`].
+    pub fn stats(self) -> Result<CollectionStats, Error> {
+        stats::collect(&self.walker, None)
+    }
+
+    /// Like [`Self::stats`] but also counts the recipes with each tag
+    ///
+    /// The metadata is taken from `cache` when the recipe has not changed.
+    pub fn stats_with_tags(
+        self,
+        cache: &mut MetadataIndex,
+        parser: &cooklang::CooklangParser,
+    ) -> Result<CollectionStats, Error> {
+        stats::collect(&self.walker, Some((cache, parser)))
+    }
+
+    /// Load a [complete index](`FsIndex`) previously stored with
+    /// [`FsIndex::save`]
+    ///
+    /// This does not walk the directory, so it's much faster in slow file
+    /// systems, but the index may be outdated. Use [`FsIndex::refresh`] to
+    /// update it.
+    #[tracing::instrument(level = "debug", skip(self), err)]
+    pub fn load(self, file: &Utf8Path) -> Result<FsIndex, Error> {
+        let stored = StoredIndex::read(file)?;
+        Ok(self.from_stored(stored))
+    }
+
+    /// Load a [complete index](`FsIndex`) previously stored with
+    /// [`FsIndex::save`] if the collection has not changed, or create a new
+    /// one and store it
+    ///
+    /// Changes are detected with the modification time of the dirs, that
+    /// changes when a file is added, removed or renamed. Checking them is
+    /// faster than walking the collection in slow file systems.
+    #[tracing::instrument(level = "debug", skip(self), err)]
+    pub fn load_or_build(self, file: &Utf8Path) -> Result<FsIndex, Error> {
+        match StoredIndex::read(file) {
+            Ok(stored) if stored.is_current(&self.base_path) => {
+                return Ok(self.from_stored(stored));
+            }
+            Ok(_) => tracing::debug!("stored index is outdated"),
+            Err(e) => tracing::debug!("can't load stored index: {e}"),
+        }
+        let index = self.indexed()?;
+        index.save(file)?;
+        Ok(index)
+    }
+
+    fn from_stored(self, stored: StoredIndex) -> FsIndex {
+        let mut cache = self.cache;
+        for path in stored.recipes {
+            let path = self.base_path.join(path);
+            if let Some(name) = path.file_stem() {
+                cache.insert(name, &path);
+            }
+        }
+        FsIndex {
+            base_path: self.base_path,
+            cache,
+            walker: self.walker,
+            dirs: stored.dirs,
+        }
+    }
+}
+
+/// Format of the file written by [`FsIndex::save`]
+#[derive(Serialize, Deserialize)]
+struct StoredIndex {
+    version: u32,
+    /// Relative to the base path
+    recipes: Vec<Utf8PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dirs: Option<Vec<DirTime>>,
+}
+
+impl StoredIndex {
+    const VERSION: u32 = 1;
+
+    fn read(file: &Utf8Path) -> Result<Self, Error> {
+        let content = std::fs::read(file)?;
+        let stored: Self = serde_json::from_slice(&content).map_err(Error::IndexFile)?;
+        if stored.version != Self::VERSION {
+            return Err(Error::IndexFile(serde::de::Error::custom(
+                "unsupported index file version",
+            )));
+        }
+        Ok(stored)
+    }
+
+    /// Checks that no dir changed since it was stored
+    ///
+    /// The dirs are checked in parallel.
+    fn is_current(&self, base_path: &Utf8Path) -> bool {
+        self.dirs.as_ref().is_some_and(|dirs| {
+            walker::map_par(dirs, |d| d.is_current(base_path))
+                .into_iter()
+                .all(|current| current)
+        })
+    }
+}
+
+/// Modification time of a dir when it was indexed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DirTime {
+    /// Relative to the base path
+    path: Utf8PathBuf,
+    /// `None` if the platform does not have it, so it never is current
+    modified: Option<std::time::SystemTime>,
+}
+
+impl DirTime {
+    fn new(base_path: &Utf8Path, path: &Utf8Path) -> Self {
+        Self {
+            path: path.strip_prefix(base_path).unwrap_or(path).to_path_buf(),
+            modified: path.metadata().and_then(|m| m.modified()).ok(),
+        }
+    }
+
+    fn is_current(&self, base_path: &Utf8Path) -> bool {
+        let current = base_path
+            .join(&self.path)
+            .metadata()
+            .and_then(|m| m.modified());
+        self.modified.is_some() && current.ok() == self.modified
+    }
 }
 
-#[tracing::instrument(level = "debug", skip_all, ret)]
-fn index_all(cache: &mut Cache, walker: &mut Walker) -> Result<(), Error> {
+/// Indexes every recipe left in the walker
+///
+/// Returns the modification times of the dirs found.
+#[tracing::instrument(level = "debug", skip_all, err)]
+fn index_all(cache: &mut Cache, walker: &mut Walker) -> Result<Vec<DirTime>, Error> {
+    let mut dirs = Vec::new();
+    let base_path = walker.base_path().to_path_buf();
     for entry in walker {
         let entry = entry?;
+        if entry.file_type().is_dir() {
+            dirs.push(DirTime::new(&base_path, entry.path()));
+            continue;
+        }
         let Some((entry_name, path)) = process_entry(&entry) else {
             continue;
         };
         cache.insert(entry_name, path);
     }
-    Ok(())
+    Ok(dirs)
+}
+
+/// Indexes every recipe with a new walk, including the dirs of the base path
+///
+/// The dirs are read and their modification times checked in parallel.
+#[tracing::instrument(level = "debug", skip_all, err)]
+fn index_fresh(cache: &mut Cache, walker: &Walker) -> Result<Vec<DirTime>, Error> {
+    // before walking, so a change while walking makes it outdated
+    let base_path = walker.base_path();
+    let mut dirs = vec![DirTime::new(base_path, base_path)];
+    walker.clear_skipped();
+    let mut dir_paths = Vec::new();
+    let mut recipes = Vec::new();
+    for entries in walker.walk_par(walker::default_threads()) {
+        for entry in entries? {
+            if entry.file_type().is_dir() {
+                dir_paths.push(entry.into_path());
+            } else if entry.is_recipe_file() {
+                recipes.push(entry.into_path());
+            }
+        }
+    }
+    // in the order of a sequential walk, so the same recipe is preferred when
+    // names only differ by case
+    recipes.sort_by(|a, b| walk_order(a, b));
+    dir_paths.sort_by(|a, b| walk_order(a, b));
+    dirs.extend(walker::map_par(&dir_paths, |path| {
+        DirTime::new(base_path, path)
+    }));
+    for path in &recipes {
+        if let Some(name) = path.file_stem() {
+            cache.insert(name, path);
+        }
+    }
+    Ok(dirs)
+}
+
+/// Order of the paths in a [`Walker`], breadth first and sorted by name
+fn walk_order(a: &Utf8Path, b: &Utf8Path) -> std::cmp::Ordering {
+    let depth = |p: &Utf8Path| p.components().count();
+    depth(a).cmp(&depth(b)).then_with(|| a.cmp(b))
 }
 
 /// Lazy index of a directory for cooklang recipes
@@ -116,33 +412,75 @@ pub struct LazyFsIndex {
 /// Index of a directory for cooklang recipes
 ///
 /// The index contains all recipes in the directory.
+///
+/// Once created, it does not touch the file system to find recipes that are
+/// already indexed, so it's well suited for slow file systems. Changes are not
+/// detected automatically, they have to be added with [`Self::insert`] and
+/// [`Self::remove`] or by calling [`Self::refresh`]. With the `watch` feature,
+/// [`Self::watch`] does it from file system notifications.
+///
+/// It can be shared between threads. To also update it while shared, use
+/// [`SharedFsIndex`].
 #[derive(Debug)]
 pub struct FsIndex {
     base_path: Utf8PathBuf,
     cache: Cache,
-}
-
-#[derive(Debug, Default)]
-struct Cache {
-    recipes: HashMap<String, Vec<Utf8PathBuf>>,
+    /// Not started walker to refresh the index
+    walker: Walker,
+    /// Dirs found when walking, to know if a stored index is outdated.
+    /// `None` if they are not known.
+    dirs: Option<Vec<DirTime>>,
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("Recipe not found: '{0}'")]
     NotFound(String),
+    #[error("Many recipes match '{recipe}'")]
+    Ambiguous {
+        recipe: String,
+        /// Paths of the recipes, prefixed with the base path
+        candidates: Vec<Utf8PathBuf>,
+    },
     #[error(transparent)]
     Io(#[from] std::io::Error),
     #[error("Invalid name: '{0}'")]
     InvalidName(String),
+    #[error("Invalid name '{name}': {reason}")]
+    UnsafeName { name: String, reason: &'static str },
     #[error(transparent)]
     NotRecipe(#[from] NotRecipe),
     #[error("Path points outside the base dir: '{0}'")]
     OutsideBase(String),
+    #[error(transparent)]
+    Locked(#[from] Locked),
+    #[error("Invalid index file")]
+    IndexFile(#[source] serde_json::Error),
+    #[error("Recipe already exists: '{0}'")]
+    AlreadyExists(String),
+    #[error("Recipe changed since it was read: '{0}'")]
+    Conflict(String),
+    #[error("Recipe is read-only: '{0}'")]
+    ReadOnly(String),
+    #[error("Invalid transaction journal")]
+    Journal(#[source] serde_json::Error),
+    #[error("Invalid data in '{path}'")]
+    Sidecar {
+        path: Utf8PathBuf,
+        #[source]
+        source: toml::de::Error,
+    },
+    #[error("Invalid glob")]
+    Glob(#[from] globset::Error),
+    #[error(transparent)]
+    Image(#[from] RecipeImageError),
+    #[cfg(feature = "watch")]
+    #[error("Can't watch the collection")]
+    Watch(#[from] notify::Error),
 }
 
 #[derive(Debug, thiserror::Error)]
-#[error("Non UTF8 path")]
+#[error("Non UTF8 path: {}", .0.display())]
 pub struct NonUtf8(std::path::PathBuf);
 
 impl FsIndex {
@@ -161,28 +499,180 @@ impl FsIndex {
     /// performs a lookup in the index.
     ///
     /// The recipe cannot be outside the base path.
+    ///
+    /// If the path is already in the index, the file system is not accessed.
     pub fn resolve(
         &self,
         recipe: &str,
         relative_to: Option<&Utf8Path>,
     ) -> Result<RecipeEntry, Error> {
+        if let Ok(path) = recipe_path(recipe, relative_to, &self.base_path) {
+            if self.cache.contains_path(&path) {
+                return Ok(RecipeEntry::new(path));
+            }
+        }
         try_path(recipe, relative_to, &self.base_path).or_else(|_| self.get(recipe))
     }
 
+    /// Resolves a recipe reference written in the recipe at `base_recipe`
+    ///
+    /// A reference like `./sauces/Marinara` is looked for from the dir of the
+    /// recipe first. If it's not there, in the whole collection like
+    /// [`Self::get`], and then only by its name, `Marinara`.
+    pub fn resolve_relative(
+        &self,
+        base_recipe: &Utf8Path,
+        reference: &str,
+    ) -> Result<RecipeEntry, Error> {
+        self.resolve(reference, base_recipe.parent())
+            .or_else(|e| resolve_by_name(reference, e, |name| self.get(name)))
+    }
+
+    /// Alternate names of a recipe, see [`FsIndexBuilder::aliases`]
+    pub fn aliases_of(&self, path: &Utf8Path) -> Vec<&str> {
+        self.cache.aliases_of(path)
+    }
+
+    /// Non UTF-8 paths that were skipped when walking, see
+    /// [`FsIndexBuilder::non_utf8`]
+    ///
+    /// Empty for an index loaded from a file until it's refreshed.
+    pub fn skipped_paths(&self) -> Vec<std::path::PathBuf> {
+        self.walker.skipped()
+    }
+
+    /// Walks the directory again, replacing the current index
+    ///
+    /// If walking fails, the current index is kept.
+    #[tracing::instrument(level = "debug", skip(self), err)]
+    pub fn refresh(&mut self) -> Result<(), Error> {
+        let mut cache = self.cache.emptied();
+        let dirs = index_fresh(&mut cache, &self.walker)?;
+        self.cache = cache;
+        self.dirs = Some(dirs);
+        Ok(())
+    }
+
+    /// Stores the index in a file
+    ///
+    /// Load it again with [`FsIndexBuilder::load`].
+    #[tracing::instrument(level = "debug", skip(self), err)]
+    pub fn save(&self, file: &Utf8Path) -> Result<(), Error> {
+        let mut recipes = self
+            .cache
+            .paths()
+            .filter_map(|p| p.strip_prefix(&self.base_path).ok())
+            .map(Utf8Path::to_path_buf)
+            .collect::<Vec<_>>();
+        recipes.sort_unstable();
+        let stored = StoredIndex {
+            version: StoredIndex::VERSION,
+            recipes,
+            dirs: self.dirs.clone(),
+        };
+        let content = serde_json::to_vec(&stored).map_err(Error::IndexFile)?;
+        safe_write(file, content, WriteOptions::default())?;
+        Ok(())
+    }
+
+    /// Get a recipe from the index
+    ///
+    /// # Errors
+    /// [`Error::Ambiguous`] when recipes with the same name in different dirs
+    /// match. Use a longer path or [`Self::get_all`].
     pub fn get(&self, recipe: &str) -> Result<RecipeEntry, Error> {
-        let (name, path) = into_name_path(recipe)?;
-        match self.cache.get(&name, &path) {
-            Some(path) => Ok(RecipeEntry::new(path)),
-            None => Err(Error::NotFound(recipe.to_string())),
-        }
+        self.cache.find(recipe).map(RecipeEntry::new)
     }
 
-    pub fn get_all(&self) -> impl Iterator<Item = RecipeEntry> + '_ {
+    /// Every recipe that matches, the one [`Self::get`] prefers first
+    pub fn get_all(&self, recipe: &str) -> Vec<RecipeEntry> {
+        let Ok((name, path)) = into_name_path(recipe) else {
+            return Vec::new();
+        };
         self.cache
-            .recipes
-            .values()
-            .flatten()
-            .map(|p| RecipeEntry::new(p.to_path_buf()))
+            .get_all(&name, &path)
+            .into_iter()
+            .map(RecipeEntry::new)
+            .collect()
+    }
+
+    /// Every recipe in the index, sorted by path with [`Collation::Binary`]
+    pub fn iter(&self) -> impl Iterator<Item = RecipeEntry> + '_ {
+        let mut paths = self.cache.paths().collect::<Vec<_>>();
+        Collation::Binary.sort_by_path(&mut paths, |p| *p);
+        paths.into_iter().map(RecipeEntry::new)
+    }
+
+    /// Recipes with a path that matches a glob, sorted like [`Self::iter`]
+    ///
+    /// The pattern is relative to the base path, like `soups/**/chicken*`.
+    /// See [`RecipeGlob`].
+    pub fn glob(&self, pattern: &str) -> Result<Vec<RecipeEntry>, Error> {
+        let glob = RecipeGlob::new(pattern)?;
+        Ok(self
+            .iter()
+            .filter(|entry| {
+                entry
+                    .path()
+                    .strip_prefix(&self.base_path)
+                    .is_ok_and(|p| glob.is_match(p))
+            })
+            .collect())
+    }
+
+    /// Recipes with a name similar to the query, the best match first
+    ///
+    /// Unlike [`Self::get`], it allows partial names and typos, so
+    /// `brocolli sou` finds `Broccoli Soup.cook`. See
+    /// [`NameMatching::fuzzy_score`].
+    pub fn search(&self, query: &str) -> Vec<RecipeEntry> {
+        self.cache
+            .search(query)
+            .into_iter()
+            .map(RecipeEntry::new)
+            .collect()
+    }
+
+    /// Names with more than one recipe, sorted by name
+    ///
+    /// Referencing them by name alone is ambiguous if they are in different
+    /// dirs. Names are compared like the queries, see [`NameMatching`]. The
+    /// paths are in disk, sorted.
+    pub fn duplicates(&self) -> Vec<(String, Vec<Utf8PathBuf>)> {
+        self.cache.duplicates()
+    }
+
+    /// Number of recipes in the index
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    /// Every recipe nested in its dirs, sorted like [`Self::iter`]
+    ///
+    /// Built from the index, the file system is not accessed.
+    pub fn tree(&self) -> RecipeTree {
+        RecipeTree::from_paths(&self.base_path, self.cache.paths())
+    }
+
+    /// Recipes directly inside a dir, sorted like [`Self::iter`]
+    ///
+    /// The dir is relative to the base path, empty for the top level. Recipes
+    /// in subdirs are not included.
+    pub fn recipes_in(&self, dir: &str) -> impl Iterator<Item = RecipeEntry> + '_ {
+        let dir = self
+            .base_path
+            .join(normalize_separators(dir).trim_matches('/'));
+        let mut paths = self
+            .cache
+            .paths()
+            .filter(|p| p.parent() == Some(dir.as_path()))
+            .collect::<Vec<_>>();
+        Collation::Binary.sort_by_path(&mut paths, |p| *p);
+        paths.into_iter().map(RecipeEntry::new)
     }
 
     /// Remove a recipe from the index
@@ -206,6 +696,43 @@ impl FsIndex {
         Ok(())
     }
 
+    /// Moves a recipe and its images, updating the index
+    ///
+    /// Both paths are in disk and have to be prefixed with the base path, `to`
+    /// with the same extension as `from`. Missing dirs are created. Nothing is moved
+    /// if a file already exists in the destination.
+    ///
+    /// # Panics
+    /// If `from` does not start with the base path.
+    pub fn move_recipe(&mut self, from: &Utf8Path, to: &Utf8Path) -> Result<RecipeEntry, Error> {
+        let relative = to
+            .strip_prefix(&self.base_path)
+            .map_err(|_| Error::OutsideBase(to.to_string()))?;
+        check_relative_path(relative.as_str())?;
+        if to.extension() != from.extension() {
+            return Err(Error::InvalidName(to.to_string()));
+        }
+        rename::move_recipe_files(from, to)?;
+        self.remove(from)?;
+        self.insert(to)?;
+        Ok(RecipeEntry::new(to))
+    }
+
+    /// Writes a new recipe and adds it to the index
+    ///
+    /// See [`create_recipe`] for the name and collisions.
+    pub fn create(
+        &mut self,
+        name: &str,
+        content: &str,
+        on_collision: OnCollision,
+        options: WriteOptions,
+    ) -> Result<RecipeEntry, Error> {
+        let entry = create_recipe(&self.base_path, name, content, on_collision, options)?;
+        self.insert(entry.path())?;
+        Ok(entry)
+    }
+
     /// Manually add a recipe to the index
     ///
     /// This does not check if the path contains references to parent
@@ -227,7 +754,7 @@ impl FsIndex {
         assert!(path.is_file(), "path does not exist or is not a file");
 
         // if its known, do nothing
-        if self.get(path.as_str()).is_ok() {
+        if self.cache.contains_path(path) {
             return Ok(());
         }
 
@@ -244,17 +771,45 @@ impl LazyFsIndex {
 
     /// Check if the index contains a recipe
     pub fn contains(&self, recipe: &str) -> bool {
-        self.get(recipe).is_ok()
+        matches!(self.get(recipe), Ok(_) | Err(Error::Ambiguous { .. }))
+    }
+
+    /// Non UTF-8 paths skipped so far, see [`FsIndex::skipped_paths`]
+    pub fn skipped_paths(&self) -> Vec<std::path::PathBuf> {
+        self.walker.borrow().skipped()
+    }
+
+    /// Forgets the recipes found so far, so the dirs are walked again
+    ///
+    /// Use it after creating, moving or removing recipes without going
+    /// through the index.
+    pub fn refresh(&self) {
+        let mut cache = self.cache.borrow_mut();
+        *cache = cache.emptied();
+        let mut walker = self.walker.borrow_mut();
+        *walker = walker.restart();
     }
 
     /// Completes the lazy indexing returning a complete [`FsIndex`]
     pub fn index_all(self) -> Result<FsIndex, Error> {
         let mut cache = self.cache.into_inner();
         let mut walker = self.walker.into_inner();
+        if walker.is_fresh() {
+            let dirs = index_fresh(&mut cache, &walker)?;
+            return Ok(FsIndex {
+                base_path: self.base_path,
+                cache,
+                walker,
+                dirs: Some(dirs),
+            });
+        }
+        // some dirs may have been walked before
         index_all(&mut cache, &mut walker)?;
         Ok(FsIndex {
             base_path: self.base_path,
             cache,
+            walker: walker.restart(),
+            dirs: None,
         })
     }
 
@@ -270,42 +825,68 @@ impl LazyFsIndex {
         try_path(recipe, relative_to, &self.base_path).or_else(|_| self.get(recipe))
     }
 
+    /// See [`FsIndex::resolve_relative`]
+    pub fn resolve_relative(
+        &self,
+        base_recipe: &Utf8Path,
+        reference: &str,
+    ) -> Result<RecipeEntry, Error> {
+        self.resolve(reference, base_recipe.parent())
+            .or_else(|e| resolve_by_name(reference, e, |name| self.get(name)))
+    }
+
+    /// Recipes with a name similar to the query, see [`FsIndex::search`]
+    ///
+    /// This has to walk the rest of the directory.
+    pub fn search(&self, query: &str) -> Result<Vec<RecipeEntry>, Error> {
+        let mut cache = self.cache.borrow_mut();
+        index_all(&mut cache, &mut self.walker.borrow_mut())?;
+        Ok(cache
+            .search(query)
+            .into_iter()
+            .map(RecipeEntry::new)
+            .collect())
+    }
+
     /// Get a recipe from the index
     ///
     /// The input recipe is a partial path with or without the .cook extension.
+    ///
+    /// # Errors
+    /// Like [`FsIndex::get`].
     #[tracing::instrument(level = "debug", name = "lazy_index_get", skip(self))]
     pub fn get(&self, recipe: &str) -> Result<RecipeEntry, Error> {
         let (name, path) = into_name_path(recipe)?;
+        let depth = |p: &Utf8Path| p.components().count();
+        let mut found = self.cache.borrow().get(&name, &path).map(|p| depth(&p));
 
-        // Is in cache?
-        if let Some(path) = self.cache.borrow().get(&name, &path) {
-            return Ok(RecipeEntry::new(path));
-        }
-
-        // Walk until found or no more files
-        // as walk is breadth-first and sorted by filename, the first found will
-        // be the wanted: outermost alphabetically
+        // Walk until found and then the rest of the dirs at the same depth, to
+        // know if it's ambiguous. As walk is breadth-first and sorted by
+        // filename, the first found will be the wanted: outermost
+        // alphabetically
         let mut walker = self.walker.borrow_mut();
         for entry in walker.by_ref() {
             let entry = entry?;
-            let Some((entry_name, entry_path)) = process_entry(&entry) else {
-                continue;
-            };
-
-            // Add to cache
-            self.cache.borrow_mut().insert(entry_name, entry_path);
-
-            if compare_path(entry_path, &path) {
-                return Ok(RecipeEntry::new(entry_path));
+            let past_found = found.is_some_and(|d| depth(entry.path()) > d);
+            if let Some((entry_name, entry_path)) = process_entry(&entry) {
+                // Add to cache
+                let mut cache = self.cache.borrow_mut();
+                cache.insert(entry_name, entry_path);
+                if found.is_none() && cache.matching().compare_path(entry_path, &path) {
+                    found = Some(depth(entry_path));
+                }
+            }
+            if past_found {
+                break;
             }
         }
-        Err(Error::NotFound(recipe.to_string()))
+        self.cache.borrow().find(recipe).map(RecipeEntry::new)
     }
 }
 
 fn process_entry(dir_entry: &DirEntry) -> Option<(&str, &Utf8Path)> {
-    // Ignore non files or not .cook files
-    if !dir_entry.is_cooklang_file() {
+    // Ignore non files or not recipe files
+    if !dir_entry.is_recipe_file() {
         return None;
     }
 
@@ -314,65 +895,27 @@ fn process_entry(dir_entry: &DirEntry) -> Option<(&str, &Utf8Path)> {
     Some((entry_name, dir_entry.path()))
 }
 
-impl Cache {
-    fn get(&self, name: &str, path: &Utf8Path) -> Option<Utf8PathBuf> {
-        let paths = self.recipes.get(&name.to_lowercase())?;
-        paths.iter().find(|p| compare_path(p, path)).cloned()
-    }
-
-    fn insert(&mut self, name: &str, path: &Utf8Path) {
-        tracing::trace!("adding {name}:{path} to index cache");
-        let recipes = self.recipes.entry(name.to_lowercase()).or_default();
-        let pos = recipes.partition_point(|p| {
-            // less components first. same, alphabetically
-            match p.components().count().cmp(&path.components().count()) {
-                std::cmp::Ordering::Less => true,
-                std::cmp::Ordering::Equal => p.as_str() < path.as_str(),
-                std::cmp::Ordering::Greater => false,
-            }
-        });
-        recipes.insert(pos, path.to_path_buf());
-    }
-
-    fn remove(&mut self, name: &str, path: &Utf8Path) {
-        tracing::trace!("removing {name}:{path} from index cache");
-        if let Some(recipes) = self.recipes.get_mut(&name.to_lowercase()) {
-            // can't do swap so "outer" recipes remain first
-            if let Some(index) = recipes.iter().position(|r| r == path) {
-                recipes.remove(index);
-            }
-        }
-    }
-}
-
-fn into_name_path(recipe: &str) -> Result<(String, Utf8PathBuf), Error> {
-    let path = Utf8PathBuf::from(recipe);
-    let name = path
-        .file_stem()
-        .ok_or_else(|| Error::InvalidName(recipe.into()))?
-        .to_string();
-    Ok((name, path))
-}
-
-fn compare_path_key(p: &Utf8Path) -> Utf8PathBuf {
-    Utf8PathBuf::from(p.as_str().to_lowercase()).with_extension("")
-}
-
-fn compare_path(full: &Utf8Path, suffix: &Utf8Path) -> bool {
-    // only compare the end, so partial paths are a valid form of referencing recipes
-    compare_path_key(full).ends_with(compare_path_key(suffix))
-}
-
 /// Get all recipes from a path with a depth limit
+///
+/// The recipes are returned breadth first, each dir sorted by file name in
+/// binary order. Sort them with a [`Collation`] to list them.
+///
+/// `markdown` includes Markdown recipes, see [`FsIndexBuilder::markdown`], and
+/// `follow_links` follows symbolic links, see [`FsIndexBuilder::follow_links`].
 pub fn all_recipes(
     base_path: impl AsRef<std::path::Path>,
     max_depth: usize,
+    markdown: bool,
+    follow_links: bool,
 ) -> Result<impl Iterator<Item = RecipeEntry>, std::io::Error> {
     let base_path: &Utf8Path = base_path
         .as_ref()
         .try_into()
         .map_err(|e: camino::FromPathError| e.into_io_error())?;
-    let walker = Walker::new(base_path, max_depth).flatten();
+    let mut walker = Walker::new(base_path, max_depth);
+    walker.set_markdown(markdown);
+    walker.set_follow_links(follow_links);
+    let walker = walker.flatten();
     let grouped = group_images(walker);
     Ok(grouped.filter_map(|e| match e {
         Entry::Dir(_) => None,
@@ -380,6 +923,33 @@ pub fn all_recipes(
     }))
 }
 
+/// Like [`all_recipes`] but reads the dirs in parallel
+///
+/// Much faster in big collections or network file systems. The recipes come
+/// in no particular order, sort them with a [`Collation`]. Dirs that can't be
+/// read are skipped, like in [`all_recipes`].
+pub fn all_recipes_par(
+    base_path: impl AsRef<std::path::Path>,
+    max_depth: usize,
+    markdown: bool,
+    follow_links: bool,
+) -> Result<impl Iterator<Item = RecipeEntry>, std::io::Error> {
+    let base_path: &Utf8Path = base_path
+        .as_ref()
+        .try_into()
+        .map_err(|e: camino::FromPathError| e.into_io_error())?;
+    let mut walker = Walker::new(base_path, max_depth);
+    walker.set_markdown(markdown);
+    walker.set_follow_links(follow_links);
+    let dirs = walker.walk_par(walker::default_threads());
+    Ok(dirs.into_iter().flatten().flat_map(|entries| {
+        group_images(entries.into_iter()).filter_map(|e| match e {
+            Entry::Dir(_) => None,
+            Entry::Recipe(r) => Some(r),
+        })
+    }))
+}
+
 /// Walks a single directory retrieving recipes and other directories
 pub fn walk_dir(
     path: impl AsRef<std::path::Path>,
@@ -410,16 +980,18 @@ fn group_images(walker: impl Iterator<Item = DirEntry>) -> impl Iterator<Item =
             loop {
                 match self.iter.next()? {
                     dir if dir.file_type().is_dir() => return Some(Entry::Dir(dir)),
-                    r if r.is_cooklang_file() => {
+                    r if r.is_recipe_file() => {
                         let recipe_name = r.file_stem();
                         // because file are sorted by name, recipe images will be with the
                         // recipes
                         let mut images = past_images
                             .into_iter()
-                            .filter_map(|e| Image::new(recipe_name, e))
+                            .filter_map(|e| Image::from_path(recipe_name, e.into_path()))
                             .collect::<Vec<_>>();
                         while let Some(image_entry) = self.iter.next_if(|e| e.is_image()) {
-                            if let Some(image) = Image::new(recipe_name, image_entry) {
+                            if let Some(image) =
+                                Image::from_path(recipe_name, image_entry.into_path())
+                            {
                                 images.push(image);
                             }
                         }
@@ -441,6 +1013,22 @@ fn group_images(walker: impl Iterator<Item = DirEntry>) -> impl Iterator<Item =
     }
 }
 
+/// Last fallback of [`FsIndex::resolve_relative`], only for references with
+/// dirs
+fn resolve_by_name(
+    reference: &str,
+    error: Error,
+    get: impl FnOnce(&str) -> Result<RecipeEntry, Error>,
+) -> Result<RecipeEntry, Error> {
+    match into_name_path(reference) {
+        Ok((name, path)) if path.components().count() > 1 => match get(&name) {
+            Err(Error::NotFound(_)) => Err(error),
+            res => res,
+        },
+        _ => Err(error),
+    }
+}
+
 pub enum Entry {
     Dir(DirEntry),
     Recipe(RecipeEntry),
@@ -452,67 +1040,12 @@ fn try_path(
     relative_to: Option<&Utf8Path>,
     base_path: &Utf8Path,
 ) -> Result<RecipeEntry, Error> {
-    let mut path = Utf8PathBuf::from(recipe).with_extension("cook");
-
-    if path
-        .components()
-        .any(|c| matches!(c, Utf8Component::Prefix(_)))
-    {
-        return Err(Error::InvalidName(recipe.to_string()));
-    }
-
-    if path.has_root() {
-        let no_root = path.as_str().trim_start_matches(['/', '\\']);
-        path = base_path.join(no_root);
-    } else if let Some(parent) = relative_to {
-        path = parent.join(&path);
-    }
-    path = norm_path(&path);
-
-    if !path.starts_with(base_path) {
-        return Err(Error::OutsideBase(recipe.to_string()));
-    }
-
+    let path = recipe_path(recipe, relative_to, base_path)?;
     DirEntry::new(&path)
         .map_err(Error::from)
         .and_then(|e| RecipeEntry::try_from(e).map_err(Error::from))
 }
 
-fn norm_path(path: &Utf8Path) -> Utf8PathBuf {
-    let mut components = path.components().peekable();
-    let mut ret = if let Some(c @ Utf8Component::Prefix(..)) = components.peek().cloned() {
-        components.next();
-        Utf8PathBuf::from(c.as_str())
-    } else {
-        Utf8PathBuf::new()
-    };
-
-    for component in components {
-        match component {
-            Utf8Component::Prefix(..) => unreachable!(),
-            Utf8Component::RootDir => {
-                ret.push(component.as_str());
-            }
-            Utf8Component::CurDir => {
-                if ret.components().count() == 0 {
-                    ret.push(component.as_str());
-                }
-            }
-            Utf8Component::ParentDir => {
-                if ret.components().count() > 0 {
-                    ret.pop();
-                } else {
-                    ret.push(component.as_str());
-                }
-            }
-            Utf8Component::Normal(c) => {
-                ret.push(c);
-            }
-        }
-    }
-    ret
-}
-
 #[derive(Debug, Clone)]
 pub struct RecipeEntry {
     path: Utf8PathBuf,
@@ -550,13 +1083,59 @@ impl RecipeEntry {
     }
 
     pub fn relative_name(&self) -> &str {
-        self.path.as_str().trim_end_matches(".cook")
+        let path = self.path.as_str();
+        match self.path.extension() {
+            Some(ext) => &path[..path.len() - ext.len() - 1],
+            None => path,
+        }
+    }
+
+    /// Checks if the recipe is in a Markdown file
+    pub fn is_markdown(&self) -> bool {
+        self.path.extension() == Some(MARKDOWN_EXTENSION)
+    }
+
+    /// Renames the recipe file and its images, keeping it in the same dir
+    ///
+    /// The new name is without the extension. Indexes are not updated, use
+    /// [`FsIndex::move_recipe`] for that.
+    pub fn rename(&self, new_name: &str) -> Result<RecipeEntry, Error> {
+        let ext = self.path.extension().unwrap_or("cook");
+        let file_name = format!("{new_name}.{ext}");
+        check_file_name(&file_name)?;
+        let to = self.path.with_file_name(file_name);
+        rename::move_recipe_files(&self.path, &to)?;
+        Ok(RecipeEntry::new(to))
+    }
+
+    /// Reads the recipe and hashes it ignoring whitespace and comments
+    ///
+    /// See [`ContentHash::of_recipe`]. Use [`RecipeContent::hash`] to detect
+    /// any change in the file.
+    pub fn content_hash(&self) -> std::io::Result<ContentHash> {
+        Ok(self.read()?.semantic_hash())
     }
 
     /// Reads the content of the entry
+    ///
+    /// Files that are not UTF-8 are transcoded, see [`Encoding`]. A warning is
+    /// logged when this happens.
+    ///
+    /// For Markdown files the content is only the recipe, see
+    /// [`FsIndexBuilder::markdown`].
     pub fn read(&self) -> std::io::Result<RecipeContent> {
-        let content = std::fs::read_to_string(&self.path)?;
-        Ok(RecipeContent::new(content))
+        let bytes = std::fs::read(&self.path)?;
+        let (mut content, encoding) = encoding::decode(bytes);
+        if self.is_markdown() {
+            content = markdown::extract(&content);
+        }
+        if encoding != Encoding::Utf8 {
+            tracing::warn!(
+                "'{}' is not UTF-8, read as {encoding}. Save it as UTF-8 to avoid problems.",
+                self.path
+            );
+        }
+        Ok(RecipeContent::new(content, encoding))
     }
 
     /// Finds the images of the recipe
@@ -566,6 +1145,23 @@ impl RecipeEntry {
     pub fn images(&self) -> &[Image] {
         self.images.get_or_init(|| recipe_images(&self.path))
     }
+
+    /// Finds the sidecar files of the recipe, see [`Sidecar`]
+    pub fn sidecars(&self) -> Vec<Sidecar> {
+        recipe_sidecars(&self.path)
+    }
+
+    /// Reads the nutrition facts of `<recipe>.nutrition.toml`, if it exists
+    pub fn nutrition(&self) -> Result<Option<Nutrition>, Error> {
+        sidecar::read_sidecar(&self.path, NUTRITION_KIND, "toml")?
+            .map(|(path, content)| sidecar::parse_nutrition(path, &content))
+            .transpose()
+    }
+
+    /// Reads the notes of `<recipe>.notes.md`, if it exists
+    pub fn notes(&self) -> Result<Option<String>, Error> {
+        Ok(sidecar::read_sidecar(&self.path, NOTES_KIND, "md")?.map(|(_, content)| content))
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -575,7 +1171,7 @@ impl TryFrom<DirEntry> for RecipeEntry {
     type Error = NotRecipe;
 
     fn try_from(value: DirEntry) -> Result<Self, Self::Error> {
-        if !value.is_cooklang_file() {
+        if !value.is_recipe_file() {
             return Err(NotRecipe(value.into_path()));
         }
         Ok(Self::new(value.into_path()))
@@ -585,11 +1181,35 @@ impl TryFrom<DirEntry> for RecipeEntry {
 #[derive(Debug, Clone)]
 pub struct RecipeContent {
     content: String,
+    encoding: Encoding,
+    hash: OnceCell<ContentHash>,
 }
 
 impl RecipeContent {
-    fn new(content: String) -> Self {
-        Self { content }
+    fn new(content: String, encoding: Encoding) -> Self {
+        Self {
+            content,
+            encoding,
+            hash: OnceCell::new(),
+        }
+    }
+
+    /// Hash of the text
+    ///
+    /// It's calculated the first time and then cached.
+    pub fn hash(&self) -> ContentHash {
+        *self.hash.get_or_init(|| ContentHash::of(&self.content))
+    }
+
+    /// Hash that ignores whitespace and comments, see
+    /// [`ContentHash::of_recipe`]
+    pub fn semantic_hash(&self) -> ContentHash {
+        ContentHash::of_recipe(&self.content)
+    }
+
+    /// Encoding the file was read with
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
     }
 
     /// Parses the metadata of the recipe
@@ -629,57 +1249,6 @@ impl RecipeContent {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
-pub struct Image {
-    pub indexes: Option<ImageIndexes>,
-    pub path: Utf8PathBuf,
-}
-
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
-pub struct ImageIndexes {
-    section: u16,
-    step: u16,
-}
-
-impl Image {
-    fn new(recipe_name: &str, entry: DirEntry) -> Option<Self> {
-        let parts = entry.file_name().rsplitn(4, '.').collect::<Vec<_>>();
-
-        // no dots, so no extension
-        if parts.len() == 1 {
-            return None;
-        }
-
-        let name = *parts.last().unwrap();
-        let ext = *parts.first().unwrap();
-
-        if name != recipe_name || !IMAGE_EXTENSIONS.contains(&ext) {
-            return None;
-        }
-
-        let indexes = match &parts[1..parts.len() - 1] {
-            [step, section] => {
-                let section = section.parse::<u16>().ok()?;
-                let step = step.parse::<u16>().ok()?;
-                Some(ImageIndexes { section, step })
-            }
-            [step] => {
-                let step = step.parse::<u16>().ok()?;
-                Some(ImageIndexes { section: 0, step })
-            }
-            _ => None,
-        };
-
-        Some(Image {
-            indexes,
-            path: entry.into_path(),
-        })
-    }
-}
-
-/// Valid image extensions
-pub const IMAGE_EXTENSIONS: &[&str] = &["jpeg", "jpg", "png", "heic", "gif", "webp"];
-
 /// Get a list of the images of the recipe
 ///
 /// See [IMAGE_EXTENSIONS].
@@ -695,7 +1264,7 @@ pub fn recipe_images(path: &Utf8Path) -> Vec<Image> {
     let mut images = dir
         .filter_map(|e| e.ok()) // skip error
         .filter(|e| e.file_type().map(|t| t.is_file()).unwrap_or(false)) // skip non-file
-        .filter_map(|e| Image::new(recipe_name, DirEntry::new(e.path()).ok()?))
+        .filter_map(|e| Image::from_path(recipe_name, e.path().to_path_buf()))
         .collect::<Vec<_>>();
     images.sort_unstable();
     images
@@ -723,7 +1292,7 @@ pub fn check_recipe_images<D, V: QuantityValue>(
 ) -> Result<(), Vec<RecipeImageError>> {
     let mut errors = Vec::new();
     for image in images {
-        if let Some(ImageIndexes { section, step }) = image.indexes {
+        if let (Some(section), Some(step)) = (image.section(), image.step()) {
             let Some(recipe_section) = recipe.sections.get(section as usize) else {
                 errors.push(RecipeImageError::MissingSection {
                     section,
@@ -747,3 +1316,31 @@ pub fn check_recipe_images<D, V: QuantityValue>(
         Err(errors)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lazy_refresh() {
+        let dir = std::env::temp_dir().join(format!("cooklang-fs-lazy-{}", std::process::id()));
+        let dir = Utf8PathBuf::from_path_buf(dir).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("Pasta.cook"), "").unwrap();
+
+        let index = new_index(&dir, 10).unwrap().lazy();
+        assert!(index.contains("Pasta"));
+        assert!(!index.contains("Soup"));
+
+        std::fs::write(dir.join("Soup.cook"), "").unwrap();
+        std::fs::remove_file(dir.join("Pasta.cook")).unwrap();
+        // already walked
+        assert!(!index.contains("Soup"));
+
+        index.refresh();
+        assert!(!index.contains("Pasta"));
+        assert!(index.contains("Soup"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    fs::{self, File, TryLockError},
+    io::{Read, Seek, Write},
+    time::{Duration, Instant},
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::Error;
+
+/// Name of the lock file created inside the locked dir
+pub const LOCK_FILE: &str = "lock";
+
+/// How long [`CollectionLock::acquire`] waits for another process
+const LOCK_WAIT: Duration = Duration::from_secs(2);
+const LOCK_RETRY: Duration = Duration::from_millis(50);
+
+/// Advisory lock over a collection
+///
+/// Hold it while modifying recipes or any persistent data of the collection
+/// so concurrent `chef` processes don't race on writes. The lock is released
+/// when dropped or when the process dies.
+///
+/// This is advisory, other programs like editors will not respect it.
+#[derive(Debug)]
+pub struct CollectionLock {
+    file: File,
+    path: Utf8PathBuf,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Collection is locked by {}", holder(.0))]
+pub struct Locked(Option<u32>);
+
+impl Locked {
+    /// PID of the process holding the lock, if known
+    pub fn pid(&self) -> Option<u32> {
+        self.0
+    }
+}
+
+fn holder(pid: &Option<u32>) -> String {
+    match pid {
+        Some(pid) => format!("PID {pid}"),
+        None => "another process".to_string(),
+    }
+}
+
+impl CollectionLock {
+    /// Locks the collection, waiting a bit if another process holds it
+    ///
+    /// `dir` is where the lock file will be created, usually the collection
+    /// config dir.
+    ///
+    /// # Errors
+    /// [`Error::Locked`] if the lock could not be acquired in time.
+    #[tracing::instrument(level = "debug")]
+    pub fn acquire(dir: &Utf8Path) -> Result<Self, Error> {
+        let start = Instant::now();
+        loop {
+            match Self::try_acquire(dir) {
+                Err(Error::Locked(_)) if start.elapsed() < LOCK_WAIT => {
+                    std::thread::sleep(LOCK_RETRY);
+                }
+                res => return res,
+            }
+        }
+    }
+
+    /// Same as [`Self::acquire`] but fails inmediatly if the lock is taken
+    pub fn try_acquire(dir: &Utf8Path) -> Result<Self, Error> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(LOCK_FILE);
+        let mut file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let mut content = String::new();
+                let pid = file
+                    .read_to_string(&mut content)
+                    .ok()
+                    .and_then(|_| content.trim().parse().ok());
+                return Err(Locked(pid).into());
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+
+        file.set_len(0)?;
+        file.rewind()?;
+        write!(file, "{}", std::process::id())?;
+        file.flush()?;
+
+        tracing::debug!("acquired lock {path}");
+        Ok(Self { file, path })
+    }
+
+    pub fn path(&self) -> &Utf8Path {
+        &self.path
+    }
+}
+
+impl Drop for CollectionLock {
+    fn drop(&mut self) {
+        // the file is kept, removing it would let another process lock a
+        // different inode while someone is waiting on this one
+        let _ = self.file.set_len(0);
+        let _ = self.file.unlock();
+        tracing::debug!("released lock {}", self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> Utf8PathBuf {
+        let dir = std::env::temp_dir().join(format!("cooklang-fs-{name}-{}", std::process::id()));
+        let dir = Utf8PathBuf::from_path_buf(dir).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn contention() {
+        let dir = test_dir("lock-contention");
+        let lock = CollectionLock::try_acquire(&dir).unwrap();
+        assert_eq!(lock.path(), dir.join(LOCK_FILE));
+        assert!(matches!(
+            CollectionLock::try_acquire(&dir),
+            Err(Error::Locked(_))
+        ));
+
+        let start = Instant::now();
+        assert!(matches!(
+            CollectionLock::acquire(&dir),
+            Err(Error::Locked(_))
+        ));
+        assert!(start.elapsed() >= LOCK_WAIT);
+
+        // acquire waits for the holder to release it
+        let holder = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            drop(lock);
+        });
+        let lock = CollectionLock::acquire(&dir).unwrap();
+        holder.join().unwrap();
+        drop(lock);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn released_on_drop() {
+        let dir = test_dir("lock-drop");
+        let lock = CollectionLock::try_acquire(&dir).unwrap();
+        drop(lock);
+        let lock = CollectionLock::try_acquire(&dir).unwrap();
+        drop(lock);
+        // the file stays, empty
+        assert_eq!(fs::read_to_string(dir.join(LOCK_FILE)).unwrap(), "");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn locked_error_has_holder_pid() {
+        let dir = test_dir("lock-pid");
+        let lock = CollectionLock::try_acquire(&dir).unwrap();
+        let pid = std::process::id();
+        assert_eq!(
+            fs::read_to_string(dir.join(LOCK_FILE)).unwrap(),
+            pid.to_string()
+        );
+        let Err(Error::Locked(locked)) = CollectionLock::try_acquire(&dir) else {
+            panic!("lock was not contended");
+        };
+        assert_eq!(locked.pid(), Some(pid));
+        assert_eq!(
+            locked.to_string(),
+            format!("Collection is locked by PID {pid}")
+        );
+        assert_eq!(
+            Locked(None).to_string(),
+            "Collection is locked by another process"
+        );
+        drop(lock);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
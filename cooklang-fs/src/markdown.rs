@@ -0,0 +1,192 @@
+//! Recipes inside Markdown files
+//!
+//! A `.md` file is a recipe when it has a fenced code block with the
+//! `cooklang` (or `cook`) info string, like notes in Obsidian or a static site.
+//! The recipe is the text of those blocks, and the YAML front matter becomes
+//! the metadata. Everything else is dropped.
+//!
+//! The extracted text keeps the lines where they were in the file, so errors
+//! point to the right line.
+
+/// Extension of Markdown files, enable them with
+/// [`crate::FsIndexBuilder::markdown`]
+pub const MARKDOWN_EXTENSION: &str = "md";
+
+/// Checks if the Markdown text has a cooklang block
+pub fn has_recipe(text: &str) -> bool {
+    let mut fence = None;
+    text.lines()
+        .any(|line| matches!(next_fence(&mut fence, line), Line::Open(true)))
+}
+
+/// Extracts the recipe of a Markdown text
+pub fn extract(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut lines = text.lines().peekable();
+
+    if lines.peek().is_some_and(|l| l.trim_end() == "---") {
+        lines.next();
+        out.push('\n');
+        front_matter(&mut lines, &mut out);
+    }
+
+    let mut fence = None;
+    let mut in_recipe = false;
+    for line in lines {
+        match next_fence(&mut fence, line) {
+            Line::Open(is_recipe) => in_recipe = is_recipe,
+            Line::Close => in_recipe = false,
+            Line::Text if in_recipe => out.push_str(line),
+            Line::Text => {}
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Converts the front matter to metadata lines until the closing `---`
+///
+/// Only top level keys are used. Values that are lists, inline or one item
+/// per line, are joined with commas.
+fn front_matter<'a>(lines: &mut impl Iterator<Item = &'a str>, out: &mut String) {
+    // one per line, the metadata goes in the line of the key
+    let mut meta = Vec::new();
+    let mut pending: Option<(usize, &str, Vec<&str>)> = None;
+    for line in lines {
+        let trimmed = line.trim();
+        meta.push(String::new());
+        if trimmed == "---" || trimmed == "..." {
+            break;
+        }
+        if let Some(item) = trimmed.strip_prefix("- ") {
+            if let Some((_, _, items)) = &mut pending {
+                items.push(unquote(item.trim()));
+            }
+            continue;
+        }
+        if line.starts_with([' ', '\t']) || trimmed.starts_with('#') || trimmed.is_empty() {
+            continue;
+        }
+        if let Some((at, key, items)) = pending.take() {
+            meta[at] = entry(key, &items);
+        }
+        let Some((key, value)) = trimmed.split_once(':') else {
+            continue;
+        };
+        let at = meta.len() - 1;
+        let key = unquote(key.trim());
+        let value = value.trim();
+        if value.is_empty() {
+            pending = Some((at, key, Vec::new()));
+        } else if let Some(list) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            let items = list
+                .split(',')
+                .map(|i| unquote(i.trim()))
+                .collect::<Vec<_>>();
+            meta[at] = entry(key, &items);
+        } else {
+            meta[at] = entry(key, &[unquote(value)]);
+        }
+    }
+    if let Some((at, key, items)) = pending {
+        meta[at] = entry(key, &items);
+    }
+    for line in meta {
+        out.push_str(&line);
+        out.push('\n');
+    }
+}
+
+fn entry(key: &str, values: &[&str]) -> String {
+    if values.is_empty() {
+        return String::new();
+    }
+    format!(">> {key}: {}", values.join(", "))
+}
+
+fn unquote(s: &str) -> &str {
+    for q in ['"', '\''] {
+        if let Some(inner) = s.strip_prefix(q).and_then(|s| s.strip_suffix(q)) {
+            return inner;
+        }
+    }
+    s
+}
+
+enum Line {
+    /// Opening fence, `true` if it's a cooklang block
+    Open(bool),
+    Close,
+    Text,
+}
+
+/// Classifies a line, `fence` is the open fence if any
+fn next_fence<'a>(fence: &mut Option<&'a str>, line: &'a str) -> Line {
+    let trimmed = line.trim_start();
+    match *fence {
+        Some(open) => {
+            let close = trimmed.trim_end();
+            if close.starts_with(open) && close.chars().all(|c| Some(c) == open.chars().next()) {
+                *fence = None;
+                Line::Close
+            } else {
+                Line::Text
+            }
+        }
+        None => {
+            let Some(c) = trimmed.chars().next().filter(|c| matches!(*c, '`' | '~')) else {
+                return Line::Text;
+            };
+            let len = trimmed.chars().take_while(|&x| x == c).count();
+            if len < 3 {
+                return Line::Text;
+            }
+            let (open, info) = trimmed.split_at(len);
+            *fence = Some(open);
+            let lang = info.split_whitespace().next().unwrap_or_default();
+            Line::Open(lang.eq_ignore_ascii_case("cooklang") || lang.eq_ignore_ascii_case("cook"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTE: &str = "---
+title: Pasta
+tags:
+  - dinner
+  - 'quick'
+servings: 2
+---
+# Pasta
+
+Some notes.
+
+```cooklang
+Boil @water{1%l}.
+
+Add @pasta{200%g}.
+```
+
+```
+not this
+```
+";
+
+    #[test]
+    fn extract_note() {
+        assert!(has_recipe(NOTE));
+        assert!(!has_recipe("```rust\nfn main() {}\n```"));
+        let recipe = extract(NOTE);
+        assert_eq!(recipe.lines().count(), NOTE.lines().count());
+        let lines = recipe.lines().collect::<Vec<_>>();
+        assert_eq!(lines[1], ">> title: Pasta");
+        assert_eq!(lines[2], ">> tags: dinner, quick");
+        assert_eq!(lines[5], ">> servings: 2");
+        assert_eq!(lines[12], "Boil @water{1%l}.");
+        assert_eq!(lines[14], "Add @pasta{200%g}.");
+        assert!(!recipe.contains("notes") && !recipe.contains("not this"));
+    }
+}
@@ -0,0 +1,720 @@
+//! Pure logic to find recipes and their images
+//!
+//! Nothing here touches the file system, it only works with paths and names,
+//! so it can be used in wasm or with any [`RecipeStore`](crate::RecipeStore).
+
+use std::collections::HashMap;
+
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{
+    names::{fold_case, normalize_separators, strip_accents},
+    Error,
+};
+
+/// Valid image extensions
+pub const IMAGE_EXTENSIONS: &[&str] = &["jpeg", "jpg", "png", "heic", "gif", "webp"];
+
+/// How a query is compared with the recipe names
+///
+/// Both are always normalized to Unicode NFC, so the same text typed in
+/// different ways matches, and case folded with [`fold_case`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NameMatching {
+    /// Ignore case
+    #[default]
+    IgnoreCase,
+    /// Ignore case and accents, `Crème Anglaise` finds `Creme Anglaise.cook`
+    IgnoreAccents,
+}
+
+impl NameMatching {
+    /// Normalizes a name or path so equal keys match
+    pub fn key(self, name: &str) -> String {
+        let key = fold_case(&name.nfc().collect::<String>());
+        match self {
+            NameMatching::IgnoreCase => key,
+            NameMatching::IgnoreAccents => strip_accents(&key).into_owned(),
+        }
+    }
+
+    /// Checks if a query path matches a full recipe path
+    pub fn compare_path(self, full: &Utf8Path, suffix: &Utf8Path) -> bool {
+        // only compare the end, so partial paths are a valid form of referencing recipes
+        self.path_key(full).ends_with(self.path_key(suffix))
+    }
+
+    fn path_key(self, p: &Utf8Path) -> Utf8PathBuf {
+        Utf8PathBuf::from(self.key(p.as_str())).with_extension("")
+    }
+
+    /// How well a query matches a recipe name, lower is better
+    ///
+    /// `None` if it does not match. From best to worst: the same name, names
+    /// that start with the query, names that contain it and names where every
+    /// word of the query is close to a word of the name, so typos like
+    /// `brocolli sou` find `Broccoli Soup`.
+    pub fn fuzzy_score(self, query: &str, name: &str) -> Option<u32> {
+        let query = self.key(query.trim());
+        let name = self.key(name);
+        let query_words = words(&query).collect::<Vec<_>>();
+        if query_words.is_empty() {
+            return None;
+        }
+        if name == query {
+            return Some(0);
+        }
+        if name.starts_with(&query) {
+            return Some(1);
+        }
+        if name.contains(&query) {
+            return Some(2);
+        }
+        let name_words = words(&name).collect::<Vec<_>>();
+        let mut score = 10;
+        for q in query_words {
+            score += name_words.iter().filter_map(|w| word_score(q, w)).min()?;
+        }
+        Some(score)
+    }
+}
+
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+}
+
+/// Like [`NameMatching::fuzzy_score`] for a single word
+///
+/// Allows one typo every 4 letters.
+fn word_score(query: &str, word: &str) -> Option<u32> {
+    if word == query {
+        return Some(0);
+    }
+    if word.starts_with(query) {
+        return Some(1);
+    }
+    if word.contains(query) {
+        return Some(2);
+    }
+    let query = query.chars().collect::<Vec<_>>();
+    let word = word.chars().collect::<Vec<_>>();
+    let max = query.len() / 4;
+    let prefix = &word[..query.len().min(word.len())];
+    let distance = edit_distance(&query, &word).min(edit_distance(&query, prefix) + 1);
+    (max > 0 && distance <= max).then_some(3 + distance as u32)
+}
+
+/// Levenshtein distance
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.iter().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == cb { prev } else { prev + 1 };
+            prev = row[j + 1];
+            row[j + 1] = cost.min(row[j] + 1).min(prev + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Recipe paths indexed by name
+///
+/// This is the lookup policy of the indexes: a query is a partial path, with
+/// or without extension, that matches the end of a recipe path according to
+/// the [`NameMatching`]. When many recipes match, an exact match is
+/// preferred, and then the one with less components, then alphabetically. If
+/// the preferred ones are in different dirs, [`Self::find`] fails because the
+/// query is ambiguous.
+///
+/// If no recipe matches, the query is looked up in the aliases.
+#[derive(Debug, Default, Clone)]
+pub struct NameIndex {
+    recipes: HashMap<String, Vec<Utf8PathBuf>>,
+    matching: NameMatching,
+    aliases: Vec<(String, Utf8PathBuf)>,
+}
+
+impl NameIndex {
+    pub fn new(matching: NameMatching) -> Self {
+        Self {
+            recipes: HashMap::new(),
+            matching,
+            aliases: Vec::new(),
+        }
+    }
+
+    /// Changes the [`NameMatching`], updating the recipes already in the index
+    pub fn set_matching(&mut self, matching: NameMatching) {
+        self.matching = matching;
+        let paths = std::mem::take(&mut self.recipes).into_values().flatten();
+        for path in paths.collect::<Vec<_>>() {
+            if let Some(name) = path.file_stem() {
+                self.insert(name, &path);
+            }
+        }
+    }
+
+    /// An index with the same configuration and aliases, but no recipes
+    pub fn emptied(&self) -> Self {
+        Self {
+            recipes: HashMap::new(),
+            matching: self.matching,
+            aliases: self.aliases.clone(),
+        }
+    }
+
+    /// Adds an alternate name for the recipe at `path`
+    ///
+    /// The alias is compared with the whole query, not only the name.
+    pub fn add_alias(&mut self, alias: &str, path: &Utf8Path) {
+        self.aliases.push((alias.to_string(), path.to_path_buf()));
+    }
+
+    /// Aliases of the recipe at `path`
+    pub fn aliases_of(&self, path: &Utf8Path) -> Vec<&str> {
+        self.aliases
+            .iter()
+            .filter(|(_, p)| p == path)
+            .map(|(alias, _)| alias.as_str())
+            .collect()
+    }
+
+    fn alias(&self, query: &Utf8Path) -> Option<&Utf8Path> {
+        let key = self.matching.key(query.as_str());
+        self.aliases
+            .iter()
+            .find(|(alias, _)| self.matching.key(alias) == key)
+            .map(|(_, path)| path.as_path())
+    }
+
+    pub fn matching(&self) -> NameMatching {
+        self.matching
+    }
+
+    /// Finds the path of a recipe from the `name` and `path` of a query
+    ///
+    /// Split the query with [`into_name_path`]. If it's ambiguous, the
+    /// preferred is returned anyway.
+    pub fn get(&self, name: &str, path: &Utf8Path) -> Option<Utf8PathBuf> {
+        self.get_all(name, path).into_iter().next()
+    }
+
+    /// Every recipe that matches the query, the preferred first
+    ///
+    /// If no recipe matches, the alias.
+    pub fn get_all(&self, name: &str, path: &Utf8Path) -> Vec<Utf8PathBuf> {
+        let mut matches = self
+            .recipes
+            .get(&self.matching.key(name))
+            .into_iter()
+            .flatten()
+            .filter(|p| self.matching.compare_path(p, path))
+            .cloned()
+            .collect::<Vec<_>>();
+        if matches.is_empty() {
+            return self
+                .alias(path)
+                .map(Utf8Path::to_path_buf)
+                .into_iter()
+                .collect();
+        }
+        // in case sensitive file systems two recipes may only differ by case,
+        // so an exact match is preferred
+        matches.sort_by_key(|p| !is_exact(p, path));
+        matches
+    }
+
+    /// Finds the path of a recipe from a query
+    ///
+    /// # Errors
+    /// [`Error::Ambiguous`] if the preferred recipes are in different dirs.
+    pub fn find(&self, recipe: &str) -> Result<Utf8PathBuf, Error> {
+        let (name, path) = into_name_path(recipe)?;
+        let mut candidates = self.get_all(&name, &path);
+        let Some(first) = candidates.first() else {
+            return Err(Error::NotFound(recipe.to_string()));
+        };
+        let rank = |p: &Utf8Path| (is_exact(p, &path), p.components().count());
+        let (first_rank, first_dir) = (rank(first), first.parent().map(Utf8Path::to_path_buf));
+        candidates.retain(|p| rank(p) == first_rank);
+        if candidates
+            .iter()
+            .all(|p| p.parent() == first_dir.as_deref())
+        {
+            return Ok(candidates.swap_remove(0));
+        }
+        Err(Error::Ambiguous {
+            recipe: recipe.to_string(),
+            candidates,
+        })
+    }
+
+    pub fn contains_path(&self, path: &Utf8Path) -> bool {
+        path.file_stem()
+            .and_then(|name| self.recipes.get(&self.matching.key(name)))
+            .is_some_and(|paths| paths.iter().any(|p| p == path))
+    }
+
+    pub fn insert(&mut self, name: &str, path: &Utf8Path) {
+        tracing::trace!("adding {name}:{path} to index cache");
+        let recipes = self.recipes.entry(self.matching.key(name)).or_default();
+        let pos = recipes.partition_point(|p| {
+            // less components first. same, alphabetically
+            match p.components().count().cmp(&path.components().count()) {
+                std::cmp::Ordering::Less => true,
+                std::cmp::Ordering::Equal => p.as_str() < path.as_str(),
+                std::cmp::Ordering::Greater => false,
+            }
+        });
+        recipes.insert(pos, path.to_path_buf());
+    }
+
+    pub fn remove(&mut self, name: &str, path: &Utf8Path) {
+        tracing::trace!("removing {name}:{path} from index cache");
+        if let Some(recipes) = self.recipes.get_mut(&self.matching.key(name)) {
+            // can't do swap so "outer" recipes remain first
+            if let Some(index) = recipes.iter().position(|r| r == path) {
+                recipes.remove(index);
+            }
+        }
+    }
+
+    /// Number of recipes, without the aliases
+    pub fn len(&self) -> usize {
+        self.recipes.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Names with more than one recipe, see [`crate::FsIndex::duplicates`]
+    pub fn duplicates(&self) -> Vec<(String, Vec<Utf8PathBuf>)> {
+        let mut duplicates = self
+            .recipes
+            .values()
+            .filter(|paths| paths.len() > 1)
+            .map(|paths| {
+                let mut paths = paths.clone();
+                paths.sort_unstable();
+                let name = paths[0].file_stem().unwrap_or_default().to_string();
+                (name, paths)
+            })
+            .collect::<Vec<_>>();
+        duplicates.sort_unstable();
+        duplicates
+    }
+
+    /// Every path in the index, in no particular order
+    pub fn paths(&self) -> impl Iterator<Item = &Utf8Path> {
+        self.recipes.values().flatten().map(Utf8PathBuf::as_path)
+    }
+
+    /// Recipes with a name similar to the query, the best match first
+    ///
+    /// Only the file name of the query is used. See
+    /// [`NameMatching::fuzzy_score`].
+    pub fn search(&self, query: &str) -> Vec<&Utf8Path> {
+        let query = normalize_separators(query);
+        let query = query.rsplit('/').next().unwrap_or_default();
+        let query = query.strip_suffix(".cook").unwrap_or(query);
+        let mut found = self
+            .paths()
+            .filter_map(|p| Some((self.matching.fuzzy_score(query, p.file_stem()?)?, p)))
+            .collect::<Vec<_>>();
+        found.sort_by(|(a, a_path), (b, b_path)| {
+            a.cmp(b)
+                .then_with(|| {
+                    a_path
+                        .components()
+                        .count()
+                        .cmp(&b_path.components().count())
+                })
+                .then_with(|| a_path.cmp(b_path))
+        });
+        found.into_iter().map(|(_, p)| p).collect()
+    }
+}
+
+fn is_exact(full: &Utf8Path, query: &Utf8Path) -> bool {
+    full.with_extension("").ends_with(query.with_extension(""))
+}
+
+/// Splits a recipe query into the recipe name and the path
+pub fn into_name_path(recipe: &str) -> Result<(String, Utf8PathBuf), Error> {
+    let path = Utf8PathBuf::from(normalize_separators(recipe).as_ref());
+    let name = path
+        .file_stem()
+        .ok_or_else(|| Error::InvalidName(recipe.into()))?
+        .to_string();
+    Ok((name, path))
+}
+
+/// Checks if a query path matches a full recipe path ignoring case
+///
+/// See [`NameMatching::compare_path`] for other policies.
+pub fn compare_path(full: &Utf8Path, suffix: &Utf8Path) -> bool {
+    NameMatching::default().compare_path(full, suffix)
+}
+
+/// A glob pattern over recipe paths, see [`crate::FsIndex::glob`]
+///
+/// `*` and `?` don't match `/`, `**` matches any number of dirs. The
+/// extension is optional and case is ignored.
+#[derive(Debug, Clone)]
+pub struct RecipeGlob(globset::GlobMatcher);
+
+impl RecipeGlob {
+    pub fn new(pattern: &str) -> Result<Self, Error> {
+        let pattern = pattern.trim_start_matches('/');
+        let glob = globset::GlobBuilder::new(pattern)
+            .case_insensitive(true)
+            .literal_separator(true)
+            .build()?;
+        Ok(Self(glob.compile_matcher()))
+    }
+
+    /// Checks a recipe path relative to the base path
+    pub fn is_match(&self, path: &Utf8Path) -> bool {
+        let path = normalize_separators(path.as_str());
+        let path = Utf8Path::new(path.as_ref());
+        self.0.is_match(path) || self.0.is_match(path.with_extension(""))
+    }
+}
+
+/// Builds the path a recipe query points to, without checking if it exists
+pub(crate) fn recipe_path(
+    recipe: &str,
+    relative_to: Option<&Utf8Path>,
+    base_path: &Utf8Path,
+) -> Result<Utf8PathBuf, Error> {
+    let mut path = Utf8PathBuf::from(normalize_separators(recipe).as_ref()).with_extension("cook");
+
+    if path
+        .components()
+        .any(|c| matches!(c, Utf8Component::Prefix(_)))
+    {
+        return Err(Error::InvalidName(recipe.to_string()));
+    }
+
+    if path.has_root() {
+        let no_root = path.as_str().trim_start_matches(['/', '\\']);
+        path = base_path.join(no_root);
+    } else if let Some(parent) = relative_to {
+        path = parent.join(&path);
+    }
+    path = norm_path(&path);
+
+    // an empty base path accepts everything, but not going up
+    let escapes = matches!(path.components().next(), Some(Utf8Component::ParentDir));
+    if !path.starts_with(base_path) || escapes {
+        return Err(Error::OutsideBase(recipe.to_string()));
+    }
+
+    Ok(path)
+}
+
+fn norm_path(path: &Utf8Path) -> Utf8PathBuf {
+    let mut components = path.components().peekable();
+    let mut ret = if let Some(c @ Utf8Component::Prefix(..)) = components.peek().cloned() {
+        components.next();
+        Utf8PathBuf::from(c.as_str())
+    } else {
+        Utf8PathBuf::new()
+    };
+
+    for component in components {
+        match component {
+            Utf8Component::Prefix(..) => unreachable!(),
+            Utf8Component::RootDir => {
+                ret.push(component.as_str());
+            }
+            Utf8Component::CurDir => {
+                if ret.components().count() == 0 {
+                    ret.push(component.as_str());
+                }
+            }
+            Utf8Component::ParentDir => {
+                if ret.components().count() > 0 {
+                    ret.pop();
+                } else {
+                    ret.push(component.as_str());
+                }
+            }
+            Utf8Component::Normal(c) => {
+                ret.push(c);
+            }
+        }
+    }
+    ret
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Image {
+    pub indexes: Option<ImageIndexes>,
+    pub path: Utf8PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct ImageIndexes {
+    section: u16,
+    step: u16,
+}
+
+impl ImageIndexes {
+    /// Both start at 0
+    pub fn new(section: u16, step: u16) -> Self {
+        Self { section, step }
+    }
+
+    pub fn section(&self) -> u16 {
+        self.section
+    }
+
+    pub fn step(&self) -> u16 {
+        self.step
+    }
+}
+
+impl Image {
+    /// Creates the image if the file at `path` is an image of the recipe
+    ///
+    /// The file name has to be `<recipe>.<ext>` for the main image,
+    /// `<recipe>.<step>.<ext>` for a step in the first section or
+    /// `<recipe>.<section>.<step>.<ext>`.
+    pub fn from_path(recipe_name: &str, path: Utf8PathBuf) -> Option<Self> {
+        let parts = path.file_name()?.rsplitn(4, '.').collect::<Vec<_>>();
+
+        // no dots, so no extension
+        if parts.len() == 1 {
+            return None;
+        }
+
+        let name = *parts.last().unwrap();
+        let ext = *parts.first().unwrap();
+
+        if name != recipe_name || !IMAGE_EXTENSIONS.contains(&ext) {
+            return None;
+        }
+
+        let indexes = match &parts[1..parts.len() - 1] {
+            [step, section] => {
+                let section = section.parse::<u16>().ok()?;
+                let step = step.parse::<u16>().ok()?;
+                Some(ImageIndexes { section, step })
+            }
+            [step] => {
+                let step = step.parse::<u16>().ok()?;
+                Some(ImageIndexes { section: 0, step })
+            }
+            _ => None,
+        };
+
+        Some(Image { indexes, path })
+    }
+
+    pub fn section(&self) -> Option<u16> {
+        self.indexes.as_ref().map(|i| i.section)
+    }
+
+    pub fn step(&self) -> Option<u16> {
+        self.indexes.as_ref().map(|i| i.step)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicates() {
+        let mut index = NameIndex::default();
+        index.insert("Pasta", Utf8Path::new("base/Lunch/Pasta.cook"));
+        index.insert("Soup", Utf8Path::new("base/Soup.cook"));
+        index.insert("pasta", Utf8Path::new("base/Dinner/pasta.cook"));
+        assert_eq!(
+            index.duplicates(),
+            [(
+                "pasta".to_string(),
+                vec![
+                    Utf8PathBuf::from("base/Dinner/pasta.cook"),
+                    Utf8PathBuf::from("base/Lunch/Pasta.cook")
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn glob() {
+        let glob = RecipeGlob::new("soups/**/chicken*").unwrap();
+        let matches = |p: &str| glob.is_match(Utf8Path::new(p));
+        assert!(matches("soups/Chicken Noodle.cook"));
+        assert!(matches("Soups/Asian/chicken ramen.cook"));
+        assert!(!matches("Soups/Asian/Pork ramen.cook"));
+        assert!(!matches("Dinner/soups/chicken.cook"));
+        let glob = RecipeGlob::new("*/Pasta").unwrap();
+        assert!(glob.is_match(Utf8Path::new("Dinner/Pasta.cook")));
+        assert!(!glob.is_match(Utf8Path::new("Dinner/Italian/Pasta.cook")));
+        assert!(RecipeGlob::new("[a").is_err());
+    }
+
+    #[test]
+    fn lookup_case() {
+        let mut index = NameIndex::default();
+        index.insert("Pasta", Utf8Path::new("base/Pasta.cook"));
+        index.insert("pasta", Utf8Path::new("base/pasta.cook"));
+        index.insert("Soup", Utf8Path::new("base/Soup.cook"));
+
+        let get = |index: &NameIndex, name: &str| index.find(name).unwrap();
+        assert_eq!(get(&index, "Pasta"), "base/Pasta.cook");
+        assert_eq!(get(&index, "pasta"), "base/pasta.cook");
+        assert_eq!(get(&index, "PASTA"), "base/Pasta.cook");
+        assert_eq!(get(&index, "soup"), "base/Soup.cook");
+
+        index.remove("Pasta", Utf8Path::new("base/Pasta.cook"));
+        assert_eq!(get(&index, "Pasta"), "base/pasta.cook");
+    }
+
+    #[test]
+    fn lookup_normalized() {
+        let mut index = NameIndex::new(NameMatching::IgnoreAccents);
+        index.insert("Creme Brulee", Utf8Path::new("base/Creme Brulee.cook"));
+        index.insert("Straße", Utf8Path::new("base/Straße.cook"));
+        assert_eq!(
+            index.find("crème brûlée").unwrap(),
+            "base/Creme Brulee.cook"
+        );
+        assert_eq!(
+            index.find("CRÈME BRÛLÉE").unwrap(),
+            "base/Creme Brulee.cook"
+        );
+        assert_eq!(index.find("strasse").unwrap(), "base/Straße.cook");
+
+        let mut index = NameIndex::new(NameMatching::IgnoreCase);
+        index.insert("CRÈME BRÛLÉE", Utf8Path::new("base/CRÈME BRÛLÉE.cook"));
+        assert!(index.find("crème brûlée").is_ok());
+        assert!(index.find("creme brulee").is_err());
+    }
+
+    #[test]
+    fn lookup_ambiguous() {
+        let mut index = NameIndex::default();
+        index.insert("Pasta", Utf8Path::new("base/Dinner/Pasta.cook"));
+        index.insert("Pasta", Utf8Path::new("base/Lunch/Pasta.cook"));
+        index.insert("Pasta", Utf8Path::new("base/Lunch/Italian/Pasta.cook"));
+
+        let Err(Error::Ambiguous { candidates, .. }) = index.find("Pasta") else {
+            panic!("not ambiguous");
+        };
+        assert_eq!(
+            candidates,
+            ["base/Dinner/Pasta.cook", "base/Lunch/Pasta.cook"]
+        );
+        assert_eq!(index.find("Lunch/Pasta").unwrap(), "base/Lunch/Pasta.cook");
+        assert_eq!(index.get_all("Pasta", Utf8Path::new("Pasta")).len(), 3);
+
+        index.insert("Pasta", Utf8Path::new("base/Pasta.cook"));
+        assert_eq!(index.find("Pasta").unwrap(), "base/Pasta.cook");
+    }
+
+    #[test]
+    fn lookup_accents() {
+        // "Crème" decomposed, like macOS file names
+        let decomposed = "Cre\u{300}me Anglaise";
+        let mut index = NameIndex::new(NameMatching::IgnoreCase);
+        index.insert(decomposed, Utf8Path::new("base/Cre\u{300}me Anglaise.cook"));
+        assert!(index.find("crème anglaise").is_ok());
+        assert!(index.find("Creme Anglaise").is_err());
+
+        let mut index = NameIndex::new(NameMatching::IgnoreAccents);
+        index.insert("Creme Anglaise", Utf8Path::new("base/Creme Anglaise.cook"));
+        assert_eq!(
+            index.find("Crème Anglaise").unwrap(),
+            "base/Creme Anglaise.cook"
+        );
+        assert!(index.find("base/CRÈME anglaise").is_ok());
+    }
+
+    #[test]
+    fn aliases() {
+        let mut index = NameIndex::default();
+        index.insert("Tomato", Utf8Path::new("base/Sauces/Tomato.cook"));
+        index.insert("Pesto", Utf8Path::new("base/Pesto.cook"));
+        index.add_alias("Grandma's sauce", Utf8Path::new("base/Sauces/Tomato.cook"));
+        index.add_alias("Pesto", Utf8Path::new("base/Sauces/Tomato.cook"));
+
+        assert_eq!(
+            index.find("grandma's sauce").unwrap(),
+            "base/Sauces/Tomato.cook"
+        );
+        // recipes go first
+        assert_eq!(index.find("Pesto").unwrap(), "base/Pesto.cook");
+        assert_eq!(
+            index.aliases_of(Utf8Path::new("base/Sauces/Tomato.cook")),
+            ["Grandma's sauce", "Pesto"]
+        );
+    }
+
+    #[test]
+    fn fuzzy_search() {
+        let mut index = NameIndex::default();
+        for name in [
+            "Broccoli Soup",
+            "Broccoli",
+            "Tomato Soup",
+            "Soupe à l'oignon",
+        ] {
+            index.insert(name, &Utf8Path::new("base").join(format!("{name}.cook")));
+        }
+        let search = |query: &str| {
+            index
+                .search(query)
+                .into_iter()
+                .map(|p| p.file_stem().unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(search("brocolli sou"), ["Broccoli Soup"]);
+        assert_eq!(search("broccoli"), ["Broccoli", "Broccoli Soup"]);
+        assert_eq!(
+            search("soup"),
+            ["Soupe à l'oignon", "Broccoli Soup", "Tomato Soup"]
+        );
+        assert_eq!(search("Dinner/tomato.cook"), ["Tomato Soup"]);
+        assert!(search("pasta").is_empty());
+        assert!(search("  ").is_empty());
+    }
+
+    #[test]
+    fn windows_separators() {
+        let (name, path) = into_name_path("Dinner\\Pasta").unwrap();
+        assert_eq!(name, "Pasta");
+        assert_eq!(path, "Dinner/Pasta");
+    }
+
+    #[test]
+    fn image_names() {
+        let image = |name: &str| Image::from_path("Pasta", Utf8PathBuf::from(name));
+        assert_eq!(image("Pasta.jpg").unwrap().indexes, None);
+        assert_eq!(image("Pasta.2.png").unwrap().step(), Some(2));
+        let img = image("Pasta.1.3.webp").unwrap();
+        assert_eq!((img.section(), img.step()), (Some(1), Some(3)));
+        assert!(image("Pasta.txt").is_none());
+        assert!(image("Soup.jpg").is_none());
+    }
+
+    #[test]
+    fn relative_paths_stay_inside() {
+        let base = Utf8Path::new("");
+        assert!(recipe_path("../Soup", None, base).is_err());
+        assert_eq!(
+            recipe_path("../Soup", Some(Utf8Path::new("Dinner")), base).unwrap(),
+            "Soup.cook"
+        );
+    }
+}
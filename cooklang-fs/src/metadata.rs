@@ -0,0 +1,200 @@
+//! Cache of the metadata of the recipes
+//!
+//! Filtering a collection by tag needs the metadata of every recipe. Parsing
+//! it is cheap for one recipe but adds up in large collections, so
+//! [`MetadataIndex`] keeps it in a file and only parses a recipe again when
+//! its modification time or size changed.
+
+use std::{borrow::Cow, collections::HashMap, time::SystemTime};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use cooklang::{metadata::RecipeTime, CooklangParser, Metadata};
+use serde::{Deserialize, Serialize};
+
+use crate::{safe_write, Error, RecipeEntry, WriteOptions};
+
+/// Metadata of a recipe that is cached
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecipeMetadata {
+    pub tags: Vec<String>,
+    pub servings: Option<Vec<u32>>,
+    /// Minutes
+    pub prep_time: Option<u32>,
+    /// Minutes
+    pub cook_time: Option<u32>,
+    /// Minutes, the sum of the others if they are given instead
+    pub total_time: Option<u32>,
+    /// Every entry, as written
+    pub map: HashMap<String, String>,
+}
+
+impl RecipeMetadata {
+    pub fn new(meta: &Metadata) -> Self {
+        let (prep_time, cook_time) = match meta.time() {
+            Some(RecipeTime::Composed {
+                prep_time,
+                cook_time,
+            }) => (*prep_time, *cook_time),
+            _ => (None, None),
+        };
+        Self {
+            tags: meta.tags().unwrap_or(&[]).to_vec(),
+            servings: meta.servings().map(<[u32]>::to_vec),
+            prep_time,
+            cook_time,
+            total_time: meta.time().map(RecipeTime::total),
+            map: meta
+                .map
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        }
+    }
+}
+
+/// Metadata of the recipes of a collection, stored in a file
+///
+/// The paths are relative to the base path, so the file can be used from
+/// anywhere.
+#[derive(Debug)]
+pub struct MetadataIndex {
+    base_path: Utf8PathBuf,
+    entries: HashMap<Utf8PathBuf, Cached>,
+    changed: bool,
+}
+
+/// Format of the file written by [`MetadataIndex::save`]
+#[derive(Serialize, Deserialize)]
+struct StoredMetadata<'a> {
+    version: u32,
+    entries: Cow<'a, HashMap<Utf8PathBuf, Cached>>,
+}
+
+impl StoredMetadata<'_> {
+    const VERSION: u32 = 1;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Cached {
+    modified: SystemTime,
+    len: u64,
+    /// `None` if the metadata can't be parsed
+    meta: Option<RecipeMetadata>,
+}
+
+impl MetadataIndex {
+    /// Empty index for the collection at `base_path`
+    pub fn new(base_path: impl Into<Utf8PathBuf>) -> Self {
+        Self {
+            base_path: base_path.into(),
+            entries: HashMap::new(),
+            changed: false,
+        }
+    }
+
+    /// Loads the index stored with [`Self::save`]
+    ///
+    /// It's only a cache, so a missing or invalid file gives an empty index.
+    pub fn load(base_path: impl Into<Utf8PathBuf>, file: &Utf8Path) -> Self {
+        let mut index = Self::new(base_path);
+        let content = match std::fs::read(file) {
+            Ok(content) => content,
+            Err(e) => {
+                tracing::debug!("can't read metadata cache: {e}");
+                return index;
+            }
+        };
+        match serde_json::from_slice::<StoredMetadata>(&content) {
+            Ok(stored) if stored.version == StoredMetadata::VERSION => {
+                index.entries = stored.entries.into_owned();
+            }
+            Ok(_) => tracing::debug!("unsupported metadata cache version"),
+            Err(e) => tracing::debug!("invalid metadata cache: {e}"),
+        }
+        index
+    }
+
+    /// Metadata of a recipe, parsing it if it changed
+    ///
+    /// Returns `None` when the metadata can't be parsed.
+    pub fn get(
+        &mut self,
+        entry: &RecipeEntry,
+        parser: &CooklangParser,
+    ) -> Result<Option<&RecipeMetadata>, Error> {
+        let fs_meta = entry.path().metadata()?;
+        let modified = fs_meta.modified()?;
+        let len = fs_meta.len();
+        let key = self.key(entry.path());
+
+        let fresh = self
+            .entries
+            .get(&key)
+            .is_some_and(|c| c.modified == modified && c.len == len);
+        if !fresh {
+            let meta = entry
+                .read()?
+                .metadata(parser)
+                .into_output()
+                .map(|m| RecipeMetadata::new(&m));
+            self.entries.insert(
+                key.clone(),
+                Cached {
+                    modified,
+                    len,
+                    meta,
+                },
+            );
+            self.changed = true;
+        }
+        Ok(self.entries[&key].meta.as_ref())
+    }
+
+    /// Forgets the recipes that were removed from the disk
+    pub fn retain_existing(&mut self) {
+        let before = self.entries.len();
+        let base_path = &self.base_path;
+        self.entries
+            .retain(|path, _| base_path.join(path).is_file());
+        self.changed |= self.entries.len() != before;
+    }
+
+    /// Stores the index in a file, if anything changed since it was loaded
+    pub fn save(&mut self, file: &Utf8Path) -> Result<(), Error> {
+        if !self.changed {
+            return Ok(());
+        }
+        let stored = StoredMetadata {
+            version: StoredMetadata::VERSION,
+            entries: Cow::Borrowed(&self.entries),
+        };
+        let content = serde_json::to_vec(&stored).map_err(Error::IndexFile)?;
+        safe_write(file, content, WriteOptions::default())?;
+        self.changed = false;
+        Ok(())
+    }
+
+    fn key(&self, path: &Utf8Path) -> Utf8PathBuf {
+        path.strip_prefix(&self.base_path)
+            .unwrap_or(path)
+            .to_path_buf()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata() {
+        let parser = CooklangParser::new(cooklang::Extensions::all(), cooklang::Converter::empty());
+        let recipe = parser
+            .parse_metadata(">> tags: dinner, quick\n>> servings: 2|4\n")
+            .into_output()
+            .unwrap();
+        let meta = RecipeMetadata::new(&recipe);
+        assert_eq!(meta.tags, ["dinner", "quick"]);
+        assert_eq!(meta.servings, Some(vec![2, 4]));
+        assert_eq!(meta.map["servings"], "2|4");
+    }
+}
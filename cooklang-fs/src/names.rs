@@ -0,0 +1,168 @@
+//! Validation of file names created by chef
+//!
+//! Collections are usually synced between different operating systems, so
+//! names have to be valid in all of them, not just the current one. The most
+//! restrictive is Windows.
+
+use std::borrow::Cow;
+
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::Error;
+
+/// Names that can't be used for a file in Windows, even with an extension
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM0", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7",
+    "COM8", "COM9", "COM¹", "COM²", "COM³", "LPT0", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6",
+    "LPT7", "LPT8", "LPT9", "LPT¹", "LPT²", "LPT³",
+];
+
+const INVALID_CHARS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Max length in bytes of a single component in most file systems
+const MAX_COMPONENT_LEN: usize = 255;
+
+/// Checks that a single file or dir name is valid in every platform
+pub fn check_file_name(name: &str) -> Result<(), Error> {
+    let err = |reason| {
+        Err(Error::UnsafeName {
+            name: name.to_string(),
+            reason,
+        })
+    };
+
+    if name.is_empty() {
+        return err("empty name");
+    }
+    if name == "." || name == ".." {
+        return err("relative component");
+    }
+    if name.len() > MAX_COMPONENT_LEN {
+        return err("name too long");
+    }
+    if name
+        .chars()
+        .any(|c| c.is_control() || INVALID_CHARS.contains(&c))
+    {
+        return err("invalid character");
+    }
+    if name.ends_with(['.', ' ']) {
+        return err("ends with a dot or space");
+    }
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+    if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(stem)) {
+        return err("reserved name in Windows");
+    }
+    Ok(())
+}
+
+/// Checks a relative path made of `/` or `\` separated names
+///
+/// Every component is checked with [`check_file_name`].
+pub fn check_relative_path(path: &str) -> Result<(), Error> {
+    if path.starts_with(['/', '\\']) {
+        return Err(Error::UnsafeName {
+            name: path.to_string(),
+            reason: "absolute path",
+        });
+    }
+    path.split(['/', '\\']).try_for_each(check_file_name)
+}
+
+/// Use `/` as the separator in recipe references
+///
+/// References written in Windows may use `\`, which is not a separator in
+/// other platforms.
+pub fn normalize_separators(reference: &str) -> Cow<'_, str> {
+    if reference.contains('\\') {
+        Cow::Owned(reference.replace('\\', "/"))
+    } else {
+        Cow::Borrowed(reference)
+    }
+}
+
+/// Lowercase that also folds the letters without a single lowercase form,
+/// `Straße` and `STRASSE` become `strasse`
+///
+/// It's not the full Unicode case folding, only the letters used in recipe
+/// names that [`str::to_lowercase`] misses.
+pub fn fold_case(text: &str) -> String {
+    let lower = text.to_lowercase();
+    if lower.is_ascii() {
+        return lower;
+    }
+    let mut folded = String::with_capacity(lower.len());
+    for c in lower.chars() {
+        match c {
+            'ß' => folded.push_str("ss"),
+            'ς' => folded.push('σ'),
+            'ſ' => folded.push('s'),
+            'ﬀ' => folded.push_str("ff"),
+            'ﬁ' => folded.push_str("fi"),
+            'ﬂ' => folded.push_str("fl"),
+            _ => folded.push(c),
+        }
+    }
+    folded
+}
+
+/// Removes the accents of a text, `Crème` becomes `Creme`
+///
+/// The text is decomposed and the combining marks dropped, so only letters
+/// with a base letter lose the accent. Others, like `ø`, are kept.
+pub fn strip_accents(text: &str) -> Cow<'_, str> {
+    if text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(
+        text.nfd()
+            .filter(|c| !is_combining_mark(*c))
+            .nfc()
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_names() {
+        assert!(check_file_name("Pasta.cook").is_ok());
+        assert!(check_file_name("Tarta de queso").is_ok());
+        assert!(check_file_name("console.cook").is_ok());
+        assert!(check_file_name("").is_err());
+        assert!(check_file_name("..").is_err());
+        assert!(check_file_name("CON").is_err());
+        assert!(check_file_name("con.cook").is_err());
+        assert!(check_file_name("Lpt1.tar.gz").is_err());
+        assert!(check_file_name("COM0").is_err());
+        assert!(check_file_name("lpt0.cook").is_err());
+        assert!(check_file_name("com¹.cook").is_err());
+        assert!(check_file_name("LPT³").is_err());
+        assert!(check_file_name("COM10.cook").is_ok());
+        assert!(check_file_name("what?.cook").is_err());
+        assert!(check_file_name("trailing.").is_err());
+        assert!(check_file_name("trailing ").is_err());
+        assert!(check_file_name(&"a".repeat(256)).is_err());
+    }
+
+    #[test]
+    fn relative_paths() {
+        assert!(check_relative_path("Dinner/Pasta").is_ok());
+        assert!(check_relative_path("Dinner\\Pasta").is_ok());
+        assert!(check_relative_path("/Pasta").is_err());
+        assert!(check_relative_path("Dinner//Pasta").is_err());
+        assert!(check_relative_path("../Pasta").is_err());
+        assert!(check_relative_path("aux/Pasta").is_err());
+        assert_eq!(normalize_separators("Dinner\\Pasta"), "Dinner/Pasta");
+    }
+
+    #[test]
+    fn case_folding() {
+        assert_eq!(fold_case("Pasta"), "pasta");
+        assert_eq!(fold_case("Straße"), fold_case("STRASSE"));
+        assert_eq!(fold_case("ΟΔΟΣ"), fold_case("οδοσ"));
+        assert_eq!(fold_case("CRÈME BRÛLÉE"), "crème brûlée");
+    }
+}
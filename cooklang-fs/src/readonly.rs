@@ -0,0 +1,82 @@
+//! Recipes that chef must not change
+//!
+//! A recipe is read-only if its metadata has `readonly: true` or any of its
+//! dirs has a [`READONLY_FILE`], like recipes installed from somewhere else
+//! that would be overwritten on update. Writes through this crate refuse to
+//! change them unless forced.
+
+use std::fs;
+
+use camino::Utf8Path;
+
+use crate::{Error, MARKDOWN_EXTENSION};
+
+/// File that makes every recipe in its dir and subdirs read-only
+pub const READONLY_FILE: &str = ".readonly";
+
+/// Metadata key that makes a recipe read-only
+pub const READONLY_KEY: &str = "readonly";
+
+/// Checks if a recipe, existing or not, is read-only
+pub fn is_readonly(path: &Utf8Path) -> bool {
+    in_readonly_dir(path) || fs::read_to_string(path).is_ok_and(|text| readonly_in_metadata(&text))
+}
+
+/// Fails with [`Error::ReadOnly`] when writing to `path` is not allowed
+///
+/// Only recipe files are checked.
+pub(crate) fn check_writable(path: &Utf8Path, force: bool) -> Result<(), Error> {
+    let is_recipe =
+        matches!(path.extension(), Some(ext) if ext == "cook" || ext == MARKDOWN_EXTENSION);
+    if !force && is_recipe && is_readonly(path) {
+        return Err(Error::ReadOnly(path.to_string()));
+    }
+    Ok(())
+}
+
+fn in_readonly_dir(path: &Utf8Path) -> bool {
+    path.ancestors()
+        .skip(1)
+        .filter(|dir| !dir.as_str().is_empty())
+        .any(|dir| dir.join(READONLY_FILE).is_file())
+}
+
+/// Looks for the key in `>>` lines and the YAML front matter, without parsing
+/// the recipe
+fn readonly_in_metadata(text: &str) -> bool {
+    let is_true = |line: &str| {
+        line.split_once(':').is_some_and(|(key, value)| {
+            key.trim() == READONLY_KEY && matches!(value.trim(), "true" | "yes")
+        })
+    };
+    let mut lines = text.lines();
+    if text.starts_with("---") {
+        for line in lines.by_ref().skip(1) {
+            if line.trim_end() == "---" {
+                break;
+            }
+            if is_true(line) {
+                return true;
+            }
+        }
+    }
+    lines.any(|line| line.trim_start().strip_prefix(">>").is_some_and(is_true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata() {
+        assert!(readonly_in_metadata(">> readonly: true\nBoil @water."));
+        assert!(readonly_in_metadata(
+            "---\ntitle: Soup\nreadonly: yes\n---\n"
+        ));
+        assert!(!readonly_in_metadata(">> readonly: false\n"));
+        assert!(!readonly_in_metadata("readonly: true\n"));
+        assert!(!readonly_in_metadata(
+            "---\ntitle: Soup\n---\nreadonly: true\n"
+        ));
+    }
+}
@@ -0,0 +1,89 @@
+//! Moving recipes with their images and sidecar files
+
+use std::fs;
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{readonly::check_writable, recipe_images, recipe_sidecars, Error};
+
+/// Moves a recipe file, its images and sidecars, see [`crate::FsIndex::move_recipe`]
+///
+/// Nothing is moved if any destination already exists or the recipe is
+/// read-only. If a move fails, the files already moved are moved back.
+pub(crate) fn move_recipe_files(from: &Utf8Path, to: &Utf8Path) -> Result<(), Error> {
+    check_writable(from, false)?;
+    check_writable(to, false)?;
+    let mut moves = vec![(from.to_path_buf(), to.to_path_buf())];
+    for image in recipe_images(from) {
+        let Some(dest) = image_dest(&image.path, from, to) else {
+            continue;
+        };
+        moves.push((image.path, dest));
+    }
+    for sidecar in recipe_sidecars(from) {
+        let Some(dest) = image_dest(&sidecar.path, from, to) else {
+            continue;
+        };
+        moves.push((sidecar.path, dest));
+    }
+
+    // only changing the case in a case insensitive file system is the same file
+    let taken = |src: &Utf8Path, dest: &Utf8Path| {
+        dest.exists() && dest.canonicalize_utf8().ok() != src.canonicalize_utf8().ok()
+    };
+    if let Some((_, dest)) = moves.iter().find(|(src, dest)| taken(src, dest)) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("'{dest}' already exists"),
+        )
+        .into());
+    }
+
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    for (done, (src, dest)) in moves.iter().enumerate() {
+        if let Err(e) = fs::rename(src, dest) {
+            for (src, dest) in moves[..done].iter().rev() {
+                if let Err(e) = fs::rename(dest, src) {
+                    tracing::error!("Can't move '{dest}' back to '{src}': {e}");
+                }
+            }
+            return Err(e.into());
+        }
+    }
+    Ok(())
+}
+
+/// Path of an image or sidecar of the recipe `from` when the recipe is moved
+/// to `to`
+///
+/// The name changes and the indexes and extension are kept, so
+/// `Dinner/Pasta.1.jpeg` becomes `Lunch/Noodles.1.jpeg`.
+fn image_dest(image: &Utf8Path, from: &Utf8Path, to: &Utf8Path) -> Option<Utf8PathBuf> {
+    let suffix = image.file_name()?.strip_prefix(from.file_stem()?)?;
+    Some(to.with_file_name(format!("{}{suffix}", to.file_stem()?)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_names() {
+        let dest = |image: &str| {
+            image_dest(
+                Utf8Path::new(image),
+                Utf8Path::new("base/Dinner/Pasta.cook"),
+                Utf8Path::new("base/Lunch/Noodles.cook"),
+            )
+            .unwrap()
+        };
+        assert_eq!(dest("base/Dinner/Pasta.jpeg"), "base/Lunch/Noodles.jpeg");
+        assert_eq!(dest("base/Dinner/Pasta.3.png"), "base/Lunch/Noodles.3.png");
+        assert_eq!(
+            dest("base/Dinner/Pasta.1.3.webp"),
+            "base/Lunch/Noodles.1.3.webp"
+        );
+    }
+}
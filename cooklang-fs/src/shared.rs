@@ -0,0 +1,134 @@
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use camino::Utf8Path;
+
+use crate::{index_fresh, Error, FsIndex, OnCollision, RecipeEntry, WriteOptions};
+
+/// A [`FsIndex`] that can be cloned and updated from many threads
+///
+/// [`FsIndex`] is already `Send + Sync`, this is for when it also has to be
+/// updated while shared, like in a server. Clones point to the same index.
+///
+/// Lookups take a read lock, so they don't block each other. [`Self::refresh`]
+/// walks the directory without the lock and only takes it to swap the result.
+#[derive(Debug, Clone)]
+pub struct SharedFsIndex {
+    inner: Arc<RwLock<FsIndex>>,
+}
+
+impl From<FsIndex> for SharedFsIndex {
+    fn from(index: FsIndex) -> Self {
+        Self::new(index)
+    }
+}
+
+impl SharedFsIndex {
+    pub fn new(index: FsIndex) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(index)),
+        }
+    }
+
+    /// Locks the index to use the rest of the [`FsIndex`] methods
+    pub fn read(&self) -> RwLockReadGuard<'_, FsIndex> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Locks the index to modify it
+    pub fn write(&self) -> RwLockWriteGuard<'_, FsIndex> {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// See [`FsIndex::resolve`]
+    pub fn resolve(
+        &self,
+        recipe: &str,
+        relative_to: Option<&Utf8Path>,
+    ) -> Result<RecipeEntry, Error> {
+        self.read().resolve(recipe, relative_to)
+    }
+
+    /// See [`FsIndex::resolve_relative`]
+    pub fn resolve_relative(
+        &self,
+        base_recipe: &Utf8Path,
+        reference: &str,
+    ) -> Result<RecipeEntry, Error> {
+        self.read().resolve_relative(base_recipe, reference)
+    }
+
+    /// See [`FsIndex::get`]
+    pub fn get(&self, recipe: &str) -> Result<RecipeEntry, Error> {
+        self.read().get(recipe)
+    }
+
+    /// See [`FsIndex::get_all`]
+    pub fn get_all(&self, recipe: &str) -> Vec<RecipeEntry> {
+        self.read().get_all(recipe)
+    }
+
+    /// See [`FsIndex::glob`]
+    pub fn glob(&self, pattern: &str) -> Result<Vec<RecipeEntry>, Error> {
+        self.read().glob(pattern)
+    }
+
+    /// See [`FsIndex::contains`]
+    pub fn contains(&self, recipe: &str) -> bool {
+        self.read().contains(recipe)
+    }
+
+    /// See [`FsIndex::insert`]
+    pub fn insert(&self, path: &Utf8Path) -> Result<(), Error> {
+        self.write().insert(path)
+    }
+
+    /// See [`FsIndex::remove`]
+    pub fn remove(&self, path: &Utf8Path) -> Result<(), Error> {
+        self.write().remove(path)
+    }
+
+    /// See [`FsIndex::create`]
+    pub fn create(
+        &self,
+        name: &str,
+        content: &str,
+        on_collision: OnCollision,
+        options: WriteOptions,
+    ) -> Result<RecipeEntry, Error> {
+        self.write().create(name, content, on_collision, options)
+    }
+
+    /// See [`FsIndex::move_recipe`]
+    pub fn move_recipe(&self, from: &Utf8Path, to: &Utf8Path) -> Result<RecipeEntry, Error> {
+        self.write().move_recipe(from, to)
+    }
+
+    /// Walks the directory again, replacing the current index
+    ///
+    /// Unlike [`FsIndex::refresh`], the index can still be used while
+    /// walking. If walking fails, the current index is kept.
+    #[tracing::instrument(level = "debug", skip(self), err)]
+    pub fn refresh(&self) -> Result<(), Error> {
+        let (mut cache, walker) = {
+            let index = self.read();
+            (index.cache.emptied(), index.walker.clone())
+        };
+        let dirs = index_fresh(&mut cache, &walker)?;
+        let mut index = self.write();
+        index.cache = cache;
+        index.dirs = Some(dirs);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<FsIndex>();
+        assert_send_sync::<SharedFsIndex>();
+    }
+}
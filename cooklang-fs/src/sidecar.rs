@@ -0,0 +1,148 @@
+//! Files with extra data of a recipe
+//!
+//! A sidecar is a file next to the recipe named `<recipe>.<kind>.<ext>`, like
+//! `Pasta.nutrition.toml` or `Pasta.notes.md`. The kind can't be a number, so
+//! step images are not sidecars. They are moved with the recipe.
+//!
+//! Some kinds have a known format, see [`RecipeEntry::nutrition`] and
+//! [`RecipeEntry::notes`]. Any other kind is left to the application.
+//!
+//! [`RecipeEntry::nutrition`]: crate::RecipeEntry::nutrition
+//! [`RecipeEntry::notes`]: crate::RecipeEntry::notes
+
+use std::collections::BTreeMap;
+
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, IMAGE_EXTENSIONS};
+
+/// Kind of the nutrition facts sidecar, a TOML file
+pub const NUTRITION_KIND: &str = "nutrition";
+/// Kind of the notes sidecar, a Markdown file
+pub const NOTES_KIND: &str = "notes";
+
+/// A file with extra data of a recipe
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Sidecar {
+    pub kind: String,
+    pub path: Utf8PathBuf,
+}
+
+impl Sidecar {
+    /// Checks if a file is a sidecar of a recipe
+    pub fn from_path(recipe_name: &str, path: Utf8PathBuf) -> Option<Self> {
+        let rest = path
+            .file_name()?
+            .strip_prefix(recipe_name)?
+            .strip_prefix('.')?;
+        let (kind, ext) = rest.split_once('.')?;
+        if kind.is_empty()
+            || ext.is_empty()
+            || ext.contains('.')
+            || kind.bytes().all(|b| b.is_ascii_digit())
+            || !kind
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_'))
+            || IMAGE_EXTENSIONS.contains(&ext)
+        {
+            return None;
+        }
+        Some(Self {
+            kind: kind.to_string(),
+            path,
+        })
+    }
+
+    pub fn extension(&self) -> &str {
+        self.path.extension().unwrap_or_default()
+    }
+}
+
+/// Nutrition facts, from a `<recipe>.nutrition.toml` sidecar
+///
+/// ```toml
+/// per = "1 serving"
+/// calories = 520
+/// protein = 18.5
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Nutrition {
+    /// What the values are for, like `100 g` or `1 serving`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per: Option<String>,
+    /// Kilocalories
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub calories: Option<f64>,
+    /// Every other value, in grams, like `fat` or `protein`
+    #[serde(flatten)]
+    pub values: BTreeMap<String, f64>,
+}
+
+/// Finds the sidecars of a recipe, sorted by kind
+pub fn recipe_sidecars(path: &Utf8Path) -> Vec<Sidecar> {
+    let Some(dir) = path.parent().and_then(|dir| dir.read_dir_utf8().ok()) else {
+        return vec![];
+    };
+    let Some(recipe_name) = path.file_stem() else {
+        return vec![];
+    };
+    let mut sidecars = dir
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+        .filter_map(|e| Sidecar::from_path(recipe_name, e.path().to_path_buf()))
+        .collect::<Vec<_>>();
+    sidecars.sort_unstable();
+    sidecars
+}
+
+/// Reads a sidecar of a kind, `None` if it doesn't exist
+pub(crate) fn read_sidecar(
+    recipe: &Utf8Path,
+    kind: &str,
+    ext: &str,
+) -> Result<Option<(Utf8PathBuf, String)>, Error> {
+    let Some(name) = recipe.file_stem() else {
+        return Ok(None);
+    };
+    let path = recipe.with_file_name(format!("{name}.{kind}.{ext}"));
+    match std::fs::read_to_string(&path) {
+        Ok(content) => Ok(Some((path, content))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub(crate) fn parse_nutrition(path: Utf8PathBuf, content: &str) -> Result<Nutrition, Error> {
+    toml::from_str(content).map_err(|source| Error::Sidecar { path, source })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sidecar_names() {
+        let kind = |file: &str| Sidecar::from_path("Pasta", file.into()).map(|s| s.kind);
+        assert_eq!(kind("Pasta.nutrition.toml").as_deref(), Some("nutrition"));
+        assert_eq!(kind("Pasta.notes.md").as_deref(), Some("notes"));
+        assert_eq!(kind("Pasta.cook"), None);
+        assert_eq!(kind("Pasta.1.txt"), None);
+        assert_eq!(kind("Pasta.cover.jpg"), None);
+        assert_eq!(kind("Pasta.a.b.toml"), None);
+        assert_eq!(kind("Pastas.notes.md"), None);
+    }
+
+    #[test]
+    fn nutrition() {
+        let nutrition = parse_nutrition(
+            "Pasta.nutrition.toml".into(),
+            "per = \"1 serving\"\ncalories = 520\nprotein = 18.5\n",
+        )
+        .unwrap();
+        assert_eq!(nutrition.per.as_deref(), Some("1 serving"));
+        assert_eq!(nutrition.calories, Some(520.0));
+        assert_eq!(nutrition.values["protein"], 18.5);
+        assert!(parse_nutrition("x".into(), "fat = \"a lot\"").is_err());
+    }
+}
@@ -0,0 +1,86 @@
+//! Counts of what is in a collection
+
+use std::collections::{BTreeMap, HashMap};
+
+use camino::Utf8PathBuf;
+use cooklang::CooklangParser;
+use serde::Serialize;
+
+use crate::{images::image_recipe, walker::Walker, Error, MetadataIndex, RecipeEntry};
+
+/// Statistics of a collection, from [`FsIndexBuilder::stats`]
+///
+/// [`FsIndexBuilder::stats`]: crate::FsIndexBuilder::stats
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CollectionStats {
+    pub recipes: usize,
+    /// Dirs inside the base path, not counting it
+    pub dirs: usize,
+    pub images: usize,
+    /// Bytes of the recipes and images
+    pub size: u64,
+    /// Images that don't belong to any recipe, prefixed with the base path
+    pub orphan_images: Vec<Utf8PathBuf>,
+    /// Recipes with each tag, empty unless the metadata is parsed with
+    /// [`FsIndexBuilder::stats_with_tags`]
+    ///
+    /// [`FsIndexBuilder::stats_with_tags`]: crate::FsIndexBuilder::stats_with_tags
+    pub tags: BTreeMap<String, usize>,
+    /// Recipes without tags, only counted with the metadata
+    pub untagged: usize,
+}
+
+pub(crate) fn collect(
+    walker: &Walker,
+    metadata: Option<(&mut MetadataIndex, &CooklangParser)>,
+) -> Result<CollectionStats, Error> {
+    let mut stats = CollectionStats::default();
+    let mut recipes = Vec::new();
+    let mut images = Vec::new();
+    walker.clear_skipped();
+    for entries in walker.walk_par(crate::walker::default_threads()) {
+        for entry in entries? {
+            if entry.file_type().is_dir() {
+                stats.dirs += 1;
+            } else if entry.is_recipe_file() {
+                recipes.push(entry.into_path());
+            } else if entry.is_image() {
+                images.push(entry.into_path());
+            }
+        }
+    }
+
+    stats.recipes = recipes.len();
+    stats.images = images.len();
+    for path in recipes.iter().chain(&images) {
+        // it may be removed while walking
+        stats.size += path.metadata().map(|m| m.len()).unwrap_or(0);
+    }
+
+    let names = recipes
+        .iter()
+        .map(|p| (p.with_extension(""), p.clone()))
+        .collect::<HashMap<_, _>>();
+    stats.orphan_images = images
+        .into_iter()
+        .filter(|image| image_recipe(image, &names).is_none())
+        .collect();
+    stats.orphan_images.sort_unstable();
+
+    if let Some((cache, parser)) = metadata {
+        for path in &recipes {
+            let entry = RecipeEntry::new(path);
+            match cache.get(&entry, parser) {
+                Ok(Some(meta)) if meta.tags.is_empty() => stats.untagged += 1,
+                Ok(Some(meta)) => {
+                    for tag in &meta.tags {
+                        *stats.tags.entry(tag.clone()).or_default() += 1;
+                    }
+                }
+                Ok(None) => tracing::debug!("invalid metadata in '{path}'"),
+                Err(e) => tracing::warn!("Can't read the metadata of '{path}': {e}"),
+            }
+        }
+    }
+    Ok(stats)
+}
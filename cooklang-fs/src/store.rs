@@ -0,0 +1,353 @@
+//! Abstraction over where the recipes are stored
+//!
+//! [`StoreIndex`] uses the [`matching`](crate::matching) logic with any
+//! [`RecipeStore`], so the same lookups work with the file system
+//! ([`FsStore`]), memory ([`InMemoryStore`]) or other sources.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{
+    matching::{recipe_path, NameIndex, NameMatching},
+    recipe_images,
+    walker::Walker,
+    Collation, Encoding, Error, Image, NonUtf8Policy, RecipeContent, RecipeEntry,
+};
+
+/// Source of recipes and their images
+///
+/// Paths are relative to [`Self::base_path`] and include it, like the paths
+/// of the [`FsIndex`](crate::FsIndex).
+pub trait RecipeStore {
+    /// Path every recipe path starts with
+    fn base_path(&self) -> &Utf8Path;
+
+    /// Paths of every recipe, with the `.cook` extension
+    fn recipe_paths(&self) -> Result<Vec<Utf8PathBuf>, Error>;
+
+    /// Reads the text of a recipe
+    fn read(&self, path: &Utf8Path) -> Result<RecipeContent, Error>;
+
+    /// Images of a recipe, sorted
+    fn images(&self, path: &Utf8Path) -> Result<Vec<Image>, Error>;
+}
+
+/// Recipes in a directory of the file system
+#[derive(Debug, Clone)]
+pub struct FsStore {
+    base_path: Utf8PathBuf,
+    walker: Walker,
+}
+
+impl FsStore {
+    pub fn new(base_path: impl AsRef<Utf8Path>, max_depth: usize) -> Self {
+        let base_path = base_path.as_ref().to_path_buf();
+        let walker = Walker::new(&base_path, max_depth);
+        Self { base_path, walker }
+    }
+
+    /// Sets a config dir, see [`FsIndexBuilder::config_dir`](crate::FsIndexBuilder::config_dir)
+    pub fn config_dir(mut self, dir: String) -> Self {
+        self.walker.set_config_dir(dir);
+        self
+    }
+
+    /// Ignores a path, see [`FsIndexBuilder::ignore`](crate::FsIndexBuilder::ignore)
+    pub fn ignore(mut self, path: String) -> Self {
+        self.walker.ignore(path);
+        self
+    }
+
+    /// Sets what to do with non UTF-8 paths
+    pub fn non_utf8(mut self, policy: NonUtf8Policy) -> Self {
+        self.walker.set_non_utf8_policy(policy);
+        self
+    }
+}
+
+impl RecipeStore for FsStore {
+    fn base_path(&self) -> &Utf8Path {
+        &self.base_path
+    }
+
+    fn recipe_paths(&self) -> Result<Vec<Utf8PathBuf>, Error> {
+        let mut paths = Vec::new();
+        for entry in self.walker.restart() {
+            let entry = entry?;
+            if entry.is_cooklang_file() {
+                paths.push(entry.into_path());
+            }
+        }
+        Ok(paths)
+    }
+
+    fn read(&self, path: &Utf8Path) -> Result<RecipeContent, Error> {
+        Ok(RecipeEntry::new(path).read()?)
+    }
+
+    fn images(&self, path: &Utf8Path) -> Result<Vec<Image>, Error> {
+        Ok(recipe_images(path))
+    }
+}
+
+/// Recipes and images kept in memory
+///
+/// Useful to embed a collection in an application or to test code that uses
+/// a [`StoreIndex`] without touching the disk. Paths are given relative to
+/// the base path.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryStore {
+    base_path: Utf8PathBuf,
+    recipes: BTreeMap<Utf8PathBuf, String>,
+    images: BTreeSet<Utf8PathBuf>,
+}
+
+impl InMemoryStore {
+    pub fn new(base_path: impl AsRef<Utf8Path>) -> Self {
+        Self {
+            base_path: base_path.as_ref().to_path_buf(),
+            ..Default::default()
+        }
+    }
+
+    /// Adds a recipe, see [`Self::insert_recipe`]
+    pub fn with_recipe(mut self, path: impl AsRef<Utf8Path>, text: impl Into<String>) -> Self {
+        self.insert_recipe(path, text);
+        self
+    }
+
+    /// Adds an image, see [`Self::insert_image`]
+    pub fn with_image(mut self, path: impl AsRef<Utf8Path>) -> Self {
+        self.insert_image(path);
+        self
+    }
+
+    /// Adds or replaces a recipe
+    ///
+    /// The `.cook` extension is added to the path. Returns the full path.
+    pub fn insert_recipe(
+        &mut self,
+        path: impl AsRef<Utf8Path>,
+        text: impl Into<String>,
+    ) -> Utf8PathBuf {
+        let path = self.base_path.join(path.as_ref().with_extension("cook"));
+        self.recipes.insert(path.clone(), text.into());
+        path
+    }
+
+    /// Adds an image file
+    ///
+    /// It's only listed as an image of a recipe if the name matches, like in
+    /// the file system. Returns the full path.
+    pub fn insert_image(&mut self, path: impl AsRef<Utf8Path>) -> Utf8PathBuf {
+        let path = self.base_path.join(path);
+        self.images.insert(path.clone());
+        path
+    }
+
+    /// Removes a recipe or an image from its full path
+    pub fn remove(&mut self, path: &Utf8Path) -> bool {
+        self.recipes.remove(path).is_some() || self.images.remove(path)
+    }
+}
+
+impl RecipeStore for InMemoryStore {
+    fn base_path(&self) -> &Utf8Path {
+        &self.base_path
+    }
+
+    fn recipe_paths(&self) -> Result<Vec<Utf8PathBuf>, Error> {
+        Ok(self.recipes.keys().cloned().collect())
+    }
+
+    fn read(&self, path: &Utf8Path) -> Result<RecipeContent, Error> {
+        self.recipes
+            .get(path)
+            .map(|text| RecipeContent::new(text.clone(), Encoding::Utf8))
+            .ok_or_else(|| Error::NotFound(path.to_string()))
+    }
+
+    fn images(&self, path: &Utf8Path) -> Result<Vec<Image>, Error> {
+        let Some(recipe_name) = path.file_stem() else {
+            return Ok(vec![]);
+        };
+        // already sorted by path, but images are sorted by indexes first
+        let mut images = self
+            .images
+            .iter()
+            .filter(|image| image.parent() == path.parent())
+            .filter_map(|image| Image::from_path(recipe_name, image.clone()))
+            .collect::<Vec<_>>();
+        images.sort_unstable();
+        Ok(images)
+    }
+}
+
+/// Index of the recipes in a [`RecipeStore`]
+///
+/// It behaves like [`FsIndex`](crate::FsIndex), but the store is only used to
+/// list the recipes when created or refreshed.
+#[derive(Debug)]
+pub struct StoreIndex<S> {
+    store: S,
+    names: NameIndex,
+}
+
+impl<S: RecipeStore> StoreIndex<S> {
+    pub fn new(store: S) -> Result<Self, Error> {
+        Self::with_name_matching(store, NameMatching::default())
+    }
+
+    /// Creates the index with a [`NameMatching`] other than the default
+    pub fn with_name_matching(store: S, matching: NameMatching) -> Result<Self, Error> {
+        let mut index = Self {
+            store,
+            names: NameIndex::new(matching),
+        };
+        index.refresh()?;
+        Ok(index)
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    pub fn store_mut(&mut self) -> &mut S {
+        &mut self.store
+    }
+
+    /// Lists the recipes of the store again
+    pub fn refresh(&mut self) -> Result<(), Error> {
+        let mut names = self.names.emptied();
+        for path in self.store.recipe_paths()? {
+            if let Some(name) = path.file_stem() {
+                names.insert(name, &path);
+            }
+        }
+        self.names = names;
+        Ok(())
+    }
+
+    /// Adds an alternate name for a recipe, see [`FsIndexBuilder::aliases`](crate::FsIndexBuilder::aliases)
+    pub fn add_alias(&mut self, alias: &str, path: &Utf8Path) {
+        let path = self.store.base_path().join(path);
+        self.names.add_alias(alias, &path);
+    }
+
+    pub fn contains(&self, recipe: &str) -> bool {
+        matches!(self.get(recipe), Ok(_) | Err(Error::Ambiguous { .. }))
+    }
+
+    /// Get a recipe path from a partial path, with or without extension
+    ///
+    /// # Errors
+    /// Like [`FsIndex::get`](crate::FsIndex::get).
+    pub fn get(&self, recipe: &str) -> Result<Utf8PathBuf, Error> {
+        self.names.find(recipe)
+    }
+
+    /// Resolves a recipe query first trying directly as a path and if it fails
+    /// performs a lookup in the index.
+    pub fn resolve(
+        &self,
+        recipe: &str,
+        relative_to: Option<&Utf8Path>,
+    ) -> Result<Utf8PathBuf, Error> {
+        if let Ok(path) = recipe_path(recipe, relative_to, self.store.base_path()) {
+            if self.names.contains_path(&path) {
+                return Ok(path);
+            }
+        }
+        self.get(recipe)
+    }
+
+    /// Every recipe path, sorted with [`Collation::Binary`]
+    pub fn recipes(&self) -> Vec<&Utf8Path> {
+        let mut paths = self.names.paths().collect::<Vec<_>>();
+        Collation::Binary.sort_by_path(&mut paths, |p| *p);
+        paths
+    }
+
+    pub fn read(&self, recipe: &str) -> Result<RecipeContent, Error> {
+        self.store.read(&self.get(recipe)?)
+    }
+
+    pub fn images(&self, recipe: &str) -> Result<Vec<Image>, Error> {
+        self.store.images(&self.get(recipe)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> InMemoryStore {
+        InMemoryStore::new("recipes")
+            .with_recipe("Pasta", ">> tags: quick\n@pasta{}")
+            .with_recipe("Dinner/Pasta", "@pasta{} and @sauce{}")
+            .with_recipe("Dinner/Soup", "@water{}")
+            .with_image("Pasta.jpg")
+            .with_image("Pasta.1.png")
+            .with_image("Dinner/Pasta.txt")
+            .with_image("Dinner/Soup.2.webp")
+    }
+
+    #[test]
+    fn lookup_without_fs() {
+        let index = StoreIndex::new(store()).unwrap();
+
+        assert_eq!(index.get("pasta").unwrap(), "recipes/Pasta.cook");
+        assert_eq!(
+            index.get("Dinner/Pasta").unwrap(),
+            "recipes/Dinner/Pasta.cook"
+        );
+        assert_eq!(index.read("soup").unwrap().text(), "@water{}");
+        assert_eq!(
+            index
+                .resolve("Soup", Some(Utf8Path::new("recipes/Dinner")))
+                .unwrap(),
+            "recipes/Dinner/Soup.cook"
+        );
+        assert!(index.resolve("../Soup", None).is_err());
+        assert!(!index.contains("Salad"));
+    }
+
+    #[test]
+    fn images() {
+        let index = StoreIndex::new(store()).unwrap();
+
+        let pasta = index.images("Pasta").unwrap();
+        assert_eq!(pasta.len(), 2);
+        assert_eq!(pasta[0].path, "recipes/Pasta.jpg");
+        assert_eq!(pasta[1].step(), Some(1));
+        assert!(index.images("Dinner/Pasta").unwrap().is_empty());
+        assert_eq!(index.images("Soup").unwrap()[0].step(), Some(2));
+    }
+
+    #[test]
+    fn metadata() {
+        let index = StoreIndex::new(store()).unwrap();
+        let parser = cooklang::CooklangParser::extended();
+
+        let content = index.read("Pasta").unwrap();
+        let meta = content.metadata(&parser).into_output().unwrap();
+        assert!(meta.map.contains_key("tags"));
+    }
+
+    #[test]
+    fn refresh() {
+        let mut index = StoreIndex::new(store()).unwrap();
+        index.store_mut().insert_recipe("Salad", "@lettuce{}");
+        assert!(!index.contains("Salad"));
+        index.refresh().unwrap();
+        assert!(index.contains("Salad"));
+
+        let removed = index
+            .store_mut()
+            .remove(Utf8Path::new("recipes/Salad.cook"));
+        assert!(removed);
+        index.refresh().unwrap();
+        assert!(!index.contains("Salad"));
+    }
+}
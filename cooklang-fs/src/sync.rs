@@ -0,0 +1,282 @@
+//! Revision based sync with clients that keep a copy of the collection
+//!
+//! A [`SyncLog`] numbers the changes of a [`RecipeStore`]. Every time a recipe
+//! is added, changed or removed the revision of the collection increases and
+//! the recipe gets it. A client remembers the revision of its last sync and
+//! asks for the [changes since](SyncLog::changes_since) then.
+//!
+//! To send its own edits, the client gives for each recipe the hash of the
+//! content it started from. If the recipe changed since, it's a [`Conflict`]
+//! and nothing is applied, the client has to pull, merge and try again.
+//! [`SyncLog::plan_push`] turns the edits into a
+//! [`Transaction`](crate::transaction::Transaction) so they are applied
+//! together.
+//!
+//! Removed recipes are remembered so clients that sync later also remove
+//! them.
+
+use std::{borrow::Cow, collections::BTreeMap};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::{safe_write, transaction::Operation, ContentHash, Error, RecipeStore, WriteOptions};
+
+/// Revisions of the recipes of a collection, stored in a file
+///
+/// The paths are relative to the base path of the store and include the
+/// `.cook` extension.
+#[derive(Debug, Default)]
+pub struct SyncLog {
+    revision: u64,
+    recipes: BTreeMap<Utf8PathBuf, Revision>,
+}
+
+/// Format of the file written by [`SyncLog::save`]
+#[derive(Serialize, Deserialize)]
+struct StoredLog<'a> {
+    version: u32,
+    revision: u64,
+    recipes: Cow<'a, BTreeMap<Utf8PathBuf, Revision>>,
+}
+
+impl StoredLog<'_> {
+    const VERSION: u32 = 1;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct Revision {
+    revision: u64,
+    /// `None` if the recipe was removed
+    hash: Option<ContentHash>,
+}
+
+/// A recipe that changed, see [`SyncLog::changes_since`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncChange {
+    /// Relative to the base path
+    pub path: Utf8PathBuf,
+    pub revision: u64,
+    /// `None` if the recipe was removed
+    pub hash: Option<ContentHash>,
+}
+
+/// An edit made by a client
+#[derive(Debug, Clone)]
+pub struct PushChange {
+    /// Relative to the base path
+    pub path: Utf8PathBuf,
+    /// Hash of the content the client edited, `None` for a new recipe
+    pub base: Option<ContentHash>,
+    /// New content, `None` to remove the recipe
+    pub content: Option<String>,
+}
+
+/// A recipe that changed in the collection since the client got it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub path: Utf8PathBuf,
+    /// Current revision of the recipe, 0 if it never existed
+    pub revision: u64,
+    /// Current hash, `None` if the recipe doesn't exist
+    pub hash: Option<ContentHash>,
+}
+
+impl SyncLog {
+    /// Loads a log stored with [`Self::save`], empty if the file doesn't
+    /// exist
+    pub fn load(file: &Utf8Path) -> Result<Self, Error> {
+        let content = match std::fs::read(file) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+        let stored: StoredLog = serde_json::from_slice(&content).map_err(Error::IndexFile)?;
+        if stored.version != StoredLog::VERSION {
+            return Err(Error::IndexFile(serde::de::Error::custom(format!(
+                "unsupported sync log version {}",
+                stored.version
+            ))));
+        }
+        Ok(Self {
+            revision: stored.revision,
+            recipes: stored.recipes.into_owned(),
+        })
+    }
+
+    /// Stores the log in a file
+    pub fn save(&self, file: &Utf8Path) -> Result<(), Error> {
+        let stored = StoredLog {
+            version: StoredLog::VERSION,
+            revision: self.revision,
+            recipes: Cow::Borrowed(&self.recipes),
+        };
+        let content = serde_json::to_vec(&stored).map_err(Error::IndexFile)?;
+        safe_write(file, content, WriteOptions::default())
+    }
+
+    /// Current revision of the collection, 0 before the first scan
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Reads every recipe of the store and records the ones that changed
+    ///
+    /// All the changes of a scan get the same revision. Returns `true` if
+    /// there were any.
+    pub fn scan(&mut self, store: &impl RecipeStore) -> Result<bool, Error> {
+        let base_path = store.base_path();
+        let mut current = BTreeMap::new();
+        for path in store.recipe_paths()? {
+            let hash = store.read(&path)?.hash();
+            let key = path.strip_prefix(base_path).unwrap_or(&path).to_path_buf();
+            current.insert(key, hash);
+        }
+
+        let next = self.revision + 1;
+        let mut changed = false;
+        for (path, rev) in &mut self.recipes {
+            if rev.hash.is_some() && !current.contains_key(path) {
+                *rev = Revision {
+                    revision: next,
+                    hash: None,
+                };
+                changed = true;
+            }
+        }
+        for (path, hash) in current {
+            let rev = self.recipes.entry(path).or_insert(Revision {
+                revision: next,
+                hash: None,
+            });
+            if rev.hash != Some(hash) {
+                *rev = Revision {
+                    revision: next,
+                    hash: Some(hash),
+                };
+                changed = true;
+            }
+        }
+        if changed {
+            self.revision = next;
+        }
+        Ok(changed)
+    }
+
+    /// Recipes that changed after `revision`, sorted by path
+    pub fn changes_since(&self, revision: u64) -> Vec<SyncChange> {
+        self.recipes
+            .iter()
+            .filter(|(_, rev)| rev.revision > revision)
+            .map(|(path, rev)| SyncChange {
+                path: path.clone(),
+                revision: rev.revision,
+                hash: rev.hash,
+            })
+            .collect()
+    }
+
+    /// Operations to apply the edits of a client, or every conflict
+    ///
+    /// Scan the store first so the log is up to date. The operations check
+    /// the hashes again when committed, in case the recipes change in
+    /// between.
+    pub fn plan_push(&self, changes: &[PushChange]) -> Result<Vec<Operation>, Vec<Conflict>> {
+        let mut operations = Vec::new();
+        let mut conflicts = Vec::new();
+        for change in changes {
+            let rev = self.recipes.get(&change.path);
+            let current = rev.and_then(|r| r.hash);
+            if current != change.base {
+                conflicts.push(Conflict {
+                    path: change.path.clone(),
+                    revision: rev.map_or(0, |r| r.revision),
+                    hash: current,
+                });
+                continue;
+            }
+            let path = change.path.clone();
+            let op = match (&change.content, current) {
+                (Some(content), None) => Operation::Create {
+                    path,
+                    content: content.clone(),
+                },
+                (Some(content), Some(_)) => Operation::Update {
+                    path,
+                    content: content.clone(),
+                    expected: current,
+                },
+                (None, Some(_)) => Operation::Delete {
+                    path,
+                    expected: current,
+                },
+                // already removed
+                (None, None) => continue,
+            };
+            operations.push(op);
+        }
+        if conflicts.is_empty() {
+            Ok(operations)
+        } else {
+            Err(conflicts)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryStore;
+
+    #[test]
+    fn revisions() {
+        let mut store = InMemoryStore::new("recipes")
+            .with_recipe("Pasta", "@pasta{}")
+            .with_recipe("Soup", "@water{}");
+        let mut log = SyncLog::default();
+        assert!(log.scan(&store).unwrap());
+        assert_eq!(log.revision(), 1);
+        assert_eq!(log.changes_since(0).len(), 2);
+        assert!(!log.scan(&store).unwrap());
+
+        store.insert_recipe("Pasta", "@pasta{} and @sauce{}");
+        store.remove(Utf8Path::new("recipes/Soup.cook"));
+        assert!(log.scan(&store).unwrap());
+        let changes = log.changes_since(1);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].path, "Pasta.cook");
+        assert_eq!(
+            changes[0].hash,
+            Some(ContentHash::of("@pasta{} and @sauce{}"))
+        );
+        assert_eq!(changes[1].path, "Soup.cook");
+        assert_eq!(changes[1].hash, None);
+        assert!(log.changes_since(2).is_empty());
+    }
+
+    #[test]
+    fn push_conflicts() {
+        let store = InMemoryStore::new("recipes").with_recipe("Pasta", "@pasta{}");
+        let mut log = SyncLog::default();
+        log.scan(&store).unwrap();
+
+        let edit = |base| PushChange {
+            path: "Pasta.cook".into(),
+            base,
+            content: Some("@pasta{2%kg}".into()),
+        };
+        let ops = log
+            .plan_push(&[edit(Some(ContentHash::of("@pasta{}")))])
+            .unwrap();
+        assert!(matches!(ops[..], [Operation::Update { .. }]));
+
+        let conflicts = log
+            .plan_push(&[edit(Some(ContentHash::of("old")))])
+            .unwrap_err();
+        assert_eq!(conflicts[0].revision, 1);
+        assert_eq!(conflicts[0].hash, Some(ContentHash::of("@pasta{}")));
+
+        // created by someone else
+        assert!(log.plan_push(&[edit(None)]).is_err());
+    }
+}
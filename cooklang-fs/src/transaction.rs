@@ -0,0 +1,297 @@
+//! Changing several recipes at once
+//!
+//! A [`Transaction`] creates, updates and deletes recipes so either all the
+//! changes are done or none. Before touching a recipe it writes a journal with
+//! the files involved. If the process dies in the middle, [`recover`] uses it
+//! to undo the changes that were already done.
+//!
+//! The new contents are written next to the recipes and the replaced recipes
+//! are kept until the end, both in hidden files that the index ignores. The
+//! deleted recipes can go to a [`Trash`] at the end.
+
+use std::{collections::HashSet, fs, io};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    check_relative_path,
+    readonly::check_writable,
+    safe_write,
+    write::{sync_parent, write_tmp},
+    ContentHash, Error, RecipeEntry, Trash, WriteOptions,
+};
+
+/// A change to a recipe, the path is relative to the base path
+#[derive(Debug, Clone)]
+pub enum Operation {
+    /// Fails if the recipe exists
+    Create { path: Utf8PathBuf, content: String },
+    /// Fails if the recipe doesn't exist or, when `expected` is given, if its
+    /// content changed
+    Update {
+        path: Utf8PathBuf,
+        content: String,
+        expected: Option<ContentHash>,
+    },
+    /// Like [`Operation::Update`], but removes the recipe
+    Delete {
+        path: Utf8PathBuf,
+        expected: Option<ContentHash>,
+    },
+}
+
+impl Operation {
+    pub fn path(&self) -> &Utf8Path {
+        match self {
+            Operation::Create { path, .. }
+            | Operation::Update { path, .. }
+            | Operation::Delete { path, .. } => path,
+        }
+    }
+
+    fn content(&self) -> Option<&str> {
+        match self {
+            Operation::Create { content, .. } | Operation::Update { content, .. } => Some(content),
+            Operation::Delete { .. } => None,
+        }
+    }
+}
+
+/// Changes to apply together, see the [module docs](self)
+///
+/// Hold a [`CollectionLock`](crate::CollectionLock) while committing it.
+#[derive(Debug)]
+pub struct Transaction {
+    base_path: Utf8PathBuf,
+    journal: Utf8PathBuf,
+    operations: Vec<Operation>,
+    trash: Option<Trash>,
+    force: bool,
+}
+
+/// Format of the journal file
+#[derive(Debug, Serialize, Deserialize)]
+struct Journal {
+    steps: Vec<Step>,
+}
+
+/// Files of an operation
+#[derive(Debug, Serialize, Deserialize)]
+struct Step {
+    target: Utf8PathBuf,
+    /// New content, renamed to the target
+    staged: Option<Utf8PathBuf>,
+    /// Where the target is moved before replacing or deleting it
+    backup: Option<Utf8PathBuf>,
+}
+
+impl Transaction {
+    /// Empty transaction for the collection at `base_path`, writing the
+    /// journal to `journal`
+    pub fn new(base_path: impl Into<Utf8PathBuf>, journal: impl Into<Utf8PathBuf>) -> Self {
+        Self {
+            base_path: base_path.into(),
+            journal: journal.into(),
+            operations: Vec::new(),
+            trash: None,
+            force: false,
+        }
+    }
+
+    /// Moves the deleted recipes to a trash instead of removing them
+    pub fn with_trash(mut self, trash: Trash) -> Self {
+        self.trash = Some(trash);
+        self
+    }
+
+    /// Allows changing read-only recipes, see [`crate::is_readonly`]
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    pub fn push(&mut self, operation: Operation) -> &mut Self {
+        self.operations.push(operation);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// Applies every operation, or none if one fails
+    ///
+    /// Everything is checked before changing anything.
+    ///
+    /// # Errors
+    /// - [`Error::UnsafeName`] or [`Error::InvalidName`] for paths that are
+    ///   not `.cook` files in the collection or appear twice.
+    /// - [`Error::AlreadyExists`] creating a recipe that exists.
+    /// - [`Error::NotFound`] updating or deleting a recipe that doesn't.
+    /// - [`Error::Conflict`] when the content is not the expected one.
+    /// - [`Error::ReadOnly`] for read-only recipes, unless forced.
+    #[tracing::instrument(level = "debug", skip_all, fields(len = self.len()), err)]
+    pub fn commit(self) -> Result<(), Error> {
+        self.check()?;
+        let steps = self.plan();
+        let journal = Journal { steps };
+        let content = serde_json::to_vec(&journal).map_err(Error::Journal)?;
+        safe_write(&self.journal, content, WriteOptions::default())?;
+
+        if let Err(e) = self.apply(&journal.steps) {
+            rollback(&journal.steps);
+            fs::remove_file(&self.journal)?;
+            return Err(e.into());
+        }
+        fs::remove_file(&self.journal)?;
+        for (op, step) in self.operations.iter().zip(&journal.steps) {
+            let Some(backup) = &step.backup else {
+                continue;
+            };
+            let res = match (&self.trash, op) {
+                (Some(trash), Operation::Delete { .. }) => {
+                    trash.put_as(backup, &step.target).map(|_| ())
+                }
+                _ => fs::remove_file(backup).map_err(Error::from),
+            };
+            if let Err(e) = res {
+                tracing::warn!("Can't remove '{backup}': {e}");
+            }
+        }
+        Ok(())
+    }
+
+    fn check(&self) -> Result<(), Error> {
+        let mut seen = HashSet::new();
+        for op in &self.operations {
+            let path = op.path();
+            check_relative_path(path.as_str())?;
+            if path.components().any(|c| c.as_str().starts_with('.')) {
+                return Err(Error::UnsafeName {
+                    name: path.to_string(),
+                    reason: "hidden file or dir",
+                });
+            }
+            if path.extension() != Some("cook") || !seen.insert(path) {
+                return Err(Error::InvalidName(path.to_string()));
+            }
+            let target = self.base_path.join(path);
+            check_writable(&target, self.force)?;
+            let expected = match op {
+                Operation::Create { .. } => {
+                    if target.exists() {
+                        return Err(Error::AlreadyExists(path.to_string()));
+                    }
+                    continue;
+                }
+                Operation::Update { expected, .. } | Operation::Delete { expected, .. } => expected,
+            };
+            if !target.is_file() {
+                return Err(Error::NotFound(path.to_string()));
+            }
+            if let Some(expected) = expected {
+                if RecipeEntry::new(&target).read()?.hash() != *expected {
+                    return Err(Error::Conflict(path.to_string()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn plan(&self) -> Vec<Step> {
+        self.operations
+            .iter()
+            .map(|op| {
+                let target = self.base_path.join(op.path());
+                Step {
+                    staged: op.content().map(|_| side_path(&target, "new")),
+                    backup: (!matches!(op, Operation::Create { .. }))
+                        .then(|| side_path(&target, "old")),
+                    target,
+                }
+            })
+            .collect()
+    }
+
+    fn apply(&self, steps: &[Step]) -> io::Result<()> {
+        for (op, step) in self.operations.iter().zip(steps) {
+            if let (Some(content), Some(staged)) = (op.content(), &step.staged) {
+                if let Some(parent) = staged.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                write_tmp(staged, content.as_bytes())?;
+            }
+        }
+        for step in steps {
+            if let Some(backup) = &step.backup {
+                fs::rename(&step.target, backup)?;
+            }
+            if let Some(staged) = &step.staged {
+                fs::rename(staged, &step.target)?;
+            }
+            sync_parent(&step.target);
+        }
+        Ok(())
+    }
+}
+
+/// Undoes a transaction that was interrupted
+///
+/// Returns `true` if there was one. Call it with the collection locked before
+/// changing anything.
+#[tracing::instrument(level = "debug", err)]
+pub fn recover(journal: &Utf8Path) -> Result<bool, Error> {
+    let content = match fs::read(journal) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    let journal_content: Journal = serde_json::from_slice(&content).map_err(Error::Journal)?;
+    rollback(&journal_content.steps);
+    fs::remove_file(journal)?;
+    Ok(true)
+}
+
+/// Puts back the original files, the recipes created did not exist before
+fn rollback(steps: &[Step]) {
+    for step in steps.iter().rev() {
+        let res = match &step.backup {
+            Some(backup) if backup.is_file() => fs::rename(backup, &step.target),
+            // not moved yet
+            Some(_) => Ok(()),
+            None if step.target.is_file() => fs::remove_file(&step.target),
+            None => Ok(()),
+        };
+        if let Err(e) = res {
+            tracing::error!("Can't restore '{}': {e}", step.target);
+        }
+        if let Some(staged) = step.staged.as_ref().filter(|s| s.is_file()) {
+            if let Err(e) = fs::remove_file(staged) {
+                tracing::warn!("Can't remove '{staged}': {e}");
+            }
+        }
+    }
+}
+
+/// Hidden file next to `target`
+fn side_path(target: &Utf8Path, kind: &str) -> Utf8PathBuf {
+    let name = target.file_name().unwrap_or_default();
+    target.with_file_name(format!(".{name}.txn-{kind}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn side_paths() {
+        let target = Utf8Path::new("base/Soups/Leek.cook");
+        assert_eq!(side_path(target, "new"), "base/Soups/.Leek.cook.txn-new");
+        assert_eq!(side_path(target, "old"), "base/Soups/.Leek.cook.txn-old");
+    }
+}
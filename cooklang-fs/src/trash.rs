@@ -0,0 +1,186 @@
+//! Deleted files, kept to restore them
+//!
+//! A file is moved to `<trash>/<unix time>/<path>`, with the path relative to
+//! the collection, so it can be put back where it was. Files deleted in the
+//! same second share the dir unless they have the same path, then the dir is
+//! `<unix time>-<n>`.
+
+use std::{
+    fs, io,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::Serialize;
+
+use crate::Error;
+
+/// Where the deleted files of a collection go
+#[derive(Debug, Clone)]
+pub struct Trash {
+    base_path: Utf8PathBuf,
+    dir: Utf8PathBuf,
+}
+
+/// A file in the [`Trash`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TrashedFile {
+    /// Unix time when it was deleted
+    pub deleted: u64,
+    /// Where it was, relative to the base path
+    pub path: Utf8PathBuf,
+    /// Where it is now
+    pub location: Utf8PathBuf,
+}
+
+impl Trash {
+    /// Trash of the collection at `base_path`, stored in `dir`
+    pub fn new(base_path: impl Into<Utf8PathBuf>, dir: impl Into<Utf8PathBuf>) -> Self {
+        Self {
+            base_path: base_path.into(),
+            dir: dir.into(),
+        }
+    }
+
+    /// Moves a file of the collection to the trash
+    pub fn put(&self, path: &Utf8Path) -> Result<TrashedFile, Error> {
+        self.put_as(path, path)
+    }
+
+    /// Moves `from` to the trash as if it was the file at `path`
+    ///
+    /// For files that were moved out of the way before deleting them.
+    pub(crate) fn put_as(&self, from: &Utf8Path, path: &Utf8Path) -> Result<TrashedFile, Error> {
+        let relative = path
+            .strip_prefix(&self.base_path)
+            .map_err(|_| Error::OutsideBase(path.to_string()))?;
+        let deleted = unix_now();
+        let mut slot = self.dir.join(deleted.to_string());
+        let mut n = 0;
+        while slot.join(relative).exists() {
+            n += 1;
+            slot = self.dir.join(format!("{deleted}-{n}"));
+        }
+        let location = slot.join(relative);
+        if let Some(parent) = location.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        move_file(from, &location)?;
+        Ok(TrashedFile {
+            deleted,
+            path: relative.to_path_buf(),
+            location,
+        })
+    }
+
+    /// Files in the trash, the last deleted first
+    pub fn list(&self) -> Result<Vec<TrashedFile>, Error> {
+        let mut files = Vec::new();
+        for (deleted, slot) in self.slots()? {
+            collect_files(&slot, &slot, deleted, &mut files)?;
+        }
+        files.sort_by(|a, b| b.deleted.cmp(&a.deleted).then_with(|| a.path.cmp(&b.path)));
+        Ok(files)
+    }
+
+    /// Puts a file back where it was
+    ///
+    /// Fails with [`Error::AlreadyExists`] if there is a file there now.
+    pub fn restore(&self, file: &TrashedFile) -> Result<Utf8PathBuf, Error> {
+        let dest = self.base_path.join(&file.path);
+        if dest.exists() {
+            return Err(Error::AlreadyExists(file.path.to_string()));
+        }
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        move_file(&file.location, &dest)?;
+        // remove the dirs left empty, up to the trash dir
+        for dir in file.location.ancestors().skip(1) {
+            if dir == self.dir || fs::remove_dir(dir).is_err() {
+                break;
+            }
+        }
+        Ok(dest)
+    }
+
+    /// Deletes for good the files deleted before `before`, a unix time, or
+    /// every file
+    ///
+    /// Returns how many were deleted.
+    pub fn empty(&self, before: Option<u64>) -> Result<usize, Error> {
+        let mut count = 0;
+        for (deleted, slot) in self.slots()? {
+            if before.is_some_and(|before| deleted >= before) {
+                continue;
+            }
+            let mut files = Vec::new();
+            collect_files(&slot, &slot, deleted, &mut files)?;
+            fs::remove_dir_all(&slot)?;
+            count += files.len();
+        }
+        Ok(count)
+    }
+
+    /// Dirs of the trash with the time they were created
+    fn slots(&self) -> Result<Vec<(u64, Utf8PathBuf)>, Error> {
+        let entries = match self.dir.read_dir_utf8() {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let mut slots = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let time = entry.file_name().split('-').next().unwrap_or_default();
+            match time.parse() {
+                Ok(deleted) if entry.file_type()?.is_dir() => {
+                    slots.push((deleted, entry.into_path()))
+                }
+                _ => tracing::debug!("unknown file in the trash: {}", entry.path()),
+            }
+        }
+        Ok(slots)
+    }
+}
+
+fn collect_files(
+    slot: &Utf8Path,
+    dir: &Utf8Path,
+    deleted: u64,
+    files: &mut Vec<TrashedFile>,
+) -> Result<(), Error> {
+    for entry in dir.read_dir_utf8()? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            collect_files(slot, entry.path(), deleted, files)?;
+        } else {
+            let location = entry.into_path();
+            files.push(TrashedFile {
+                deleted,
+                path: location
+                    .strip_prefix(slot)
+                    .unwrap_or(&location)
+                    .to_path_buf(),
+                location,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Renames a file, copying it if the destination is in another file system
+fn move_file(from: &Utf8Path, to: &Utf8Path) -> io::Result<()> {
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,98 @@
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{Collation, RecipeEntry};
+
+/// Dirs and recipes of a collection, nested like in the disk
+///
+/// Dirs without recipes, directly or in a subdir, are not included.
+#[derive(Debug, Clone, Default)]
+pub struct RecipeTree {
+    /// Name of the dir, empty for the base path
+    pub name: String,
+    /// Relative to the base path
+    pub path: Utf8PathBuf,
+    pub dirs: Vec<RecipeTree>,
+    pub recipes: Vec<RecipeEntry>,
+}
+
+impl RecipeTree {
+    /// Builds the tree from the paths of the recipes, sorted with
+    /// [`Collation::Binary`]
+    ///
+    /// Paths outside `base_path` are placed as if they were relative to it.
+    pub fn from_paths<'a>(
+        base_path: &Utf8Path,
+        paths: impl IntoIterator<Item = &'a Utf8Path>,
+    ) -> Self {
+        let mut root = Self::default();
+        for path in paths {
+            let relative = path.strip_prefix(base_path).unwrap_or(path);
+            let mut node = &mut root;
+            for dir in relative.parent().into_iter().flat_map(Utf8Path::components) {
+                let pos = match node.dirs.iter().position(|d| d.name == dir.as_str()) {
+                    Some(pos) => pos,
+                    None => {
+                        let path = node.path.join(dir);
+                        node.dirs.push(Self {
+                            name: dir.to_string(),
+                            path,
+                            ..Default::default()
+                        });
+                        node.dirs.len() - 1
+                    }
+                };
+                node = &mut node.dirs[pos];
+            }
+            node.recipes.push(RecipeEntry::new(path));
+        }
+        root.sort(Collation::Binary);
+        root
+    }
+
+    /// Sorts the dirs and the recipes of every level
+    pub fn sort(&mut self, collation: Collation) {
+        collation.sort_by_name(&mut self.dirs, |d| &d.name);
+        collation.sort_by_path(&mut self.recipes, |r| r.path());
+        for dir in &mut self.dirs {
+            dir.sort(collation);
+        }
+    }
+
+    /// Number of recipes, including subdirs
+    pub fn len(&self) -> usize {
+        self.recipes.len() + self.dirs.iter().map(Self::len).sum::<usize>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.recipes.is_empty() && self.dirs.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested() {
+        let paths = [
+            "base/Soups/Cream/Leek.cook",
+            "base/Pasta.cook",
+            "base/Soups/Tomato.cook",
+            "base/Desserts/Flan.cook",
+            "base/Bread.cook",
+        ];
+        let tree = RecipeTree::from_paths(Utf8Path::new("base"), paths.map(Utf8Path::new));
+        assert_eq!(tree.len(), 5);
+        assert_eq!(tree.name, "");
+        fn names(t: &RecipeTree) -> Vec<&str> {
+            t.recipes.iter().map(|r| r.name()).collect()
+        }
+        assert_eq!(names(&tree), ["Bread", "Pasta"]);
+        assert_eq!(tree.dirs[0].name, "Desserts");
+        let soups = &tree.dirs[1];
+        assert_eq!(soups.path, "Soups");
+        assert_eq!(names(soups), ["Tomato"]);
+        assert_eq!(soups.dirs[0].path, "Soups/Cream");
+        assert_eq!(names(&soups.dirs[0]), ["Leek"]);
+    }
+}
@@ -1,18 +1,48 @@
-use std::{collections::VecDeque, fs::FileType};
+use std::{
+    collections::{BTreeSet, VecDeque},
+    fs::FileType,
+    sync::{mpsc, Arc, Condvar, Mutex},
+};
 
 use camino::{Utf8Path, Utf8PathBuf};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::{Deserialize, Serialize};
 
-use crate::IMAGE_EXTENSIONS;
+use crate::{markdown, IMAGE_EXTENSIONS};
+
+/// What to do when the walker finds a path that is not valid UTF-8
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NonUtf8Policy {
+    /// Stop with an error
+    #[default]
+    Error,
+    /// Log a warning and ignore the entry
+    Skip,
+    /// Ignore the entry without logging
+    Ignore,
+    /// Keep the entry, replacing the invalid parts of the name with `U+FFFD`
+    ///
+    /// The entry is listed, but the file can't be opened by that path.
+    Lossy,
+}
+
+/// File with gitignore syntax at the base path to skip files and dirs
+pub const IGNORE_FILE: &str = ".cookignore";
 
 /// Breadth-first, sorted by file name, .cook filtered, dir walker.
 ///
 /// Paths are relative to the base path, with the base path included. So when
 /// walking over `dir`, entries will be `dir/whatever.cook`.
 ///
-/// Files/dirs starting with '.' are ignored.
+/// Files/dirs starting with '.' are ignored, and the ones that match the
+/// [`IGNORE_FILE`] of the base path.
+///
+/// Currently, it returns dirs, cooklang files and images. Markdown files with
+/// a recipe are returned too if [enabled](Self::set_markdown).
 ///
-/// Currently, it returns dirs, cooklang files and images.
-#[derive(Debug)]
+/// Symbolic links are not followed unless [enabled](Self::set_follow_links).
+#[derive(Debug, Clone)]
 pub struct Walker {
     base_path: Utf8PathBuf,
     max_depth: usize,
@@ -20,8 +50,35 @@ pub struct Walker {
     current: std::vec::IntoIter<DirEntry>,
     config_dir: Option<String>,
     ignore: Vec<String>,
+    cookignore: Option<Gitignore>,
+    non_utf8: NonUtf8Policy,
+    markdown: bool,
+    follow_links: bool,
+    batch_stats: bool,
+    link_roots: LinkRoots,
+    skipped: SkippedPaths,
 }
 
+/// Canonical paths of the base path and the targets of the links followed
+///
+/// A link to a path inside one of them is skipped, it would be a duplicate
+/// or a cycle.
+#[derive(Debug, Default)]
+struct LinkRoots(Mutex<Vec<Utf8PathBuf>>);
+
+impl Clone for LinkRoots {
+    fn clone(&self) -> Self {
+        Self(Mutex::new(self.0.lock().unwrap().clone()))
+    }
+}
+
+/// Non UTF-8 paths that were not returned
+///
+/// Shared by the clones and restarted walkers, so they are known after a
+/// parallel walk.
+#[derive(Debug, Clone, Default)]
+struct SkippedPaths(Arc<Mutex<BTreeSet<std::path::PathBuf>>>);
+
 impl Walker {
     pub fn new(dir: impl AsRef<Utf8Path>, max_depth: usize) -> Self {
         let dir = dir.as_ref();
@@ -34,9 +91,108 @@ impl Walker {
             current: Vec::new().into_iter(),
             config_dir: None,
             ignore: Vec::new(),
+            cookignore: load_cookignore(dir),
+            non_utf8: NonUtf8Policy::default(),
+            markdown: false,
+            follow_links: false,
+            batch_stats: false,
+            link_roots: LinkRoots::default(),
+            skipped: SkippedPaths::default(),
         }
     }
 
+    pub fn base_path(&self) -> &Utf8Path {
+        &self.base_path
+    }
+
+    /// A new walker with the same configuration that starts from the
+    /// beginning
+    pub fn restart(&self) -> Self {
+        let mut dirs = VecDeque::new();
+        dirs.push_back(self.base_path.clone());
+        Self {
+            dirs,
+            current: Vec::new().into_iter(),
+            base_path: self.base_path.clone(),
+            config_dir: self.config_dir.clone(),
+            ignore: self.ignore.clone(),
+            cookignore: self.cookignore.clone(),
+            link_roots: self.new_link_roots(),
+            skipped: self.skipped.clone(),
+            ..*self
+        }
+    }
+
+    /// Sets what to do with non UTF-8 paths
+    pub fn set_non_utf8_policy(&mut self, policy: NonUtf8Policy) {
+        self.non_utf8 = policy;
+    }
+
+    /// Non UTF-8 paths skipped by this walker and its clones, sorted
+    pub fn skipped(&self) -> Vec<std::path::PathBuf> {
+        self.skipped.0.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Forgets the skipped paths, before walking everything again
+    pub fn clear_skipped(&self) {
+        self.skipped.0.lock().unwrap().clear();
+    }
+
+    /// Also returns Markdown files with a cooklang block
+    ///
+    /// Each `.md` file is read to know if it has a recipe.
+    pub fn set_markdown(&mut self, markdown: bool) {
+        self.markdown = markdown;
+    }
+
+    /// Follows symbolic links to files and dirs
+    ///
+    /// Links to something inside the base path or a dir already reached
+    /// through another link are skipped, so the same recipe is not found
+    /// twice and there are no cycles.
+    pub fn set_follow_links(&mut self, follow: bool) {
+        self.follow_links = follow;
+        self.link_roots = self.new_link_roots();
+    }
+
+    /// Gets the file types of the entries of a dir in a few threads
+    ///
+    /// Network file systems may need a round trip for each one.
+    pub fn set_batch_stats(&mut self, batch: bool) {
+        self.batch_stats = batch;
+    }
+
+    fn new_link_roots(&self) -> LinkRoots {
+        let roots = if self.follow_links {
+            self.base_path.canonicalize_utf8().into_iter().collect()
+        } else {
+            Vec::new()
+        };
+        LinkRoots(Mutex::new(roots))
+    }
+
+    /// File type of the target of a link, `None` if it has to be skipped
+    fn follow_link(&self, link: &Utf8Path) -> Option<FileType> {
+        let target = match link.canonicalize_utf8() {
+            Ok(target) => target,
+            Err(e) => {
+                tracing::debug!("Skipping broken link {link}: {e}");
+                return None;
+            }
+        };
+        let file_type = target.metadata().ok()?.file_type();
+        let mut roots = self.link_roots.0.lock().unwrap();
+        if roots
+            .iter()
+            .any(|root| target.starts_with(root) || root.starts_with(&target))
+        {
+            tracing::debug!("Skipping link {link}, {target} is already walked");
+            return None;
+        }
+        roots.push(target);
+        Some(file_type)
+    }
+
     /// Sets a config dir to the walker
     ///
     /// If this dir is found not in the top level, a warning will be printed.
@@ -54,33 +210,166 @@ impl Walker {
         self.ignore.push(dir);
     }
 
-    #[tracing::instrument(level = "trace", skip(self), ret)]
+    /// Checks if a recipe path would be found by walking
+    ///
+    /// The path is relative to the base path. The file system is not accessed,
+    /// so any Markdown file is included if they are enabled.
+    pub fn includes(&self, path: &Utf8Path) -> bool {
+        (path.extension() == Some("cook")
+            || (self.markdown && path.extension() == Some(markdown::MARKDOWN_EXTENSION)))
+            && self
+                .included_depth(path)
+                .is_some_and(|depth| depth <= self.max_depth + 1)
+            && !self.cookignored(&self.base_path.join(path), false)
+    }
+
+    /// Like [`Self::includes`] for a dir
+    pub fn includes_dir(&self, path: &Utf8Path) -> bool {
+        self.included_depth(path)
+            .is_some_and(|depth| depth <= self.max_depth)
+            && !self.cookignored(&self.base_path.join(path), true)
+    }
+
+    /// Checks the [`IGNORE_FILE`], the path has to start with the base path
+    fn cookignored(&self, path: &Utf8Path, is_dir: bool) -> bool {
+        self.cookignore
+            .as_ref()
+            .is_some_and(|ignore| ignore.matched_path_or_any_parents(path, is_dir).is_ignore())
+    }
+
+    /// Number of components, `None` if one is ignored
+    fn included_depth(&self, path: &Utf8Path) -> Option<usize> {
+        let mut depth = 0;
+        for component in path.components() {
+            let camino::Utf8Component::Normal(name) = component else {
+                return None;
+            };
+            if name.starts_with('.') || self.ignore.iter().any(|d| d == name) {
+                return None;
+            }
+            depth += 1;
+        }
+        (depth > 0).then_some(depth)
+    }
+
+    fn is_markdown_recipe(&self, entry: &DirEntry) -> bool {
+        self.markdown
+            && entry.is_markdown_file()
+            && std::fs::read_to_string(entry.path()).is_ok_and(|text| markdown::has_recipe(&text))
+    }
+
+    /// Checks if nothing has been walked yet
+    pub(crate) fn is_fresh(&self) -> bool {
+        self.dirs.len() == 1 && self.dirs[0] == self.base_path && self.current.len() == 0
+    }
+
+    /// Walks from the beginning reading the dirs in `threads` threads
+    ///
+    /// Each message has the entries of a dir, sorted like [`Walker`] does, but
+    /// the dirs come in no particular order. An error reading a dir does not
+    /// stop the walk. The threads stop when the receiver is dropped.
+    pub fn walk_par(
+        &self,
+        threads: usize,
+    ) -> mpsc::Receiver<Result<Vec<DirEntry>, std::io::Error>> {
+        let (tx, rx) = mpsc::channel();
+        let queue = Arc::new(DirQueue::new(self.base_path.clone()));
+        let walker = Arc::new(self.restart());
+        for _ in 0..threads.max(1) {
+            let (tx, queue, walker) = (tx.clone(), Arc::clone(&queue), Arc::clone(&walker));
+            std::thread::spawn(move || {
+                while let Some(dir) = queue.pop() {
+                    let res = walker.read_dir(&dir).map(|(dirs, entries)| {
+                        queue.push(dirs);
+                        entries
+                    });
+                    queue.done();
+                    if tx.send(res).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        rx
+    }
+
     fn process_dir(&mut self, dir: &Utf8Path) -> Result<(), std::io::Error> {
+        let (new_dirs, new_entries) = self.read_dir(dir)?;
+        self.dirs.extend(new_dirs);
+        self.current = new_entries.into_iter();
+        Ok(())
+    }
+
+    /// Reads a dir returning the dirs to walk next and the entries
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn read_dir(
+        &self,
+        dir: &Utf8Path,
+    ) -> Result<(Vec<Utf8PathBuf>, Vec<DirEntry>), std::io::Error> {
         // the entire dir needs to be processed as one because entry order
         // is not guaranteed, so we need to sort
         let mut new_dirs = Vec::new();
         let mut new_entries = Vec::new();
-        for e in dir.read_dir_utf8()? {
-            let e = e?;
-            let ft = e.file_type()?;
+        let entries = dir
+            .as_std_path()
+            .read_dir()?
+            .collect::<Result<Vec<_>, _>>()?;
+        let file_types = if self.batch_stats {
+            map_par(&entries, |e| e.file_type())
+        } else {
+            entries.iter().map(|e| e.file_type()).collect()
+        };
+        for (e, ft) in entries.into_iter().zip(file_types) {
+            let mut ft = ft?;
+            let e = match Utf8PathBuf::from_path_buf(e.path()) {
+                Ok(path) => path,
+                Err(path) => match self.non_utf8 {
+                    NonUtf8Policy::Error => {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            crate::NonUtf8(path),
+                        ))
+                    }
+                    NonUtf8Policy::Skip => {
+                        tracing::warn!("Skipping non UTF-8 path: {}", path.display());
+                        self.skipped.0.lock().unwrap().insert(path);
+                        continue;
+                    }
+                    NonUtf8Policy::Ignore => {
+                        self.skipped.0.lock().unwrap().insert(path);
+                        continue;
+                    }
+                    NonUtf8Policy::Lossy => Utf8PathBuf::from(path.to_string_lossy().into_owned()),
+                },
+            };
+            let file_name = e.file_name().unwrap_or(e.as_str());
 
             // print warning for unexpected config dir
             if let Some(config_dir) = &self.config_dir {
-                if ft.is_dir()
-                    && e.file_name() == config_dir
-                    && entry_depth(e.path(), &self.base_path) > 1
-                {
+                if ft.is_dir() && file_name == config_dir && entry_depth(&e, &self.base_path) > 1 {
                     tracing::warn!("Config dir `{config_dir}` found not in base path. It will be ignored. You may be running the application in the wrong directory.");
                 }
             }
 
             // filter dot files/dirs and explicit filters
-            if e.file_name().starts_with('.') || self.ignore.iter().any(|d| d == e.file_name()) {
+            if file_name.starts_with('.') || self.ignore.iter().any(|d| d == file_name) {
                 continue;
             }
+            if let Some(ignore) = &self.cookignore {
+                if ignore.matched(&e, ft.is_dir()).is_ignore() {
+                    continue;
+                }
+            }
+
+            if self.follow_links && ft.is_symlink() {
+                match self.follow_link(&e) {
+                    Some(target) => ft = target,
+                    None => continue,
+                }
+            }
 
             let entry = DirEntry {
-                path: e.into_path(),
+                path: e,
                 file_type: ft,
             };
 
@@ -89,7 +378,10 @@ impl Walker {
                 if depth <= self.max_depth {
                     new_dirs.push(entry.path().to_path_buf());
                 }
-            } else if !(entry.is_cooklang_file() || entry.is_image()) {
+            } else if !(entry.is_cooklang_file()
+                || entry.is_image()
+                || self.is_markdown_recipe(&entry))
+            {
                 continue;
             }
             new_entries.push(entry);
@@ -101,9 +393,96 @@ impl Walker {
                 .cmp(&b.file_type.is_dir())
                 .then_with(|| a.file_name().cmp(b.file_name()))
         });
-        self.dirs.extend(new_dirs);
-        self.current = new_entries.into_iter();
-        Ok(())
+        Ok((new_dirs, new_entries))
+    }
+}
+
+/// Threads used by default in [`Walker::walk_par`]
+///
+/// Reading dirs is mostly waiting for the disk, so at least a few even with
+/// one core.
+pub fn default_threads() -> usize {
+    std::thread::available_parallelism()
+        .map_or(1, usize::from)
+        .max(4)
+}
+
+/// Calls `f` with every item in [`default_threads`] threads
+///
+/// Meant for file system calls, like stats or reads, that are mostly waiting
+/// in slow file systems. The results keep the order of the items.
+pub fn map_par<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    if items.len() < 2 {
+        return items.iter().map(f).collect();
+    }
+    let chunk = items.len().div_ceil(default_threads());
+    let f = &f;
+    std::thread::scope(|s| {
+        let handles = items
+            .chunks(chunk)
+            .map(|items| s.spawn(move || items.iter().map(f).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("map_par thread panicked"))
+            .collect()
+    })
+}
+
+/// Dirs left to read by the threads of [`Walker::walk_par`]
+struct DirQueue {
+    state: Mutex<DirQueueState>,
+    changed: Condvar,
+}
+
+struct DirQueueState {
+    dirs: VecDeque<Utf8PathBuf>,
+    /// Dirs being read, that may add more
+    reading: usize,
+}
+
+impl DirQueue {
+    fn new(base_path: Utf8PathBuf) -> Self {
+        Self {
+            state: Mutex::new(DirQueueState {
+                dirs: VecDeque::from([base_path]),
+                reading: 0,
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    /// Next dir to read, waits if other threads may find more. `None` when
+    /// every dir is read.
+    fn pop(&self) -> Option<Utf8PathBuf> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(dir) = state.dirs.pop_front() {
+                state.reading += 1;
+                return Some(dir);
+            }
+            if state.reading == 0 {
+                return None;
+            }
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+
+    fn push(&self, dirs: Vec<Utf8PathBuf>) {
+        if dirs.is_empty() {
+            return;
+        }
+        self.state.lock().unwrap().dirs.extend(dirs);
+        self.changed.notify_all();
+    }
+
+    /// Marks a popped dir as read
+    fn done(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.reading -= 1;
+        if state.reading == 0 {
+            self.changed.notify_all();
+        }
     }
 }
 
@@ -164,6 +543,19 @@ impl DirEntry {
         self.file_type.is_file() && self.path.extension().is_some_and(|e| e == "cook")
     }
 
+    pub fn is_markdown_file(&self) -> bool {
+        self.file_type.is_file()
+            && self
+                .path
+                .extension()
+                .is_some_and(|e| e == markdown::MARKDOWN_EXTENSION)
+    }
+
+    /// A cooklang file or a Markdown file, that may have a recipe
+    pub fn is_recipe_file(&self) -> bool {
+        self.is_cooklang_file() || self.is_markdown_file()
+    }
+
     pub fn is_image(&self) -> bool {
         self.path
             .extension()
@@ -171,6 +563,24 @@ impl DirEntry {
     }
 }
 
+fn load_cookignore(dir: &Utf8Path) -> Option<Gitignore> {
+    let path = dir.join(IGNORE_FILE);
+    if !path.is_file() {
+        return None;
+    }
+    let mut builder = GitignoreBuilder::new(dir);
+    if let Some(e) = builder.add(&path) {
+        tracing::warn!("Error in {path}: {e}");
+    }
+    match builder.build() {
+        Ok(ignore) => Some(ignore),
+        Err(e) => {
+            tracing::warn!("Ignoring {path}: {e}");
+            None
+        }
+    }
+}
+
 /// Calculates the depth of a path in relation to a base path.
 ///
 /// # Panics
@@ -181,3 +591,86 @@ fn entry_depth(path: &Utf8Path, base_path: &Utf8Path) -> usize {
         .components()
         .count()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn includes() {
+        let mut walker = Walker::new("base", 1);
+        walker.set_config_dir("config".into());
+        let includes = |p: &str| walker.includes(Utf8Path::new(p));
+        assert!(includes("Pasta.cook"));
+        assert!(includes("Dinner/Pasta.cook"));
+        assert!(!includes("Dinner/Italian/Pasta.cook"));
+        assert!(!includes(".hidden/Pasta.cook"));
+        assert!(!includes("config/Pasta.cook"));
+        assert!(!includes("../Pasta.cook"));
+        assert!(!includes("Pasta.jpg"));
+        assert!(walker.includes_dir(Utf8Path::new("Dinner")));
+        assert!(!walker.includes_dir(Utf8Path::new("Dinner/Italian")));
+        assert!(!walker.includes_dir(Utf8Path::new(".git")));
+        assert!(!includes("Pasta.md"));
+        walker.set_markdown(true);
+        assert!(walker.includes(Utf8Path::new("Pasta.md")));
+    }
+
+    #[test]
+    fn cookignore() {
+        let mut walker = Walker::new("base", 10);
+        let mut builder = GitignoreBuilder::new("base");
+        builder.add_line(None, "Drafts/").unwrap();
+        builder.add_line(None, "*.template.cook").unwrap();
+        walker.cookignore = Some(builder.build().unwrap());
+        let includes = |p: &str| walker.includes(Utf8Path::new(p));
+        assert!(includes("Pasta.cook"));
+        assert!(!includes("Drafts/Pasta.cook"));
+        assert!(!includes("Dinner/Drafts/Pasta.cook"));
+        assert!(!includes("Dinner/Soup.template.cook"));
+        assert!(!walker.includes_dir(Utf8Path::new("Drafts")));
+    }
+
+    #[test]
+    fn map_par_keeps_order() {
+        let items = (0..100).collect::<Vec<u32>>();
+        let doubled = map_par(&items, |n| n * 2);
+        assert_eq!(doubled, items.iter().map(|n| n * 2).collect::<Vec<_>>());
+        assert!(map_par(&[] as &[u32], |n| *n).is_empty());
+    }
+
+    // other unix file systems may reject non UTF-8 names
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn lossy_non_utf8() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let dir = std::env::temp_dir().join(format!("cooklang-fs-lossy-{}", std::process::id()));
+        let dir = Utf8PathBuf::from_path_buf(dir).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("Pasta.cook"), "").unwrap();
+        let name = OsStr::from_bytes(b"Cr\xe8me.cook");
+        std::fs::write(dir.as_std_path().join(name), "").unwrap();
+
+        let mut walker = Walker::new(&dir, 1);
+        walker.set_non_utf8_policy(NonUtf8Policy::Lossy);
+        let names = walker
+            .by_ref()
+            .map(|e| e.unwrap().file_name().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Cr\u{FFFD}me.cook", "Pasta.cook"]);
+        assert!(walker.skipped().is_empty());
+
+        let mut walker = Walker::new(&dir, 1);
+        walker.set_non_utf8_policy(NonUtf8Policy::Ignore);
+        let names = walker
+            .by_ref()
+            .map(|e| e.unwrap().file_name().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Pasta.cook"]);
+        assert_eq!(walker.skipped().len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,149 @@
+//! Keeping a [`FsIndex`] up to date with file system notifications
+//!
+//! Needs the `watch` feature.
+
+use std::sync::{mpsc, RwLockReadGuard};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use notify::{event::ModifyKind, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{matching::into_name_path, Error, FsIndex, SharedFsIndex};
+
+/// A change in the recipes of a [`WatchedIndex`]
+///
+/// The paths are prefixed with the base path, like the ones in the index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Added(Utf8PathBuf),
+    Removed(Utf8PathBuf),
+    /// The content changed, the index is the same
+    Modified(Utf8PathBuf),
+    /// A dir was added, the whole index was walked again
+    Refreshed,
+}
+
+/// A [`FsIndex`] that updates itself when recipes are added, renamed or
+/// deleted
+///
+/// Created with [`FsIndex::watch`]. The index is updated from the watcher
+/// thread, so it's a [`SharedFsIndex`]. Stops watching when dropped.
+pub struct WatchedIndex {
+    index: SharedFsIndex,
+    changes: mpsc::Receiver<Change>,
+    _watcher: RecommendedWatcher,
+}
+
+impl FsIndex {
+    /// Starts watching the base path for changes
+    pub fn watch(self) -> Result<WatchedIndex, Error> {
+        let watched_path = self.base_path.canonicalize_utf8()?;
+        let index = SharedFsIndex::new(self);
+        let (tx, changes) = mpsc::channel();
+
+        let handler_index = index.clone();
+        let handler_path = watched_path.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+                let event = match res {
+                    Ok(event) => event,
+                    Err(e) => {
+                        tracing::error!("Error in file watcher: {e}");
+                        return;
+                    }
+                };
+                let mut index = handler_index.write();
+                for change in index.apply(&handler_path, &event) {
+                    // nobody listening is fine
+                    let _ = tx.send(change);
+                }
+            })?;
+        watcher.watch(watched_path.as_std_path(), RecursiveMode::Recursive)?;
+
+        Ok(WatchedIndex {
+            index,
+            changes,
+            _watcher: watcher,
+        })
+    }
+
+    /// Updates the index with a watcher event, returning what changed
+    fn apply(&mut self, watched_path: &Utf8Path, event: &notify::Event) -> Vec<Change> {
+        let mut changes = Vec::new();
+        // the watcher gives canonical paths, the index uses the base path
+        let paths = event.paths.iter().filter_map(|p| {
+            let relative = p.strip_prefix(watched_path).ok()?;
+            Utf8Path::from_path(relative).map(Utf8Path::to_path_buf)
+        });
+        for relative in paths {
+            let path = self.base_path.join(&relative);
+            let exists = match event.kind {
+                EventKind::Create(_) => true,
+                EventKind::Remove(_) => false,
+                // renames come as one or two events depending on the
+                // platform, so just check the file
+                EventKind::Modify(ModifyKind::Name(_)) => path.exists(),
+                EventKind::Modify(_) => {
+                    if self.cache.contains_path(&path) {
+                        changes.push(Change::Modified(path));
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            if path.is_dir() {
+                if self.walker.includes_dir(&relative) {
+                    if let Err(e) = self.refresh() {
+                        tracing::error!("Can't refresh the index: {e}");
+                    }
+                    changes.push(Change::Refreshed);
+                }
+            } else if exists {
+                if self.walker.includes(&relative) && !self.cache.contains_path(&path) {
+                    if let Ok((name, _)) = into_name_path(path.as_str()) {
+                        self.cache.insert(&name, &path);
+                        changes.push(Change::Added(path));
+                    }
+                }
+            } else {
+                // a removed dir is not a dir anymore, so check the content
+                let removed = self
+                    .cache
+                    .paths()
+                    .filter(|p| p.starts_with(&path))
+                    .map(Utf8Path::to_path_buf)
+                    .collect::<Vec<_>>();
+                for path in removed {
+                    if let Some(name) = path.file_stem() {
+                        self.cache.remove(name, &path);
+                    }
+                    changes.push(Change::Removed(path));
+                }
+            }
+        }
+        changes
+    }
+}
+
+impl WatchedIndex {
+    /// Current state of the index
+    ///
+    /// Don't keep the guard for long, the watcher can't update it meanwhile.
+    pub fn index(&self) -> RwLockReadGuard<'_, FsIndex> {
+        self.index.read()
+    }
+
+    /// A handle to the index to use from other threads
+    ///
+    /// It keeps being updated while this is not dropped.
+    pub fn shared(&self) -> SharedFsIndex {
+        self.index.clone()
+    }
+
+    /// Changes applied to the index, in order
+    ///
+    /// Use [`mpsc::Receiver::try_iter`] to poll them or
+    /// [`mpsc::Receiver::recv`] to wait for the next one.
+    pub fn changes(&self) -> &mpsc::Receiver<Change> {
+        &self.changes
+    }
+}
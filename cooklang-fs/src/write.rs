@@ -0,0 +1,173 @@
+use std::{fs, io::Write};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+/// Extension added to the backup of a file replaced by [`safe_write`]
+pub const BACKUP_EXTENSION: &str = "bak";
+
+/// Options for [`safe_write`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WriteOptions {
+    /// Keep the previous content of the file in `<file>.bak`
+    ///
+    /// Only one backup is kept, it is overwritten on every write.
+    pub backup: bool,
+    /// Write recipes even if they are read-only, see [`crate::is_readonly`]
+    pub force: bool,
+}
+
+/// Writes a file so it's never left half written
+///
+/// The content is written to a temporary file in the same dir, flushed to the
+/// disk and then renamed over `path`. On a crash or power loss the file will
+/// have either the old or the new content, but never a mix of both.
+///
+/// The temporary file starts with a `.`, so the index and walkers ignore it.
+///
+/// Read-only recipes fail with [`std::io::ErrorKind::PermissionDenied`]
+/// unless [`WriteOptions::force`] is set.
+#[tracing::instrument(level = "debug", skip(contents), err)]
+pub fn safe_write(
+    path: &Utf8Path,
+    contents: impl AsRef<[u8]>,
+    options: WriteOptions,
+) -> std::io::Result<()> {
+    if let Err(e) = crate::readonly::check_writable(path, options.force) {
+        return Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied, e));
+    }
+    let tmp = tmp_path(path)?;
+
+    let res = write_tmp(&tmp, contents.as_ref()).and_then(|_| {
+        if options.backup && path.is_file() {
+            fs::copy(path, backup_path(path))?;
+        }
+        fs::rename(&tmp, path)
+    });
+    if res.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    res?;
+
+    sync_parent(path);
+    Ok(())
+}
+
+/// Path of the backup [`safe_write`] creates for `path`
+pub fn backup_path(path: &Utf8Path) -> Utf8PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_string();
+    name.push('.');
+    name.push_str(BACKUP_EXTENSION);
+    path.with_file_name(name)
+}
+
+fn tmp_path(path: &Utf8Path) -> std::io::Result<Utf8PathBuf> {
+    let file_name = path.file_name().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "path has no file name")
+    })?;
+    let name = format!(".{file_name}.{}.tmp", std::process::id());
+    Ok(path.with_file_name(name))
+}
+
+pub(crate) fn write_tmp(tmp: &Utf8Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(tmp)?;
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
+/// Make the rename durable. This is best effort, not every platform allows
+/// opening a dir.
+pub(crate) fn sync_parent(path: &Utf8Path) {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent().filter(|p| !p.as_str().is_empty()) {
+        if let Ok(dir) = fs::File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> Utf8PathBuf {
+        let dir = std::env::temp_dir().join(format!("cooklang-fs-{name}-{}", std::process::id()));
+        let dir = Utf8PathBuf::from_path_buf(dir).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn dir_files(dir: &Utf8Path) -> Vec<String> {
+        let mut files = dir
+            .read_dir_utf8()
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string())
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn replaces_through_tmp_file() {
+        let dir = test_dir("write-replace");
+        let path = dir.join("notes.txt");
+        safe_write(&path, "first", WriteOptions::default()).unwrap();
+        safe_write(&path, "second", WriteOptions::default()).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(dir_files(&dir), ["notes.txt"]);
+        assert!(tmp_path(&path)
+            .unwrap()
+            .file_name()
+            .unwrap()
+            .starts_with('.'));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_backup() {
+        let dir = test_dir("write-backup");
+        let path = dir.join("notes.txt");
+        let options = WriteOptions {
+            backup: true,
+            ..Default::default()
+        };
+        safe_write(&path, "first", options).unwrap();
+        assert!(!backup_path(&path).exists());
+        safe_write(&path, "second", options).unwrap();
+        safe_write(&path, "third", options).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "third");
+        assert_eq!(fs::read_to_string(backup_path(&path)).unwrap(), "second");
+        assert_eq!(dir_files(&dir), ["notes.txt", "notes.txt.bak"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failure_keeps_original() {
+        let dir = test_dir("write-failure");
+        let path = dir.join("notes.txt");
+        fs::write(&path, "original").unwrap();
+
+        // a dir in the way of the tmp file makes the write fail
+        fs::create_dir(tmp_path(&path).unwrap()).unwrap();
+        assert!(safe_write(&path, "new", WriteOptions::default()).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "original");
+        fs::remove_dir(tmp_path(&path).unwrap()).unwrap();
+
+        // read-only recipes are not touched
+        let recipe = dir.join("Soup.cook");
+        fs::write(&recipe, ">> readonly: true\nA @leek.\n").unwrap();
+        let err = safe_write(&recipe, "new", WriteOptions::default()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+        assert_eq!(
+            fs::read_to_string(&recipe).unwrap(),
+            ">> readonly: true\nA @leek.\n"
+        );
+        assert_eq!(dir_files(&dir), ["Soup.cook", "notes.txt"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
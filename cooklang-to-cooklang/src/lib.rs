@@ -10,33 +10,140 @@ use cooklang::{
     IngredientReferenceTarget, Recipe,
 };
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Options for [`print_cooklang_with_options`]
+///
+/// This implements [`Serialize`] and [`Deserialize`], so you can embed it in
+/// other configuration.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct Options {
+    /// Metadata keys that go first, in this order
+    ///
+    /// The rest keep their order after them.
+    pub metadata_order: Vec<String>,
+    /// Align the values of the metadata
+    ///
+    /// ```cooklang
+    /// >> title:    Bread
+    /// >> servings: 2
+    /// ```
+    pub align_metadata: bool,
+    /// Blank lines before a section heading
+    ///
+    /// At least 1.
+    pub section_blank_lines: usize,
+    /// Spaces of indentation of the wrapped lines of a step
+    pub step_indent: usize,
+    /// Spaces around the `%` between the value and the unit
+    pub quantity_spacing: QuantitySpacing,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            metadata_order: [
+                "title",
+                "name",
+                "description",
+                "tags",
+                "servings",
+                "time",
+                "source",
+                "author",
+            ]
+            .map(String::from)
+            .to_vec(),
+            align_metadata: false,
+            section_blank_lines: 1,
+            step_indent: 0,
+            quantity_spacing: QuantitySpacing::Compact,
+        }
+    }
+}
+
+impl Options {
+    /// Position of a metadata key in [`Self::metadata_order`]
+    ///
+    /// The keys that are not there go after all the others.
+    pub fn metadata_rank(&self, key: &str) -> usize {
+        self.metadata_order
+            .iter()
+            .position(|k| k.eq_ignore_ascii_case(key.trim()))
+            .unwrap_or(self.metadata_order.len())
+    }
+
+    /// Writes a metadata line, without the line break
+    ///
+    /// `key_width` is the width of the longest key, only used to align the
+    /// values.
+    pub fn metadata_line(&self, key: &str, value: &str, key_width: usize) -> String {
+        if self.align_metadata {
+            let pad = key_width.saturating_sub(key.chars().count());
+            format!(">> {key}:{:pad$} {value}", "")
+        } else {
+            format!(">> {key}: {value}")
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum QuantitySpacing {
+    /// `{1%cup}`
+    #[default]
+    Compact,
+    /// `{1 % cup}`
+    Spaced,
+}
 
 pub fn print_cooklang<D, V: QuantityValue>(
     recipe: &Recipe<D, V>,
+    writer: impl io::Write,
+) -> io::Result<()> {
+    print_cooklang_with_options(recipe, &Options::default(), writer)
+}
+
+pub fn print_cooklang_with_options<D, V: QuantityValue>(
+    recipe: &Recipe<D, V>,
+    opts: &Options,
     mut writer: impl io::Write,
 ) -> io::Result<()> {
     let w = &mut writer;
 
-    metadata(w, &recipe.metadata)?;
+    metadata(w, &recipe.metadata, opts)?;
     writeln!(w)?;
-    sections(w, recipe)?;
+    sections(w, recipe, opts)?;
 
     Ok(())
 }
 
-fn metadata(w: &mut impl io::Write, metadata: &Metadata) -> io::Result<()> {
+fn metadata(w: &mut impl io::Write, metadata: &Metadata, opts: &Options) -> io::Result<()> {
     // TODO if the recipe has been scaled and multiple servings are defined
     // it can lead to the recipe not parsing.
 
-    for (key, value) in &metadata.map {
-        writeln!(w, ">> {key}: {value}")?;
+    let mut entries = metadata.map.iter().collect::<Vec<_>>();
+    entries.sort_by_key(|(key, _)| opts.metadata_rank(key));
+    let key_width = entries
+        .iter()
+        .map(|(key, _)| key.chars().count())
+        .max()
+        .unwrap_or(0);
+    for (key, value) in entries {
+        writeln!(w, "{}", opts.metadata_line(key, value, key_width))?;
     }
     Ok(())
 }
 
-fn sections<D, V: QuantityValue>(w: &mut impl io::Write, recipe: &Recipe<D, V>) -> io::Result<()> {
+fn sections<D, V: QuantityValue>(
+    w: &mut impl io::Write,
+    recipe: &Recipe<D, V>,
+    opts: &Options,
+) -> io::Result<()> {
     for (index, section) in recipe.sections.iter().enumerate() {
-        w_section(w, section, recipe, index)?;
+        w_section(w, section, recipe, index, opts)?;
     }
     Ok(())
 }
@@ -46,7 +153,14 @@ fn w_section<D, V: QuantityValue>(
     section: &Section,
     recipe: &Recipe<D, V>,
     index: usize,
+    opts: &Options,
 ) -> io::Result<()> {
+    if index > 0 {
+        // the content before already ends with a blank line
+        for _ in 1..opts.section_blank_lines {
+            writeln!(w)?;
+        }
+    }
     if let Some(name) = &section.name {
         writeln!(w, "== {name} ==")?;
     } else if index > 0 {
@@ -54,7 +168,7 @@ fn w_section<D, V: QuantityValue>(
     }
     for content in &section.content {
         match content {
-            cooklang::Content::Step(step) => w_step(w, step, recipe)?,
+            cooklang::Content::Step(step) => w_step(w, step, recipe, opts)?,
             cooklang::Content::Text(text) => w_text_block(w, text)?,
         }
         writeln!(w)?;
@@ -66,6 +180,7 @@ fn w_step<D, V: QuantityValue>(
     w: &mut impl io::Write,
     step: &Step,
     recipe: &Recipe<D, V>,
+    opts: &Options,
 ) -> io::Result<()> {
     let mut step_str = String::new();
     for item in &step.items {
@@ -87,6 +202,7 @@ fn w_step<D, V: QuantityValue>(
                     alias: igr.alias.as_deref(),
                     quantity: igr.quantity.as_ref(),
                     note: igr.note.as_deref(),
+                    spacing: opts.quantity_spacing,
                 }
                 .format(&mut step_str)
             }
@@ -100,6 +216,7 @@ fn w_step<D, V: QuantityValue>(
                     alias: cw.alias.as_deref(),
                     quantity: cw.quantity.clone().map(|v| Quantity::new(v, None)).as_ref(),
                     note: None,
+                    spacing: opts.quantity_spacing,
                 }
                 .format(&mut step_str)
             }
@@ -113,6 +230,7 @@ fn w_step<D, V: QuantityValue>(
                     alias: None,
                     quantity: t.quantity.as_ref(),
                     note: None,
+                    spacing: opts.quantity_spacing,
                 }
                 .format(&mut step_str)
             }
@@ -125,9 +243,13 @@ fn w_step<D, V: QuantityValue>(
             }
         }
     }
-    let width = textwrap::termwidth().min(80);
+    let width = wrap_width();
+    let indent = " ".repeat(opts.step_indent);
     let options = textwrap::Options::new(width)
-        .word_separator(textwrap::WordSeparator::Custom(component_word_separator));
+        .subsequent_indent(&indent)
+        .word_separator(textwrap::WordSeparator::Custom(component_word_separator))
+        .word_splitter(textwrap::WordSplitter::NoHyphenation)
+        .break_words(false);
     let lines = textwrap::wrap(step_str.trim(), options);
     for line in lines {
         writeln!(w, "{line}")?;
@@ -136,7 +258,7 @@ fn w_step<D, V: QuantityValue>(
 }
 
 fn w_text_block(w: &mut impl io::Write, text: &str) -> io::Result<()> {
-    let width = textwrap::termwidth().min(80);
+    let width = wrap_width();
     let indent = "> ";
     let options = textwrap::Options::new(width)
         .initial_indent(indent)
@@ -148,8 +270,20 @@ fn w_text_block(w: &mut impl io::Write, text: &str) -> io::Result<()> {
     Ok(())
 }
 
-// This prevents spliting a multi word component in two lines, because that's
-// invalid.
+/// Terminal width, up to 80
+#[cfg(feature = "terminal")]
+fn wrap_width() -> usize {
+    textwrap::termwidth().min(80)
+}
+
+#[cfg(not(feature = "terminal"))]
+fn wrap_width() -> usize {
+    80
+}
+
+// This prevents spliting a component in two lines, because that's invalid. Multi
+// word components end with `{...}`, single word ones at the next whitespace,
+// and both can have a `(note)`.
 fn component_word_separator<'a>(
     line: &'a str,
 ) -> Box<dyn Iterator<Item = textwrap::core::Word<'a>> + 'a> {
@@ -157,7 +291,9 @@ fn component_word_separator<'a>(
 
     let re = {
         static RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
-        RE.get_or_init(|| regex::Regex::new(r"[@#~][^@#~]*\{[^\}]*\}").unwrap())
+        RE.get_or_init(|| {
+            regex::Regex::new(r"[@#~](?:[^@#~]*\{[^\}]*\}|[^\s(]+)(?:\([^\)]*\))?").unwrap()
+        })
     };
 
     let mut words = vec![];
@@ -168,8 +304,12 @@ fn component_word_separator<'a>(
         if last_added < component.start() {
             words.extend(default_separator.find_words(&line[last_added..component.start()]));
         }
-        words.push(Word::from(&line[component.range()]));
-        last_added = component.end();
+        // keep the whitespace after the component with it, otherwise it
+        // becomes an empty word that can start the next line
+        let rest = &line[component.end()..];
+        let end = component.end() + rest.len() - rest.trim_start_matches(' ').len();
+        words.push(Word::from(&line[component.start()..end]));
+        last_added = end;
     }
     if last_added < line.len() {
         words.extend(default_separator.find_words(&line[last_added..]));
@@ -185,6 +325,7 @@ struct ComponentFormatter<'a, V: QuantityValue> {
     alias: Option<&'a str>,
     quantity: Option<&'a Quantity<V>>,
     note: Option<&'a str>,
+    spacing: QuantitySpacing,
 }
 
 enum ComponentKind {
@@ -209,14 +350,14 @@ impl<'a, V: QuantityValue> ComponentFormatter<'a, V> {
                 Modifiers::NEW => '+',
                 _ => panic!("Unknown modifier: {:?}", m),
             });
-            if m == Modifiers::REF && self.intermediate_data.is_some() {
+            if let Some(IntermediateData {
+                ref_mode,
+                target_kind,
+                val,
+            }) = self.intermediate_data.filter(|_| m == Modifiers::REF)
+            {
                 use cooklang::parser::IntermediateRefMode::*;
                 use cooklang::parser::IntermediateTargetKind::*;
-                let IntermediateData {
-                    ref_mode,
-                    target_kind,
-                    val,
-                } = self.intermediate_data.unwrap();
                 let repr = match (target_kind, ref_mode) {
                     (Step, Number) => format!("{val}"),
                     (Step, Relative) => format!("~{val}"),
@@ -242,7 +383,10 @@ impl<'a, V: QuantityValue> ComponentFormatter<'a, V> {
             w.push('{');
             w.push_str(&q.value.to_string());
             if let Some(unit) = q.unit_text() {
-                write!(w, "%{}", unit).unwrap();
+                match self.spacing {
+                    QuantitySpacing::Compact => write!(w, "%{}", unit).unwrap(),
+                    QuantitySpacing::Spaced => write!(w, " % {}", unit).unwrap(),
+                }
             }
             w.push('}');
         } else if multi_word {
@@ -278,3 +422,29 @@ fn calculate_intermediate_data(
 
     Some(d)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn components_are_single_words() {
+        let words: Vec<_> = component_word_separator(
+            "Add @olive oil{2%tbsp}(extra virgin) and @salt(coarse) to #pan.",
+        )
+        .map(|w| w.word)
+        .collect();
+        assert_eq!(
+            words,
+            [
+                "Add",
+                "@olive oil{2%tbsp}(extra virgin)",
+                "and",
+                "@salt(coarse)",
+                "to",
+                "#pan."
+            ]
+        );
+        assert!(component_word_separator("@a{} @b{}  ").all(|w| !w.word.is_empty()));
+    }
+}
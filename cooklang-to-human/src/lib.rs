@@ -17,7 +17,9 @@ use owo_colors::OwoColorize;
 use std::fmt::Write;
 use tabular::{Row, Table};
 
+mod quantity;
 mod style;
+pub use quantity::{quantity_formatter, set_quantity_formatter, QuantityFormatter};
 use style::styles;
 pub use style::{set_styles, CookStyles};
 
@@ -68,7 +70,7 @@ fn tag_color(tag: &str) -> owo_colors::AnsiColors {
         .enumerate()
         .map(|(i, c)| c as usize * i)
         .reduce(usize::wrapping_add)
-        .map(|h| (h % 7))
+        .map(|h| h % 7)
         .unwrap_or_default();
     match hash {
         0 => owo_colors::AnsiColors::Red,
@@ -216,11 +218,11 @@ fn ingredients(w: &mut impl io::Write, recipe: &ScaledRecipe, converter: &Conver
             .iter()
             .map(|q| quantity_fmt(q).style(outcome_style).to_string())
             .reduce(|s, q| format!("{s}, {q}"))
-            .unwrap_or_default();
+            .unwrap_or_else(|| no_quantity_label().dimmed().to_string());
         row.add_ansi_cell(format!("{content}{}", outcome_char.style(outcome_style)));
 
         if let Some(note) = &igr.note {
-            row.add_cell(format!("({note})"));
+            row.add_ansi_cell(format!("({note})").style(styles().note));
         } else {
             row.add_cell("");
         }
@@ -268,14 +270,14 @@ fn cookware(w: &mut impl io::Write, recipe: &ScaledRecipe) -> Result {
         } else {
             let t = amount
                 .iter()
-                .map(|q| q.to_string())
+                .map(|q| quantity_formatter().quantity(q))
                 .reduce(|s, q| format!("{s}, {q}"))
                 .unwrap();
             row.add_ansi_cell(t);
         }
 
         if let Some(note) = &item.note {
-            row.add_cell(format!("({note})"));
+            row.add_ansi_cell(format!("({note})").style(styles().note));
         } else {
             row.add_cell("");
         }
@@ -294,7 +296,7 @@ fn steps(w: &mut impl io::Write, recipe: &ScaledRecipe) -> Result {
                 w,
                 "{: ^width$}",
                 format!("─── § {} ───", section_index + 1),
-                width = TERM_WIDTH
+                width = wrap_width()
             )?;
         }
 
@@ -428,6 +430,14 @@ fn step_text(recipe: &ScaledRecipe, section: &Section, step: &Step) -> (String,
             )
             .unwrap();
         }
+        if let Some(note) = &igr.note {
+            write!(
+                &mut igrs_text,
+                " {}",
+                format!("({note})").style(styles().note)
+            )
+            .unwrap();
+        }
         if i != step_igrs_line.len() - 1 {
             igrs_text += ", ";
         }
@@ -500,10 +510,16 @@ fn write_igr_count(
 }
 
 fn quantity_fmt(qty: &Quantity) -> String {
+    let f = quantity_formatter();
     if let Some(unit) = qty.unit() {
-        format!("{} {}", qty.value, unit.text().italic())
+        format!(
+            "{}{}{}",
+            f.value(&qty.value),
+            f.unit_separator(),
+            unit.text().italic()
+        )
     } else {
-        format!("{}", qty.value)
+        f.value(&qty.value)
     }
 }
 
@@ -530,14 +546,53 @@ fn print_wrapped(w: &mut impl io::Write, text: &str) -> Result {
     print_wrapped_with_options(w, text, |o| o)
 }
 
-static TERM_WIDTH: once_cell::sync::Lazy<usize> =
-    once_cell::sync::Lazy::new(|| textwrap::termwidth().min(80));
+static NO_QUANTITY_LABEL: once_cell::sync::OnceCell<String> = once_cell::sync::OnceCell::new();
+
+/// Set the text shown in the ingredient list for ingredients without a quantity
+///
+/// By default nothing is shown. Like [`set_wrap_width`], this can only be
+/// called once and before any formatting is done.
+pub fn set_no_quantity_label(label: String) -> bool {
+    NO_QUANTITY_LABEL.set(label).is_ok()
+}
+
+fn no_quantity_label() -> &'static str {
+    NO_QUANTITY_LABEL.get_or_init(String::new)
+}
+
+static TERM_WIDTH: once_cell::sync::OnceCell<usize> = once_cell::sync::OnceCell::new();
+
+/// Set the width used to wrap text
+///
+/// By default it is the terminal width, up to 80, or 80 without the
+/// `terminal` feature. Set it to get the same
+/// output everywhere, like in tests.
+///
+/// Returns true if the width was set. This can only be called once and before
+/// any formatting is done, otherwise it will return false.
+pub fn set_wrap_width(width: usize) -> bool {
+    TERM_WIDTH.set(width).is_ok()
+}
+
+fn wrap_width() -> usize {
+    *TERM_WIDTH.get_or_init(default_wrap_width)
+}
+
+#[cfg(feature = "terminal")]
+fn default_wrap_width() -> usize {
+    textwrap::termwidth().min(80)
+}
+
+#[cfg(not(feature = "terminal"))]
+fn default_wrap_width() -> usize {
+    80
+}
 
 fn print_wrapped_with_options<F>(w: &mut impl io::Write, text: &str, f: F) -> Result
 where
     F: FnOnce(textwrap::Options) -> textwrap::Options,
 {
-    let options = f(textwrap::Options::new(*TERM_WIDTH));
+    let options = f(textwrap::Options::new(wrap_width()));
     let lines = textwrap::wrap(text, options);
     for line in lines {
         writeln!(w, "{}", line)?;
@@ -0,0 +1,152 @@
+use cooklang::quantity::{Number, Quantity, Value};
+use serde::{Deserialize, Serialize};
+
+/// How numbers and quantities are written
+///
+/// The default is like the [`Display`](std::fmt::Display) of [`Value`]: up to
+/// 3 decimals with a dot, no thousands separator and a space before the unit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuantityFormatter {
+    /// Max decimal places, the trailing zeros are not written
+    pub decimals: usize,
+    pub decimal_separator: char,
+    /// Written between every 3 digits of the integer part
+    pub thousands_separator: Option<char>,
+    /// Space between the value and the unit, like `1.5 l` instead of `1.5l`
+    pub unit_space: bool,
+}
+
+impl Default for QuantityFormatter {
+    fn default() -> Self {
+        Self {
+            decimals: 3,
+            decimal_separator: '.',
+            thousands_separator: None,
+            unit_space: true,
+        }
+    }
+}
+
+impl QuantityFormatter {
+    pub fn number(&self, n: f64) -> String {
+        let text = format!("{:.*}", self.decimals, n);
+        let (int, frac) = text.split_once('.').unwrap_or((&text, ""));
+        let frac = frac.trim_end_matches('0');
+        let (sign, int) = match int.strip_prefix('-') {
+            // no "-0" after rounding
+            Some(int) if int.bytes().all(|b| b == b'0') && frac.is_empty() => ("", int),
+            Some(int) => ("-", int),
+            None => ("", int),
+        };
+
+        let mut out = String::from(sign);
+        for (i, c) in int.chars().enumerate() {
+            if i > 0 && (int.len() - i) % 3 == 0 {
+                if let Some(sep) = self.thousands_separator {
+                    out.push(sep);
+                }
+            }
+            out.push(c);
+        }
+        if !frac.is_empty() {
+            out.push(self.decimal_separator);
+            out.push_str(frac);
+        }
+        out
+    }
+
+    pub fn value(&self, value: &Value) -> String {
+        match value {
+            Value::Number(n) => self.number_value(n),
+            Value::Range { start, end } => {
+                format!("{}-{}", self.number_value(start), self.number_value(end))
+            }
+            Value::Text(t) => t.clone(),
+        }
+    }
+
+    /// Goes between the value and the unit
+    pub fn unit_separator(&self) -> &'static str {
+        if self.unit_space {
+            " "
+        } else {
+            ""
+        }
+    }
+
+    pub fn quantity(&self, qty: &Quantity) -> String {
+        match qty.unit_text() {
+            Some(unit) => format!("{}{}{unit}", self.value(&qty.value), self.unit_separator()),
+            None => self.value(&qty.value),
+        }
+    }
+
+    /// Fractions are kept as they are
+    fn number_value(&self, n: &Number) -> String {
+        match n {
+            Number::Regular(n) => self.number(*n),
+            other => other.to_string(),
+        }
+    }
+}
+
+static QUANTITY_FORMATTER: once_cell::sync::OnceCell<QuantityFormatter> =
+    once_cell::sync::OnceCell::new();
+
+/// Set how quantities are written
+///
+/// Like [`set_wrap_width`](crate::set_wrap_width), this can only be called
+/// once and before any formatting is done.
+pub fn set_quantity_formatter(formatter: QuantityFormatter) -> bool {
+    QUANTITY_FORMATTER.set(formatter).is_ok()
+}
+
+/// The formatter used for quantities, see [`set_quantity_formatter`]
+pub fn quantity_formatter() -> &'static QuantityFormatter {
+    QUANTITY_FORMATTER.get_or_init(QuantityFormatter::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers() {
+        let f = QuantityFormatter::default();
+        assert_eq!(f.number(1.5), "1.5");
+        assert_eq!(f.number(2.0), "2");
+        assert_eq!(f.number(1.0 / 3.0), "0.333");
+        assert_eq!(f.number(1234567.25), "1234567.25");
+        assert_eq!(f.number(-0.0001), "0");
+        assert_eq!(f.number(-2.5), "-2.5");
+
+        let f = QuantityFormatter {
+            decimals: 1,
+            decimal_separator: ',',
+            thousands_separator: Some('.'),
+            unit_space: true,
+        };
+        assert_eq!(f.number(1.5), "1,5");
+        assert_eq!(f.number(1234567.26), "1.234.567,3");
+        assert_eq!(f.number(-1000.0), "-1.000");
+        assert_eq!(f.number(999.0), "999");
+    }
+
+    #[test]
+    fn quantities() {
+        let f = QuantityFormatter {
+            decimal_separator: ',',
+            unit_space: false,
+            ..Default::default()
+        };
+        let q = Quantity::new(Value::Number(Number::Regular(1.5)), Some("l".into()));
+        assert_eq!(f.quantity(&q), "1,5l");
+        let range = Value::Range {
+            start: Number::Regular(1.5),
+            end: Number::Regular(2.0),
+        };
+        assert_eq!(f.value(&range), "1,5-2");
+        assert_eq!(f.value(&Value::Text("a pinch".into())), "a pinch");
+    }
+}
@@ -67,6 +67,7 @@ generate_styles_struct! {
     pub intermediate_ref: Style  = Style::new().fg_color(color!(BrightYellow)).italic(),
     pub section_name: Style      = Style::new().bold().underline(),
     pub step_igr_quantity: Style = Style::new().dimmed(),
+    pub note: Style              = Style::new().dimmed().italic(),
 }
 
 static STYLE: once_cell::sync::OnceCell<OwoStyles> = once_cell::sync::OnceCell::new();
@@ -447,14 +447,39 @@ fn print_wrapped(w: &mut impl io::Write, text: &str) -> Result {
     print_wrapped_with_options(w, text, |o| o)
 }
 
-static TERM_WIDTH: once_cell::sync::Lazy<usize> =
-    once_cell::sync::Lazy::new(|| textwrap::termwidth().min(80));
+static TERM_WIDTH: once_cell::sync::OnceCell<usize> = once_cell::sync::OnceCell::new();
+
+/// Set the width used to wrap text
+///
+/// By default it is the terminal width, up to 80, or 80 without the
+/// `terminal` feature. Set it to get the same
+/// output everywhere, like in tests.
+///
+/// Returns true if the width was set. This can only be called once and before
+/// any formatting is done, otherwise it will return false.
+pub fn set_wrap_width(width: usize) -> bool {
+    TERM_WIDTH.set(width).is_ok()
+}
+
+fn wrap_width() -> usize {
+    *TERM_WIDTH.get_or_init(default_wrap_width)
+}
+
+#[cfg(feature = "terminal")]
+fn default_wrap_width() -> usize {
+    textwrap::termwidth().min(80)
+}
+
+#[cfg(not(feature = "terminal"))]
+fn default_wrap_width() -> usize {
+    80
+}
 
 fn print_wrapped_with_options<F>(w: &mut impl io::Write, text: &str, f: F) -> Result
 where
     F: FnOnce(textwrap::Options) -> textwrap::Options,
 {
-    let options = f(textwrap::Options::new(*TERM_WIDTH));
+    let options = f(textwrap::Options::new(wrap_width()));
     let lines = textwrap::wrap(text, options);
     for line in lines {
         writeln!(w, "{}", line)?;
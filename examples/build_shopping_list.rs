@@ -0,0 +1,98 @@
+//! Merges the ingredients of two recipes into a shopping list grouped by
+//! aisle, with the packages to buy, like `chef shopping-list`
+//!
+//! ```sh
+//! cargo run --example build_shopping_list
+//! ```
+
+use anyhow::{bail, Result};
+use cooklang_chef::{
+    fs::Collation,
+    scaling::{check_text, TextQuantities},
+    shopping_list::{aisle, IngredientList, Purchases},
+    ConverterBuilder, CooklangParser, Extensions,
+};
+
+const RECIPES: &[(&str, &str, u32)] = &[
+    (
+        "Pancakes",
+        ">> servings: 2\nMix @eggs{2}, @flour{125%g} and @milk{250%ml}.\n",
+        4,
+    ),
+    (
+        "Omelette",
+        ">> servings: 1\nBeat @eggs{3} and cook with @butter{10%g}.\n",
+        2,
+    ),
+];
+
+const AISLE: &str = "\
+[dairy]
+milk
+butter
+eggs
+
+[baking]
+flour
+";
+
+const PURCHASE: &str = r#"
+eggs = { unit = "dozen", plural = "dozens", size = 12 }
+butter = { unit = "block", size = "250 g" }
+"#;
+
+fn main() -> Result<()> {
+    let mut builder = ConverterBuilder::new();
+    builder.add_bundled_units()?;
+    let parser = CooklangParser::new(Extensions::all(), builder.finish()?);
+    let converter = parser.converter();
+
+    let mut list = IngredientList::new();
+    for &(name, text, servings) in RECIPES {
+        let res = parser.parse(text);
+        if !res.is_valid() {
+            res.into_report().eprint(name, text, true)?;
+            bail!("Error parsing recipe");
+        }
+        let (recipe, _warnings) = res.into_result().unwrap();
+        let mut recipe = recipe.scale(servings, converter);
+        // text quantities can't be added to others
+        check_text(&mut recipe, name, TextQuantities::Drop)?;
+        list.add_recipe(&recipe, converter);
+    }
+
+    let aisle = match aisle::parse(AISLE) {
+        Ok(aisle) => aisle,
+        Err(e) => {
+            cooklang_chef::cooklang::error::write_rich_error(
+                &e,
+                "aisle.conf",
+                AISLE,
+                true,
+                std::io::stderr(),
+            )?;
+            bail!("Error parsing aisle file");
+        }
+    };
+    let purchases = Purchases::parse(PURCHASE, converter)?;
+
+    for (category, items) in list.categorize(&aisle) {
+        println!("[{category}]");
+        let mut items = items.into_iter().collect::<Vec<_>>();
+        Collation::default().sort_by_name(&mut items, |(name, _)| name.as_str());
+        for (name, quantity) in items {
+            let purchase = purchases.get(&name, &quantity);
+            let quantity = quantity
+                .iter()
+                .map(|q| q.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            match purchase {
+                Some(purchase) => println!("{name}: {quantity} → {purchase}"),
+                None => println!("{name}: {quantity}"),
+            }
+        }
+        println!();
+    }
+    Ok(())
+}
@@ -0,0 +1,37 @@
+//! Runs a query over a recipe, like `chef query`
+//!
+//! ```sh
+//! cargo run --example query_recipe -- '.ingredients[].name'
+//! ```
+
+use anyhow::{bail, Context, Result};
+use cooklang_chef::{query::Query, Converter, CooklangParser, Extensions};
+
+const RECIPE: &str = "\
+>> servings: 2
+
+Boil @pasta{200%g} in a #pot.
+Mix with @tomato sauce{150%ml} and @basil.
+";
+
+fn main() -> Result<()> {
+    let expr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| ".ingredients[].name".to_string());
+    let query = Query::parse(&expr).context("Invalid query")?;
+
+    let parser = CooklangParser::new(Extensions::all(), Converter::empty());
+    let res = parser.parse(RECIPE);
+    if !res.is_valid() {
+        res.into_report().eprint("pasta.cook", RECIPE, true)?;
+        bail!("Error parsing recipe");
+    }
+    let (recipe, _warnings) = res.into_result().unwrap();
+    let recipe = recipe.default_scale();
+
+    let value = serde_json::to_value(&recipe)?;
+    for v in query.run(&value)? {
+        println!("{v}");
+    }
+    Ok(())
+}
@@ -0,0 +1,52 @@
+//! Parses a recipe, scales it to 4 servings and prints it like `chef recipe`
+//!
+//! ```sh
+//! cargo run --example scale_and_render
+//! ```
+
+use anyhow::{bail, Result};
+use cooklang_chef::{
+    compound,
+    render::human::print_human,
+    scaling::{check_scaled, ScaleReport, TextQuantities},
+    unit_system::UnitSystem,
+    ConverterBuilder, CooklangParser, Extensions,
+};
+
+const RECIPE: &str = "\
+>> servings: 2
+
+Mix @flour{1 lb 4 oz} with @water{300%ml} and a @salt{pinch}.
+Knead for ~{10%minutes} and bake in the #oven.
+";
+
+fn main() -> Result<()> {
+    let mut builder = ConverterBuilder::new();
+    builder.add_bundled_units()?;
+    let parser = CooklangParser::new(Extensions::all(), builder.finish()?);
+    let converter = parser.converter();
+
+    let res = parser.parse(RECIPE);
+    if !res.is_valid() {
+        res.into_report().eprint("bread.cook", RECIPE, true)?;
+        bail!("Error parsing recipe");
+    }
+    let (mut recipe, _warnings) = res.into_result().unwrap();
+
+    // `1 lb 4 oz` is a single quantity for chef
+    compound::normalize(&mut recipe, converter);
+
+    let by_servings = ScaleReport::by_servings(&recipe);
+    let mut recipe = recipe.scale(4, converter);
+    let report = ScaleReport::new(&recipe, &by_servings);
+    // the pinch of salt can't be scaled, keep it as is
+    check_scaled(&mut recipe, "bread.cook", TextQuantities::KeepText)?;
+    let _ = recipe.convert(UnitSystem::Metric.into(), converter);
+
+    let mut stdout = std::io::stdout().lock();
+    print_human(&recipe, "Bread", converter, &mut stdout)?;
+    if let Some(report) = report {
+        report.write_human(&mut stdout)?;
+    }
+    Ok(())
+}
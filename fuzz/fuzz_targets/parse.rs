@@ -0,0 +1,13 @@
+//! Arbitrary input to the parser
+#![no_main]
+
+use std::sync::OnceLock;
+
+use cooklang::CooklangParser;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    static PARSER: OnceLock<CooklangParser> = OnceLock::new();
+    let parser = PARSER.get_or_init(CooklangParser::extended);
+    cooklang_testing::props::parse_no_panic(parser, input);
+});
@@ -0,0 +1,15 @@
+//! Generated recipes with random syntax characters inserted
+#![no_main]
+
+use std::sync::OnceLock;
+
+use cooklang::CooklangParser;
+use cooklang_testing::{Gen, Rng};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    static PARSER: OnceLock<CooklangParser> = OnceLock::new();
+    let parser = PARSER.get_or_init(CooklangParser::extended);
+    let input = Gen::new(Rng::from_bytes(data)).noisy_recipe();
+    cooklang_testing::props::parse_no_panic(parser, &input);
+});
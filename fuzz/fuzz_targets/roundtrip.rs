@@ -0,0 +1,17 @@
+//! Formatting a generated recipe and parsing it again keeps the recipe
+#![no_main]
+
+use std::sync::OnceLock;
+
+use cooklang::CooklangParser;
+use cooklang_testing::{Gen, Rng};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    static PARSER: OnceLock<CooklangParser> = OnceLock::new();
+    let parser = PARSER.get_or_init(CooklangParser::extended);
+    let input = Gen::new(Rng::from_bytes(data)).recipe();
+    if let Err(err) = cooklang_testing::props::roundtrip_fmt(parser, &input) {
+        panic!("{err}\ninput:\n{input}");
+    }
+});
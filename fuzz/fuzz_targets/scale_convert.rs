@@ -0,0 +1,27 @@
+//! Scaling and converting a generated recipe commute
+#![no_main]
+
+use std::sync::OnceLock;
+
+use cooklang::{convert::System, CooklangParser};
+use cooklang_testing::{Gen, Rng};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    static PARSER: OnceLock<CooklangParser> = OnceLock::new();
+    let parser = PARSER.get_or_init(CooklangParser::extended);
+    let Some((&first, rest)) = data.split_first() else {
+        return;
+    };
+    let target = first as u32 % 12 + 1;
+    let system = if first & 0x80 == 0 {
+        System::Metric
+    } else {
+        System::Imperial
+    };
+    let input = Gen::new(Rng::from_bytes(rest)).recipe();
+    if let Err(err) = cooklang_testing::props::scale_convert_commute(parser, &input, target, system)
+    {
+        panic!("{err}\ninput:\n{input}");
+    }
+});
@@ -0,0 +1,37 @@
+//! Archived recipes
+//!
+//! A recipe is archived if it's inside the `archive` dir of the collection or
+//! has `archived: true` in the metadata. They are hidden from the lists and the
+//! search, but can still be used by name.
+//!
+//! The recipes submitted to `chef serve --submissions` wait for review in the
+//! `inbox` dir, hidden the same way.
+
+use camino::Utf8Path;
+use cooklang::Metadata;
+
+/// Dir in the base path of the collection with the archived recipes
+pub const ARCHIVE_DIR: &str = "archive";
+
+/// Dir in the base path with the submitted recipes pending review
+pub const INBOX_DIR: &str = "inbox";
+
+/// Checks if a path is in the archive or the inbox dir
+pub fn in_archive_dir(base_path: &Utf8Path, path: &Utf8Path) -> bool {
+    path.strip_prefix(base_path)
+        .ok()
+        .and_then(|p| p.components().next())
+        .is_some_and(|c| matches!(c.as_str(), ARCHIVE_DIR | INBOX_DIR))
+}
+
+/// Checks the `archived` metadata key
+pub fn archived_in_metadata(meta: &Metadata) -> bool {
+    meta.map
+        .get("archived")
+        .is_some_and(|v| is_archived_value(v))
+}
+
+/// Checks the value of the `archived` metadata key
+pub fn is_archived_value(value: &str) -> bool {
+    value.trim().eq_ignore_ascii_case("true")
+}
@@ -3,10 +3,13 @@ use clap::{Args, Parser, Subcommand};
 use cooklang::Extensions;
 
 use crate::cmd::{
-    collection, config, convert, edit, generate_completions, list, new, recipe, shopping_list,
-    units,
+    batch_exec, blame, collection, config, convert, debug, doctor, edit, fmt, generate_completions,
+    grep, health, images, init, lint, list, merge_driver, new, query, recipe, shopping_list, stats,
+    trash, units,
 };
 
+#[cfg(feature = "backup")]
+use crate::cmd::backup;
 #[cfg(feature = "serve")]
 use crate::cmd::serve;
 
@@ -46,6 +49,8 @@ pub enum Command {
     Convert(convert::ConvertArgs),
     /// See loaded configuration
     Config(config::ConfigArgs),
+    /// Start a recipe collection
+    Init(init::InitArgs),
     /// Manage the recipe collection
     Collection(collection::CollectionArgs),
     /// Generate shell completions
@@ -54,9 +59,40 @@ pub enum Command {
     New(new::NewArgs),
     /// Edit an existing recipe
     Edit(edit::EditArgs),
+    /// Extract fields of a recipe with a jq-like query
+    Query(query::QueryArgs),
+    /// Search steps by the ingredients, cookware or text they have
+    Grep(grep::GrepArgs),
+    /// Check recipes for common mistakes
+    Lint(lint::LintArgs),
+    /// Score the recipes and the collection from their problems
+    Health(health::HealthArgs),
+    /// Rewrite recipes in a consistent format
+    Fmt(fmt::FmtArgs),
+    /// Show the last commit that changed each step and metadata entry
+    Blame(blame::BlameArgs),
+    /// Find images that don't belong to any recipe and prune them
+    Images(images::ImagesArgs),
+    /// Count the recipes, images and tags of the collection
+    Stats(stats::StatsArgs),
+    #[cfg(feature = "backup")]
+    /// Save the collection to a file or restore it
+    Backup(backup::BackupArgs),
+    /// See, restore or empty the deleted files
+    Trash(trash::TrashArgs),
+    /// Merge concurrent edits of a recipe, as a git merge driver
+    #[command(hide_short_help = true)]
+    MergeDriver(merge_driver::MergeDriverArgs),
+    /// Check the environment and the collection files for problems
+    Doctor(doctor::DoctorArgs),
+    /// Run the commands of a file, loading the collection once
+    BatchExec(batch_exec::BatchExecArgs),
+    /// Debugging and performance tools
+    #[command(hide = true)]
+    Debug(debug::DebugArgs),
 }
 
-#[derive(Debug, Args)]
+#[derive(Debug, Clone, Args)]
 pub struct GlobalArgs {
     /// A units TOML file
     #[arg(long, action = clap::ArgAction::Append, hide_short_help = true, global = true)]
@@ -0,0 +1,145 @@
+//! Arithmetic on quantities
+//!
+//! `cooklang` can only add quantities, with [`Quantity::try_add`]. This adds
+//! subtraction and multiplication and division by a number, with the same
+//! rules: text values can't be operated on and units have to be convertible
+//! to each other. The result is in the unit of the left side.
+//!
+//! Ranges are operated on element by element, like `try_add` does.
+
+use anyhow::{bail, Result};
+use cooklang::{
+    quantity::{Number, Value},
+    Converter, Quantity,
+};
+
+/// Subtraction, multiplication and division of [`Value`]
+pub trait ValueExt: Sized {
+    fn try_sub(&self, rhs: &Self) -> Result<Self>;
+    fn try_mul(&self, factor: f64) -> Result<Self>;
+    /// Fails when dividing by 0
+    fn try_div(&self, divisor: f64) -> Result<Self>;
+}
+
+/// Subtraction, multiplication and division of [`Quantity`]
+pub trait QuantityExt: Sized {
+    /// Subtracts `rhs` converted to the unit of `self`
+    ///
+    /// The result can be negative.
+    fn try_sub(&self, rhs: &Self, converter: &Converter) -> Result<Self>;
+    fn try_mul(&self, factor: f64) -> Result<Self>;
+    /// Fails when dividing by 0
+    fn try_div(&self, divisor: f64) -> Result<Self>;
+}
+
+impl ValueExt for Value {
+    fn try_sub(&self, rhs: &Self) -> Result<Self> {
+        let (start, end) = match (bounds(self)?, bounds(rhs)?) {
+            ((a, b), (c, d)) if a == b && c == d => return Ok(number(a - c)),
+            ((a, b), (c, d)) => (a - c, b - d),
+        };
+        Ok(range(start, end))
+    }
+
+    fn try_mul(&self, factor: f64) -> Result<Self> {
+        if !factor.is_finite() {
+            bail!("Can't multiply by {factor}");
+        }
+        map(self, |n| n * factor)
+    }
+
+    fn try_div(&self, divisor: f64) -> Result<Self> {
+        if divisor == 0.0 || !divisor.is_finite() {
+            bail!("Can't divide by {divisor}");
+        }
+        map(self, |n| n / divisor)
+    }
+}
+
+impl QuantityExt for Quantity<Value> {
+    fn try_sub(&self, rhs: &Self, converter: &Converter) -> Result<Self> {
+        // adding the opposite has the same unit checks and conversions
+        let opposite = QuantityExt::try_mul(rhs, -1.0)?;
+        Ok(self.try_add(&opposite, converter)?)
+    }
+
+    fn try_mul(&self, factor: f64) -> Result<Self> {
+        let mut q = self.clone();
+        q.value = ValueExt::try_mul(&self.value, factor)?;
+        Ok(q)
+    }
+
+    fn try_div(&self, divisor: f64) -> Result<Self> {
+        let mut q = self.clone();
+        q.value = ValueExt::try_div(&self.value, divisor)?;
+        Ok(q)
+    }
+}
+
+/// Start and end of a value, the same for a number
+fn bounds(value: &Value) -> Result<(f64, f64)> {
+    match value {
+        Value::Number(n) => Ok((n.value(), n.value())),
+        Value::Range { start, end } => Ok((start.value(), end.value())),
+        Value::Text(t) => bail!("Can't operate on a text value: '{t}'"),
+    }
+}
+
+fn map(value: &Value, f: impl Fn(f64) -> f64) -> Result<Value> {
+    match bounds(value)? {
+        (start, end) if matches!(value, Value::Range { .. }) => Ok(range(f(start), f(end))),
+        (n, _) => Ok(number(f(n))),
+    }
+}
+
+fn number(n: f64) -> Value {
+    Value::Number(Number::Regular(n))
+}
+
+fn range(start: f64, end: f64) -> Value {
+    Value::Range {
+        start: Number::Regular(start),
+        end: Number::Regular(end),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(value: &Value) -> (f64, f64) {
+        bounds(value).unwrap()
+    }
+
+    #[test]
+    fn value_arithmetic() {
+        let a = number(5.0);
+        let r = range(2.0, 4.0);
+        assert_eq!(values(&a.try_sub(&number(1.5)).unwrap()), (3.5, 3.5));
+        assert!(matches!(a.try_sub(&number(1.5)).unwrap(), Value::Number(_)));
+        assert_eq!(values(&r.try_sub(&number(1.0)).unwrap()), (1.0, 3.0));
+        assert_eq!(values(&a.try_sub(&r).unwrap()), (3.0, 1.0));
+        assert_eq!(values(&ValueExt::try_mul(&r, 1.5).unwrap()), (3.0, 6.0));
+        assert_eq!(values(&ValueExt::try_div(&a, 2.0).unwrap()), (2.5, 2.5));
+
+        assert!(ValueExt::try_div(&a, 0.0).is_err());
+        let text = Value::Text("a pinch".into());
+        assert!(text.try_sub(&a).is_err());
+        assert!(ValueExt::try_mul(&text, 2.0).is_err());
+    }
+
+    #[test]
+    fn quantity_keeps_unit() {
+        let q = Quantity::new(number(300.0), Some("g".into()));
+        let half = QuantityExt::try_div(&q, 2.0).unwrap();
+        assert_eq!(half.unit_text(), Some("g"));
+        assert_eq!(values(&half.value), (150.0, 150.0));
+
+        let converter = Converter::empty();
+        let count = Quantity::new(number(3.0), None);
+        let left = count
+            .try_sub(&Quantity::new(number(1.0), None), &converter)
+            .unwrap();
+        assert_eq!(values(&left.value), (2.0, 2.0));
+    }
+}
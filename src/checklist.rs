@@ -0,0 +1,135 @@
+use std::collections::BTreeSet;
+
+use anyhow::{Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use cooklang_fs::{ContentHash, WriteOptions};
+use serde::{Deserialize, Serialize};
+
+use crate::COOK_DIR;
+
+/// Dir inside `.cooklang` where chef keeps state between runs
+pub const STATE_DIR: &str = "state";
+
+/// Checked items of a shopping list
+///
+/// It's stored in `.cooklang/state/` keyed by a hash of the list, so the
+/// same list always gets the same state back, and a different list starts
+/// clean.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Checklist {
+    #[serde(skip)]
+    path: Utf8PathBuf,
+    pub checked: BTreeSet<String>,
+}
+
+/// Key of a list from its recipes and their servings
+///
+/// The paths are made relative to the collection and sorted, so the CLI and
+/// the web UI get the same key for the same recipes in any order. Servings
+/// should be the default ones when not given, see
+/// [`default_servings`](crate::cmd::shopping_list::default_servings).
+pub fn list_key<'a>(
+    base_path: &Utf8Path,
+    recipes: impl IntoIterator<Item = (&'a Utf8Path, Option<u32>)>,
+) -> String {
+    let base_path = canonical(base_path);
+    let mut lines = recipes
+        .into_iter()
+        .map(|(path, servings)| {
+            let path = canonical(path);
+            let path = path.strip_prefix(&base_path).unwrap_or(&path);
+            let path = path.as_str().replace('\\', "/");
+            match servings {
+                Some(servings) => format!("{path}*{servings}"),
+                None => path,
+            }
+        })
+        .collect::<Vec<_>>();
+    lines.sort_unstable();
+    // 16 chars is enough to not collide in a collection
+    ContentHash::of(&lines.join("\n")).to_string()[..16].to_string()
+}
+
+fn canonical(path: &Utf8Path) -> Utf8PathBuf {
+    path.canonicalize_utf8()
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+impl Checklist {
+    pub fn path(base_path: &Utf8Path, key: &str) -> Utf8PathBuf {
+        base_path
+            .join(COOK_DIR)
+            .join(STATE_DIR)
+            .join(format!("shopping-list-{key}.json"))
+    }
+
+    /// Loads the state of a list, empty if there is none
+    pub fn load(base_path: &Utf8Path, key: &str) -> Result<Self> {
+        let path = Self::path(base_path, key);
+        let mut checklist = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str::<Self>(&content)
+                .with_context(|| format!("Invalid checklist state file: {path}"))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e).context("Failed to read checklist state"),
+        };
+        checklist.path = path;
+        Ok(checklist)
+    }
+
+    pub fn is_checked(&self, item: &str) -> bool {
+        self.checked.contains(item)
+    }
+
+    pub fn set(&mut self, item: &str, checked: bool) {
+        if checked {
+            self.checked.insert(item.to_string());
+        } else {
+            self.checked.remove(item);
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create state dir")?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        cooklang_fs::safe_write(&self.path, content, WriteOptions::default())
+            .context("Failed to write checklist state")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_ignores_order_and_base_path() {
+        let key = |base: &str, recipes: &[(&str, Option<u32>)]| {
+            list_key(
+                Utf8Path::new(base),
+                recipes.iter().map(|(p, s)| (Utf8Path::new(p), *s)),
+            )
+        };
+        let a = key(
+            "/no/such/base",
+            &[
+                ("/no/such/base/Pancakes.cook", Some(4)),
+                ("/no/such/base/Salad.cook", None),
+            ],
+        );
+        let b = key(
+            "/no/such/base/",
+            &[
+                ("/no/such/base/Salad.cook", None),
+                ("/no/such/base/Pancakes.cook", Some(4)),
+            ],
+        );
+        assert_eq!(a, b);
+        assert_eq!(a.len(), 16);
+        assert_ne!(
+            a,
+            key("/no/such/base", &[("/no/such/base/Pancakes.cook", Some(2))])
+        );
+    }
+}
@@ -1,12 +1,28 @@
+#[cfg(feature = "backup")]
+pub mod backup;
+pub mod batch_exec;
+pub mod blame;
 pub mod collection;
 pub mod config;
 pub mod convert;
+pub mod debug;
+pub mod doctor;
 pub mod edit;
+pub mod fmt;
 pub mod generate_completions;
+pub mod grep;
+pub mod health;
+pub mod images;
+pub mod init;
+pub mod lint;
 pub mod list;
+pub mod merge_driver;
 pub mod new;
+pub mod query;
 pub mod recipe;
 #[cfg(feature = "serve")]
 pub mod serve;
 pub mod shopping_list;
+pub mod stats;
+pub mod trash;
 pub mod units;
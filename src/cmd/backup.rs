@@ -0,0 +1,297 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read, Write},
+};
+
+use anstream::{eprintln, println};
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+use clap::{Args, Subcommand, ValueEnum};
+
+use crate::{
+    config::{INDEX_FILE, JOURNAL_FILE},
+    Context, COOK_DIR,
+};
+
+#[derive(Debug, Args)]
+pub struct BackupArgs {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Save the collection to a compressed file
+    ///
+    /// It has every file of the collection: recipes, images, the local config
+    /// and the state of the web UI. The stored index, that can be rebuilt, and
+    /// the `.git` dir are left out.
+    Create {
+        /// File to create, `<collection>-backup.tar.zst` by default
+        #[arg(short, long)]
+        output: Option<Utf8PathBuf>,
+
+        /// Overwrite the file if it exists
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Restore a backup into the collection
+    ///
+    /// Missing files are created and files with the same content are left
+    /// alone.
+    Restore {
+        /// File created with `backup create`
+        file: Utf8PathBuf,
+
+        /// What to do with files that exist with other content
+        #[arg(long, value_enum, default_value_t = OnConflict::Abort)]
+        on_conflict: OnConflict,
+
+        /// Only show what would change
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+enum OnConflict {
+    /// Don't restore anything
+    Abort,
+    /// Keep the current files
+    Skip,
+    /// Replace them, with a `.bak` copy if `backup` is enabled in the config
+    Overwrite,
+}
+
+pub fn run(ctx: &Context, args: BackupArgs) -> Result<()> {
+    match args.command {
+        Command::Create { output, force } => create(ctx, output, force),
+        Command::Restore {
+            file,
+            on_conflict,
+            dry_run,
+        } => restore(ctx, &file, on_conflict, dry_run),
+    }
+}
+
+/// Files that are not worth keeping, relative to the base path
+fn is_excluded(relative: &Utf8Path) -> bool {
+    let cook_dir = Utf8Path::new(COOK_DIR);
+    relative == ".git"
+        || relative == cook_dir.join(INDEX_FILE)
+        || relative == cook_dir.join(JOURNAL_FILE)
+        || relative == cook_dir.join(cooklang_fs::LOCK_FILE)
+}
+
+fn create(ctx: &Context, output: Option<Utf8PathBuf>, force: bool) -> Result<()> {
+    let output = match output {
+        Some(output) => output,
+        None => {
+            let name = ctx
+                .base_path
+                .canonicalize_utf8()?
+                .file_name()
+                .unwrap_or("collection")
+                .to_string();
+            Utf8PathBuf::from(format!("{name}-backup.tar.zst"))
+        }
+    };
+    if output.exists() && !force {
+        bail!("'{output}' already exists, pass `--force` to overwrite it");
+    }
+
+    let _lock = ctx.lock_collection()?;
+    let file = fs::File::create(&output).with_context(|| format!("Failed to create '{output}'"))?;
+    // don't include the backup in itself
+    let output = output.canonicalize_utf8()?;
+    let mut tar = tar::Builder::new(zstd::Encoder::new(file, 0)?);
+    let mut count = 0;
+    add_dir(
+        &mut tar,
+        &ctx.base_path,
+        Utf8Path::new(""),
+        &output,
+        &mut count,
+    )?;
+    tar.into_inner()?.finish()?.sync_all()?;
+    eprintln!("Saved {count} files to {output}");
+    Ok(())
+}
+
+fn add_dir(
+    tar: &mut tar::Builder<impl Write>,
+    base_path: &Utf8Path,
+    dir: &Utf8Path,
+    output: &Utf8Path,
+    count: &mut usize,
+) -> Result<()> {
+    let mut entries = base_path
+        .join(dir)
+        .read_dir_utf8()?
+        .filter_map(|e| match e {
+            Ok(e) => Some(e),
+            Err(e) => {
+                tracing::warn!("Skipping a file in '{dir}': {e}");
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.file_name().cmp(b.file_name()));
+    for entry in entries {
+        let relative = dir.join(entry.file_name());
+        if is_excluded(&relative) {
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            add_dir(tar, base_path, &relative, output, count)?;
+        } else if entry.path().is_file() {
+            if Some(entry.file_name()) == output.file_name()
+                && entry.path().canonicalize_utf8().ok().as_deref() == Some(output)
+            {
+                continue;
+            }
+            tar.append_path_with_name(entry.path(), relative.as_str())
+                .with_context(|| format!("Failed to add '{relative}'"))?;
+            *count += 1;
+        } else {
+            tracing::warn!("Skipping '{relative}': not a file");
+        }
+    }
+    Ok(())
+}
+
+/// Path of a file in a backup, if it's safe to restore
+fn entry_path(path: &std::path::Path) -> Result<Utf8PathBuf> {
+    let Some(path) = Utf8Path::from_path(path) else {
+        bail!("Non UTF-8 path in the backup: {}", path.display());
+    };
+    if !path
+        .components()
+        .all(|c| matches!(c, Utf8Component::Normal(_) | Utf8Component::CurDir))
+    {
+        bail!("Unsafe path in the backup: '{path}'");
+    }
+    Ok(path.to_path_buf())
+}
+
+/// Files of a backup, with their content read lazily
+fn read_backup(file: &Utf8Path) -> Result<tar::Archive<impl Read>> {
+    let file = fs::File::open(file).with_context(|| format!("Failed to open '{file}'"))?;
+    Ok(tar::Archive::new(zstd::Decoder::new(file)?))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Existing {
+    Same,
+    Different,
+}
+
+fn restore(ctx: &Context, file: &Utf8Path, on_conflict: OnConflict, dry_run: bool) -> Result<()> {
+    let _lock = ctx.lock_collection()?;
+
+    // first compare with the current files, so nothing is written on abort
+    let mut existing = HashMap::new();
+    let mut archive = read_backup(file)?;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry_path(&entry.path()?)?;
+        let dest = ctx.base_path.join(&path);
+        let current = match fs::read(&dest) {
+            Ok(current) => current,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read '{dest}'")),
+        };
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content)?;
+        let state = if content == current {
+            Existing::Same
+        } else {
+            Existing::Different
+        };
+        existing.insert(path, state);
+    }
+
+    let mut conflicts = existing
+        .iter()
+        .filter(|(_, s)| **s == Existing::Different)
+        .map(|(p, _)| p.as_str())
+        .collect::<Vec<_>>();
+    conflicts.sort_unstable();
+    if on_conflict == OnConflict::Abort && !conflicts.is_empty() && !dry_run {
+        bail!(
+            "{} files exist with other content:\n  {}\nPass `--on-conflict skip` or `--on-conflict overwrite`",
+            conflicts.len(),
+            conflicts.join("\n  ")
+        );
+    }
+
+    let (mut created, mut overwritten, mut skipped) = (0, 0, 0);
+    let mut archive = read_backup(file)?;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry_path(&entry.path()?)?;
+        let action = match existing.get(&path) {
+            Some(Existing::Same) => continue,
+            Some(Existing::Different) if on_conflict == OnConflict::Overwrite => {
+                overwritten += 1;
+                "overwrite"
+            }
+            Some(Existing::Different) => {
+                skipped += 1;
+                "conflict"
+            }
+            None => {
+                created += 1;
+                "create"
+            }
+        };
+        if dry_run {
+            println!("{action:>9} {path}");
+            continue;
+        }
+        if action == "conflict" {
+            continue;
+        }
+        let dest = ctx.base_path.join(&path);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content)?;
+        cooklang_fs::safe_write(&dest, content, ctx.config.write_options())
+            .with_context(|| format!("Failed to restore '{path}'"))?;
+    }
+
+    if !dry_run {
+        eprintln!("Restored {created} new files, overwritten {overwritten}, skipped {skipped}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn excluded() {
+        assert!(is_excluded(Utf8Path::new(".git")));
+        assert!(is_excluded(Utf8Path::new(".cooklang/index.json")));
+        assert!(!is_excluded(Utf8Path::new(".cooklang/config.toml")));
+        assert!(!is_excluded(Utf8Path::new("Pasta.cook")));
+    }
+
+    #[test]
+    fn unsafe_paths() {
+        assert!(entry_path("Soups/Leek.cook".as_ref()).is_ok());
+        assert!(entry_path("../Leek.cook".as_ref()).is_err());
+        assert!(entry_path("/etc/passwd".as_ref()).is_err());
+    }
+}
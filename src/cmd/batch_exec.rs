@@ -0,0 +1,152 @@
+use std::io::Read;
+
+use anstream::eprintln;
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8PathBuf;
+use clap::{Args, Parser};
+use owo_colors::OwoColorize;
+
+use crate::{args::Command, Context};
+
+#[derive(Debug, Args)]
+pub struct BatchExecArgs {
+    /// File with a command on each line, `-` for stdin
+    ///
+    /// The lines are what goes after `chef`, like `recipe Pasta -s 4`, with
+    /// shell quoting. Empty lines and lines starting with `#` are skipped.
+    #[arg(long, value_name = "FILE")]
+    from: Utf8PathBuf,
+
+    /// Run the remaining commands after one fails
+    #[arg(short, long)]
+    keep_going: bool,
+
+    /// Print each command before running it
+    #[arg(long)]
+    echo: bool,
+}
+
+/// A line of the batch file
+#[derive(Debug, Parser)]
+#[command(no_binary_name = true)]
+struct Line {
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// Runs the commands with the same context
+///
+/// The config, units and recipe index are loaded once for all of them.
+pub fn run(ctx: &Context, args: BatchExecArgs) -> Result<()> {
+    let content = if args.from == "-" {
+        let mut buf = String::new();
+        std::io::stdin()
+            .read_to_string(&mut buf)
+            .context("Failed to read stdin")?;
+        buf
+    } else {
+        std::fs::read_to_string(&args.from)
+            .with_context(|| format!("Failed to read {}", args.from))?
+    };
+    let lines = commands(&content);
+
+    let mut failed = 0;
+    for (number, line) in &lines {
+        if args.echo {
+            eprintln!("{}", format!("> {line}").dimmed());
+        }
+        if let Err(e) = run_line(ctx, line) {
+            failed += 1;
+            eprintln!("{} line {number}: {e:#}", "Error".red().bold());
+            if !args.keep_going {
+                bail!("Stopped at line {number} of {}", args.from);
+            }
+        }
+    }
+    if failed > 0 {
+        bail!("{failed} of {} commands failed", lines.len());
+    }
+    Ok(())
+}
+
+fn run_line(ctx: &Context, line: &str) -> Result<()> {
+    let command = parse_line(line)?;
+    let changes = changes_recipes(&command);
+    let res = crate::run_with_context(ctx, command);
+    // even if it failed, it may have changed something
+    if changes {
+        ctx.recipe_index.refresh();
+    }
+    res
+}
+
+fn parse_line(line: &str) -> Result<Command> {
+    let words = shell_words::split(line)?;
+    let command = Line::try_parse_from(words)?.command;
+    match command {
+        Command::BatchExec(_) => bail!("`batch-exec` can't run in a batch"),
+        // the context is for the collection it was started in
+        Command::Init(_) => bail!("`init` can't run in a batch"),
+        command => Ok(command),
+    }
+}
+
+/// Commands that may create, move or remove recipes, so the index is walked
+/// again for the next lines
+fn changes_recipes(command: &Command) -> bool {
+    !matches!(
+        command,
+        Command::Recipe(_)
+            | Command::List(_)
+            | Command::ShoppingList(_)
+            | Command::Units(_)
+            | Command::Convert(_)
+            | Command::Config(_)
+            | Command::GenerateCompletions(_)
+            | Command::Query(_)
+            | Command::Grep(_)
+            | Command::Lint(_)
+            | Command::Health(_)
+            | Command::Blame(_)
+            | Command::Stats(_)
+            | Command::Doctor(_)
+            | Command::Debug(_)
+    )
+}
+
+/// The commands with their line number, starting at 1
+fn commands(content: &str) -> Vec<(usize, &str)> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines() {
+        let content = "# bulk\nrecipe Pasta -s 4\n\n  stats --json  \n#fmt\n";
+        assert_eq!(
+            commands(content),
+            [(2, "recipe Pasta -s 4"), (4, "stats --json")]
+        );
+    }
+
+    #[test]
+    fn mutating_commands() {
+        let changes = |line| changes_recipes(&parse_line(line).unwrap());
+        assert!(changes("new Soups/Leek -E"));
+        assert!(changes("trash empty"));
+        #[cfg(feature = "backup")]
+        assert!(changes("backup restore collection.zip"));
+        assert!(!changes("recipe Pasta -s 4"));
+        assert!(!changes("stats --json"));
+        assert!(parse_line("init").is_err());
+        assert!(parse_line("batch-exec --from other.txt").is_err());
+    }
+}
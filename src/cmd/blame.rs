@@ -0,0 +1,305 @@
+use std::{ops::Range, process::Stdio};
+
+use anstream::println;
+use anyhow::{bail, Context as _, Result};
+use clap::Args;
+use owo_colors::OwoColorize;
+use serde::Serialize;
+
+use crate::{
+    util::{format_age, resolve_recipe, unix_now},
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct BlameArgs {
+    /// Recipe name
+    recipe: String,
+
+    /// Output the parts as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+/// What a group of lines of a recipe is
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum Part {
+    Metadata {
+        key: String,
+    },
+    Section {
+        name: String,
+    },
+    /// Starting at 1 in each section
+    Step {
+        number: usize,
+    },
+    Note,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+struct Commit {
+    hash: String,
+    author: String,
+    /// Unix time of the author
+    time: u64,
+    summary: String,
+}
+
+#[derive(Serialize)]
+struct Blamed<'a> {
+    #[serde(flatten)]
+    part: &'a Part,
+    /// Starting at 1, both included
+    lines: [usize; 2],
+    commit: &'a Commit,
+}
+
+/// Shows the last commit that changed each step and metadata entry
+pub fn run(ctx: &Context, args: BlameArgs) -> Result<()> {
+    let entry = resolve_recipe(&ctx.recipe_index, &args.recipe)?;
+    if entry.is_markdown() {
+        bail!("`blame` only works with .cook recipes");
+    }
+    let path = entry.path();
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read '{path}'"))?;
+    let commits = git_blame(path)?;
+
+    let blamed = parts(&text)
+        .into_iter()
+        .filter_map(|(part, range)| {
+            // the newest line of the part
+            let commit = commits.get(range.clone())?.iter().max_by_key(|c| c.time)?;
+            Some((part, range, commit))
+        })
+        .collect::<Vec<_>>();
+
+    if args.json {
+        let json = blamed
+            .iter()
+            .map(|(part, range, commit)| Blamed {
+                part,
+                lines: [range.start + 1, range.end],
+                commit: *commit,
+            })
+            .collect::<Vec<_>>();
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
+
+    let lines = text.lines().collect::<Vec<_>>();
+    let now = unix_now();
+    for (part, range, commit) in blamed {
+        let label = match part {
+            Part::Metadata { key } => format!("metadata {key}"),
+            Part::Section { name } => format!("section {name}"),
+            Part::Step { number } => format!("step {number}"),
+            Part::Note => "note".to_string(),
+        };
+        let first = lines[range.start].trim();
+        let first = match first.char_indices().nth(50) {
+            Some((i, _)) => format!("{}…", &first[..i]),
+            None => first.to_string(),
+        };
+        let hash = commit.hash.get(..8).unwrap_or(&commit.hash);
+        println!(
+            "{} {:<16} {:>14}  {:<14} {}",
+            hash.yellow(),
+            truncate(&commit.author, 16),
+            format_age(now.saturating_sub(commit.time)).dimmed(),
+            label.bold(),
+            first.dimmed()
+        );
+    }
+    Ok(())
+}
+
+fn truncate(s: &str, len: usize) -> &str {
+    match s.char_indices().nth(len) {
+        Some((i, _)) => &s[..i],
+        None => s,
+    }
+}
+
+/// Commit of every line of a file
+fn git_blame(path: &camino::Utf8Path) -> Result<Vec<Commit>> {
+    let (dir, file) = match (path.parent(), path.file_name()) {
+        (Some(dir), Some(file)) if !dir.as_str().is_empty() => (dir, file),
+        (_, Some(file)) => (camino::Utf8Path::new("."), file),
+        _ => bail!("Invalid recipe path '{path}'"),
+    };
+    let output = std::process::Command::new("git")
+        .args(["blame", "--line-porcelain", "--", file])
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .output()
+        .context("Failed to run git, is it installed?")?;
+    if !output.status.success() {
+        bail!(
+            "git blame failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(parse_porcelain(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses `git blame --line-porcelain`, which repeats the commit info for
+/// every line
+fn parse_porcelain(output: &str) -> Vec<Commit> {
+    let mut commits = Vec::new();
+    let mut current = Commit::default();
+    let mut header = true;
+    for line in output.lines() {
+        if line.starts_with('\t') {
+            commits.push(std::mem::take(&mut current));
+            header = true;
+            continue;
+        }
+        if header {
+            current.hash = line.split(' ').next().unwrap_or_default().to_string();
+            header = false;
+            continue;
+        }
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match key {
+            "author" => current.author = value.to_string(),
+            "author-time" => current.time = value.parse().unwrap_or_default(),
+            "summary" => current.summary = value.to_string(),
+            _ => {}
+        }
+    }
+    commits
+}
+
+/// Splits a recipe in its parts and the lines of each one, starting at 0
+///
+/// Steps are separated by blank lines, like the parser does. Comment only
+/// blocks are not a part.
+fn parts(text: &str) -> Vec<(Part, Range<usize>)> {
+    let lines = text.lines().collect::<Vec<_>>();
+    let mut parts = Vec::new();
+    let mut i = 0;
+
+    if lines.first().is_some_and(|l| l.trim_end() == "---") {
+        i = 1;
+        while i < lines.len() && lines[i].trim_end() != "---" {
+            let line = lines[i];
+            let is_key = !line.starts_with([' ', '\t', '-', '#']) && line.contains(':');
+            if is_key {
+                let key = line.split_once(':').map_or(line, |(k, _)| k).trim();
+                let part = Part::Metadata {
+                    key: key.to_string(),
+                };
+                parts.push((part, i..i + 1));
+            } else if let Some((_, range)) = parts.last_mut() {
+                // the value continues
+                range.end = i + 1;
+            }
+            i += 1;
+        }
+        i += 1;
+    }
+
+    let mut step = 0;
+    while i < lines.len() {
+        let line = lines[i].trim();
+        if line.is_empty() || line.starts_with("--") {
+            i += 1;
+            continue;
+        }
+        if let Some(entry) = line.strip_prefix(">>") {
+            let key = entry.split_once(':').map_or(entry, |(k, _)| k).trim();
+            let part = Part::Metadata {
+                key: key.to_string(),
+            };
+            parts.push((part, i..i + 1));
+            i += 1;
+            continue;
+        }
+        if line.starts_with('=') {
+            let name = line.trim_matches('=').trim().to_string();
+            parts.push((Part::Section { name }, i..i + 1));
+            step = 0;
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < lines.len() && !lines[i].trim().is_empty() && !lines[i].trim().starts_with(">>") {
+            i += 1;
+        }
+        let part = if line.starts_with('>') {
+            Part::Note
+        } else {
+            step += 1;
+            Part::Step { number: step }
+        };
+        parts.push((part, start..i));
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recipe_parts() {
+        let text = ">> servings: 2\n>> tags: easy\n\nBoil @water.\nAdd @salt.\n\n-- comment\n\n> A note\n\n== Sauce ==\n\nMix.\n";
+        let parts = parts(text);
+        let meta = |key: &str| Part::Metadata {
+            key: key.to_string(),
+        };
+        assert_eq!(
+            parts,
+            vec![
+                (meta("servings"), 0..1),
+                (meta("tags"), 1..2),
+                (Part::Step { number: 1 }, 3..5),
+                (Part::Note, 8..9),
+                (
+                    Part::Section {
+                        name: "Sauce".to_string()
+                    },
+                    10..11
+                ),
+                (Part::Step { number: 1 }, 12..13),
+            ]
+        );
+
+        let text = "---\ntitle: Soup\ntags:\n  - easy\n---\nBoil.\n";
+        assert_eq!(
+            parts(text),
+            vec![
+                (meta("title"), 1..2),
+                (meta("tags"), 2..4),
+                (Part::Step { number: 1 }, 5..6),
+            ]
+        );
+    }
+
+    #[test]
+    fn porcelain() {
+        let output = "\
+1234abcd 1 1 2
+author Ana
+author-mail <ana@example.com>
+author-time 1700000000
+summary Add soup
+filename Soup.cook
+\t>> servings: 2
+1234abcd 2 2
+author Ana
+author-time 1700000000
+summary Add soup
+filename Soup.cook
+\tBoil.
+";
+        let commits = parse_porcelain(output);
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[1].hash, "1234abcd");
+        assert_eq!(commits[1].author, "Ana");
+        assert_eq!(commits[1].time, 1_700_000_000);
+        assert_eq!(commits[1].summary, "Add soup");
+    }
+}
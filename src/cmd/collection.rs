@@ -6,10 +6,10 @@ use clap::{Args, Subcommand};
 
 use crate::{
     config::{
-        config_file_path, global_file_path, global_store, store_at_path, ChefConfig, Config,
-        CHEF_CONFIG_FILE, DEFAULT_CONFIG_FILE,
+        config_file_path, global_file_path, global_store, index_file_path, store_at_path,
+        ChefConfig, Config, CHEF_CONFIG_FILE, DEFAULT_CONFIG_FILE,
     },
-    Context, COOK_DIR,
+    index_builder, Context, COOK_DIR,
 };
 
 #[derive(Debug, Args)]
@@ -39,6 +39,10 @@ enum Command {
     Get,
     /// Removes the default collection
     Unset,
+    /// Index the collection and store the index
+    ///
+    /// The stored index is used by `serve --offline`.
+    Reindex,
 }
 
 pub fn run(ctx: &Context, args: CollectionArgs) -> Result<()> {
@@ -85,6 +89,19 @@ pub fn run(ctx: &Context, args: CollectionArgs) -> Result<()> {
                 eprintln!("No default collection is set");
             }
         }
+        Command::Reindex => {
+            if !ctx.is_collection {
+                bail!("`reindex` needs to run inside a collection");
+            }
+            // locked while walking, so the index has no half done changes
+            let _lock = ctx.lock_collection()?;
+            let index = index_builder(&ctx.base_path, &ctx.config)?
+                .batch_stats(true)
+                .indexed()?;
+            let file = index_file_path(&ctx.base_path);
+            index.save(&file)?;
+            eprintln!("Index stored in {file}");
+        }
     }
     Ok(())
 }
@@ -104,7 +121,7 @@ fn create_collection(path: &Utf8Path) -> Result<()> {
     Ok(())
 }
 
-fn set_default_collection(global: &ChefConfig, path: Option<Utf8PathBuf>) -> Result<()> {
+pub fn set_default_collection(global: &ChefConfig, path: Option<Utf8PathBuf>) -> Result<()> {
     let mut global = global.clone();
     global.default_collection = path.map(|p| p.canonicalize_utf8()).transpose()?;
     global_store(CHEF_CONFIG_FILE, &global)
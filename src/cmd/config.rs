@@ -1,22 +1,25 @@
-use std::{fs, path::Path};
-
 use anstream::{print, println};
-use anyhow::{bail, Context as _, Result};
-use camino::Utf8PathBuf;
-use clap::Args;
-use cooklang::Extensions;
+use anyhow::{Context as _, Result};
+use camino::Utf8Path;
+use clap::{Args, Subcommand};
 
 use crate::{
     config::{
-        config_file_path, global_file_path, global_store, store_at_path, ChefConfig, Config,
-        CHEF_CONFIG_FILE, DEFAULT_CONFIG_FILE,
+        config_file_path, global_file_path, migrate, CHEF_CONFIG_FILE, CONFIG_VERSION,
+        DEFAULT_CONFIG_FILE,
     },
-    Context, COOK_DIR, UTF8_PATH_PANIC,
+    Context,
 };
 
+mod setup;
+
 #[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct ConfigArgs {
-    /// Run the basic interactive config setup
+    #[command(subcommand)]
+    command: Option<ConfigCommand>,
+
+    /// Run the basic interactive config setup, same as `config setup`
     #[arg(long, exclusive = true)]
     setup: bool,
     /// Display the chef config, common to all collections
@@ -24,151 +27,34 @@ pub struct ConfigArgs {
     chef: bool,
 }
 
-pub fn run_setup(config: &Config, chef_config: &ChefConfig) -> Result<()> {
-    use inquire::{Confirm, Text};
-    use owo_colors::OwoColorize;
-
-    let mut config = config.clone();
-
-    let chef = "chef".green().italic().to_string();
-    let cooklang = "cooklang".yellow().to_string();
-
-    println!("Welcome to {chef}!");
-    println!();
-    println!(
-        "{chef} uses an extended version of {cooklang}. You can learn \
-        more here:\n\thttps://github.com/cooklang/cooklang-rs/blob/main/extensions.md"
-    );
-    println!();
-    config.extensions = extensions_prompt(config.extensions)?;
-
-    println!();
-    for line in textwrap::wrap(
-        &format!(
-            "Chef uses collections to store recipes. A collection is just a \
-            directory where a `{COOK_DIR}` dir exists. If you set up a default \
-            collection, you can run {chef} anywhere and access your recipes. \
-            Otherwise, you will have to provide a path or be in a collection."
-        ),
-        textwrap::termwidth().min(80),
-    ) {
-        println!("{line}");
-    }
-    println!();
-
-    let initial_path = chef_config.default_collection.clone().unwrap_or_else(|| {
-        let dirs = directories::UserDirs::new();
-        let parent = if let Some(d) = &dirs {
-            d.document_dir().unwrap_or(d.home_dir())
-        } else {
-            Path::new(".")
-        };
-        let dp = parent.join("Recipes");
-        Utf8PathBuf::from_path_buf(dp).expect(UTF8_PATH_PANIC)
-    });
-    let path = Text::new("Default collection path:")
-        .with_initial_value(initial_path.as_str())
-        .with_help_message("Leave empty or press ESC for none")
-        .prompt_skippable()?
-        .filter(|s| !s.is_empty())
-        .map(Utf8PathBuf::from);
-
-    if let Some(path) = &path {
-        if path.exists() {
-            if !path.is_dir() {
-                bail!("The path is not a dir: {path}");
-            }
-            if !path.join(COOK_DIR).is_dir() && path.read_dir()?.any(|_| true) {
-                bail!("The path is not empty: {path}");
-            }
-        } else {
-            let create = Confirm::new("The directory does not exist. Do you want to create it?")
-                .with_default(true)
-                .prompt()?;
-            if create {
-                fs::create_dir_all(path).context("Failed to create recipes directory")?;
-            } else {
-                bail!("Cancelled");
-            }
-        }
-
-        let config_path = config_file_path(path);
-        if config_path.is_file() {
-            let override_file = Confirm::new(&format!("The config file '{config_path}' already exists and it's content will be lost, do you want to override it?"))
-                .with_default(false)
-                .prompt()?;
-            if override_file {
-                store_at_path(&config_path, &config)?;
-            }
-        } else {
-            if let Some(parent) = config_path.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            store_at_path(&config_path, &config)?;
-        }
-    }
-    println!("Default collection configured");
-
-    println!();
-    for line in textwrap::wrap(
-        &format!(
-            "If you use {chef} outside a collection by using the \
-            `--path` arg , {chef} will use the default configuration."
-        ),
-        textwrap::termwidth().min(80),
-    ) {
-        println!("{line}");
-    }
-    println!();
-
-    let set_default = Confirm::new("Do you want this to be the default config as well?")
-        .with_default(true)
-        .prompt()?;
-    if set_default {
-        global_store(DEFAULT_CONFIG_FILE, &config)?;
-    }
-
-    global_store(
-        CHEF_CONFIG_FILE,
-        ChefConfig {
-            default_collection: path,
-            ..chef_config.clone()
-        },
-    )?;
-
-    println!();
-    println!("{chef} is configured!");
-
-    Ok(())
-}
-
-fn extensions_prompt(enabled: Extensions) -> Result<Extensions> {
-    use std::ops::BitOr;
-
-    let items = Extensions::all()
-        .iter_names()
-        .map(|(name, _)| name)
-        .collect::<Vec<_>>();
-    let enabled = Extensions::all()
-        .iter()
-        .enumerate()
-        .filter_map(|(index, flag)| enabled.contains(flag).then_some(index))
-        .collect::<Vec<_>>();
-
-    let selected = inquire::MultiSelect::new("Enable extensions", items)
-        .with_default(&enabled)
-        .prompt()?;
-
-    Ok(selected
-        .iter()
-        .map(|n| Extensions::from_name(n).unwrap())
-        .fold(Extensions::empty(), Extensions::bitor))
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// Configure chef, asking for what is not given
+    ///
+    /// With `--defaults` and the args it can run without any prompt, for
+    /// scripts and dotfiles.
+    Setup(setup::SetupArgs),
+    /// Upgrade the config files written by an older chef
+    ///
+    /// Migrates the collection config and the global default config. The
+    /// previous content is kept in a `.bak` file next to each one. Comments
+    /// are not kept.
+    Migrate {
+        /// Only show what would change
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 pub fn run(ctx: &Context, args: ConfigArgs) -> Result<()> {
+    match args.command {
+        Some(ConfigCommand::Setup(setup_args)) => return run_setup(ctx, setup_args),
+        Some(ConfigCommand::Migrate { dry_run }) => return run_migrate(ctx, dry_run),
+        None => {}
+    }
+
     if args.setup {
-        run_setup(&ctx.config, &ctx.chef_config)?;
-        return Ok(());
+        return run_setup(ctx, Default::default());
     }
 
     if args.chef {
@@ -178,6 +64,10 @@ pub fn run(ctx: &Context, args: ConfigArgs) -> Result<()> {
     }
 }
 
+fn run_setup(ctx: &Context, args: setup::SetupArgs) -> Result<()> {
+    setup::run_setup(&ctx.config, &ctx.chef_config, &ctx.global_args, args)
+}
+
 fn display_regular(ctx: &Context) -> Result<()> {
     use owo_colors::OwoColorize;
 
@@ -227,3 +117,50 @@ fn display_chef_config(ctx: &Context) -> Result<()> {
     println!("{fence}");
     Ok(())
 }
+
+fn run_migrate(ctx: &Context, dry_run: bool) -> Result<()> {
+    let local = match &ctx.global_args.config_file {
+        Some(file) => file.clone(),
+        None => config_file_path(&ctx.base_path),
+    };
+    let global = global_file_path(DEFAULT_CONFIG_FILE)?;
+    for path in [local, global] {
+        if path.is_file() {
+            migrate_file(&path, dry_run)?;
+        }
+    }
+    Ok(())
+}
+
+fn migrate_file(path: &Utf8Path, dry_run: bool) -> Result<()> {
+    use owo_colors::OwoColorize;
+
+    let content = std::fs::read_to_string(path)?;
+    let mut table: toml::Table =
+        toml::from_str(&content).with_context(|| format!("Invalid config file: {path}"))?;
+    let from = migrate::version(&table)?;
+    if from == CONFIG_VERSION {
+        println!("{path} {}", "is up to date".dimmed());
+        return Ok(());
+    }
+    let notes = migrate::migrate(&mut table).with_context(|| format!("Can't migrate {path}"))?;
+    println!("{path}: version {from} → {CONFIG_VERSION}");
+    for note in notes {
+        println!("  - {note}");
+    }
+    if dry_run {
+        return Ok(());
+    }
+
+    let options = cooklang_fs::WriteOptions {
+        backup: true,
+        ..Default::default()
+    };
+    cooklang_fs::safe_write(path, toml::to_string_pretty(&table)?, options)?;
+    println!(
+        "  {} {}",
+        "Backup at".dimmed(),
+        cooklang_fs::backup_path(path).yellow()
+    );
+    Ok(())
+}
@@ -0,0 +1,286 @@
+use std::{fs, path::Path};
+
+use anstream::println;
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8PathBuf;
+use clap::Args;
+use cooklang::Extensions;
+
+use crate::{
+    config::{
+        config_file_path, global_store, store_at_path, ChefConfig, Config, CHEF_CONFIG_FILE,
+        DEFAULT_CONFIG_FILE,
+    },
+    GlobalArgs, COOK_DIR, UTF8_PATH_PANIC,
+};
+
+#[derive(Debug, Default, Args)]
+pub struct SetupArgs {
+    /// Don't ask anything, use the default for what is not given
+    ///
+    /// The extensions are taken from the global extension args, like
+    /// `--all-extensions`.
+    #[arg(long)]
+    defaults: bool,
+
+    /// Default collection path
+    #[arg(long, value_name = "PATH", conflicts_with = "no_collection")]
+    collection: Option<Utf8PathBuf>,
+
+    /// Don't set a default collection
+    #[arg(long)]
+    no_collection: bool,
+
+    /// Create the collection dir if it does not exist
+    #[arg(long)]
+    create: bool,
+
+    /// Replace the config of the collection if it already exists
+    #[arg(long)]
+    overwrite: bool,
+
+    /// Use the config as the global default config too
+    #[arg(long, value_name = "BOOL")]
+    set_default_config: Option<bool>,
+}
+
+/// Answers of the setup, `None` if they have to be asked
+struct Answers {
+    extensions: Option<Extensions>,
+    collection: Option<Option<Utf8PathBuf>>,
+    create: Option<bool>,
+    overwrite: Option<bool>,
+    set_default_config: Option<bool>,
+}
+
+impl Answers {
+    fn new(args: SetupArgs, global_args: &GlobalArgs, config: &Config) -> Self {
+        let defaults = args.defaults;
+        let flag = |given: bool, default: bool| match (given, defaults) {
+            (true, _) => Some(true),
+            (false, true) => Some(default),
+            (false, false) => None,
+        };
+        let extensions_given = global_args.no_extensions
+            || global_args.all_extensions
+            || global_args.compat_extensions
+            || !global_args.extensions.is_empty();
+
+        Self {
+            extensions: (extensions_given || defaults).then_some(config.extensions),
+            collection: if args.no_collection {
+                Some(None)
+            } else if let Some(path) = args.collection {
+                Some(Some(path))
+            } else {
+                defaults.then_some(None)
+            },
+            create: flag(args.create, true),
+            overwrite: flag(args.overwrite, false),
+            set_default_config: args.set_default_config.or(defaults.then_some(true)),
+        }
+    }
+}
+
+pub fn run_setup(
+    config: &Config,
+    chef_config: &ChefConfig,
+    global_args: &GlobalArgs,
+    args: SetupArgs,
+) -> Result<()> {
+    use owo_colors::OwoColorize;
+
+    let answers = Answers::new(args, global_args, config);
+    let mut config = config.clone();
+
+    let chef = "chef".green().italic().to_string();
+    let cooklang = "cooklang".yellow().to_string();
+
+    println!("Welcome to {chef}!");
+    println!();
+    config.extensions = match answers.extensions {
+        Some(extensions) => extensions,
+        None => {
+            println!(
+                "{chef} uses an extended version of {cooklang}. You can learn \
+                more here:\n\thttps://github.com/cooklang/cooklang-rs/blob/main/extensions.md"
+            );
+            println!();
+            prompt::extensions(config.extensions)?
+        }
+    };
+
+    let path = match answers.collection {
+        Some(path) => path,
+        None => {
+            println!();
+            for line in textwrap::wrap(
+                &format!(
+                    "Chef uses collections to store recipes. A collection is just a \
+                    directory where a `{COOK_DIR}` dir exists. If you set up a default \
+                    collection, you can run {chef} anywhere and access your recipes. \
+                    Otherwise, you will have to provide a path or be in a collection."
+                ),
+                crate::util::term_width(),
+            ) {
+                println!("{line}");
+            }
+            println!();
+
+            let initial_path = chef_config.default_collection.clone().unwrap_or_else(|| {
+                let dirs = directories::UserDirs::new();
+                let parent = if let Some(d) = &dirs {
+                    d.document_dir().unwrap_or(d.home_dir())
+                } else {
+                    Path::new(".")
+                };
+                let dp = parent.join("Recipes");
+                Utf8PathBuf::from_path_buf(dp).expect(UTF8_PATH_PANIC)
+            });
+            prompt::collection(&initial_path)?
+        }
+    };
+
+    if let Some(path) = &path {
+        if path.exists() {
+            if !path.is_dir() {
+                bail!("The path is not a dir: {path}");
+            }
+            if !path.join(COOK_DIR).is_dir() && path.read_dir()?.any(|_| true) {
+                bail!("The path is not empty: {path}");
+            }
+        } else {
+            let create = match answers.create {
+                Some(create) => create,
+                None => prompt::confirm(
+                    "The directory does not exist. Do you want to create it?",
+                    true,
+                )?,
+            };
+            if create {
+                fs::create_dir_all(path).context("Failed to create recipes directory")?;
+            } else {
+                bail!("Cancelled");
+            }
+        }
+
+        let config_path = config_file_path(path);
+        if config_path.is_file() {
+            let override_file = match answers.overwrite {
+                Some(overwrite) => overwrite,
+                None => prompt::confirm(&format!("The config file '{config_path}' already exists and it's content will be lost, do you want to override it?"), false)?,
+            };
+            if override_file {
+                store_at_path(&config_path, &config)?;
+            }
+        } else {
+            if let Some(parent) = config_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            store_at_path(&config_path, &config)?;
+        }
+        println!("Default collection configured");
+    }
+
+    let set_default = match answers.set_default_config {
+        Some(set_default) => set_default,
+        None => {
+            println!();
+            for line in textwrap::wrap(
+                &format!(
+                    "If you use {chef} outside a collection by using the \
+                    `--path` arg , {chef} will use the default configuration."
+                ),
+                crate::util::term_width(),
+            ) {
+                println!("{line}");
+            }
+            println!();
+            prompt::confirm("Do you want this to be the default config as well?", true)?
+        }
+    };
+    if set_default {
+        global_store(DEFAULT_CONFIG_FILE, &config)?;
+    }
+
+    global_store(
+        CHEF_CONFIG_FILE,
+        ChefConfig {
+            default_collection: path,
+            ..chef_config.clone()
+        },
+    )?;
+
+    println!();
+    println!("{chef} is configured!");
+
+    Ok(())
+}
+
+#[cfg(feature = "interactive")]
+mod prompt {
+    use anyhow::Result;
+    use camino::{Utf8Path, Utf8PathBuf};
+    use cooklang::Extensions;
+    use inquire::{Confirm, Text};
+
+    pub fn extensions(enabled: Extensions) -> Result<Extensions> {
+        use std::ops::BitOr;
+
+        let items = Extensions::all()
+            .iter_names()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        let enabled = Extensions::all()
+            .iter()
+            .enumerate()
+            .filter_map(|(index, flag)| enabled.contains(flag).then_some(index))
+            .collect::<Vec<_>>();
+
+        let selected = inquire::MultiSelect::new("Enable extensions", items)
+            .with_default(&enabled)
+            .prompt()?;
+
+        Ok(selected
+            .iter()
+            .map(|n| Extensions::from_name(n).unwrap())
+            .fold(Extensions::empty(), Extensions::bitor))
+    }
+
+    pub fn collection(initial_path: &Utf8Path) -> Result<Option<Utf8PathBuf>> {
+        let path = Text::new("Default collection path:")
+            .with_initial_value(initial_path.as_str())
+            .with_help_message("Leave empty or press ESC for none")
+            .prompt_skippable()?
+            .filter(|s| !s.is_empty())
+            .map(Utf8PathBuf::from);
+        Ok(path)
+    }
+
+    pub fn confirm(message: &str, default: bool) -> Result<bool> {
+        Ok(Confirm::new(message).with_default(default).prompt()?)
+    }
+}
+
+/// Without prompts every answer has to be given
+#[cfg(not(feature = "interactive"))]
+mod prompt {
+    use anyhow::{bail, Result};
+    use camino::{Utf8Path, Utf8PathBuf};
+    use cooklang::Extensions;
+
+    const NO_PROMPTS: &str =
+        "chef was built without prompts (`interactive` feature), use `--defaults` or give the answers as args";
+
+    pub fn extensions(_: Extensions) -> Result<Extensions> {
+        bail!(NO_PROMPTS)
+    }
+
+    pub fn collection(_: &Utf8Path) -> Result<Option<Utf8PathBuf>> {
+        bail!(NO_PROMPTS)
+    }
+
+    pub fn confirm(_: &str, _: bool) -> Result<bool> {
+        bail!(NO_PROMPTS)
+    }
+}
@@ -1,4 +1,5 @@
 use anstream::println;
+use anyhow::{bail, Context as _};
 use clap::Args;
 use cooklang::{
     convert::{ConvertTo, Converter, System},
@@ -6,34 +7,98 @@ use cooklang::{
     Quantity, Value,
 };
 
+use crate::{compound, unit_preference::UnitPreferences};
+
 #[derive(Debug, Args)]
 pub struct ConvertArgs {
-    /// Value to convert, can have decimals
-    value: f64,
-    /// Unit to convert from
-    unit: String,
-    /// Unit to convert to. Can also be "metric", "imperial", or "fit"
+    /// Quantity to convert and the unit to convert to
+    ///
+    /// The quantity is a value, that can have decimals, and its unit. It can
+    /// also have more than one unit, like "1 lb 4 oz".
     ///
+    /// The unit to convert to can also be "metric", "imperial", or "fit".
     /// "metric" and "imperial" will convert to the best possible unit
-    /// in one of those systems.
+    /// in one of those systems. "fit" will try to convert to the best unit in
+    /// the same system. These follow the `unit_preferences` of the config.
+    #[arg(
+        required = true,
+        num_args = 2..,
+        value_names = ["QUANTITY", "TO"],
+        allow_negative_numbers = true
+    )]
+    args: Vec<String>,
+
+    /// Show the result with more than one unit when it fits, like "1 lb 4 oz"
+    #[arg(short, long)]
+    compound: bool,
+
+    /// Convert a difference between two values, like "10 ºC hotter"
     ///
-    /// "fit" will try to convert to the best unit in the same system.
-    to: String,
+    /// Offsets, like the 32 degrees between ºC and ºF, are not applied.
+    #[arg(short, long)]
+    difference: bool,
 }
 
-pub fn run(converter: &Converter, args: ConvertArgs) -> anyhow::Result<()> {
+pub fn run(
+    converter: &Converter,
+    preferences: &UnitPreferences,
+    args: ConvertArgs,
+) -> anyhow::Result<()> {
     use owo_colors::OwoColorize;
 
-    let to = match args.to.as_str() {
+    let (to, quantity) = args.args.split_last().expect("at least 2 args");
+    let (value, unit) = compound::parse(&quantity.join(" "), converter)?;
+
+    let to = match to.as_str() {
         "fit" | "best" => ConvertTo::SameSystem,
         "metric" => ConvertTo::Best(System::Metric),
         "imperial" => ConvertTo::Best(System::Imperial),
-        _ => ConvertTo::Unit(cooklang::convert::ConvertUnit::Key(&args.to)),
+        _ => ConvertTo::Unit(cooklang::convert::ConvertUnit::Key(to)),
     };
 
-    let mut quantity = Quantity::new(Value::Number(Number::Regular(args.value)), Some(args.unit));
+    let from = converter
+        .find_unit(&unit)
+        .with_context(|| format!("Unknown unit: '{unit}'"))?;
+    if let ConvertTo::Unit(cooklang::convert::ConvertUnit::Key(key)) = to {
+        let target = converter
+            .find_unit(key)
+            .with_context(|| format!("Unknown unit: '{key}'"))?;
+        if target.physical_quantity != from.physical_quantity {
+            bail!(
+                "Can't convert {} ({}) to {} ({})",
+                unit,
+                from.physical_quantity,
+                key,
+                target.physical_quantity
+            );
+        }
+    }
 
+    let mut quantity = Quantity::new(Value::Number(Number::Regular(value)), Some(unit));
     quantity.convert(to, converter)?;
+    if !matches!(to, ConvertTo::Unit(_)) {
+        preferences.apply(&mut quantity, converter);
+    }
+
+    if args.difference {
+        // the conversion chose the unit, but the value has to be recalculated
+        // without the offsets
+        let target = quantity
+            .unit_text()
+            .and_then(|u| converter.find_unit(u))
+            .expect("converted to a known unit");
+        let value = value * from.ratio / target.ratio;
+        quantity.value = Value::Number(Number::Regular(value));
+    }
+
+    if args.compound {
+        if let (Value::Number(n), Some(unit)) = (&quantity.value, quantity.unit_text()) {
+            if let Some(text) = compound::render(n.value(), unit, converter) {
+                println!("{text}");
+                return Ok(());
+            }
+        }
+    }
 
     println!(
         "{:#} {}",
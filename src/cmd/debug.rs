@@ -0,0 +1,160 @@
+use std::time::Duration;
+
+use anstream::println;
+use anyhow::{bail, Result};
+use camino::Utf8PathBuf;
+use clap::{Args, Subcommand};
+use cooklang_fs::RecipeEntry;
+
+use crate::{
+    metrics::{measure_parse, Phase},
+    usage,
+    util::resolve_recipe,
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct DebugArgs {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Measure the time and memory it takes to parse a recipe
+    ///
+    /// Allocations are only reported when built with the `alloc-metrics`
+    /// feature.
+    Parse(ParseArgs),
+    /// Show the slowest commands in this machine
+    ///
+    /// Reads the usage log, enable it with `usage_log = true` in the config.
+    Slowest(SlowestArgs),
+}
+
+#[derive(Debug, Args)]
+struct ParseArgs {
+    /// Recipe to parse
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    recipe: Utf8PathBuf,
+
+    /// Parse the recipe this many times and report the fastest
+    #[arg(short = 'n', long, default_value_t = 1)]
+    repeat: u32,
+
+    /// Output the metrics as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Debug, Args)]
+struct SlowestArgs {
+    /// Number of commands to show
+    #[arg(short = 'n', long, default_value_t = 10)]
+    limit: usize,
+
+    /// Output the stats as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+pub fn run(ctx: &Context, args: DebugArgs) -> Result<()> {
+    match args.command {
+        Command::Parse(args) => parse(ctx, args),
+        Command::Slowest(args) => slowest(ctx, args),
+    }
+}
+
+fn parse(ctx: &Context, args: ParseArgs) -> Result<()> {
+    let entry = if args.recipe.extension().is_some_and(|e| e == "cook") && args.recipe.is_file() {
+        RecipeEntry::new(&args.recipe)
+    } else {
+        resolve_recipe(&ctx.recipe_index, args.recipe.as_str())?
+    };
+    let content = entry.read()?;
+    let parser = ctx.parser()?;
+
+    let metrics = (0..args.repeat.max(1))
+        .map(|_| {
+            let options = ctx.parse_options(Some(entry.path()));
+            measure_parse(parser, content.text(), options).1
+        })
+        .min_by_key(|m| m.total.duration)
+        .expect("at least one run");
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&metrics)?);
+        return Ok(());
+    }
+
+    use tabular::{row, Table};
+    println!(
+        "{}: {} bytes, {} events",
+        entry.path(),
+        metrics.input_len,
+        metrics.events
+    );
+    let mut table = Table::new("{:<}  {:>}  {:>}  {:>}");
+    table.add_row(row!("phase", "time", "allocs", "bytes"));
+    for (name, phase) in [
+        ("parse", metrics.parse),
+        ("analysis", metrics.analysis),
+        ("total", metrics.total),
+    ] {
+        table.add_row(phase_row(name, &phase));
+    }
+    print!("{table}");
+    Ok(())
+}
+
+fn slowest(ctx: &Context, args: SlowestArgs) -> Result<()> {
+    let path = usage::usage_file_path(&ctx.base_path);
+    if !path.is_file() {
+        if ctx.config.usage_log {
+            bail!("The usage log is empty, run some commands first");
+        }
+        bail!("No usage log. Enable it with `usage_log = true` in the config");
+    }
+    let entries = usage::read(&path)?;
+    let mut stats = usage::slowest(&entries);
+    stats.truncate(args.limit);
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+
+    use tabular::{row, Table};
+    let ms = |v: f64| format_duration(Duration::from_secs_f64(v / 1e3));
+    let mut table = Table::new("{:<}  {:>}  {:>}  {:>}  {:>}");
+    table.add_row(row!("command", "runs", "median", "mean", "max"));
+    for s in stats {
+        table.add_row(row!(
+            s.command,
+            s.runs,
+            ms(s.median_ms),
+            ms(s.mean_ms),
+            ms(s.max_ms)
+        ));
+    }
+    print!("{table}");
+    Ok(())
+}
+
+fn phase_row(name: &str, phase: &Phase) -> tabular::Row {
+    let opt = |v: Option<u64>| v.map(|v| v.to_string()).unwrap_or_else(|| "-".into());
+    tabular::row!(
+        name,
+        format_duration(phase.duration),
+        opt(phase.allocations),
+        opt(phase.allocated_bytes)
+    )
+}
+
+fn format_duration(d: Duration) -> String {
+    if d < Duration::from_millis(1) {
+        format!("{:.1}µs", d.as_secs_f64() * 1e6)
+    } else {
+        format!("{:.3}ms", d.as_secs_f64() * 1e3)
+    }
+}
@@ -0,0 +1,237 @@
+use std::path::{Path, PathBuf};
+
+use anstream::println;
+use anyhow::{bail, Result};
+use camino::Utf8Path;
+use clap::Args;
+use owo_colors::OwoColorize;
+
+use crate::{
+    config::{config_file_path, global_file_path, CHEF_CONFIG_FILE, DEFAULT_CONFIG_FILE},
+    index_builder,
+    purchase::Purchases,
+    Context, COOK_DIR,
+};
+
+#[derive(Debug, Args)]
+pub struct DoctorArgs {
+    /// Only check the environment: features, external tools, paths and
+    /// permissions
+    #[arg(long)]
+    env: bool,
+}
+
+/// Features chef can be built with
+const FEATURES: &[(&str, bool, &str)] = &[
+    ("serve", cfg!(feature = "serve"), "web UI"),
+    (
+        "interactive",
+        cfg!(feature = "interactive"),
+        "prompts and `shopping-list --interactive`",
+    ),
+    ("send-email", cfg!(feature = "send-email"), "`--send email`"),
+    ("backup", cfg!(feature = "backup"), "`backup` command"),
+    (
+        "alloc-metrics",
+        cfg!(feature = "alloc-metrics"),
+        "allocations in `debug parse`",
+    ),
+];
+
+pub fn run(ctx: &Context, args: DoctorArgs) -> Result<()> {
+    let mut report = Report::default();
+
+    println!("{}", "Features".bold());
+    for &(name, enabled, description) in FEATURES {
+        if enabled {
+            report.ok(&format!("{name} {}", description.dimmed()));
+        } else {
+            report.info(&format!("{name} {}", "not compiled in".dimmed()));
+        }
+    }
+
+    println!("{}", "Tools".bold());
+    match ctx.chef_config.editor().as_deref() {
+        Ok([program, ..]) => report.tool("editor", program, true),
+        Ok([]) => report.problem("editor: empty command"),
+        Err(e) => report.problem(&format!("editor: {e}")),
+    }
+    match ctx.config.send.print.command.first() {
+        Some(program) => report.tool("print", program, false),
+        None => report.problem("print: empty command in the config"),
+    }
+    report.tool("git", "git", false);
+    if let Some(email) = &ctx.config.send.email {
+        if std::env::var_os(&email.password_env).is_some() {
+            report.ok(&format!("email password in ${}", email.password_env));
+        } else {
+            report.problem(&format!(
+                "email password: ${} is not set",
+                email.password_env
+            ));
+        }
+    }
+
+    println!("{}", "Paths".bold());
+    report.ok(&format!("collection {}", ctx.base_path.yellow()));
+    if !ctx.is_collection {
+        report.info(&format!("not a collection, no {COOK_DIR} dir"));
+    }
+    let local = match &ctx.global_args.config_file {
+        Some(file) => file.clone(),
+        None => config_file_path(&ctx.base_path),
+    };
+    report.file("config", &local);
+    report.file("default config", &global_file_path(DEFAULT_CONFIG_FILE)?);
+    report.file("chef config", &global_file_path(CHEF_CONFIG_FILE)?);
+    match &ctx.chef_config.default_collection {
+        Some(path) if path.is_dir() => report.ok(&format!("default collection {}", path.yellow())),
+        Some(path) => report.problem(&format!("default collection {path} does not exist")),
+        None => report.info("no default collection"),
+    }
+
+    println!("{}", "Permissions".bold());
+    if ctx.is_collection {
+        report.writable(&ctx.base_path.join(COOK_DIR));
+    }
+    report.writable(&ctx.base_path);
+    if let Some(dir) = global_file_path(CHEF_CONFIG_FILE)?.parent() {
+        if dir.is_dir() {
+            report.writable(dir);
+        }
+    }
+
+    if !args.env {
+        println!("{}", "Collection files".bold());
+        match ctx.parser() {
+            Ok(parser) => {
+                for file in ctx.config.units(&ctx.base_path) {
+                    report.ok(&format!("units {}", file.yellow()));
+                }
+                if let Some(path) = ctx.config.aisle(&ctx.base_path) {
+                    match std::fs::read_to_string(&path) {
+                        Ok(content) if cooklang::aisle::parse(&content).is_ok() => {
+                            report.ok(&format!("aisle {}", path.yellow()))
+                        }
+                        Ok(_) => report.problem(&format!("aisle {path} has errors")),
+                        Err(e) => report.problem(&format!("aisle {path}: {e}")),
+                    }
+                }
+                if let Some(path) = ctx.config.purchase(&ctx.base_path) {
+                    match Purchases::load(&path, parser.converter()) {
+                        Ok(_) => report.ok(&format!("purchase units {}", path.yellow())),
+                        Err(e) => report.problem(&format!("purchase units: {e:#}")),
+                    }
+                }
+            }
+            Err(e) => report.problem(&format!("units: {e:#}")),
+        }
+
+        if ctx.is_collection {
+            println!("{}", "Recipes".bold());
+            let index = index_builder(&ctx.base_path, &ctx.config)?.indexed()?;
+            report.ok(&format!("{} recipes", index.len()));
+            for path in index.skipped_paths() {
+                let path = path.strip_prefix(&ctx.base_path).unwrap_or(&path);
+                report.warning(&format!(
+                    "skipped non UTF-8 path: {}",
+                    path.display().to_string().dimmed()
+                ));
+            }
+            for (name, paths) in index.duplicates() {
+                let paths = paths
+                    .iter()
+                    .map(|p| p.strip_prefix(&ctx.base_path).unwrap_or(p).as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                report.warning(&format!(
+                    "'{name}' is the name of many recipes, reference them with a path: {}",
+                    paths.dimmed()
+                ));
+            }
+        }
+    }
+
+    println!();
+    if report.problems > 0 {
+        bail!("Found {} problem(s)", report.problems);
+    }
+    println!("{}", "No problems found".green());
+    Ok(())
+}
+
+#[derive(Default)]
+struct Report {
+    problems: usize,
+}
+
+impl Report {
+    fn ok(&self, text: &str) {
+        println!("  {} {text}", "✓".green());
+    }
+
+    fn info(&self, text: &str) {
+        println!("  {} {text}", "-".dimmed());
+    }
+
+    /// Something to review, not a problem
+    fn warning(&self, text: &str) {
+        println!("  {} {text}", "!".yellow().bold());
+    }
+
+    fn problem(&mut self, text: &str) {
+        self.problems += 1;
+        println!("  {} {text}", "✗".red().bold());
+    }
+
+    /// External program, a problem if it's `required` and not found
+    fn tool(&mut self, name: &str, program: &str, required: bool) {
+        match find_program(program) {
+            Some(path) => self.ok(&format!("{name} {}", path.display().yellow())),
+            None if required => self.problem(&format!("{name}: '{program}' not found")),
+            None => self.info(&format!(
+                "{name} {}",
+                format!("'{program}' not found").dimmed()
+            )),
+        }
+    }
+
+    fn file(&mut self, name: &str, path: &Utf8Path) {
+        if path.is_file() {
+            self.ok(&format!("{name} {}", path.yellow()));
+        } else {
+            self.info(&format!("{name} {}", format!("{path} not found").dimmed()));
+        }
+    }
+
+    fn writable(&mut self, dir: &Utf8Path) {
+        match dir.metadata() {
+            Ok(m) if m.permissions().readonly() => {
+                self.problem(&format!("{dir} is read only"));
+            }
+            Ok(_) => self.ok(&format!("{dir} is writable")),
+            Err(e) => self.problem(&format!("{dir}: {e}")),
+        }
+    }
+}
+
+/// Path of a program, like `which`
+fn find_program(program: &str) -> Option<PathBuf> {
+    let program = Path::new(program);
+    if program.components().count() > 1 {
+        return program.is_file().then(|| program.to_path_buf());
+    }
+    let extensions = if cfg!(windows) {
+        vec!["", ".exe", ".cmd", ".bat"]
+    } else {
+        vec![""]
+    };
+    std::env::split_paths(&std::env::var_os("PATH")?).find_map(|dir| {
+        extensions.iter().find_map(|ext| {
+            let mut name = program.as_os_str().to_owned();
+            name.push(ext);
+            let path = dir.join(name);
+            path.is_file().then_some(path)
+        })
+    })
+}
@@ -1,17 +1,24 @@
-use anyhow::{Context as _, Result};
+use anyhow::{bail, Context as _, Result};
 use clap::Args;
 
-use crate::Context;
+use crate::{util::resolve_recipe, Context};
 
 #[derive(Debug, Args)]
 pub struct EditArgs {
     /// Recipe name
     name: String,
+
+    /// Edit it even if it's read-only
+    #[arg(short, long)]
+    force: bool,
 }
 
 pub fn run(args: EditArgs, ctx: &Context) -> Result<()> {
-    let entry = ctx.recipe_index.resolve(&args.name, None)?;
+    let entry = resolve_recipe(&ctx.recipe_index, &args.name)?;
     let path = entry.path();
+    if !args.force && cooklang_fs::is_readonly(path) {
+        bail!("'{path}' is read-only, pass `--force` to edit it anyway");
+    }
 
     let editor = ctx
         .chef_config
@@ -0,0 +1,205 @@
+use anstream::println;
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8PathBuf;
+use clap::Args;
+use cooklang_to_cooklang::{print_cooklang_with_options, Options};
+
+use crate::{
+    unit_system::UnitSystem,
+    util::{recipe_entries, unwrap_recipe},
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct FmtArgs {
+    /// Recipes to format, none for the whole collection
+    recipes: Vec<Utf8PathBuf>,
+
+    /// Convert the quantities to a unit system
+    ///
+    /// Quantities that depend on the servings are written with the default
+    /// servings.
+    #[arg(long, value_enum, value_name = "SYSTEM", conflicts_with = "minimal")]
+    convert_to: Option<UnitSystem>,
+
+    /// Only list the recipes that would change, without writing them
+    ///
+    /// Fails if any recipe would change.
+    #[arg(long)]
+    check: bool,
+
+    /// Only sort and align the metadata and remove trailing whitespace
+    ///
+    /// Everything else is kept byte by byte, so the diffs are small.
+    #[arg(long)]
+    minimal: bool,
+
+    /// Include the archived recipes
+    #[arg(long)]
+    include_archived: bool,
+
+    /// Also format read-only recipes
+    #[arg(short, long)]
+    force: bool,
+}
+
+pub fn run(ctx: &Context, args: FmtArgs) -> Result<()> {
+    let entries = recipe_entries(ctx, &args.recipes, args.include_archived, "fmt")?;
+    let parser = ctx.parser()?;
+
+    let _lock = (!args.check).then(|| ctx.lock_collection()).transpose()?;
+    let options = cooklang_fs::WriteOptions {
+        force: args.force,
+        ..ctx.config.write_options()
+    };
+    let mut changed = 0;
+    for entry in entries {
+        if !args.force && cooklang_fs::is_readonly(entry.path()) {
+            tracing::info!("Skipping read-only {}", entry.path());
+            continue;
+        }
+        let content = entry.read()?;
+        let res = content.parse_with_options(parser, ctx.parse_options(Some(entry.path())));
+        let recipe = unwrap_recipe(res, entry.file_name(), content.text(), ctx)
+            .with_context(|| format!("Can't format {}", entry.path()))?;
+
+        let buf = if args.minimal {
+            minimal(content.text(), &ctx.config.format).into_bytes()
+        } else {
+            let mut buf = Vec::new();
+            if let Some(system) = args.convert_to {
+                let mut recipe = recipe.default_scale();
+                let _ = recipe.convert(system.into(), parser.converter());
+                ctx.config
+                    .unit_preferences
+                    .apply_recipe(&mut recipe, parser.converter());
+                print_cooklang_with_options(&recipe, &ctx.config.format, &mut buf)?;
+            } else {
+                print_cooklang_with_options(&recipe, &ctx.config.format, &mut buf)?;
+            }
+            buf
+        };
+
+        if buf == content.text().as_bytes() {
+            continue;
+        }
+        changed += 1;
+        if args.check {
+            println!("Would format {}", entry.path());
+        } else {
+            cooklang_fs::safe_write(entry.path(), &buf, options)?;
+            println!("Formatted {}", entry.path());
+        }
+    }
+
+    if args.check && changed > 0 {
+        bail!("{changed} recipe(s) need formatting");
+    }
+    Ok(())
+}
+
+/// Sorts (and aligns, if configured) the metadata block at the start of the
+/// recipe and removes trailing whitespace
+///
+/// Line endings are kept as they are.
+fn minimal(text: &str, opts: &Options) -> String {
+    let mut lines = text
+        .split_inclusive('\n')
+        .map(|line| {
+            let (content, ending) = split_ending(line);
+            (content.trim_end(), ending)
+        })
+        .collect::<Vec<_>>();
+
+    let meta_len = lines
+        .iter()
+        .take_while(|(content, _)| content.starts_with(">>"))
+        .count();
+    // the endings stay in place, the last line may not have one
+    let endings = lines[..meta_len].iter().map(|l| l.1).collect::<Vec<_>>();
+    lines[..meta_len].sort_by_key(|(content, _)| opts.metadata_rank(metadata_entry(content).0));
+    for (line, ending) in lines[..meta_len].iter_mut().zip(endings) {
+        line.1 = ending;
+    }
+
+    let aligned = opts.align_metadata.then(|| {
+        let entries = lines[..meta_len]
+            .iter()
+            .map(|(content, _)| metadata_entry(content))
+            .collect::<Vec<_>>();
+        let key_width = entries
+            .iter()
+            .map(|(key, _)| key.chars().count())
+            .max()
+            .unwrap_or(0);
+        entries
+            .into_iter()
+            .map(|(key, value)| opts.metadata_line(key, value, key_width))
+            .collect::<Vec<_>>()
+    });
+
+    let mut out = String::with_capacity(text.len());
+    for (i, (content, ending)) in lines.into_iter().enumerate() {
+        match aligned.as_ref().and_then(|a| a.get(i)) {
+            Some(line) => out.push_str(line),
+            None => out.push_str(content),
+        }
+        out.push_str(ending);
+    }
+    out
+}
+
+/// Key and value of a metadata line
+fn metadata_entry(line: &str) -> (&str, &str) {
+    let line = line.trim_start_matches(">>");
+    match line.split_once(':') {
+        Some((key, value)) => (key.trim(), value.trim()),
+        None => (line.trim(), ""),
+    }
+}
+
+fn split_ending(line: &str) -> (&str, &str) {
+    if let Some(content) = line.strip_suffix("\r\n") {
+        (content, "\r\n")
+    } else if let Some(content) = line.strip_suffix('\n') {
+        (content, "\n")
+    } else {
+        (line, "")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minimal_keeps_untouched() {
+        let text = ">> title: Bread\n>> tags: easy\n\nMix @flour{500%g}  and @water.\n";
+        let opts = Options::default();
+        assert_eq!(minimal(text, &opts), text);
+        let text = "No metadata\r\n\r\nJust steps";
+        assert_eq!(minimal(text, &opts), text);
+    }
+
+    #[test]
+    fn minimal_sorts_metadata() {
+        let opts = Options::default();
+        let text = ">> servings: 2\n>> custom: x\n>> title: Bread  \nMix.";
+        assert_eq!(
+            minimal(text, &opts),
+            ">> title: Bread\n>> servings: 2\n>> custom: x\nMix."
+        );
+        let text = ">> servings: 2\n>> title: Bread";
+        assert_eq!(minimal(text, &opts), ">> title: Bread\n>> servings: 2");
+    }
+
+    #[test]
+    fn minimal_aligns_metadata() {
+        let opts: Options = toml::from_str("align_metadata = true").unwrap();
+        let text = ">> servings: 2\r\n>> title:Bread\r\n\r\nMix.";
+        assert_eq!(
+            minimal(text, &opts),
+            ">> title:    Bread\r\n>> servings: 2\r\n\r\nMix."
+        );
+    }
+}
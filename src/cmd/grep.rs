@@ -0,0 +1,225 @@
+use std::ops::Range;
+
+use anstream::println;
+use anyhow::{bail, Result};
+use camino::Utf8PathBuf;
+use clap::Args;
+use cooklang::{
+    model::{Content, Item, Step},
+    ScalableRecipe,
+};
+use cooklang_fs::NameMatching;
+use owo_colors::OwoColorize;
+
+use crate::{
+    ingredient_name::normalize_name,
+    util::{recipe_entries, CachedRecipeEntry},
+    Context,
+};
+
+#[derive(Debug, Args)]
+#[command(group(clap::ArgGroup::new("filter").required(true).multiple(true)))]
+pub struct GrepArgs {
+    /// Recipes to search, none for the whole collection
+    recipes: Vec<Utf8PathBuf>,
+
+    /// Steps that use an ingredient
+    ///
+    /// Can be given multiple times, a step has to use all of them.
+    #[arg(short, long, group = "filter")]
+    ingredient: Vec<String>,
+
+    /// Steps that use a cookware item
+    ///
+    /// Can be given multiple times, a step has to use all of them.
+    #[arg(short, long, group = "filter")]
+    cookware: Vec<String>,
+
+    /// Steps that contain a text, ignoring case
+    ///
+    /// Can be given multiple times, a step has to contain all of them.
+    #[arg(short, long, group = "filter")]
+    step_contains: Vec<String>,
+
+    /// Include the archived recipes
+    #[arg(long)]
+    include_archived: bool,
+}
+
+pub fn run(ctx: &Context, args: GrepArgs) -> Result<()> {
+    let entries = recipe_entries(ctx, &args.recipes, args.include_archived, "grep")?;
+    let matching = ctx.config.name_matching;
+    let normalize = ctx.config.normalize_ingredients;
+
+    let mut found = 0;
+    for entry in entries {
+        let entry = CachedRecipeEntry::new(entry);
+        let Some(recipe) = entry.parsed(ctx)?.output() else {
+            tracing::warn!("Skipping {}, it has errors", entry.path());
+            continue;
+        };
+        let path = entry
+            .path()
+            .strip_prefix(&ctx.base_path)
+            .unwrap_or(entry.path());
+
+        for (section_index, section) in recipe.sections.iter().enumerate() {
+            for content in &section.content {
+                let Content::Step(step) = content else {
+                    continue;
+                };
+                let Some(text) = step_matches(&args, matching, normalize, recipe, step) else {
+                    continue;
+                };
+                found += 1;
+                let section = match &section.name {
+                    Some(name) => format!("{} ({name})", section_index + 1),
+                    None => (section_index + 1).to_string(),
+                };
+                println!(
+                    "{}:{}:{}: {text}",
+                    path.magenta(),
+                    section.cyan(),
+                    step.number.cyan()
+                );
+            }
+        }
+    }
+
+    if found == 0 {
+        bail!("No steps found");
+    }
+    Ok(())
+}
+
+/// Returns the step text with the matches highlighted if all the filters match
+///
+/// With `normalize`, ingredients match by their normalized name, so `onions`
+/// finds `@large onion`.
+fn step_matches(
+    args: &GrepArgs,
+    matching: NameMatching,
+    normalize: bool,
+    recipe: &ScalableRecipe,
+    step: &Step,
+) -> Option<String> {
+    let ingredient_key = |name: &str| {
+        if normalize {
+            matching.key(&normalize_name(name).name)
+        } else {
+            matching.key(name)
+        }
+    };
+    let mut text = String::new();
+    let mut ingredients = Vec::new();
+    let mut cookware = Vec::new();
+    for item in &step.items {
+        let start = text.len();
+        match item {
+            Item::Text { value } => text.push_str(value),
+            &Item::Ingredient { index } => {
+                let igr = &recipe.ingredients[index];
+                text.push_str(&igr.display_name());
+                ingredients.push((ingredient_key(&igr.name), start..text.len()));
+            }
+            &Item::Cookware { index } => {
+                let cw = &recipe.cookware[index];
+                text.push_str(cw.alias.as_deref().unwrap_or(&cw.name));
+                cookware.push((matching.key(&cw.name), start..text.len()));
+            }
+            &Item::Timer { index } => {
+                let t = &recipe.timers[index];
+                match (&t.name, &t.quantity) {
+                    (_, Some(q)) => text.push_str(&q.to_string()),
+                    (Some(name), None) => text.push_str(name),
+                    (None, None) => {}
+                }
+            }
+            &Item::InlineQuantity { index } => {
+                text.push_str(&recipe.inline_quantities[index].to_string())
+            }
+        }
+    }
+    let text = text.replace('\n', " ");
+
+    let mut spans = Vec::new();
+    for name in &args.ingredient {
+        let key = ingredient_key(name);
+        let found = ingredients
+            .iter()
+            .filter(|(k, _)| *k == key)
+            .map(|(_, span)| span.clone())
+            .collect::<Vec<_>>();
+        if found.is_empty() {
+            return None;
+        }
+        spans.extend(found);
+    }
+    for name in &args.cookware {
+        let key = matching.key(name);
+        let found = cookware
+            .iter()
+            .filter(|(k, _)| *k == key)
+            .map(|(_, span)| span.clone())
+            .collect::<Vec<_>>();
+        if found.is_empty() {
+            return None;
+        }
+        spans.extend(found);
+    }
+    for needle in &args.step_contains {
+        let found = find_ignore_case(&text, needle);
+        if found.is_empty() {
+            return None;
+        }
+        spans.extend(found);
+    }
+
+    Some(highlight(&text, spans))
+}
+
+/// Byte ranges of `pattern` in `text`, ignoring case
+fn find_ignore_case(text: &str, pattern: &str) -> Vec<Range<usize>> {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    if pattern.is_empty() {
+        return Vec::new();
+    }
+    let mut found = Vec::new();
+    let mut next = 0;
+    for (start, _) in text.char_indices() {
+        if start < next {
+            continue;
+        }
+        let mut chars = text[start..].char_indices();
+        let mut end = start;
+        let matched = pattern.iter().all(|p| match chars.next() {
+            Some((i, c)) if c.to_lowercase().eq(p.to_lowercase()) => {
+                end = start + i + c.len_utf8();
+                true
+            }
+            _ => false,
+        });
+        if matched {
+            found.push(start..end);
+            next = end;
+        }
+    }
+    found
+}
+
+fn highlight(text: &str, mut spans: Vec<Range<usize>>) -> String {
+    spans.sort_by_key(|s| s.start);
+    let mut out = String::new();
+    let mut last = 0;
+    for span in spans {
+        // overlapping spans are already highlighted
+        if span.start < last {
+            continue;
+        }
+        out.push_str(&text[last..span.start]);
+        out.push_str(&text[span.clone()].green().bold().to_string());
+        last = span.end;
+    }
+    out.push_str(&text[last..]);
+    out
+}
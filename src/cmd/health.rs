@@ -0,0 +1,282 @@
+use anstream::println;
+use anyhow::{bail, Result};
+use camino::Utf8PathBuf;
+use clap::Args;
+use owo_colors::OwoColorize;
+use serde::Serialize;
+
+use crate::{
+    cmd::lint::{lint, Problem},
+    util::{recipe_entries, CachedRecipeEntry},
+    Context,
+};
+
+/// Highest score, of a recipe without issues
+const MAX_SCORE: u32 = 100;
+/// Max that orphaned images take from the collection score
+const MAX_ORPHAN_PENALTY: u32 = 10;
+
+#[derive(Debug, Args)]
+pub struct HealthArgs {
+    /// Recipes to score, none for the whole collection
+    recipes: Vec<Utf8PathBuf>,
+
+    /// Output the report as JSON
+    #[arg(long, conflicts_with = "badge")]
+    json: bool,
+
+    /// Output the collection score as a shields.io endpoint badge
+    #[arg(long)]
+    badge: bool,
+
+    /// Fail if the collection score is lower
+    #[arg(long, value_name = "SCORE")]
+    min_score: Option<u32>,
+
+    /// Include the archived recipes
+    #[arg(long)]
+    include_archived: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum IssueKind {
+    Error,
+    Warning,
+    Note,
+    Metadata,
+    Image,
+}
+
+impl IssueKind {
+    fn penalty(self) -> u32 {
+        match self {
+            IssueKind::Error => 50,
+            IssueKind::Warning => 10,
+            IssueKind::Note => 2,
+            IssueKind::Metadata | IssueKind::Image => 5,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Issue {
+    kind: IssueKind,
+    message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct RecipeHealth {
+    path: Utf8PathBuf,
+    pub score: u32,
+    issues: Vec<Issue>,
+}
+
+#[derive(Serialize)]
+struct Report {
+    score: u32,
+    recipes: Vec<RecipeHealth>,
+    /// Only when scoring the whole collection
+    orphaned_images: Vec<Utf8PathBuf>,
+}
+
+/// Endpoint badge, see <https://shields.io/badges/endpoint-badge>
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Badge {
+    schema_version: u8,
+    label: &'static str,
+    message: String,
+    color: &'static str,
+}
+
+/// Scores the recipes and the collection from their problems
+pub fn run(ctx: &Context, args: HealthArgs) -> Result<()> {
+    let whole_collection = args.recipes.is_empty();
+    let entries = recipe_entries(ctx, &args.recipes, args.include_archived, "health")?;
+
+    let mut recipes = Vec::with_capacity(entries.len());
+    for entry in entries {
+        recipes.push(recipe_health(ctx, &CachedRecipeEntry::new(entry))?);
+    }
+    recipes.sort_by(|a, b| a.score.cmp(&b.score).then_with(|| a.path.cmp(&b.path)));
+
+    let orphaned_images = if whole_collection {
+        cooklang_fs::orphaned_images(&ctx.base_path, ctx.parser()?)?
+            .into_iter()
+            .map(|image| relative(ctx, image.path))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let scores = recipes.iter().map(|r| r.score).collect::<Vec<_>>();
+    let score = collection_score(&scores, orphaned_images.len());
+
+    if args.badge {
+        let badge = Badge {
+            schema_version: 1,
+            label: "recipe health",
+            message: format!("{score}%"),
+            color: badge_color(score),
+        };
+        println!("{}", serde_json::to_string(&badge)?);
+    } else if args.json {
+        let report = Report {
+            score,
+            recipes,
+            orphaned_images,
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_human(&recipes, &orphaned_images, score);
+    }
+
+    if let Some(min) = args.min_score {
+        if score < min {
+            bail!("The score {score} is lower than {min}");
+        }
+    }
+    Ok(())
+}
+
+fn print_human(recipes: &[RecipeHealth], orphaned_images: &[Utf8PathBuf], score: u32) {
+    for recipe in recipes.iter().filter(|r| !r.issues.is_empty()) {
+        println!("{} {}", score_str(recipe.score), recipe.path.bold());
+        for issue in &recipe.issues {
+            let kind = match issue.kind {
+                IssueKind::Error => "error".red().bold().to_string(),
+                IssueKind::Warning => "warning".yellow().bold().to_string(),
+                IssueKind::Note => "note".cyan().bold().to_string(),
+                IssueKind::Metadata => "metadata".magenta().bold().to_string(),
+                IssueKind::Image => "image".blue().bold().to_string(),
+            };
+            println!("    {kind}: {}", issue.message);
+        }
+    }
+    if !orphaned_images.is_empty() {
+        println!(
+            "{} orphaned images, see `chef images`",
+            orphaned_images.len().yellow().bold()
+        );
+    }
+    let healthy = recipes.iter().filter(|r| r.issues.is_empty()).count();
+    println!(
+        "{healthy} of {} recipes without issues. Score: {}",
+        recipes.len(),
+        score_str(score)
+    );
+}
+
+/// Score colored like the badge
+pub(crate) fn score_str(score: u32) -> String {
+    let text = format!("{score:>3}");
+    match badge_color(score) {
+        "brightgreen" | "green" => text.green().bold().to_string(),
+        "yellow" => text.yellow().bold().to_string(),
+        _ => text.red().bold().to_string(),
+    }
+}
+
+/// Lint problems, parse warnings, missing metadata and images of a recipe
+pub(crate) fn recipe_health(ctx: &Context, entry: &CachedRecipeEntry) -> Result<RecipeHealth> {
+    let mut issues = Vec::new();
+    let mut issue = |kind, message: &str| {
+        issues.push(Issue {
+            kind,
+            message: message.to_string(),
+        })
+    };
+
+    for problem in lint(ctx, entry)? {
+        match problem {
+            Problem::Error(msg) => issue(IssueKind::Error, &msg),
+            Problem::Warning(msg) => issue(IssueKind::Warning, &msg),
+            Problem::Note(msg) => issue(IssueKind::Note, &msg),
+        }
+    }
+    let res = entry.parsed(ctx)?;
+    if res.is_valid() && res.report().has_warnings() {
+        issue(
+            IssueKind::Warning,
+            "has warnings, see them with `chef recipe --check`",
+        );
+    }
+    if let Some(recipe) = res.output() {
+        let meta = &recipe.metadata;
+        if meta.description().is_none() {
+            issue(IssueKind::Metadata, "no description");
+        }
+        if !meta.tags().is_some_and(|t| !t.is_empty()) {
+            issue(IssueKind::Metadata, "no tags");
+        }
+        if meta.servings().is_none() {
+            issue(IssueKind::Metadata, "no servings");
+        }
+        if !meta.map.contains_key("image") && entry.images().is_empty() {
+            issue(IssueKind::Image, "no image");
+        }
+    }
+
+    let score = score(issues.iter().map(|i| i.kind));
+    Ok(RecipeHealth {
+        path: relative(ctx, entry.path().to_owned()),
+        score,
+        issues,
+    })
+}
+
+fn relative(ctx: &Context, path: Utf8PathBuf) -> Utf8PathBuf {
+    path.strip_prefix(&ctx.base_path)
+        .map(ToOwned::to_owned)
+        .unwrap_or(path)
+}
+
+fn score(issues: impl Iterator<Item = IssueKind>) -> u32 {
+    let penalty = issues.map(IssueKind::penalty).sum::<u32>();
+    MAX_SCORE.saturating_sub(penalty)
+}
+
+/// Mean of the recipes, minus 1 for each orphaned image up to
+/// [`MAX_ORPHAN_PENALTY`]
+fn collection_score(recipes: &[u32], orphaned_images: usize) -> u32 {
+    if recipes.is_empty() {
+        return MAX_SCORE;
+    }
+    let total = recipes.iter().sum::<u32>() as f64;
+    let mean = (total / recipes.len() as f64).round() as u32;
+    let orphans = (orphaned_images as u32).min(MAX_ORPHAN_PENALTY);
+    mean.saturating_sub(orphans)
+}
+
+fn badge_color(score: u32) -> &'static str {
+    match score {
+        90.. => "brightgreen",
+        75..=89 => "green",
+        50..=74 => "yellow",
+        _ => "red",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores() {
+        assert_eq!(score(std::iter::empty()), 100);
+        let issues = [IssueKind::Warning, IssueKind::Metadata, IssueKind::Note];
+        assert_eq!(score(issues.into_iter()), 83);
+        let issues = [IssueKind::Error, IssueKind::Error, IssueKind::Warning];
+        assert_eq!(score(issues.into_iter()), 0);
+
+        assert_eq!(collection_score(&[], 3), 100);
+        assert_eq!(collection_score(&[100, 81], 0), 91);
+        assert_eq!(collection_score(&[100, 80], 3), 87);
+        assert_eq!(collection_score(&[100], 50), 90);
+
+        assert_eq!(badge_color(90), "brightgreen");
+        assert_eq!(badge_color(75), "green");
+        assert_eq!(badge_color(74), "yellow");
+        assert_eq!(badge_color(0), "red");
+    }
+}
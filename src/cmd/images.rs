@@ -0,0 +1,117 @@
+use std::fs;
+
+use anstream::{eprintln, println};
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8PathBuf;
+use clap::Args;
+use cooklang_fs::{OrphanedImage, Trash};
+use owo_colors::OwoColorize;
+
+use crate::{config::trash_path, Context};
+
+#[derive(Debug, Args)]
+pub struct ImagesArgs {
+    /// Move the orphaned images to the trash
+    ///
+    /// Images are orphaned when their recipe doesn't exist or doesn't have
+    /// the step they are for. See `chef trash` to restore them.
+    #[arg(long)]
+    prune: bool,
+
+    /// Move them to this dir instead of the trash
+    #[arg(long, value_name = "DIR", requires = "prune")]
+    move_to: Option<Utf8PathBuf>,
+
+    /// Don't ask for confirmation
+    #[arg(short, long, requires = "prune")]
+    yes: bool,
+}
+
+/// Lists the images that don't belong to a recipe, or removes them
+pub fn run(ctx: &Context, args: ImagesArgs) -> Result<()> {
+    if !ctx.is_collection {
+        bail!("`images` needs to run inside a collection");
+    }
+    let orphaned = cooklang_fs::orphaned_images(&ctx.base_path, ctx.parser()?)?;
+    if orphaned.is_empty() {
+        eprintln!("No orphaned images");
+        return Ok(());
+    }
+    for image in &orphaned {
+        print_orphan(ctx, image);
+    }
+    if !args.prune {
+        return Ok(());
+    }
+
+    let action = match &args.move_to {
+        Some(dir) => format!("Move {} images to {dir}?", orphaned.len()),
+        None => format!("Move {} images to the trash?", orphaned.len()),
+    };
+    if !args.yes && !confirm(&action)? {
+        return Ok(());
+    }
+
+    let _lock = ctx.lock_collection()?;
+    let trash = Trash::new(&ctx.base_path, trash_path(&ctx.base_path));
+    for image in &orphaned {
+        match &args.move_to {
+            Some(dir) => {
+                let relative = image
+                    .path
+                    .strip_prefix(&ctx.base_path)
+                    .unwrap_or(&image.path);
+                let dest = dir.join(relative);
+                if let Some(parent) = dest.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                move_file(&image.path, &dest)
+                    .with_context(|| format!("Failed to move '{}'", image.path))?;
+            }
+            None => {
+                trash
+                    .put(&image.path)
+                    .with_context(|| format!("Failed to delete '{}'", image.path))?;
+            }
+        }
+    }
+    eprintln!("Pruned {} images", orphaned.len());
+    Ok(())
+}
+
+fn print_orphan(ctx: &Context, image: &OrphanedImage) {
+    let path = image
+        .path
+        .strip_prefix(&ctx.base_path)
+        .unwrap_or(&image.path);
+    match &image.recipe {
+        None => println!("{path} {}", "no recipe".dimmed()),
+        Some(recipe) => {
+            let recipe = recipe.strip_prefix(&ctx.base_path).unwrap_or(recipe);
+            println!("{path} {}", format!("no such step in {recipe}").dimmed())
+        }
+    }
+}
+
+/// Renames a file, copying it if the destination is in another file system
+fn move_file(from: &camino::Utf8Path, to: &camino::Utf8Path) -> std::io::Result<()> {
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "interactive")]
+fn confirm(message: &str) -> Result<bool> {
+    match inquire::Confirm::new(message).with_default(false).prompt() {
+        Ok(answer) => Ok(answer),
+        Err(inquire::InquireError::OperationCanceled) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(not(feature = "interactive"))]
+fn confirm(_message: &str) -> Result<bool> {
+    bail!("Pass `--yes` to confirm, this build can't ask")
+}
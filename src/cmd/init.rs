@@ -0,0 +1,168 @@
+use std::{fs, process};
+
+use anstream::println;
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::Args;
+use cooklang_fs::LOCK_FILE;
+use owo_colors::OwoColorize;
+
+use crate::{
+    cmd::collection::set_default_collection,
+    config::{
+        config_file_path, store_at_path, Config, AUTO_AISLE, AUTO_PURCHASE, AUTO_UNITS, INDEX_FILE,
+        JOURNAL_FILE, METADATA_CACHE_FILE, SHARE_KEY_FILE, SYNC_LOG_FILE, TRASH_DIR,
+    },
+    usage::USAGE_FILE,
+    Context, COOK_DIR,
+};
+
+#[derive(Debug, Args)]
+pub struct InitArgs {
+    /// Collection dir, created if it does not exist
+    ///
+    /// It can already have recipes.
+    #[arg(value_name = "PATH", default_value = ".", conflicts_with = "path")]
+    new_path: Utf8PathBuf,
+
+    /// Don't add the sample recipe
+    #[arg(long)]
+    no_sample: bool,
+
+    /// Create a git repo, with a `.gitignore` for the files chef generates
+    #[arg(long)]
+    git: bool,
+
+    /// Make it the default collection
+    #[arg(long, alias = "default")]
+    set_default: bool,
+}
+
+const SAMPLE_NAME: &str = "Pancakes.cook";
+const SAMPLE_RECIPE: &str = "\
+>> servings: 4
+>> tags: breakfast, sample
+
+Mix @flour{250%g}, @milk{500%ml}, @eggs{2} and a pinch of @salt in a #bowl.
+
+Heat a #frying pan{} with some @butter and cook the pancakes for ~{2%minutes}
+per side.
+";
+
+const AISLE_STUB: &str = "\
+[baking]
+flour
+
+[dairy]
+milk
+eggs
+butter
+
+[spices]
+salt
+";
+
+const UNITS_STUB: &str = "\
+# Custom units, they are added to the bundled ones.
+# See https://github.com/cooklang/cooklang-rs/blob/main/units.toml
+";
+
+const PURCHASE_STUB: &str = "\
+# Units the ingredients are bought in, shown in the shopping lists
+# eggs = { unit = \"dozen\", size = 12 }
+# butter = { unit = \"block\", size = \"250 g\" }
+";
+
+pub fn run(ctx: &Context, args: InitArgs) -> Result<()> {
+    let path = &args.new_path;
+    if path.exists() && !path.is_dir() {
+        bail!("Path exists and it's not a dir: {path}");
+    }
+    let cook_dir = path.join(COOK_DIR);
+    if cook_dir.exists() {
+        bail!("There is already a collection in {path}");
+    }
+    fs::create_dir_all(&cook_dir).context("Failed to create collection dir")?;
+
+    store_at_path(config_file_path(path), Config::default())?;
+    write_new(&cook_dir.join(AUTO_AISLE), AISLE_STUB)?;
+    write_new(&cook_dir.join(AUTO_UNITS), UNITS_STUB)?;
+    write_new(&cook_dir.join(AUTO_PURCHASE), PURCHASE_STUB)?;
+    if !args.no_sample {
+        write_new(&path.join(SAMPLE_NAME), SAMPLE_RECIPE)?;
+    }
+
+    if args.git {
+        init_git(path)?;
+    }
+
+    if args.set_default {
+        set_default_collection(&ctx.chef_config, Some(path.clone()))?;
+    }
+
+    println!("Collection created in {}", path.yellow());
+    Ok(())
+}
+
+/// Writes a file only if it does not exist
+fn write_new(path: &Utf8Path, content: &str) -> Result<()> {
+    if !path.exists() {
+        cooklang_fs::safe_write(path, content, cooklang_fs::WriteOptions::default())
+            .with_context(|| format!("Failed to write {path}"))?;
+    }
+    Ok(())
+}
+
+fn init_git(path: &Utf8Path) -> Result<()> {
+    if !path.join(".git").exists() {
+        let status = process::Command::new("git")
+            .arg("init")
+            .arg("--quiet")
+            .arg(path)
+            .status()
+            .context("Failed to run git, is it installed?")?;
+        if !status.success() {
+            bail!("git init failed");
+        }
+    }
+
+    // generated by chef, not worth versioning
+    let ignored = [
+        format!("/{COOK_DIR}/{LOCK_FILE}"),
+        format!("/{COOK_DIR}/{INDEX_FILE}"),
+        format!("/{COOK_DIR}/{METADATA_CACHE_FILE}"),
+        format!("/{COOK_DIR}/{JOURNAL_FILE}"),
+        format!("/{COOK_DIR}/{SYNC_LOG_FILE}"),
+        // a secret
+        format!("/{COOK_DIR}/{SHARE_KEY_FILE}"),
+        format!("/{COOK_DIR}/state/"),
+        format!("/{COOK_DIR}/{TRASH_DIR}/"),
+        format!("/{COOK_DIR}/{USAGE_FILE}"),
+        "*.bak".to_string(),
+    ];
+    let gitignore = path.join(".gitignore");
+    let mut content = if gitignore.is_file() {
+        fs::read_to_string(&gitignore)?
+    } else {
+        String::new()
+    };
+    let existing = content.lines().map(str::trim).collect::<Vec<_>>();
+    let missing = ignored
+        .iter()
+        .filter(|line| !existing.contains(&line.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    for line in missing {
+        content.push_str(&line);
+        content.push('\n');
+    }
+    cooklang_fs::safe_write(&gitignore, content, cooklang_fs::WriteOptions::default())
+        .context("Failed to write .gitignore")?;
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use anstream::println;
+use anyhow::{bail, Result};
+use camino::Utf8PathBuf;
+use clap::Args;
+use cooklang::{
+    quantity::{ScalableValue, Value},
+    ScalableRecipe,
+};
+use owo_colors::OwoColorize;
+
+use crate::{
+    unit_system::{mixed_in_metadata, UnitSystem, UnitUsage},
+    util::{recipe_entries, CachedRecipeEntry},
+    variants, Context,
+};
+
+#[derive(Debug, Args)]
+pub struct LintArgs {
+    /// Recipes to check, none for the whole collection
+    recipes: Vec<Utf8PathBuf>,
+
+    /// Include the archived recipes
+    #[arg(long)]
+    include_archived: bool,
+}
+
+pub(crate) enum Problem {
+    Error(String),
+    Warning(String),
+    /// Only a suggestion, does not fail the lint
+    Note(String),
+}
+
+pub fn run(ctx: &Context, args: LintArgs) -> Result<()> {
+    let entries = recipe_entries(ctx, &args.recipes, args.include_archived, "lint")?;
+
+    let mut n_problems = 0;
+    for entry in entries {
+        let entry = CachedRecipeEntry::new(entry);
+        let problems = lint(ctx, &entry)?;
+        let path = entry
+            .path()
+            .strip_prefix(&ctx.base_path)
+            .unwrap_or(entry.path());
+        for problem in &problems {
+            match problem {
+                Problem::Error(msg) => println!("{path}: {}: {msg}", "error".red().bold()),
+                Problem::Warning(msg) => println!("{path}: {}: {msg}", "warning".yellow().bold()),
+                Problem::Note(msg) => println!("{path}: {}: {msg}", "note".cyan().bold()),
+            }
+        }
+        n_problems += problems
+            .iter()
+            .filter(|p| !matches!(p, Problem::Note(_)))
+            .count();
+    }
+
+    if n_problems > 0 {
+        bail!("Found {n_problems} problem(s)");
+    }
+    Ok(())
+}
+
+pub(crate) fn lint(ctx: &Context, entry: &CachedRecipeEntry) -> Result<Vec<Problem>> {
+    let mut problems = Vec::new();
+    let res = entry.parsed(ctx)?;
+    if !res.is_valid() {
+        problems.push(Problem::Error(
+            "has errors, see them with `chef recipe --check`".into(),
+        ));
+    }
+    if let Some(recipe) = res.output() {
+        mixed_units(ctx, recipe, &mut problems)?;
+        text_variants(recipe, &mut problems);
+        precise_staples(ctx, recipe, &mut problems);
+    }
+    Ok(problems)
+}
+
+fn mixed_units(ctx: &Context, recipe: &ScalableRecipe, problems: &mut Vec<Problem>) -> Result<()> {
+    let usage = UnitUsage::new(recipe, ctx.parser()?.converter());
+    if usage.is_mixed() && !mixed_in_metadata(&recipe.metadata) {
+        let fix = match usage.system.unwrap_or(UnitSystem::Metric) {
+            UnitSystem::Metric => "metric",
+            UnitSystem::Imperial => "imperial",
+        };
+        problems.push(Problem::Warning(format!(
+            "mixes metric ({}) and imperial ({}) units. Fix it with `chef fmt --convert-to {fix}` or allow it with `mixed_units: true` in the metadata",
+            usage.metric, usage.imperial
+        )));
+    }
+    Ok(())
+}
+
+fn text_variants(recipe: &ScalableRecipe, problems: &mut Vec<Problem>) {
+    for m in variants::mismatches(recipe) {
+        let msg = if m.servings == 0 {
+            format!(
+                "text variants {} need a list of servings in the metadata",
+                m.text
+            )
+        } else {
+            format!(
+                "text variants {} have {} options but there are {} servings",
+                m.text, m.variants, m.servings
+            )
+        };
+        problems.push(Problem::Warning(msg));
+    }
+}
+
+fn precise_staples(ctx: &Context, recipe: &ScalableRecipe, problems: &mut Vec<Problem>) {
+    for igr in &recipe.ingredients {
+        if !igr.modifiers().should_be_listed() || !ctx.config.is_staple(&igr.name) {
+            continue;
+        }
+        let precise = igr.quantity.as_ref().is_some_and(|q| {
+            q.unit().is_some()
+                && matches!(
+                    q.value,
+                    ScalableValue::Linear(Value::Number(_))
+                        | ScalableValue::Fixed(Value::Number(_))
+                )
+        });
+        if precise {
+            problems.push(Problem::Note(format!(
+                "'{}' is a staple, it's not in shopping lists. A precise quantity may not be needed, like `{{to taste}}`",
+                igr.name
+            )));
+        }
+    }
+}
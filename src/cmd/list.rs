@@ -1,9 +1,18 @@
 use anstream::print;
+use std::collections::HashMap;
+
 use anyhow::{bail, Result};
+use camino::Utf8Path;
 use clap::{builder::ArgPredicate, Args};
-use cooklang_fs::all_recipes;
+use cooklang_fs::{all_recipes_par, MetadataIndex, RecipeTree};
 
-use crate::{util::CachedRecipeEntry, Context};
+use crate::{
+    archive::{archived_in_metadata, in_archive_dir, is_archived_value},
+    cmd::health::{recipe_health, score_str},
+    config::metadata_cache_path,
+    util::CachedRecipeEntry,
+    Context,
+};
 
 #[derive(Debug, Args)]
 pub struct ListArgs {
@@ -29,6 +38,10 @@ pub struct ListArgs {
     )]
     tags: bool,
 
+    /// Show the health score of the recipes, see `chef health`
+    #[arg(long)]
+    health: bool,
+
     /// Add `check` and `images` in one flag
     #[arg(short, long)]
     long: bool,
@@ -45,9 +58,19 @@ pub struct ListArgs {
     #[arg(short = 'n', long, conflicts_with_all = ["paths", "absolute_paths"])]
     count: bool,
 
+    /// Show the recipes nested in their dirs
+    #[arg(long, conflicts_with_all = ["paths", "absolute_paths", "count"])]
+    tree: bool,
+
     /// Force to list recipes even outside a collection
     #[arg(short, long)]
     force: bool,
+
+    /// Include the archived recipes
+    ///
+    /// Recipes in the `archive` dir or with `archived: true` in the metadata.
+    #[arg(long)]
+    include_archived: bool,
 }
 
 pub fn run(ctx: &Context, args: ListArgs) -> Result<()> {
@@ -55,24 +78,44 @@ pub fn run(ctx: &Context, args: ListArgs) -> Result<()> {
         bail!("`list` needs to run inside a collection or pass `--force`");
     }
 
-    let iter = all_recipes(&ctx.base_path, ctx.config.max_depth)?.filter_map(|entry| {
+    // the recipes are parsed anyway when checking them
+    let mut cache = (ctx.is_collection && ctx.config.metadata_cache && !args.check)
+        .then(|| MetadataIndex::load(&ctx.base_path, &metadata_cache_path(&ctx.base_path)));
+
+    // sorted later
+    let iter = all_recipes_par(
+        &ctx.base_path,
+        ctx.config.max_depth,
+        ctx.config.markdown,
+        ctx.config.follow_links,
+    )?
+    .filter_map(|entry| {
+        if !args.include_archived && in_archive_dir(&ctx.base_path, entry.path()) {
+            return None;
+        }
         let entry = CachedRecipeEntry::new(entry);
-        if args.tag.is_empty() {
-            return Some(entry);
+        if args.tag.is_empty() && args.include_archived && !args.tags {
+            return Some((entry, None));
         }
-        let m = entry.metadata(ctx, args.check); // try full parse if check to avoid parsing the recipe twice
-        let Ok(metadata) = m else {
+        let Some(meta) = list_meta(ctx, &entry, args.check, cache.as_mut()) else {
+            if args.tag.is_empty() {
+                // can't know if it's archived, better show it
+                return Some((entry, None));
+            }
             tracing::warn!("Skipping '{}': could not parse metadata", entry.path());
             return None;
         };
+        if !args.include_archived && meta.archived {
+            return None;
+        }
         if !args
             .tag
             .iter()
-            .all(|t| metadata.tags().is_some_and(|tags| tags.contains(t)))
+            .all(|t| meta.tags.as_ref().is_some_and(|tags| tags.contains(t)))
         {
             return None;
         }
-        Some(entry)
+        Some((entry, Some(meta)))
     });
     if args.count {
         let mut count = 0;
@@ -80,7 +123,7 @@ pub fn run(ctx: &Context, args: ListArgs) -> Result<()> {
         let mut with_errors = 0;
         let mut with_images = 0;
         let mut total_images = 0;
-        for entry in iter {
+        for (entry, _) in iter {
             count += 1;
             if args.check || args.images {
                 if args.check {
@@ -114,23 +157,115 @@ pub fn run(ctx: &Context, args: ListArgs) -> Result<()> {
         }
         print!("{table}");
     } else {
-        let mut table = tabular::Table::new("{:<}{:<}{:<}{:<}");
+        let mut table = tabular::Table::new("{:<}{:<}{:<}{:<}{:<}");
         let mut all = iter.collect::<Vec<_>>();
-        all.sort_unstable_by(|a, b| a.path().cmp(b.path()));
-        for entry in &all {
-            let row = list_row(ctx, &args, entry)?;
-            table.add_row(row);
+        if args.tree {
+            let mut tree =
+                RecipeTree::from_paths(&ctx.base_path, all.iter().map(|(e, _)| e.path()));
+            tree.sort(ctx.config.collation);
+            let by_path = all
+                .iter()
+                .map(|(e, meta)| (e.path(), (e, meta.as_ref())))
+                .collect::<HashMap<_, _>>();
+            add_tree_rows(ctx, &args, &tree, 0, &by_path, &mut table)?;
+        } else {
+            ctx.config
+                .collation
+                .sort_by_path(&mut all, |(e, _)| e.path());
+            for (entry, meta) in &all {
+                let name = entry_name(ctx, &args, entry)?;
+                let row = list_row(ctx, &args, name, entry, meta.as_ref())?;
+                table.add_row(row);
+            }
         }
         print!("{table}");
     }
 
+    if let Some(cache) = &mut cache {
+        cache.retain_existing();
+        if let Err(e) = cache.save(&metadata_cache_path(&ctx.base_path)) {
+            tracing::warn!("Can't store the metadata cache: {e}");
+        }
+    }
+
     Ok(())
 }
 
-fn list_row(ctx: &Context, args: &ListArgs, entry: &CachedRecipeEntry) -> Result<tabular::Row> {
+/// The metadata `list` uses
+struct ListMeta {
+    tags: Option<Vec<String>>,
+    archived: bool,
+}
+
+/// Gets the metadata from the cache if enabled, `None` if it can't be parsed
+fn list_meta(
+    ctx: &Context,
+    entry: &CachedRecipeEntry,
+    check: bool,
+    cache: Option<&mut MetadataIndex>,
+) -> Option<ListMeta> {
+    if let Some(cache) = cache {
+        let meta = match cache.get(entry, ctx.parser().ok()?) {
+            Ok(meta) => meta?,
+            Err(e) => {
+                tracing::warn!("Can't read '{}': {e}", entry.path());
+                return None;
+            }
+        };
+        return Some(ListMeta {
+            tags: (!meta.tags.is_empty()).then(|| meta.tags.clone()),
+            archived: meta
+                .map
+                .get("archived")
+                .is_some_and(|v| is_archived_value(v)),
+        });
+    }
+    // try full parse if check to avoid parsing the recipe twice
+    let meta = entry.metadata(ctx, check).ok()?;
+    Some(ListMeta {
+        tags: meta.tags().map(<[String]>::to_vec),
+        archived: archived_in_metadata(meta),
+    })
+}
+
+/// Rows of the dirs, then the recipes, of a level of the tree
+fn add_tree_rows(
+    ctx: &Context,
+    args: &ListArgs,
+    tree: &RecipeTree,
+    depth: usize,
+    by_path: &HashMap<&Utf8Path, (&CachedRecipeEntry, Option<&ListMeta>)>,
+    table: &mut tabular::Table,
+) -> Result<()> {
     use owo_colors::OwoColorize;
 
-    let mut row = tabular::Row::new();
+    let indent = "  ".repeat(depth);
+    for dir in &tree.dirs {
+        let mut row = tabular::Row::new();
+        row.add_ansi_cell(format!(
+            "{indent}{}{}",
+            dir.name.cyan().bold(),
+            std::path::MAIN_SEPARATOR.cyan()
+        ));
+        for _ in 0..4 {
+            row.add_cell("");
+        }
+        table.add_row(row);
+        add_tree_rows(ctx, args, dir, depth + 1, by_path, table)?;
+    }
+    for recipe in &tree.recipes {
+        let Some(&(entry, meta)) = by_path.get(recipe.path()) else {
+            continue;
+        };
+        let name = format!("{indent}{}", entry.name());
+        table.add_row(list_row(ctx, args, name, entry, meta)?);
+    }
+    Ok(())
+}
+
+/// Name of the recipe in the first column
+fn entry_name(ctx: &Context, args: &ListArgs, entry: &CachedRecipeEntry) -> Result<String> {
+    use owo_colors::OwoColorize;
 
     let name = if args.absolute_paths {
         entry.path().canonicalize()?.to_string_lossy().to_string()
@@ -152,18 +287,29 @@ fn list_row(ctx: &Context, args: &ListArgs, entry: &CachedRecipeEntry) -> Result
     } else {
         entry.name().to_string()
     };
+    Ok(name)
+}
+
+fn list_row(
+    ctx: &Context,
+    args: &ListArgs,
+    name: String,
+    entry: &CachedRecipeEntry,
+    meta: Option<&ListMeta>,
+) -> Result<tabular::Row> {
+    use owo_colors::OwoColorize;
+
+    let mut row = tabular::Row::new();
     row.add_ansi_cell(name);
 
     if args.tags {
-        if let Ok(metadata) = entry.metadata(ctx, args.check) {
-            if let Some(tags) = metadata.tags() {
-                row.add_cell(format!(" [{}]", tags.join(", ")));
-            } else {
-                row.add_ansi_cell(format!(" [{}]", "-".dimmed()));
-            }
-        } else {
-            row.add_ansi_cell(format!(" ({})", "cannot parse".red().bold()));
-        }
+        match meta {
+            Some(ListMeta {
+                tags: Some(tags), ..
+            }) => row.add_cell(format!(" [{}]", tags.join(", "))),
+            Some(_) => row.add_ansi_cell(format!(" [{}]", "-".dimmed())),
+            None => row.add_ansi_cell(format!(" ({})", "cannot parse".red().bold())),
+        };
     } else {
         row.add_cell("");
     }
@@ -185,6 +331,13 @@ fn list_row(ctx: &Context, args: &ListArgs, entry: &CachedRecipeEntry) -> Result
         row.add_cell("");
     };
 
+    if args.health {
+        let health = recipe_health(ctx, entry)?;
+        row.add_ansi_cell(format!(" [{}]", score_str(health.score).trim_start()));
+    } else {
+        row.add_cell("");
+    }
+
     Ok(row)
 }
 
@@ -0,0 +1,576 @@
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8PathBuf;
+use clap::Args;
+
+#[derive(Debug, Args)]
+pub struct MergeDriverArgs {
+    /// Common ancestor, `%O` in git
+    base: Utf8PathBuf,
+    /// Current version, `%A` in git, where the result is written
+    ours: Utf8PathBuf,
+    /// Other branch version, `%B` in git
+    theirs: Utf8PathBuf,
+
+    /// Length of the conflict markers, `%L` in git
+    #[arg(short = 'L', long, default_value_t = 7)]
+    marker_size: usize,
+}
+
+/// Merges two versions of a recipe for git
+///
+/// Metadata keys and steps (blocks separated by blank lines) are merged on
+/// their own, so edits to different keys or steps don't conflict. Conflicts
+/// are written with markers and make the command fail.
+pub fn run(args: MergeDriverArgs) -> Result<()> {
+    let read = |path: &Utf8PathBuf| {
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read '{path}'"))
+    };
+    let base = read(&args.base)?;
+    let ours = read(&args.ours)?;
+    let theirs = read(&args.theirs)?;
+
+    let (merged, conflicts) = merge(&base, &ours, &theirs, args.marker_size);
+    cooklang_fs::safe_write(&args.ours, merged, cooklang_fs::WriteOptions::default())?;
+    if conflicts > 0 {
+        bail!("{conflicts} conflicts merging '{}'", args.ours);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MetaStyle {
+    None,
+    /// `>> key: value` lines
+    Lines,
+    /// YAML between `---` lines
+    FrontMatter,
+}
+
+/// A recipe split in the parts merged on their own
+///
+/// Every part is a slice of the original text, with its whitespace and line
+/// endings, so what is not changed is written back as it was.
+#[derive(Debug)]
+struct Parts<'a> {
+    style: MetaStyle,
+    /// Opening and closing `---` lines of the front matter
+    fences: (&'a str, &'a str),
+    /// The values of front matter can have many lines
+    meta: Vec<MetaEntry<'a>>,
+    /// Blank lines between the metadata and the first block
+    gap: &'a str,
+    /// Text separated by blank lines: steps, sections, notes...
+    blocks: Vec<Block<'a>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct MetaEntry<'a> {
+    key: &'a str,
+    /// The lines of the entry, with the blank lines before it
+    text: &'a str,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Block<'a> {
+    text: &'a str,
+    /// Blank lines after the block
+    sep: &'a str,
+}
+
+/// Blocks are the same if the text is, no matter how far apart they are
+impl PartialEq for Block<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text
+    }
+}
+
+fn split(text: &str) -> Option<Parts<'_>> {
+    // start offset and the line with its ending
+    let mut lines = Vec::new();
+    let mut pos = 0;
+    for line in text.split_inclusive('\n') {
+        lines.push((pos, line));
+        pos += line.len();
+    }
+    lines.push((text.len(), ""));
+    let is_blank = |line: &str| line.trim().is_empty();
+    let is_fence = |line: &str| line.trim_end() == "---";
+
+    let mut meta = Vec::new();
+    let mut style = MetaStyle::None;
+    let mut fences = ("", "");
+    let mut i = 0;
+    // where the metadata ends and where the body, with the gap, starts
+    let meta_end;
+    let body_start;
+
+    if is_fence(lines[0].1) {
+        style = MetaStyle::FrontMatter;
+        fences.0 = lines[0].1;
+        i = 1;
+        loop {
+            let (start, line) = lines[i];
+            if line.is_empty() {
+                return None;
+            }
+            i += 1;
+            if is_fence(line) {
+                fences.1 = line;
+                meta_end = start;
+                break;
+            }
+            let continues = line.starts_with([' ', '\t', '-']) || is_blank(line);
+            match line.split_once(':') {
+                Some((key, _)) if !continues => meta.push((key.trim(), start)),
+                // a comment or a line without key, can't be merged by key
+                _ if meta.is_empty() => return None,
+                _ => {}
+            }
+        }
+        body_start = lines[i].0;
+    } else {
+        let mut blank_start = None;
+        while let Some(&(start, line)) = lines.get(i).filter(|(_, l)| !l.is_empty()) {
+            if is_blank(line) {
+                blank_start.get_or_insert(start);
+            } else if let Some(entry) = line.strip_prefix(">>") {
+                let (key, _) = entry.split_once(':')?;
+                style = MetaStyle::Lines;
+                meta.push((key.trim(), blank_start.take().unwrap_or(start)));
+            } else {
+                break;
+            }
+            i += 1;
+        }
+        meta_end = blank_start.unwrap_or(lines[i].0);
+        body_start = meta_end;
+    }
+    let meta = meta
+        .iter()
+        .enumerate()
+        .map(|(n, &(key, start))| {
+            let end = meta.get(n + 1).map_or(meta_end, |(_, s)| *s);
+            MetaEntry {
+                key,
+                text: &text[start..end],
+            }
+        })
+        .collect();
+
+    let is_sep = |i: usize| !lines[i].1.is_empty() && is_blank(lines[i].1);
+    while is_sep(i) {
+        i += 1;
+    }
+    let gap = &text[body_start..lines[i].0];
+    let mut blocks = Vec::new();
+    while !lines[i].1.is_empty() {
+        let start = lines[i].0;
+        while !lines[i].1.is_empty() && !is_blank(lines[i].1) {
+            i += 1;
+        }
+        let end = lines[i].0;
+        while is_sep(i) {
+            i += 1;
+        }
+        blocks.push(Block {
+            text: &text[start..end],
+            sep: &text[end..lines[i].0],
+        });
+    }
+    Some(Parts {
+        style,
+        fences,
+        meta,
+        gap,
+        blocks,
+    })
+}
+
+impl<'a> Parts<'a> {
+    /// Blank lines at the end
+    fn trailing(&self) -> &'a str {
+        self.blocks.last().map_or("", |b| b.sep)
+    }
+}
+
+/// Part of the merged body
+enum Piece<'b, 'a> {
+    Block(&'b Block<'a>),
+    Conflict(String),
+}
+
+/// Blocks as they were written, with the separators between them
+fn join_blocks(blocks: &[Block]) -> String {
+    let mut s = String::new();
+    for (n, block) in blocks.iter().enumerate() {
+        s.push_str(block.text);
+        if n + 1 < blocks.len() {
+            s.push_str(block.sep);
+        }
+    }
+    s
+}
+
+/// Three way merge, returns the result and the number of conflicts
+fn merge(base: &str, ours: &str, theirs: &str, marker_size: usize) -> (String, usize) {
+    if ours == theirs || theirs == base {
+        return (ours.to_string(), 0);
+    }
+    if ours == base {
+        return (theirs.to_string(), 0);
+    }
+
+    let markers = Markers::new(marker_size);
+    let (Some(b), Some(o), Some(t)) = (split(base), split(ours), split(theirs)) else {
+        return (markers.conflict(ours, theirs), 1);
+    };
+    let styles = [b.style, o.style, t.style];
+    let style = styles
+        .into_iter()
+        .find(|s| *s != MetaStyle::None)
+        .unwrap_or(MetaStyle::None);
+    if styles.iter().any(|s| *s != MetaStyle::None && *s != style) {
+        return (markers.conflict(ours, theirs), 1);
+    }
+    // ours wins when both sides change the same whitespace
+    let pick = |b, o, t| match merge3(b, o, t) {
+        Merged::Clean(s) | Merged::Conflict(s, _) => s,
+    };
+
+    let mut conflicts = 0;
+    let mut out = String::new();
+    let push_line = |out: &mut String, s: &str| {
+        out.push_str(s);
+        if !s.is_empty() && !s.ends_with('\n') {
+            out.push('\n');
+        }
+    };
+
+    let fences = [&o, &t, &b]
+        .into_iter()
+        .find(|p| p.style == MetaStyle::FrontMatter)
+        .map(|p| p.fences);
+    if let Some((open, _)) = fences {
+        push_line(&mut out, open);
+    }
+    for m in merge_meta(&b.meta, &o.meta, &t.meta) {
+        match m {
+            Merged::Clean(m) => push_line(&mut out, m.text),
+            Merged::Conflict(o, t) => {
+                conflicts += 1;
+                // without the blank lines before the entry
+                out.push_str(&markers.conflict(
+                    o.map_or("", |m| m.text.trim_start()),
+                    t.map_or("", |m| m.text.trim_start()),
+                ));
+            }
+        }
+    }
+    if let Some((_, close)) = fences {
+        push_line(&mut out, close);
+    }
+
+    let mut body = Vec::new();
+    for chunk in diff3(&b.blocks, &o.blocks, &t.blocks) {
+        match chunk {
+            Merged::Clean(blocks) => body.extend(blocks.iter().map(Piece::Block)),
+            Merged::Conflict(o, t) => {
+                conflicts += 1;
+                body.push(Piece::Conflict(
+                    markers.conflict(&join_blocks(o), &join_blocks(t)),
+                ));
+            }
+        }
+    }
+    if !body.is_empty() {
+        out.push_str(pick(b.gap, o.gap, t.gap));
+        let trailing = pick(b.trailing(), o.trailing(), t.trailing());
+        for (n, piece) in body.iter().enumerate() {
+            let last = n + 1 == body.len();
+            match piece {
+                Piece::Block(block) if last => {
+                    out.push_str(block.text);
+                    out.push_str(trailing);
+                }
+                Piece::Block(block) => {
+                    push_line(&mut out, block.text);
+                    out.push_str(if block.sep.is_empty() {
+                        "\n"
+                    } else {
+                        block.sep
+                    });
+                }
+                Piece::Conflict(conflict) => {
+                    out.push_str(conflict);
+                    if !last {
+                        out.push('\n');
+                    }
+                }
+            }
+        }
+    }
+    (out, conflicts)
+}
+
+struct Markers {
+    ours: String,
+    middle: String,
+    theirs: String,
+}
+
+impl Markers {
+    fn new(size: usize) -> Self {
+        Self {
+            ours: format!("{} ours", "<".repeat(size)),
+            middle: "=".repeat(size),
+            theirs: format!("{} theirs", ">".repeat(size)),
+        }
+    }
+
+    fn conflict(&self, ours: &str, theirs: &str) -> String {
+        let mut s = String::new();
+        for part in [self.ours.as_str(), ours, &self.middle, theirs, &self.theirs] {
+            if part.is_empty() {
+                continue;
+            }
+            s.push_str(part.trim_end_matches('\n'));
+            s.push('\n');
+        }
+        s
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Merged<T, C = T> {
+    Clean(T),
+    /// Ours and theirs
+    Conflict(C, C),
+}
+
+fn merge3<T: PartialEq>(base: T, ours: T, theirs: T) -> Merged<T> {
+    if ours == theirs || theirs == base {
+        Merged::Clean(ours)
+    } else if ours == base {
+        Merged::Clean(theirs)
+    } else {
+        Merged::Conflict(ours, theirs)
+    }
+}
+
+/// Merges the metadata by key, in the order of ours with the new keys of
+/// theirs at the end
+///
+/// Keys deleted in one side and not changed in the other are left out.
+fn merge_meta<'b, 'a>(
+    base: &'b [MetaEntry<'a>],
+    ours: &'b [MetaEntry<'a>],
+    theirs: &'b [MetaEntry<'a>],
+) -> Vec<Merged<&'b MetaEntry<'a>, Option<&'b MetaEntry<'a>>>> {
+    fn get<'b, 'a>(entries: &'b [MetaEntry<'a>], key: &str) -> Option<&'b MetaEntry<'a>> {
+        entries.iter().find(|m| m.key == key)
+    }
+    fn text<'a>(entry: Option<&MetaEntry<'a>>) -> Option<&'a str> {
+        entry.map(|m| m.text)
+    }
+
+    let mut keys = ours.iter().map(|m| m.key).collect::<Vec<_>>();
+    for m in theirs.iter().chain(base) {
+        if !keys.contains(&m.key) {
+            keys.push(m.key);
+        }
+    }
+    keys.into_iter()
+        .filter_map(|key| {
+            let (b, o, t) = (get(base, key), get(ours, key), get(theirs, key));
+            match merge3(text(b), text(o), text(t)) {
+                Merged::Clean(_) => {
+                    let entry = if text(o) == text(b) { t } else { o };
+                    entry.map(Merged::Clean)
+                }
+                Merged::Conflict(_, _) => Some(Merged::Conflict(o, t)),
+            }
+        })
+        .collect()
+}
+
+/// Three way merge of two sequences
+///
+/// Changes are found with the longest common subsequence of each side with
+/// the base. Chunks changed by both sides with the same length are merged
+/// element by element.
+fn diff3<'a, T: PartialEq>(base: &'a [T], ours: &'a [T], theirs: &'a [T]) -> Vec<Merged<&'a [T]>> {
+    let mo = matches(base, ours);
+    let mt = matches(base, theirs);
+    let mut chunks = Vec::new();
+    let (mut ib, mut io, mut it) = (0, 0, 0);
+    loop {
+        // stable, the same in the three, taken from ours
+        let start = io;
+        while ib < base.len() && mo[ib] == Some(io) && mt[ib] == Some(it) {
+            ib += 1;
+            io += 1;
+            it += 1;
+        }
+        if io > start {
+            chunks.push(Merged::Clean(&ours[start..io]));
+        }
+        // changed until the next line in the three
+        let next = (ib..base.len())
+            .find(|&i| mo[i].is_some_and(|o| o >= io) && mt[i].is_some_and(|t| t >= it));
+        let (eb, eo, et) = match next {
+            Some(i) => (i, mo[i].unwrap(), mt[i].unwrap()),
+            None => (base.len(), ours.len(), theirs.len()),
+        };
+        if (eb, eo, et) == (ib, io, it) {
+            break;
+        }
+        let (b, o, t) = (&base[ib..eb], &ours[io..eo], &theirs[it..et]);
+        match merge3(b, o, t) {
+            Merged::Conflict(o, t) if o.len() == b.len() && t.len() == b.len() => {
+                for ((b, o), t) in b.chunks(1).zip(o.chunks(1)).zip(t.chunks(1)) {
+                    chunks.push(merge3(b, o, t));
+                }
+            }
+            chunk => chunks.push(chunk),
+        }
+        (ib, io, it) = (eb, eo, et);
+    }
+    chunks
+}
+
+/// For every element of `a`, its index in `b` in the longest common
+/// subsequence
+fn matches<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Option<usize>> {
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut result = vec![None; a.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            result[i] = Some(j);
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merge_clean(base: &str, ours: &str, theirs: &str) -> String {
+        let (merged, conflicts) = merge(base, ours, theirs, 7);
+        assert_eq!(conflicts, 0, "{merged}");
+        merged
+    }
+
+    #[test]
+    fn different_steps() {
+        let base = ">> servings: 2\n\nBoil @water.\n\nAdd @pasta.\n\nServe.\n";
+        let ours = ">> servings: 4\n\nBoil @water{2%l}.\n\nAdd @pasta.\n\nServe.\n";
+        let theirs = ">> servings: 2\n>> tags: easy\n\nBoil @water.\n\nAdd @pasta.\n\nServe hot.\n";
+        assert_eq!(
+            merge_clean(base, ours, theirs),
+            ">> servings: 4\n>> tags: easy\n\nBoil @water{2%l}.\n\nAdd @pasta.\n\nServe hot.\n"
+        );
+    }
+
+    #[test]
+    fn inserted_steps() {
+        let base = "A.\n\nB.\n\nC.\n";
+        let ours = "Z.\n\nA.\n\nB.\n\nC.\n";
+        let theirs = "A.\n\nB.\n\nB2.\n\nC.\n";
+        assert_eq!(
+            merge_clean(base, ours, theirs),
+            "Z.\n\nA.\n\nB.\n\nB2.\n\nC.\n"
+        );
+    }
+
+    #[test]
+    fn front_matter() {
+        let base = "---\ntitle: Soup\ntags:\n  - easy\n---\nBoil.\n";
+        let ours = "---\ntitle: Leek soup\ntags:\n  - easy\n---\nBoil.\n";
+        let theirs = "---\ntitle: Soup\ntags:\n  - easy\n  - vegan\n---\nBoil.\n";
+        assert_eq!(
+            merge_clean(base, ours, theirs),
+            "---\ntitle: Leek soup\ntags:\n  - easy\n  - vegan\n---\nBoil.\n"
+        );
+    }
+
+    #[test]
+    fn deleted_keys() {
+        let base = ">> servings: 2\n>> tags: easy\n\nBoil.\n";
+        let ours = ">> servings: 2\n\nBoil.\n";
+        let theirs = ">> servings: 4\n>> tags: easy\n\nBoil.\n";
+        assert_eq!(merge_clean(base, ours, theirs), ">> servings: 4\n\nBoil.\n");
+        assert_eq!(merge_clean(base, theirs, ours), ">> servings: 4\n\nBoil.\n");
+
+        let base = "---\ntitle: Soup\ntags: easy\n---\nBoil.\n";
+        let ours = "---\ntitle: Soup\n---\nBoil.\n";
+        let theirs = "---\ntitle: Soup\ntags: easy\n---\nBoil well.\n";
+        assert_eq!(
+            merge_clean(base, ours, theirs),
+            "---\ntitle: Soup\n---\nBoil well.\n"
+        );
+
+        // deleted and changed, without an empty line for ours
+        let (merged, conflicts) = merge(
+            ">> tags: easy\n\nBoil.\n",
+            "Boil.\n",
+            ">> tags: vegan\n\nBoil.\n",
+            7,
+        );
+        assert_eq!(conflicts, 1);
+        assert!(
+            merged.starts_with("<<<<<<< ours\n=======\n>> tags: vegan\n>>>>>>> theirs\n"),
+            "{merged}"
+        );
+    }
+
+    #[test]
+    fn keeps_whitespace() {
+        let base = ">>  servings:2 \n\n\nBoil @water.  \n\n\n\nAdd @pasta.\r\n\r\nServe.\n";
+        let ours = ">>  servings:2 \n\n\nBoil @water.  \n\n\n\nAdd @pasta.\r\n\r\nServe hot.\n";
+        let theirs =
+            ">>  servings:2 \n\n\nBoil @water.  \n\n\n\nAdd @pasta{500%g}.\r\n\r\nServe.\n";
+        assert_eq!(
+            merge_clean(base, ours, theirs),
+            ">>  servings:2 \n\n\nBoil @water.  \n\n\n\nAdd @pasta{500%g}.\r\n\r\nServe hot.\n"
+        );
+
+        // the blank lines of ours are kept, and a new last block is separated
+        let base = "A.\n\nB.\n";
+        let ours = "A2.\n\n\n\nB.\n";
+        let theirs = "A.\n\nB.\n\nC.\n";
+        assert_eq!(merge_clean(base, ours, theirs), "A2.\n\n\n\nB.\n\nC.\n");
+    }
+
+    #[test]
+    fn conflicts() {
+        let base = ">> servings: 2\n\nBoil.\n\nServe.\n";
+        let ours = ">> servings: 4\n\nBoil.\n\nServe.\n";
+        let theirs = ">> servings: 3\n\nBoil.\n\nServe cold.\n";
+        let (merged, conflicts) = merge(base, ours, theirs, 7);
+        assert_eq!(conflicts, 1);
+        assert_eq!(
+            merged,
+            "<<<<<<< ours\n>> servings: 4\n=======\n>> servings: 3\n>>>>>>> theirs\n\nBoil.\n\nServe cold.\n"
+        );
+
+        let (_, conflicts) = merge("A.\n", "B.\n", "C.\n", 7);
+        assert_eq!(conflicts, 1);
+    }
+}
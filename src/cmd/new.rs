@@ -1,8 +1,7 @@
-use std::fs;
-
+use anstream::eprintln;
 use anyhow::{bail, Context as _, Result};
-use camino::Utf8Path;
 use clap::Args;
+use cooklang_fs::OnCollision;
 
 use crate::Context;
 
@@ -16,28 +15,37 @@ pub struct NewArgs {
     /// Skip opening the editor
     #[arg(long, short = 'E')]
     no_edit: bool,
+
+    /// If the recipe exists, add a number to the name instead of failing
+    #[arg(long)]
+    suffix: bool,
 }
 
 pub fn run(args: NewArgs, ctx: &Context) -> Result<()> {
-    let file = Utf8Path::new(&args.name).with_extension("cook");
-    let valid = !file.is_absolute()
-        && file
-            .components()
-            .all(|c| matches!(c, camino::Utf8Component::Normal(_)));
-    if !valid {
-        bail!("Invalid name: {}", args.name);
-    }
-
-    let path = ctx.base_path.join(file);
+    let on_collision = if args.suffix {
+        OnCollision::Suffix
+    } else {
+        OnCollision::Fail
+    };
 
-    if path.is_file() {
-        bail!("File already exists: {}", path);
-    }
-
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)?;
-    }
-    fs::File::create(&path)?;
+    let lock = ctx.lock_collection()?;
+    let entry = match cooklang_fs::create_recipe(
+        &ctx.base_path,
+        &args.name,
+        "",
+        on_collision,
+        ctx.config.write_options(),
+    ) {
+        Ok(entry) => entry,
+        Err(cooklang_fs::Error::AlreadyExists(name)) => {
+            bail!("Recipe already exists: '{name}', pass `--suffix` to create another")
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let path = entry.path();
+    ctx.add_to_stored_index(path)?;
+    drop(lock);
+    eprintln!("Created {path}");
 
     if !args.no_edit {
         let editor = ctx
@@ -0,0 +1,77 @@
+use anstream::println;
+use anyhow::{Context as _, Result};
+use camino::Utf8PathBuf;
+use clap::Args;
+
+use crate::{
+    cmd::recipe::JsonRecipe,
+    query::Query,
+    scaling::{check_scaled, ScaleReport},
+    util::{meta_name, recipe_entries, Input},
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct QueryArgs {
+    /// Recipe to query
+    ///
+    /// This can be a full path, a partial path, or just the name.
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    recipe: Utf8PathBuf,
+
+    /// Query, like `.ingredients[].name`
+    ///
+    /// It runs over the JSON output of `chef recipe`. Supported: `.field`,
+    /// `."field"`, `[]` and `[index]`.
+    query: String,
+
+    /// Scale to a number of servings
+    #[arg(short, long, alias = "servings", value_name = "SERVINGS")]
+    scale: Option<u32>,
+
+    /// Print strings without quotes
+    #[arg(short, long)]
+    raw: bool,
+
+    /// Pretty JSON output
+    #[arg(long)]
+    pretty: bool,
+}
+
+pub fn run(ctx: &Context, args: QueryArgs) -> Result<()> {
+    let query = Query::parse(&args.query).context("Invalid query")?;
+
+    let entry = recipe_entries(ctx, std::slice::from_ref(&args.recipe), true, "query")?
+        .pop()
+        .expect("one recipe given");
+    let input = Input::File {
+        entry,
+        override_name: None,
+    };
+    let recipe = input.parse(ctx)?;
+    let converter = ctx.parser()?.converter();
+
+    let by_servings = ScaleReport::by_servings(&recipe);
+    let mut recipe = match args.scale {
+        Some(scale) => recipe.scale(scale, converter),
+        None => recipe.default_scale(),
+    };
+    let report = ScaleReport::new(&recipe, &by_servings);
+    check_scaled(&mut recipe, input.file_name(), ctx.config.text_quantities)?;
+    crate::variants::resolve(&mut recipe);
+
+    let name = match meta_name(&recipe.metadata) {
+        Some(n) => n,
+        None => input.name()?,
+    };
+    let value = serde_json::to_value(JsonRecipe::new(name, &recipe, report.as_ref(), converter))?;
+
+    for v in query.run(&value)? {
+        match v {
+            serde_json::Value::String(s) if args.raw => println!("{s}"),
+            _ if args.pretty => println!("{}", serde_json::to_string_pretty(v)?),
+            _ => println!("{}", serde_json::to_string(v)?),
+        }
+    }
+    Ok(())
+}
@@ -3,14 +3,20 @@ use std::io::Read;
 use anyhow::{bail, Context as _, Result};
 use camino::Utf8PathBuf;
 use clap::{Args, ValueEnum};
-use cooklang_fs::{check_recipe_images, recipe_images, LazyFsIndex, RecipeEntry};
+use cooklang_fs::{check_recipe_images, recipe_images, LazyFsIndex, Nutrition, RecipeEntry};
 use owo_colors::OwoColorize;
 
 use crate::{
-    util::{meta_name, unwrap_recipe, write_to_output, Input},
+    scaling::{self, check_scaled, ScaleReport, TextQuantities},
+    temperature::{self, Temperature},
+    timeline::Timeline,
+    unit_system::UnitUsage,
+    util::{meta_name, resolve_recipe, unwrap_recipe, write_to_output, Input},
     Context,
 };
 
+mod guided;
+
 #[derive(Debug, Args)]
 #[command(args_conflicts_with_subcommands = true)]
 pub struct ReadArgs {
@@ -49,6 +55,19 @@ pub struct ReadArgs {
     /// Check the recipe for errors, warnings and images
     #[arg(long, conflicts_with_all = ["ScalingArgs", "DebugArgs"])]
     check: bool,
+
+    /// Show the active and passive time of each step after the recipe
+    ///
+    /// Only for the human format, JSON always has the timeline.
+    #[arg(long, conflicts_with_all = ["check", "DebugArgs"])]
+    timeline: bool,
+
+    /// Choose the servings, or the amount of an ingredient, with prompts
+    ///
+    /// Shows the scaled ingredients and the warnings before showing or saving
+    /// the recipe.
+    #[arg(long, requires = "recipe", conflicts_with_all = ["scale", "check", "DebugArgs"])]
+    guided: bool,
 }
 
 #[derive(Debug, Args)]
@@ -61,6 +80,21 @@ struct ScalingArgs {
     /// Convert to a unit system
     #[arg(short, long, alias = "system", value_name = "SYSTEM")]
     convert: Option<System>,
+
+    /// Show imperial quantities with more than one unit, like `1 lb 4 oz`
+    #[arg(long, requires = "convert")]
+    compound: bool,
+
+    /// What to do with text quantities that can't be scaled
+    ///
+    /// Overrides the `text_quantities` config option.
+    #[arg(long, value_enum, requires = "scale")]
+    text_quantities: Option<TextQuantities>,
+
+    /// Interpolate the values per servings, like `{2|4|6}`, for servings they
+    /// don't have
+    #[arg(long)]
+    interpolate: bool,
 }
 
 #[derive(Debug, Args)]
@@ -86,6 +120,98 @@ enum OutputFormat {
     Debug,
 }
 
+/// The JSON output of a recipe
+#[derive(serde::Serialize)]
+pub struct JsonRecipe<'a> {
+    name: &'a str,
+    #[serde(flatten)]
+    recipe: &'a cooklang::ScaledRecipe,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scaling: Option<&'a ScaleReport>,
+    units: UnitUsage,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    temperatures: Vec<Temperature>,
+    #[serde(skip_serializing_if = "Timeline::is_empty")]
+    timeline: Timeline,
+    #[serde(flatten)]
+    sidecars: RecipeSidecars,
+}
+
+impl<'a> JsonRecipe<'a> {
+    pub fn new(
+        name: &'a str,
+        recipe: &'a cooklang::ScaledRecipe,
+        scaling: Option<&'a ScaleReport>,
+        converter: &cooklang::Converter,
+    ) -> Self {
+        Self {
+            name,
+            recipe,
+            scaling,
+            units: UnitUsage::new(recipe, converter),
+            temperatures: temperature::temperatures(recipe, converter),
+            timeline: Timeline::new(recipe, converter),
+            sidecars: RecipeSidecars::default(),
+        }
+    }
+
+    pub fn with_sidecars(self, sidecars: RecipeSidecars) -> Self {
+        Self { sidecars, ..self }
+    }
+}
+
+/// Data of the sidecar files shown with the recipe, see [`cooklang_fs::Sidecar`]
+#[derive(Default, serde::Serialize)]
+pub struct RecipeSidecars {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nutrition: Option<Nutrition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notes: Option<String>,
+}
+
+impl RecipeSidecars {
+    /// Reads the sidecars, logging the errors
+    pub fn read(entry: &RecipeEntry) -> Self {
+        let ok_or_warn = |res: Result<_, cooklang_fs::Error>| {
+            res.unwrap_or_else(|e| {
+                tracing::warn!("{e}");
+                None
+            })
+        };
+        Self {
+            nutrition: ok_or_warn(entry.nutrition()),
+            notes: ok_or_warn(entry.notes()),
+        }
+    }
+
+    fn write_human(&self, w: &mut impl std::io::Write) -> std::io::Result<()> {
+        if let Some(nutrition) = &self.nutrition {
+            match &nutrition.per {
+                Some(per) => writeln!(
+                    w,
+                    "{} {}",
+                    "Nutrition".bold(),
+                    format!("(per {per}):").dimmed()
+                )?,
+                None => writeln!(w, "{}", "Nutrition:".bold())?,
+            }
+            if let Some(calories) = nutrition.calories {
+                writeln!(w, "  calories: {calories} kcal")?;
+            }
+            for (name, value) in &nutrition.values {
+                writeln!(w, "  {name}: {value} g")?;
+            }
+        }
+        if let Some(notes) = &self.notes {
+            writeln!(w, "{}", "Notes:".bold())?;
+            for line in notes.trim_end().lines() {
+                writeln!(w, "  {line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Copy, ValueEnum)]
 enum System {
     Metric,
@@ -93,7 +219,7 @@ enum System {
     Imperial,
 }
 
-pub fn run(ctx: &Context, args: ReadArgs) -> Result<()> {
+pub fn run(ctx: &Context, mut args: ReadArgs) -> Result<()> {
     if args.debug.events || args.debug.ast {
         return just_events(ctx, args);
     }
@@ -105,11 +231,31 @@ pub fn run(ctx: &Context, args: ReadArgs) -> Result<()> {
 
     let recipe = input.parse(ctx)?;
 
+    if args.guided {
+        let guided = guided::guided(ctx, &recipe, args.values.interpolate, args.output.is_none())?;
+        args.values.scale = Some(guided.servings);
+        args.output = args.output.or(guided.output);
+    }
+
+    let by_servings = ScaleReport::by_servings(&recipe);
     let mut scaled_recipe = if let Some(scale) = args.values.scale {
-        recipe.scale(scale, ctx.parser()?.converter())
+        scaling::scale(
+            recipe,
+            scale,
+            args.values.interpolate,
+            ctx.parser()?.converter(),
+        )
     } else {
         recipe.default_scale()
     };
+    // before applying the policy, so it reports the real outcome
+    let report = ScaleReport::new(&scaled_recipe, &by_servings);
+    let policy = args
+        .values
+        .text_quantities
+        .unwrap_or(ctx.config.text_quantities);
+    check_scaled(&mut scaled_recipe, input.file_name(), policy)?;
+    crate::variants::resolve(&mut scaled_recipe);
 
     if let Some(system) = args.values.convert {
         let to = match system {
@@ -117,6 +263,12 @@ pub fn run(ctx: &Context, args: ReadArgs) -> Result<()> {
             System::Imperial => cooklang::convert::System::Imperial,
         };
         let _ = scaled_recipe.convert(to, ctx.parser()?.converter());
+        ctx.config
+            .unit_preferences
+            .apply_recipe(&mut scaled_recipe, ctx.parser()?.converter());
+        if args.values.compound && matches!(system, System::Imperial) {
+            crate::compound::compound_quantities(&mut scaled_recipe, ctx.parser()?.converter());
+        }
     }
 
     let format = args.format.unwrap_or_else(|| match &args.output {
@@ -133,27 +285,49 @@ pub fn run(ctx: &Context, args: ReadArgs) -> Result<()> {
         Some(n) => n,
         None => input.name()?,
     };
+    let sidecars = match &input {
+        Input::File { entry, .. } => RecipeSidecars::read(entry),
+        Input::Stdin { .. } => RecipeSidecars::default(),
+    };
 
     write_to_output(args.output.as_deref(), |mut writer| {
         match format {
-            OutputFormat::Human => cooklang_to_human::print_human(
-                &scaled_recipe,
-                name,
-                ctx.parser()?.converter(),
-                writer,
-            )?,
-            OutputFormat::Json => {
-                #[derive(serde::Serialize)]
-                struct JsonRecipe<'a> {
-                    name: &'a str,
-                    #[serde(flatten)]
-                    recipe: &'a cooklang::ScaledRecipe,
+            OutputFormat::Human => {
+                if !ctx.config.to_taste.label.is_empty() {
+                    cooklang_to_human::set_no_quantity_label(ctx.config.to_taste.label.clone());
                 }
-
-                let recipe = JsonRecipe {
-                    recipe: &scaled_recipe,
+                cooklang_to_human::print_human(
+                    &scaled_recipe,
                     name,
-                };
+                    ctx.parser()?.converter(),
+                    &mut writer,
+                )?;
+                let temperatures =
+                    temperature::temperatures(&scaled_recipe, ctx.parser()?.converter());
+                if !temperatures.is_empty() {
+                    temperature::write_human(&temperatures, &mut writer)?;
+                }
+                if let Some(report) = &report {
+                    report.write_human(&mut writer)?;
+                }
+                sidecars.write_human(&mut writer)?;
+                if args.timeline {
+                    let timeline = Timeline::new(&scaled_recipe, ctx.parser()?.converter());
+                    if timeline.is_empty() {
+                        writeln!(writer, "No timers with a time")?;
+                    } else {
+                        timeline.write_human(&mut writer)?;
+                    }
+                }
+            }
+            OutputFormat::Json => {
+                let recipe = JsonRecipe::new(
+                    name,
+                    &scaled_recipe,
+                    report.as_ref(),
+                    ctx.parser()?.converter(),
+                )
+                .with_sidecars(sidecars);
 
                 if args.pretty {
                     serde_json::to_writer_pretty(writer, &recipe)?;
@@ -161,7 +335,11 @@ pub fn run(ctx: &Context, args: ReadArgs) -> Result<()> {
                     serde_json::to_writer(writer, &recipe)?;
                 }
             }
-            OutputFormat::Cooklang => cooklang_to_cooklang::print_cooklang(&scaled_recipe, writer)?,
+            OutputFormat::Cooklang => cooklang_to_cooklang::print_cooklang_with_options(
+                &scaled_recipe,
+                &ctx.config.format,
+                writer,
+            )?,
             OutputFormat::Markdown => cooklang_to_md::print_md_with_options(
                 &scaled_recipe,
                 name,
@@ -186,7 +364,7 @@ impl ReadArgs {
             } else {
                 // RecipeInputArgs::recipe is a pathbuf even if inmediatly converted
                 // to a string to enforce validation.
-                index.resolve(query.as_str(), None)?
+                resolve_recipe(index, query.as_str())?
             };
 
             Input::File {
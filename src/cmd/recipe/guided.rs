@@ -0,0 +1,177 @@
+//! Scaling a recipe with prompts, `chef recipe --guided`
+#![cfg_attr(not(feature = "interactive"), allow(dead_code))]
+
+use camino::Utf8PathBuf;
+use cooklang::{
+    quantity::{ScalableValue, Value},
+    ScalableRecipe,
+};
+
+/// What was chosen in the prompts
+pub struct Guided {
+    pub servings: u32,
+    /// `None` to show the recipe
+    pub output: Option<Utf8PathBuf>,
+}
+
+/// Ingredient with a number that scales with the servings
+struct Amount {
+    name: String,
+    value: f64,
+    unit: Option<String>,
+}
+
+fn base_servings(recipe: &ScalableRecipe) -> u32 {
+    recipe
+        .metadata
+        .servings()
+        .and_then(|s| s.first().copied())
+        .unwrap_or(1)
+}
+
+fn amounts(recipe: &ScalableRecipe) -> Vec<Amount> {
+    recipe
+        .ingredients
+        .iter()
+        .filter(|igr| igr.modifiers().should_be_listed())
+        .filter_map(|igr| {
+            let q = igr.quantity.as_ref()?;
+            let ScalableValue::Linear(Value::Number(n)) = &q.value else {
+                return None;
+            };
+            (n.value() > 0.0).then(|| Amount {
+                name: igr.display_name().to_string(),
+                value: n.value(),
+                unit: q.unit_text().map(str::to_string),
+            })
+        })
+        .collect()
+}
+
+/// Servings where the ingredient is closest to `wanted`, at least 1
+fn servings_for_amount(base: u32, current: f64, wanted: f64) -> u32 {
+    ((base as f64 * wanted / current).round() as u32).max(1)
+}
+
+#[cfg(feature = "interactive")]
+pub fn guided(
+    ctx: &crate::Context,
+    recipe: &ScalableRecipe,
+    interpolate: bool,
+    ask_output: bool,
+) -> anyhow::Result<Guided> {
+    use anstream::println;
+    use inquire::{Confirm, CustomType, Select, Text};
+    use owo_colors::OwoColorize;
+
+    use crate::scaling::ScaleReport;
+
+    const SERVINGS: &str = "Servings";
+    const AMOUNT: &str = "Amount of an ingredient";
+    const SHOW: &str = "Show the recipe";
+    const SAVE: &str = "Save it to a file";
+
+    let converter = ctx.parser()?.converter();
+    let fmt = cooklang_to_human::quantity_formatter();
+    let base = base_servings(recipe);
+    let amounts = amounts(recipe);
+    let by_servings = ScaleReport::by_servings(recipe);
+
+    let servings = loop {
+        let by_amount = !amounts.is_empty()
+            && Select::new("Scale by", vec![SERVINGS, AMOUNT]).prompt()? == AMOUNT;
+        let (servings, wanted) = if by_amount {
+            let names = amounts.iter().map(|a| a.name.as_str()).collect();
+            let amount = &amounts[Select::new("Ingredient", names).raw_prompt()?.index];
+            let unit = amount.unit.as_deref().unwrap_or("");
+            let wanted = CustomType::<f64>::new(&format!("How much {}?", amount.name))
+                .with_help_message(&format!(
+                    "Now {}{}{unit}",
+                    fmt.number(amount.value),
+                    fmt.unit_separator()
+                ))
+                .prompt()?;
+            (
+                servings_for_amount(base, amount.value, wanted),
+                Some((amount, wanted)),
+            )
+        } else {
+            let servings = CustomType::<u32>::new("Servings")
+                .with_default(base)
+                .with_validator(|&s: &u32| {
+                    Ok(if s == 0 {
+                        inquire::validator::Validation::Invalid("At least 1".into())
+                    } else {
+                        inquire::validator::Validation::Valid
+                    })
+                })
+                .prompt()?;
+            (servings, None)
+        };
+
+        let scaled = crate::scaling::scale(recipe.clone(), servings, interpolate, converter);
+        println!("{}", format!("Ingredients for {servings} servings:").bold());
+        for entry in scaled.group_ingredients(converter) {
+            if !entry.ingredient.modifiers().should_be_listed() {
+                continue;
+            }
+            let quantity = entry
+                .quantity
+                .iter()
+                .map(|q| fmt.quantity(q))
+                .collect::<Vec<_>>()
+                .join(", ");
+            println!("  {}: {quantity}", entry.ingredient.display_name());
+        }
+        if let Some((amount, wanted)) = wanted {
+            let got = amount.value * servings as f64 / base as f64;
+            if fmt.number(got) != fmt.number(wanted) {
+                let line = format!(
+                    "Rounded to {servings} servings, {} of {} instead of {}",
+                    fmt.number(got),
+                    amount.name,
+                    fmt.number(wanted)
+                );
+                println!("{}", line.yellow());
+            }
+        }
+        if let Some(report) = ScaleReport::new(&scaled, &by_servings) {
+            report.write_human(&mut anstream::stdout().lock())?;
+        }
+
+        if Confirm::new("Use these quantities?")
+            .with_default(true)
+            .prompt()?
+        {
+            break servings;
+        }
+    };
+
+    let output = if ask_output && Select::new("Then", vec![SHOW, SAVE]).prompt()? == SAVE {
+        let path = Text::new("File:")
+            .with_help_message("The extension picks the format: .cook, .md, .json or text")
+            .prompt()?;
+        Some(Utf8PathBuf::from(path))
+    } else {
+        None
+    };
+    Ok(Guided { servings, output })
+}
+
+#[cfg(not(feature = "interactive"))]
+pub fn guided(_: &crate::Context, _: &ScalableRecipe, _: bool, _: bool) -> anyhow::Result<Guided> {
+    anyhow::bail!("chef was built without interactive prompts (`interactive` feature)")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn servings_from_amount() {
+        assert_eq!(servings_for_amount(4, 200.0, 300.0), 6);
+        assert_eq!(servings_for_amount(4, 200.0, 260.0), 5);
+        assert_eq!(servings_for_amount(1, 3.0, 1.0), 1);
+        assert_eq!(servings_for_amount(2, 100.0, 10.0), 1);
+    }
+}
@@ -8,7 +8,9 @@ use std::{
 use anyhow::Result;
 use camino::{Utf8Path, Utf8PathBuf};
 use cooklang::{CooklangParser, MetadataResult};
-use cooklang_fs::{FsIndex, RecipeEntry};
+use cooklang_fs::{
+    Collation, ContentHash, FsIndex, OnCollision, RecipeContent, RecipeEntry, WriteOptions,
+};
 use notify::{RecommendedWatcher, Watcher};
 use serde::Serialize;
 use tokio::sync::{broadcast, mpsc, RwLock};
@@ -20,42 +22,119 @@ pub struct AsyncFsIndex {
 struct Indexes {
     parser: CooklangParser,
     fs: FsIndex,
-    srch: BTreeMap<Utf8PathBuf, MetadataResult>,
+    /// `None` until the first search when it's lazy
+    srch: Option<BTreeMap<Utf8PathBuf, SrchEntry>>,
+    /// Do not read every recipe until something is searched
+    lazy_srch: bool,
+    /// File to save the index to after refreshing it
+    store: Option<Utf8PathBuf>,
+    /// Order of the search results
+    collation: Collation,
+}
+
+struct SrchEntry {
+    /// Used to only parse again when the content really changed, ignoring
+    /// whitespace and comments
+    hash: ContentHash,
+    meta: MetadataResult,
+}
+
+impl SrchEntry {
+    fn new(content: &RecipeContent, parser: &CooklangParser) -> Self {
+        Self {
+            hash: content.semantic_hash(),
+            meta: content.metadata(parser),
+        }
+    }
+}
+
+/// Reads the metadata of every recipe, a few files at the same time
+///
+/// Recipes that can't be read are left out with a warning.
+fn build_srch(parser: &CooklangParser, fs: &FsIndex) -> BTreeMap<Utf8PathBuf, SrchEntry> {
+    let entries = fs.iter().collect::<Vec<_>>();
+    cooklang_fs::map_par(&entries, |entry| match entry.read() {
+        Ok(content) => Some((entry.path().to_owned(), SrchEntry::new(&content, parser))),
+        Err(e) => {
+            tracing::warn!("Skipping '{}' in search: {e}", entry.path());
+            None
+        }
+    })
+    .into_iter()
+    .flatten()
+    .collect()
 }
 
 impl Indexes {
-    fn new(fs: FsIndex) -> Self {
+    fn new(fs: FsIndex, store: Option<Utf8PathBuf>, collation: Collation, lazy_srch: bool) -> Self {
         // Empty (owned) parser just for metadata
         let parser = cooklang::CooklangParser::new(
             cooklang::Extensions::SPECIAL_METADATA,
             cooklang::Converter::empty(),
         );
-        let mut srch = BTreeMap::new();
-        let insert_search_entry = |index: &mut BTreeMap<_, _>, entry: RecipeEntry| {
-            let content = entry.read().expect("can't read recipe");
-            let meta = content.metadata(&parser);
-            index.insert(entry.path().to_owned(), meta);
+        let mut indexes = Self {
+            fs,
+            srch: None,
+            lazy_srch,
+            parser,
+            store,
+            collation,
         };
-        for entry in fs.get_all() {
-            insert_search_entry(&mut srch, entry);
+        if !lazy_srch {
+            indexes.ensure_srch();
         }
+        indexes
+    }
 
-        Self { fs, srch, parser }
+    fn ensure_srch(&mut self) {
+        if self.srch.is_none() {
+            self.srch = Some(build_srch(&self.parser, &self.fs));
+        }
     }
 
-    fn revalidate(&mut self, path: &Utf8Path) -> Result<(), cooklang_fs::Error> {
-        self.srch.remove(path);
-        self.insert_srch(path)
+    fn refresh(&mut self) -> Result<(), cooklang_fs::Error> {
+        self.fs.refresh()?;
+        self.srch = None;
+        if !self.lazy_srch {
+            self.ensure_srch();
+        }
+        if let Some(file) = &self.store {
+            let dir = file.parent().expect("index file without parent");
+            let _lock = cooklang_fs::CollectionLock::acquire(dir)?;
+            self.fs.save(file)?;
+        }
+        Ok(())
+    }
+
+    /// Returns `false` if the content did not change
+    fn revalidate(&mut self, path: &Utf8Path) -> Result<bool, cooklang_fs::Error> {
+        let Some(srch) = &mut self.srch else {
+            return Ok(true);
+        };
+        let content = RecipeEntry::new(path).read()?;
+        if srch
+            .get(path)
+            .is_some_and(|e| e.hash == content.semantic_hash())
+        {
+            return Ok(false);
+        }
+        srch.insert(path.to_owned(), SrchEntry::new(&content, &self.parser));
+        Ok(true)
     }
 
     fn remove(&mut self, path: &Utf8Path) {
-        self.srch.remove(path);
+        if let Some(srch) = &mut self.srch {
+            srch.remove(path);
+        }
         let _ = self.fs.remove(path);
     }
 
     fn insert_srch(&mut self, path: &Utf8Path) -> Result<(), cooklang_fs::Error> {
-        let meta = RecipeEntry::new(path).read()?.metadata(&self.parser);
-        self.srch.insert(path.to_owned(), meta);
+        let Some(srch) = &mut self.srch else {
+            return Ok(());
+        };
+        let content = RecipeEntry::new(path).read()?;
+        srch.insert(path.to_owned(), SrchEntry::new(&content, &self.parser));
         Ok(())
     }
 
@@ -76,13 +155,35 @@ pub enum Update {
     Renamed { from: Utf8PathBuf, to: Utf8PathBuf },
 }
 
+/// How the index is kept up to date
+pub enum IndexMode {
+    /// Watch the file system for changes
+    Watch,
+    /// Only update on [`AsyncFsIndex::refresh`], storing the index in the
+    /// given file
+    ///
+    /// The recipes are not read until something is searched.
+    Manual { store: Option<Utf8PathBuf> },
+}
+
 impl AsyncFsIndex {
-    pub fn new(index: FsIndex) -> (Self, broadcast::Receiver<Update>) {
+    pub fn new(
+        index: FsIndex,
+        mode: IndexMode,
+        collation: Collation,
+    ) -> (Self, broadcast::Receiver<Update>) {
         let (in_updt_tx, mut in_updt_rx) = mpsc::channel::<Update>(1);
         let (out_updates_tx, out_updates_rx) = broadcast::channel::<Update>(1);
-        watch_changes_task(in_updt_tx, index.base_path());
+        let (store, lazy_srch) = match mode {
+            IndexMode::Watch => (None, false),
+            IndexMode::Manual { store } => (store, true),
+        };
+        let indexes = Indexes::new(index, store, collation, lazy_srch);
+        if !lazy_srch {
+            watch_changes_task(in_updt_tx, indexes.fs.base_path());
+        }
 
-        let indexes = Arc::new(RwLock::new(Indexes::new(index)));
+        let indexes = Arc::new(RwLock::new(indexes));
 
         let indexes2 = Arc::clone(&indexes);
         tokio::spawn(async move {
@@ -90,8 +191,11 @@ impl AsyncFsIndex {
             while let Some(update) = in_updt_rx.recv().await {
                 match &update {
                     Update::Modified { path } => {
+                        if let Ok(false) = indexes.write().await.revalidate(path) {
+                            tracing::debug!("'{path}' modified, but content did not change");
+                            continue;
+                        }
                         tracing::info!("Updated '{path}'");
-                        let _ = indexes.write().await.revalidate(path);
                     }
                     Update::Added { path } => {
                         tracing::info!("Added '{path}'");
@@ -126,6 +230,40 @@ impl AsyncFsIndex {
         indexes.fs.resolve(recipe, relative_to)
     }
 
+    /// See [`cooklang_fs::FsIndex::resolve_relative`]
+    pub fn resolve_relative_blocking(
+        &self,
+        base_recipe: &Utf8Path,
+        reference: &str,
+    ) -> Result<RecipeEntry, cooklang_fs::Error> {
+        let indexes = self.indexes.blocking_read();
+        indexes.fs.resolve_relative(base_recipe, reference)
+    }
+
+    /// See [`cooklang_fs::FsIndex::create`], locking the collection
+    pub async fn create(
+        &self,
+        name: &str,
+        content: &str,
+        on_collision: OnCollision,
+        options: WriteOptions,
+    ) -> Result<RecipeEntry, cooklang_fs::Error> {
+        let mut indexes = self.indexes.write().await;
+        tokio::task::block_in_place(|| {
+            let base_path = indexes.fs.base_path().to_owned();
+            let _lock = cooklang_fs::CollectionLock::acquire(&base_path.join(crate::COOK_DIR))?;
+            let entry = indexes.fs.create(name, content, on_collision, options)?;
+            indexes.insert_srch(entry.path())?;
+            Ok(entry)
+        })
+    }
+
+    /// Walks the collection again to find changes
+    pub async fn refresh(&self) -> Result<(), cooklang_fs::Error> {
+        let mut indexes = self.indexes.write().await;
+        tokio::task::block_in_place(|| indexes.refresh())
+    }
+
     pub async fn get(&self, recipe: &str) -> Result<RecipeEntry, cooklang_fs::Error> {
         let indexes = self.indexes.read().await;
         indexes.fs.get(recipe)
@@ -133,26 +271,54 @@ impl AsyncFsIndex {
 
     pub async fn search<T>(
         &self,
-        pred: impl Fn(&RecipeEntry, Option<&MetadataResult>) -> bool,
+        pred: impl Fn(&RecipeEntry, Option<&MetadataResult>, &[&str]) -> bool,
         map: impl Fn(RecipeEntry, Option<&MetadataResult>) -> T,
         skip: usize,
         take: usize,
     ) -> Vec<T> {
+        self.search_page(pred, map, skip, take).await.1
+    }
+
+    /// Like [`Self::search`], also returning the number of matches
+    pub async fn search_page<T>(
+        &self,
+        pred: impl Fn(&RecipeEntry, Option<&MetadataResult>, &[&str]) -> bool,
+        map: impl Fn(RecipeEntry, Option<&MetadataResult>) -> T,
+        skip: usize,
+        take: usize,
+    ) -> (usize, Vec<T>) {
+        if self.indexes.read().await.srch.is_none() {
+            let mut indexes = self.indexes.write().await;
+            tokio::task::block_in_place(|| indexes.ensure_srch());
+        }
         let indexes = self.indexes.read().await;
-        indexes
+        let mut found = indexes
             .fs
-            .get_all()
+            .iter()
             .filter_map(|entry| {
-                let meta = indexes.srch.get(entry.path());
-                match pred(&entry, meta) {
+                let meta = indexes
+                    .srch
+                    .as_ref()
+                    .and_then(|srch| srch.get(entry.path()))
+                    .map(|e| &e.meta);
+                let aliases = indexes.fs.aliases_of(entry.path());
+                match pred(&entry, meta, &aliases) {
                     true => Some((entry, meta)),
                     false => None,
                 }
             })
+            .collect::<Vec<_>>();
+        indexes
+            .collation
+            .sort_by_path(&mut found, |(entry, _)| entry.path());
+        let total = found.len();
+        let page = found
+            .into_iter()
             .skip(skip)
             .take(take)
             .map(|(entry, meta)| map(entry, meta))
-            .collect()
+            .collect();
+        (total, page)
     }
 }
 
@@ -0,0 +1,56 @@
+//! Serving several collections
+//!
+//! With `--collections`, every collection in the `collections` table of the
+//! global config is served under `/<name>`. Each one has its own index,
+//! config and watcher, it's the same as running `serve` inside it with
+//! `--base-path /<name>`. The root lists them.
+
+use anyhow::{bail, Result};
+use axum::{response::Html, routing::get, Router};
+
+/// Checks that a collection name can be used as a path segment
+pub fn collection_prefix(name: &str) -> Result<String> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+    {
+        bail!("invalid collection name '{name}', use only letters, digits, '-' and '_'");
+    }
+    Ok(format!("/{name}"))
+}
+
+/// Router with the page that links to the collections, at `{prefix}/`
+pub fn index_router(prefix: &str, names: Vec<String>) -> Router {
+    let page = minijinja::render!(
+        r#"<!DOCTYPE html>
+<html>
+<head><meta charset="utf-8"><title>Collections</title></head>
+<body>
+<h1>Collections</h1>
+<ul>
+{%- for name in names %}
+<li><a href="{{ name|urlencode }}/">{{ name }}</a></li>
+{%- endfor %}
+</ul>
+</body>
+</html>"#,
+        names
+    );
+    let route = format!("{prefix}/");
+    Router::new().route(&route, get(move || async move { Html(page) }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        assert_eq!(collection_prefix("family").unwrap(), "/family");
+        assert_eq!(collection_prefix("my_recipes-2").unwrap(), "/my_recipes-2");
+        assert!(collection_prefix("").is_err());
+        assert!(collection_prefix("a/b").is_err());
+        assert!(collection_prefix("..").is_err());
+    }
+}
@@ -0,0 +1,386 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use camino::Utf8PathBuf;
+use cooklang::MetadataResult;
+use cooklang_fs::{
+    transaction::{Operation, Transaction},
+    CollectionLock, ContentHash, Error, InvalidHash, OnCollision, RecipeEntry, Trash,
+};
+use serde::{Deserialize, Serialize};
+use tokio::task::block_in_place;
+
+use crate::{
+    archive::{archived_in_metadata, in_archive_dir},
+    cmd::serve::{proxy::BasePath, AppState, S},
+    config::{journal_file_path, trash_path},
+    query::Filter,
+    util::meta_name,
+    COOK_DIR,
+};
+
+use super::{check_path, clean_path, image_url, ok_status};
+
+const DEFAULT_PER_PAGE: usize = 50;
+const MAX_PER_PAGE: usize = 500;
+
+/// Fields of [`ApiRecipe`] that can be selected with `?fields=`
+const FIELDS: &[&str] = &[
+    "name",
+    "path",
+    "tags",
+    "emoji",
+    "description",
+    "servings",
+    "image",
+    "error",
+];
+
+#[derive(Deserialize)]
+pub struct RecipesQuery {
+    /// [`Filter`] over the JSON of each recipe, like `.tags[] == dinner`
+    q: Option<String>,
+    #[serde(default = "first_page")]
+    page: usize,
+    #[serde(default = "default_per_page")]
+    per_page: usize,
+    /// Comma separated fields to include, all by default
+    fields: Option<String>,
+}
+
+fn first_page() -> usize {
+    1
+}
+
+fn default_per_page() -> usize {
+    DEFAULT_PER_PAGE
+}
+
+#[derive(Serialize)]
+struct RecipesPage {
+    page: usize,
+    per_page: usize,
+    total: usize,
+    recipes: Vec<serde_json::Value>,
+}
+
+#[derive(Serialize)]
+struct ApiRecipe {
+    name: String,
+    /// Relative to the collection and without the extension, like in `/r/`
+    path: Utf8PathBuf,
+    tags: Vec<String>,
+    emoji: Option<String>,
+    description: Option<String>,
+    servings: Option<Vec<u32>>,
+    image: Option<String>,
+    /// The metadata could not be parsed
+    error: bool,
+}
+
+/// Lists the recipes of the collection in pages
+///
+/// `page` starts at 1 and `per_page` is at most [`MAX_PER_PAGE`].
+pub async fn recipes(
+    BasePath(base): BasePath,
+    State(state): State<S>,
+    Query(query): Query<RecipesQuery>,
+) -> Response {
+    if query.page == 0 || query.per_page == 0 || query.per_page > MAX_PER_PAGE {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("page starts at 1 and per_page must be between 1 and {MAX_PER_PAGE}"),
+        )
+            .into_response();
+    }
+    let fields = match query.fields.as_deref().map(parse_fields).transpose() {
+        Ok(fields) => fields,
+        Err(field) => {
+            return (StatusCode::BAD_REQUEST, format!("unknown field '{field}'")).into_response()
+        }
+    };
+
+    let filter = match query.q.as_deref().map(Filter::parse).transpose() {
+        Ok(filter) => filter,
+        Err(e) => return (StatusCode::BAD_REQUEST, format!("invalid query: {e}")).into_response(),
+    };
+    let skip = (query.page - 1).saturating_mul(query.per_page);
+    let (total, recipes) = state
+        .recipe_index
+        .search_page(
+            |entry, meta, _| {
+                let archived = meta.and_then(|r| r.valid_output()).is_some_and(|m| {
+                    in_archive_dir(&state.base_path, entry.path()) || archived_in_metadata(m)
+                });
+                if archived && !state.include_archived {
+                    return false;
+                }
+                // recipes with errors are still listed, a client can show them
+                let Some(filter) = &filter else {
+                    return true;
+                };
+                let recipe = api_recipe(entry.clone(), &state, &base, meta);
+                filter.matches(&serde_json::to_value(recipe).expect("recipe to json"))
+            },
+            |entry, meta| api_recipe(entry, &state, &base, meta),
+            skip,
+            query.per_page,
+        )
+        .await;
+
+    let recipes = recipes
+        .into_iter()
+        .map(|r| {
+            let mut value = serde_json::to_value(r).expect("recipe to json");
+            if let (Some(fields), Some(map)) = (&fields, value.as_object_mut()) {
+                map.retain(|k, _| fields.contains(&k.as_str()));
+            }
+            value
+        })
+        .collect();
+
+    Json(RecipesPage {
+        page: query.page,
+        per_page: query.per_page,
+        total,
+        recipes,
+    })
+    .into_response()
+}
+
+fn api_recipe(
+    entry: RecipeEntry,
+    state: &AppState,
+    base: &str,
+    meta: Option<&MetadataResult>,
+) -> ApiRecipe {
+    let path = clean_path(entry.path(), &state.base_path).with_extension("");
+    let main_image = || {
+        entry
+            .images()
+            .iter()
+            .find(|i| i.indexes.is_none())
+            .map(|i| image_url(base, &i.path, &state.base_path))
+    };
+    match meta.and_then(|res| res.valid_output()) {
+        Some(m) => ApiRecipe {
+            name: meta_name(m).unwrap_or(entry.name()).to_string(),
+            path,
+            tags: m.tags().unwrap_or(&[]).to_vec(),
+            emoji: m.emoji().map(|e| e.to_string()),
+            description: m.description().map(|d| d.to_string()),
+            servings: m.servings().map(<[u32]>::to_vec),
+            image: m.map.get("image").cloned().or_else(main_image),
+            error: false,
+        },
+        None => ApiRecipe {
+            name: entry.name().to_string(),
+            path,
+            tags: Vec::new(),
+            emoji: None,
+            description: None,
+            servings: None,
+            image: main_image(),
+            error: true,
+        },
+    }
+}
+
+/// Links of the ingredients of a recipe, see [`crate::links`]
+///
+/// Recipe links are the name in the links file or metadata, not resolved.
+pub async fn links(State(state): State<S>, Path(path): Path<String>) -> Response {
+    if let Err(e) = check_path(&path) {
+        return e.into_response();
+    }
+    let entry = ok_status!(state.recipe_index.get(&path).await, NOT_FOUND);
+    let content = ok_status!(block_in_place(|| entry.read()), NOT_FOUND).into_text();
+    let (recipe, _warnings) = ok_status!(
+        block_in_place(|| {
+            state
+                .parser
+                .parse_with_options(&content, state.parse_options(Some(entry.path())))
+                .into_result()
+        }),
+        UNPROCESSABLE_ENTITY
+    );
+    Json(state.links.for_recipe(&recipe.default_scale())).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct NewRecipe {
+    /// Path without extension, like `Soups/Leek`
+    name: String,
+    #[serde(default)]
+    content: String,
+    /// Add a number to the name if it exists instead of failing
+    #[serde(default)]
+    suffix: bool,
+}
+
+/// Creates a recipe, answering with its final path
+///
+/// Only with `--allow-edits`.
+pub async fn create(State(state): State<S>, Json(new): Json<NewRecipe>) -> Response {
+    if !state.allow_edits {
+        return edits_disabled();
+    }
+    let on_collision = if new.suffix {
+        OnCollision::Suffix
+    } else {
+        OnCollision::Fail
+    };
+    let res = state
+        .recipe_index
+        .create(
+            &new.name,
+            &new.content,
+            on_collision,
+            state.config.write_options(),
+        )
+        .await;
+    match res {
+        Ok(entry) => {
+            tracing::info!("Created '{}'", entry.path());
+            let body = serde_json::json!({
+                "name": entry.name(),
+                "path": clean_path(entry.path(), &state.base_path),
+            });
+            (StatusCode::CREATED, Json(body)).into_response()
+        }
+        Err(e) => (error_status(&e), e.to_string()).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Batch {
+    operations: Vec<BatchOperation>,
+}
+
+/// The `hash` is of the content the client changed, to detect conflicts
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum BatchOperation {
+    Create {
+        path: Utf8PathBuf,
+        content: String,
+    },
+    Update {
+        path: Utf8PathBuf,
+        content: String,
+        hash: Option<String>,
+    },
+    Delete {
+        path: Utf8PathBuf,
+        hash: Option<String>,
+    },
+}
+
+impl TryFrom<BatchOperation> for Operation {
+    type Error = InvalidHash;
+
+    fn try_from(op: BatchOperation) -> Result<Self, Self::Error> {
+        let parse = |hash: Option<String>| hash.map(|h| h.parse::<ContentHash>()).transpose();
+        Ok(match op {
+            BatchOperation::Create { path, content } => Operation::Create { path, content },
+            BatchOperation::Update {
+                path,
+                content,
+                hash,
+            } => Operation::Update {
+                path,
+                content,
+                expected: parse(hash)?,
+            },
+            BatchOperation::Delete { path, hash } => Operation::Delete {
+                path,
+                expected: parse(hash)?,
+            },
+        })
+    }
+}
+
+/// Creates, updates and deletes recipes, all of them or none
+///
+/// Only with `--allow-edits`.
+pub async fn batch(State(state): State<S>, Json(batch): Json<Batch>) -> Response {
+    if !state.allow_edits {
+        return edits_disabled();
+    }
+    let mut transaction = Transaction::new(&state.base_path, journal_file_path(&state.base_path))
+        .with_trash(Trash::new(&state.base_path, trash_path(&state.base_path)));
+    for op in batch.operations {
+        match Operation::try_from(op) {
+            Ok(op) => transaction.push(op),
+            Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        };
+    }
+    let applied = transaction.len();
+
+    let res = tokio::task::block_in_place(|| {
+        let _lock = CollectionLock::acquire(&state.base_path.join(COOK_DIR))?;
+        transaction.commit()
+    });
+    if let Err(e) = res {
+        return (error_status(&e), e.to_string()).into_response();
+    }
+    tracing::info!("Applied a batch of {applied} changes");
+
+    ok_status!(state.recipe_index.refresh().await);
+    Json(serde_json::json!({ "applied": applied })).into_response()
+}
+
+pub(super) fn edits_disabled() -> Response {
+    (
+        StatusCode::FORBIDDEN,
+        "edits are disabled, start the server with --allow-edits",
+    )
+        .into_response()
+}
+
+/// Status for an error applying changes
+pub(super) fn error_status(e: &Error) -> StatusCode {
+    match e {
+        Error::AlreadyExists(_) | Error::Conflict(_) => StatusCode::CONFLICT,
+        Error::ReadOnly(_) => StatusCode::FORBIDDEN,
+        Error::NotFound(_) => StatusCode::NOT_FOUND,
+        Error::InvalidName(_) | Error::UnsafeName { .. } => StatusCode::BAD_REQUEST,
+        Error::Locked(_) => StatusCode::SERVICE_UNAVAILABLE,
+        _ => {
+            tracing::error!("Error applying changes: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// Parses the `fields` param, the error is the unknown field
+fn parse_fields(fields: &str) -> Result<Vec<&'static str>, String> {
+    fields
+        .split(',')
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+        .map(|f| {
+            FIELDS
+                .iter()
+                .find(|&&known| known == f)
+                .copied()
+                .ok_or_else(|| f.to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields() {
+        assert_eq!(parse_fields("name,tags").unwrap(), ["name", "tags"]);
+        assert_eq!(parse_fields(" name , ,path").unwrap(), ["name", "path"]);
+        assert!(parse_fields("").unwrap().is_empty());
+        assert_eq!(parse_fields("name,ingredients").unwrap_err(), "ingredients");
+    }
+}
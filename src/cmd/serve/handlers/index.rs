@@ -9,7 +9,10 @@ use camino::Utf8PathBuf;
 use minijinja::{context, Value};
 use serde::Deserialize;
 
-use crate::cmd::serve::{locale::UserLocale, S};
+use crate::{
+    archive::{archived_in_metadata, ARCHIVE_DIR},
+    cmd::serve::{locale::UserLocale, proxy::BasePath, S},
+};
 
 use super::{check_path, clean_path, mj_ok, recipe_entry_context};
 
@@ -20,6 +23,7 @@ pub struct IndexQuery {
 
 pub async fn index(
     UserLocale(t): UserLocale,
+    BasePath(base): BasePath,
     State(state): State<S>,
     requested_path: Option<Path<String>>,
     Query(q): Query<IndexQuery>,
@@ -46,20 +50,46 @@ pub async fn index(
         }
     };
 
-    let mut folders = Vec::new();
-    let mut recipes = Vec::new();
-    for e in entries {
-        match e {
-            cooklang_fs::Entry::Dir(dir) => folders.push(context! {
+    let hide_archive = !state.include_archived && path == state.base_path;
+    let mut dirs = Vec::new();
+    let mut entries = entries
+        .filter_map(|e| match e {
+            cooklang_fs::Entry::Dir(dir) => {
+                if !(hide_archive && dir.file_name() == ARCHIVE_DIR) {
+                    dirs.push(dir);
+                }
+                None
+            }
+            cooklang_fs::Entry::Recipe(r) => Some(r),
+        })
+        .collect::<Vec<_>>();
+    let collation = state.config.collation;
+    collation.sort_by_name(&mut dirs, |d| d.file_name());
+    collation.sort_by_name(&mut entries, |r| r.file_name());
+
+    let folders = dirs
+        .iter()
+        .map(|dir| {
+            context! {
                 name => dir.file_name(),
                 path => clean_path(dir.path(), &state.base_path)
-            }),
-            cooklang_fs::Entry::Recipe(r) => {
-                let meta = r.read().ok().map(|c| c.metadata(&state.parser));
-                recipes.push(recipe_entry_context(r, &state, meta.as_ref()).unwrap());
             }
-        }
-    }
+        })
+        .collect::<Vec<_>>();
+    let recipes = entries
+        .into_iter()
+        .filter_map(|r| {
+            let meta = r.read().ok().map(|c| c.metadata(&state.parser));
+            let archived = meta
+                .as_ref()
+                .and_then(|m| m.valid_output())
+                .is_some_and(archived_in_metadata);
+            if archived && !state.include_archived {
+                return None;
+            }
+            recipe_entry_context(r, &state, &base, meta.as_ref())
+        })
+        .collect::<Vec<_>>();
 
     let tmpl = mj_ok!(state.templates.get_template("index.html"));
     let path_parts = path
@@ -70,6 +100,7 @@ pub async fn index(
 
     let res = tmpl.render(context! {
         t,
+        base,
         recipes,
         folders,
         path => Value::from_iter(path_parts),
@@ -8,20 +8,28 @@ use crate::{config::UiConfig, util::meta_name};
 use super::AppState;
 
 pub mod about;
+pub mod api;
 pub mod convert_popover;
 pub mod index;
 pub mod open_editor;
 pub mod recipe;
+pub mod refresh;
 pub mod search;
+pub mod share;
+pub mod shopping_list;
 pub mod sse_updates;
 pub mod static_file;
+pub mod submit;
+pub mod sync;
 
 pub use about::about;
 pub use convert_popover::convert_popover;
 pub use index::index;
 pub use open_editor::open_editor;
 pub use recipe::recipe;
+pub use refresh::refresh;
 pub use search::search;
+pub use shopping_list::{check_item, shopping_list};
 pub use sse_updates::sse_updates;
 pub use static_file::static_file;
 
@@ -80,7 +88,7 @@ pub fn check_path(p: &str) -> Result<(), axum::http::StatusCode> {
     Ok(())
 }
 
-fn clean_path(p: &Utf8Path, base_path: &Utf8Path) -> Utf8PathBuf {
+pub(super) fn clean_path(p: &Utf8Path, base_path: &Utf8Path) -> Utf8PathBuf {
     let p = p
         .strip_prefix(base_path)
         .expect("dir entry path not relative to base path");
@@ -91,9 +99,11 @@ fn clean_path(p: &Utf8Path, base_path: &Utf8Path) -> Utf8PathBuf {
     p
 }
 
+/// `base` is the prefix of the links, see [`BasePath`](super::proxy::BasePath)
 fn recipe_entry_context(
     r: RecipeEntry,
     state: &AppState,
+    base: &str,
     meta: Option<&MetadataResult>,
 ) -> Option<Value> {
     let mut metadata = Value::UNDEFINED;
@@ -127,22 +137,22 @@ fn recipe_entry_context(
             .images()
             .iter()
             .find(|i| i.indexes.is_none())
-            .map(|i| image_url(&i.path, &state.base_path));
+            .map(|i| image_url(base, &i.path, &state.base_path));
     }
 
     let path = clean_path(r.path(), &state.base_path).with_extension("");
 
     Some(context! {
         fallback_name => r.name(),
-        href => format!("/r/{path}"),
+        href => format!("{base}/r/{path}"),
         error,
         image,
         ..metadata,
     })
 }
 
-fn image_url(path: &Utf8Path, base_path: &Utf8Path) -> String {
-    format!("/src/{}", clean_path(path, base_path))
+fn image_url(base: &str, path: &Utf8Path, base_path: &Utf8Path) -> String {
+    format!("{base}/src/{}", clean_path(path, base_path))
 }
 
 fn tag_context(name: &str, ui_config: &UiConfig) -> Value {
@@ -165,15 +175,13 @@ fn tag_context(name: &str, ui_config: &UiConfig) -> Value {
 struct Searcher {
     tags: Vec<String>,
     name_parts: Vec<String>,
+    matching: cooklang_fs::NameMatching,
 }
 
 impl Searcher {
-    fn matches_recipe(&self, name: &str, tags: &[String]) -> bool {
-        let name = name.to_lowercase();
-        for part in &self.name_parts {
-            if !name.contains(part) {
-                return false;
-            }
+    fn matches_recipe(&self, name: &str, aliases: &[&str], tags: &[String]) -> bool {
+        if !(self.matches_name(name) || aliases.iter().any(|a| self.matches_name(a))) {
+            return false;
         }
 
         for tag in &self.tags {
@@ -184,4 +192,11 @@ impl Searcher {
 
         true
     }
+
+    fn matches_name(&self, name: &str) -> bool {
+        let name = self.matching.key(name);
+        self.name_parts
+            .iter()
+            .all(|part| name.contains(&self.matching.key(part)))
+    }
 }
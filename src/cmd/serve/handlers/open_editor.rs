@@ -2,12 +2,12 @@ use std::{net::SocketAddr, sync::Arc};
 
 use axum::{
     extract::{ConnectInfo, Path, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::{Html, IntoResponse, Response},
 };
 use minijinja::context;
 
-use crate::cmd::serve::{handlers::mj_ok, locale::UserLocale, S};
+use crate::cmd::serve::{handlers::mj_ok, locale::UserLocale, proxy::client_ip, S};
 
 use super::check_path;
 
@@ -16,8 +16,9 @@ pub async fn open_editor(
     Path(path): Path<String>,
     State(state): State<S>,
     ConnectInfo(who): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
 ) -> Response {
-    if !who.ip().is_loopback() {
+    if !client_ip(who, &headers).is_loopback() {
         tracing::warn!("Denied open editor request from '{who}': Not loopback ip");
         return StatusCode::UNAUTHORIZED.into_response();
     }
@@ -12,12 +12,17 @@ use serde::{Deserialize, Serialize};
 use tokio::task::block_in_place;
 
 use crate::{
-    cmd::serve::{
-        get_cookie,
-        handlers::{clean_path, ok_status, tag_context},
-        AppState, S,
+    cmd::{
+        recipe::RecipeSidecars,
+        serve::{
+            get_cookie,
+            handlers::{clean_path, ok_status, tag_context},
+            proxy::{client_ip, BasePath},
+            AppState, S, VIDEO_EXTENSIONS,
+        },
     },
     config::Config,
+    links::Link,
     util::{meta_name, metadata_validator},
     RECIPE_REF_ERROR,
 };
@@ -37,6 +42,7 @@ pub async fn recipe(
     Query(query): Query<RecipeQuery>,
     uri: Uri,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    BasePath(base): BasePath,
 ) -> Response {
     let units: Option<cooklang::convert::System> = match query.units.as_deref() {
         None => None,
@@ -52,7 +58,7 @@ pub async fn recipe(
     }
 
     let entry = ok_status!(state.recipe_index.get(&path).await, NOT_FOUND);
-    let content = ok_status!(tokio::fs::read_to_string(&entry.path()).await, NOT_FOUND);
+    let content = ok_status!(block_in_place(|| entry.read()), NOT_FOUND).into_text();
 
     let res = block_in_place(|| {
         state
@@ -67,21 +73,36 @@ pub async fn recipe(
     let src_path = clean_path(entry.path(), &state.base_path);
     let ctx = context! {
         t,
+        base,
         is_valid => res.is_ok(),
-        href => format!("/r/{}", src_path.with_extension("")),
+        href => format!("{base}/r/{}", src_path.with_extension("")),
         src_path,
     };
 
     match res {
-        Ok((scalable, warnings)) => {
+        Ok((mut scalable, warnings)) => {
+            crate::compound::normalize(&mut scalable, state.parser.converter());
             let scaled = {
                 let mut r = if let Some(servings) = query.scale {
                     scalable.scale(servings, state.parser.converter())
                 } else {
                     scalable.default_scale()
                 };
+                ok_status!(
+                    crate::scaling::check_scaled(
+                        &mut r,
+                        entry.name(),
+                        state.config.text_quantities
+                    ),
+                    UNPROCESSABLE_ENTITY
+                );
+                crate::variants::resolve(&mut r);
                 if let Some(target) = units {
                     let _ = r.convert(target, state.parser.converter());
+                    state
+                        .config
+                        .unit_preferences
+                        .apply_recipe(&mut r, state.parser.converter());
                 }
                 r
             };
@@ -108,16 +129,15 @@ pub async fn recipe(
                     .iter()
                     .filter(|igr| igr.modifiers().contains(Modifiers::RECIPE))
                     .filter_map(|igr| {
-                        let res = state.recipe_index.resolve_blocking(
-                            &igr.name,
-                            Some(entry.path().parent().expect("no parent for recipe entry")),
-                        );
+                        let res = state
+                            .recipe_index
+                            .resolve_relative_blocking(entry.path(), &igr.name);
 
                         match res {
                             Ok(entry) => {
                                 let path =
                                     clean_path(entry.path(), &state.base_path).with_extension("");
-                                let value = Value::from(format!("/r/{path}"));
+                                let value = Value::from(format!("{base}/r/{path}"));
                                 Some((igr.name.clone(), value))
                             }
                             Err(_) => None,
@@ -126,10 +146,36 @@ pub async fn recipe(
                     .collect()
             });
 
+            let ingredient_links: HashMap<String, Value> = block_in_place(|| {
+                state
+                    .links
+                    .for_recipe(&scaled)
+                    .into_iter()
+                    .filter_map(|(name, link)| {
+                        let href = match link {
+                            Link::Url(url) => url,
+                            Link::Recipe(recipe) => {
+                                let res = state.recipe_index.resolve_blocking(&recipe, None);
+                                let Ok(entry) = res else {
+                                    tracing::warn!(
+                                        "Recipe '{recipe}' linked from '{name}' not found"
+                                    );
+                                    return None;
+                                };
+                                let path =
+                                    clean_path(entry.path(), &state.base_path).with_extension("");
+                                format!("{base}/r/{path}")
+                            }
+                        };
+                        Some((name, Value::from(href)))
+                    })
+                    .collect()
+            });
+
             let images = Value::from_iter(entry.images().iter().map(|img| {
                 context! {
                     indexes => img.indexes,
-                    href => image_url(&img.path, &state.base_path)
+                    href => image_url(&base, &img.path, &state.base_path)
                 }
             }));
             let main_image = scaled.metadata.map.get("image").cloned().or_else(|| {
@@ -137,9 +183,17 @@ pub async fn recipe(
                     .images()
                     .iter()
                     .find(|img| img.indexes.is_none())
-                    .map(|img| image_url(&img.path, &state.base_path))
+                    .map(|img| image_url(&base, &img.path, &state.base_path))
             });
 
+            let main_video = VIDEO_EXTENSIONS
+                .iter()
+                .map(|ext| entry.path().with_extension(ext))
+                .find(|p| p.is_file())
+                .map(|p| image_url(&base, &p, &state.base_path));
+
+            let sidecars = block_in_place(|| RecipeSidecars::read(&entry));
+
             let r = make_recipe_context(scaled, state.parser.converter(), &state.config);
 
             let ctx = context! {
@@ -148,12 +202,15 @@ pub async fn recipe(
                 query,
                 path => uri.path(),
                 recipe_refs,
+                ingredient_links,
 
                 times,
                 images,
                 main_image,
+                main_video,
+                sidecars => Value::from_serialize(&sidecars),
 
-                is_loopback => addr.ip().is_loopback(),
+                is_loopback => client_ip(addr, &headers).is_loopback(),
                 igr_layout => get_cookie(&headers, "igr_layout").unwrap_or("line"),
 
                 report_html,
@@ -272,7 +329,7 @@ impl minijinja::value::StructObject for TemplateIngredient {
                 index => rel.0,
                 target => rel.1
             })),
-            "modifiers" => Value::from_serialize(&self.0.modifiers()),
+            "modifiers" => Value::from_serialize(self.0.modifiers()),
             _ => return None,
         };
 
@@ -290,12 +347,8 @@ impl minijinja::value::StructObject for TemplateCookware {
             "alias" => mj_opt!(self.0.alias.as_deref()),
             "quantity" => Value::from_serialize(&self.0.quantity),
             "note" => mj_opt!(self.0.note.as_deref()),
-            "references_to" => mj_opt!(self
-                .0
-                .relation
-                .references_to()
-                .map(|rel| Value::from_serialize(&rel))),
-            "modifiers" => Value::from_serialize(&self.0.modifiers()),
+            "references_to" => mj_opt!(self.0.relation.references_to().map(Value::from_serialize)),
+            "modifiers" => Value::from_serialize(self.0.modifiers()),
             _ => return None,
         };
 
@@ -319,16 +372,15 @@ impl AppState {
     fn checker(
         &self,
         relative_to: Option<&Utf8Path>,
-    ) -> Option<cooklang::analysis::RecipeRefCheck> {
+    ) -> Option<cooklang::analysis::RecipeRefCheck<'_>> {
         if self.config.recipe_ref_check {
-            let relative_to =
-                relative_to.map(|r| r.parent().expect("no parent for recipe entry").to_owned());
+            let relative_to = relative_to.map(Utf8Path::to_path_buf);
             Some(Box::new(move |name: &str| {
-                if self
-                    .recipe_index
-                    .resolve_blocking(name, relative_to.as_deref())
-                    .is_ok()
-                {
+                let res = match &relative_to {
+                    Some(recipe) => self.recipe_index.resolve_relative_blocking(recipe, name),
+                    None => self.recipe_index.resolve_blocking(name, None),
+                };
+                if res.is_ok() {
                     cooklang::analysis::CheckResult::Ok
                 } else {
                     cooklang::analysis::CheckResult::Warning(vec![RECIPE_REF_ERROR.into()])
@@ -339,7 +391,7 @@ impl AppState {
         }
     }
 
-    fn parse_options(&self, relative_to: Option<&Utf8Path>) -> ParseOptions {
+    pub(crate) fn parse_options(&self, relative_to: Option<&Utf8Path>) -> ParseOptions<'_> {
         ParseOptions {
             recipe_ref_check: self.checker(relative_to),
             metadata_validator: Some(Box::new(metadata_validator)),
@@ -347,7 +399,11 @@ impl AppState {
     }
 }
 
-fn report_to_html(report: &SourceReport, file_name: &str, content: &str) -> anyhow::Result<String> {
+pub(super) fn report_to_html(
+    report: &SourceReport,
+    file_name: &str,
+    content: &str,
+) -> anyhow::Result<String> {
     let mut buf = Vec::new();
     report.write(file_name, content, true, &mut buf)?;
     let ansi = String::from_utf8(buf)?;
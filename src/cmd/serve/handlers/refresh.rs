@@ -0,0 +1,15 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+
+use crate::cmd::serve::S;
+
+use super::ok_status;
+
+pub async fn refresh(State(state): State<S>) -> Response {
+    ok_status!(state.recipe_index.refresh().await);
+    tracing::info!("Index refreshed");
+    StatusCode::NO_CONTENT.into_response()
+}
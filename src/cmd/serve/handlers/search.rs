@@ -7,7 +7,8 @@ use minijinja::{context, Value};
 use serde::Deserialize;
 
 use crate::{
-    cmd::serve::S,
+    archive::{archived_in_metadata, in_archive_dir},
+    cmd::serve::{proxy::BasePath, S},
     util::{is_valid_tag, meta_name},
 };
 
@@ -36,10 +37,11 @@ where
 
 pub async fn search(
     headers: HeaderMap,
+    BasePath(base): BasePath,
     State(state): State<S>,
     Query(query): Query<SearchQuery>,
 ) -> Response {
-    let srch = Searcher::from(query);
+    let srch = Searcher::new(query, state.config.name_matching);
 
     let recipes = if srch.is_empty() {
         Vec::new()
@@ -47,14 +49,21 @@ pub async fn search(
         state
             .recipe_index
             .search(
-                |entry, meta| match meta.and_then(|r| r.valid_output()) {
+                |entry, meta, aliases| match meta.and_then(|r| r.valid_output()) {
+                    Some(m)
+                        if !state.include_archived
+                            && (in_archive_dir(&state.base_path, entry.path())
+                                || archived_in_metadata(m)) =>
+                    {
+                        false
+                    }
                     Some(m) => {
                         let name = meta_name(m).unwrap_or(entry.name());
-                        srch.matches_recipe(name, m.tags().unwrap_or(&[]))
+                        srch.matches_recipe(name, aliases, m.tags().unwrap_or(&[]))
                     }
                     None => false,
                 },
-                |entry, meta| recipe_entry_context(entry, &state, meta),
+                |entry, meta| recipe_entry_context(entry, &state, &base, meta),
                 0,
                 12,
             )
@@ -73,6 +82,7 @@ pub async fn search(
     let t = Value::from(state.locales.get_from_headers(&headers));
     let res = tmpl.render(context! {
         t,
+        base,
         recipes,
         search_query => srch.to_query(),
         is_htmx_search,
@@ -82,8 +92,8 @@ pub async fn search(
     Html(content).into_response()
 }
 
-impl From<SearchQuery> for Searcher {
-    fn from(value: SearchQuery) -> Self {
+impl Searcher {
+    fn new(value: SearchQuery, matching: cooklang_fs::NameMatching) -> Self {
         let mut tags = Vec::new();
         let mut name_parts = Vec::new();
         if let Some(q) = value.q {
@@ -97,11 +107,13 @@ impl From<SearchQuery> for Searcher {
                 }
             }
         }
-        Self { tags, name_parts }
+        Self {
+            tags,
+            name_parts,
+            matching,
+        }
     }
-}
 
-impl Searcher {
     fn to_query(&self) -> String {
         let mut q = String::new();
         for part in &self.name_parts {
@@ -0,0 +1,81 @@
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{ConnectInfo, Path, Query, Request, State},
+    http::{HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use tokio::task::block_in_place;
+use tower::ServiceExt;
+use tower_http::services::ServeFile;
+
+use crate::cmd::serve::{
+    proxy::BasePath,
+    share::{is_shared_file, unix_now},
+    S,
+};
+
+use super::{
+    check_path,
+    recipe::{recipe, RecipeQuery},
+};
+
+#[derive(Deserialize)]
+pub struct ShareQuery {
+    expires: u64,
+    sig: String,
+}
+
+/// A recipe shared with `--share`, if the link is valid
+pub async fn share(
+    headers: HeaderMap,
+    State(state): State<S>,
+    Path(path): Path<String>,
+    query: Query<RecipeQuery>,
+    uri: Uri,
+    connect_info: ConnectInfo<SocketAddr>,
+    base: BasePath,
+) -> Response {
+    let Some(key) = &state.share_key else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let Ok(Query(share)) = Query::<ShareQuery>::try_from_uri(&uri) else {
+        return StatusCode::FORBIDDEN.into_response();
+    };
+    if !key.verify(&path, share.expires, &share.sig) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    if share.expires < unix_now() {
+        return (StatusCode::GONE, "this link has expired").into_response();
+    }
+    recipe(
+        headers,
+        State(state),
+        Path(path),
+        query,
+        uri,
+        connect_info,
+        base,
+    )
+    .await
+}
+
+/// Images and videos of the shared recipes, linked from their pages
+pub async fn share_src(
+    State(state): State<S>,
+    Path(path): Path<String>,
+    request: Request,
+) -> Response {
+    if let Err(e) = check_path(&path) {
+        return e.into_response();
+    }
+    let file = state.base_path.join(&path);
+    if !block_in_place(|| is_shared_file(&state.shared, &file)) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    match ServeFile::new(file).oneshot(request).await {
+        Ok(res) => res.into_response(),
+        Err(infallible) => match infallible {},
+    }
+}
@@ -0,0 +1,151 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    Form,
+};
+use cooklang::{ingredient_list::IngredientList, quantity::Quantity};
+use minijinja::context;
+use serde::Deserialize;
+use tokio::task::block_in_place;
+
+use crate::{
+    checklist::{list_key, Checklist},
+    cmd::{
+        serve::{locale::UserLocale, proxy::BasePath, S},
+        shopping_list::{add_recipe, default_servings, prepare_recipe, skip_filter, Sources},
+    },
+};
+
+use super::{mj_ok, ok_status};
+
+#[derive(Deserialize)]
+pub struct ShoppingListQuery {
+    /// Comma separated recipes with optional servings, like the CLI:
+    /// `Pancakes*4,Salad`
+    #[serde(default)]
+    recipes: String,
+}
+
+pub async fn shopping_list(
+    UserLocale(t): UserLocale,
+    BasePath(base): BasePath,
+    State(state): State<S>,
+    Query(query): Query<ShoppingListQuery>,
+) -> Response {
+    let converter = state.parser.converter();
+    let mut list = IngredientList::new();
+    let mut sources = Sources::new();
+    let is_skipped = skip_filter(&state.config, false);
+    let mut recipes = Vec::new();
+    let mut key_recipes = Vec::new();
+    for entry in query.recipes.split(',').map(str::trim) {
+        if entry.is_empty() {
+            continue;
+        }
+        let (name, servings) = match entry.rsplit_once('*') {
+            Some((name, servings)) => (name, Some(ok_status!(servings.parse(), BAD_REQUEST))),
+            None => (entry, None),
+        };
+        let entry = ok_status!(state.recipe_index.get(name).await, NOT_FOUND);
+        let content = ok_status!(block_in_place(|| entry.read()), NOT_FOUND).into_text();
+        let (recipe, _warnings) = ok_status!(
+            block_in_place(|| {
+                state
+                    .parser
+                    .parse_with_options(&content, state.parse_options(Some(entry.path())))
+                    .into_result()
+            }),
+            UNPROCESSABLE_ENTITY
+        );
+        key_recipes.push((entry.path().to_owned(), default_servings(&recipe, servings)));
+        // like the CLI, so both get the same items and key
+        let recipe = ok_status!(
+            block_in_place(|| prepare_recipe(
+                recipe,
+                servings,
+                false,
+                entry.file_name(),
+                state.config.text_quantities,
+                &state.config,
+                converter,
+            )),
+            UNPROCESSABLE_ENTITY
+        );
+        add_recipe(
+            &mut list,
+            &mut sources,
+            entry.name(),
+            &recipe,
+            converter,
+            &is_skipped,
+            state.config.normalize_ingredients,
+        );
+        recipes.push(context! { name, servings });
+    }
+
+    let mut lines = list
+        .into_iter()
+        .map(|(igr, q)| {
+            let q = q
+                .iter()
+                .map(Quantity::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            (igr, q)
+        })
+        .collect::<Vec<_>>();
+    let key = list_key(
+        &state.base_path,
+        key_recipes
+            .iter()
+            .map(|(path, servings)| (path.as_path(), *servings)),
+    );
+    let checklist = ok_status!(block_in_place(|| Checklist::load(&state.base_path, &key)));
+    state
+        .config
+        .collation
+        .sort_by_name(&mut lines, |(i, _)| i.as_str());
+    let items = lines
+        .iter()
+        .map(|(name, quantity)| {
+            context! {
+                name,
+                quantity,
+                checked => checklist.is_checked(name),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let tmpl = mj_ok!(state.templates.get_template("shopping_list.html"));
+    let content = mj_ok!(tmpl.render(context! { t, base, key, recipes, items }));
+    Html(content).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct CheckForm {
+    item: String,
+    /// Sent by the checkbox only when it's checked
+    checked: Option<String>,
+}
+
+pub async fn check_item(
+    State(state): State<S>,
+    Path(key): Path<String>,
+    Form(form): Form<CheckForm>,
+) -> Response {
+    // the key is used in a path
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_hexdigit()) {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+    ok_status!(block_in_place(|| {
+        let _lock = state.checklist_lock.lock().unwrap();
+        // the CLI may be saving it too
+        let _collection_lock =
+            cooklang_fs::CollectionLock::acquire(&state.base_path.join(crate::COOK_DIR))?;
+        let mut checklist = Checklist::load(&state.base_path, &key)?;
+        checklist.set(&form.item, form.checked.is_some());
+        checklist.save()
+    }));
+    StatusCode::NO_CONTENT.into_response()
+}
@@ -4,15 +4,19 @@ use axum::{
     response::{IntoResponse, Redirect, Response},
 };
 
-use crate::cmd::serve::Assets;
+use crate::cmd::serve::{proxy::BasePath, Assets};
 
-pub async fn static_file(uri: Uri, headers: HeaderMap) -> Result<Response, StatusCode> {
+pub async fn static_file(
+    uri: Uri,
+    headers: HeaderMap,
+    BasePath(base): BasePath,
+) -> Result<Response, StatusCode> {
     const INDEX_HTML: &str = "index.html";
 
     let path = uri.path().trim_start_matches('/');
 
     if path.is_empty() || path == INDEX_HTML {
-        return Ok(Redirect::permanent("/").into_response());
+        return Ok(Redirect::permanent(&format!("{base}/")).into_response());
     }
 
     match Assets::get(path) {
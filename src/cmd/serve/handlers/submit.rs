@@ -0,0 +1,120 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    Form,
+};
+use camino::Utf8PathBuf;
+use minijinja::context;
+use serde::Deserialize;
+use tokio::task::block_in_place;
+
+use crate::{
+    archive::INBOX_DIR,
+    cmd::serve::{locale::UserLocale, proxy::BasePath, S},
+    hooks, COOK_DIR,
+};
+
+use super::{mj_ok, recipe::report_to_html};
+
+#[derive(Deserialize)]
+pub struct Submission {
+    name: String,
+    content: String,
+}
+
+/// Form to propose a recipe, only with `--submissions`
+pub async fn submit_form(
+    UserLocale(t): UserLocale,
+    BasePath(base): BasePath,
+    State(state): State<S>,
+) -> Response {
+    if !state.submissions {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let tmpl = mj_ok!(state.templates.get_template("submit.html"));
+    let content = mj_ok!(tmpl.render(context! { t, base }));
+    Html(content).into_response()
+}
+
+/// Stores a proposed recipe in the inbox, if it's valid
+pub async fn submit(
+    UserLocale(t): UserLocale,
+    BasePath(base): BasePath,
+    State(state): State<S>,
+    Form(submission): Form<Submission>,
+) -> Response {
+    if !state.submissions {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let tmpl = mj_ok!(state.templates.get_template("submit.html"));
+    let name = submission.name.trim();
+    let file_name = format!("{name}.cook");
+
+    let mut error = None;
+    let mut report_html = None;
+    let mut status = StatusCode::OK;
+    if let Err(e) = cooklang_fs::check_file_name(name) {
+        error = Some(e.to_string());
+        status = StatusCode::BAD_REQUEST;
+    } else if let Err(report) = state.parser.parse(&submission.content).into_result() {
+        match report_to_html(&report, &file_name, &submission.content) {
+            Ok(html) => report_html = Some(html),
+            Err(e) => error = Some(e.to_string()),
+        }
+        status = StatusCode::BAD_REQUEST;
+    } else {
+        match block_in_place(|| store(&state, &file_name, &submission.content)) {
+            Ok(path) => {
+                tracing::info!("New submission: {path}");
+                hooks::run(
+                    "submission",
+                    &state.config.hooks.submission,
+                    &state.base_path,
+                    &[("CHEF_RECIPE", path.as_str())],
+                );
+            }
+            Err(e @ cooklang_fs::Error::AlreadyExists(_)) => {
+                error = Some(e.to_string());
+                status = StatusCode::CONFLICT;
+            }
+            Err(e) => {
+                tracing::error!("Can't store a submission: {e}");
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        }
+    }
+
+    let sent = status.is_success();
+    let content = mj_ok!(tmpl.render(context! {
+        t,
+        base,
+        sent,
+        error,
+        report_html,
+        severity => "error",
+        // keep the form filled to fix it
+        name => (!sent).then_some(name),
+        content => (!sent).then_some(&submission.content),
+    }));
+    (status, Html(content)).into_response()
+}
+
+/// Writes the recipe to the inbox, returns its path
+fn store(
+    state: &crate::cmd::serve::AppState,
+    file_name: &str,
+    content: &str,
+) -> Result<Utf8PathBuf, cooklang_fs::Error> {
+    let _lock = cooklang_fs::CollectionLock::acquire(&state.base_path.join(COOK_DIR))?;
+    let dir = state.base_path.join(INBOX_DIR);
+    let path = dir.join(file_name);
+    if path.exists() {
+        return Err(cooklang_fs::Error::AlreadyExists(format!(
+            "{INBOX_DIR}/{file_name}"
+        )));
+    }
+    std::fs::create_dir_all(&dir)?;
+    cooklang_fs::safe_write(&path, content, cooklang_fs::WriteOptions::default())?;
+    Ok(path)
+}
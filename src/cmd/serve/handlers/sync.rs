@@ -0,0 +1,186 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use camino::Utf8PathBuf;
+use cooklang_fs::{
+    sync::{PushChange, SyncLog},
+    transaction::Transaction,
+    CollectionLock, ContentHash, Error, FsStore, RecipeStore, Trash,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cmd::serve::{AppState, S},
+    config::{journal_file_path, sync_log_path, trash_path},
+    COOK_DIR,
+};
+
+use super::{
+    api::{edits_disabled, error_status},
+    ok_status,
+};
+
+#[derive(Deserialize)]
+pub struct PullQuery {
+    /// Revision of the last sync, 0 for everything
+    #[serde(default)]
+    since: u64,
+}
+
+#[derive(Serialize)]
+struct Pull {
+    revision: u64,
+    changes: Vec<PulledRecipe>,
+}
+
+#[derive(Serialize)]
+struct PulledRecipe {
+    path: Utf8PathBuf,
+    revision: u64,
+    /// `None` if the recipe was removed
+    hash: Option<String>,
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct Push {
+    changes: Vec<PushedRecipe>,
+}
+
+#[derive(Deserialize)]
+struct PushedRecipe {
+    path: Utf8PathBuf,
+    /// Hash of the content the client edited, `None` for a new recipe
+    base: Option<String>,
+    /// `None` to remove the recipe
+    content: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PushConflict {
+    path: Utf8PathBuf,
+    revision: u64,
+    hash: Option<String>,
+}
+
+fn store(state: &AppState) -> FsStore {
+    FsStore::new(&state.base_path, state.config.max_depth)
+        .config_dir(COOK_DIR.to_string())
+        .non_utf8(state.config.non_utf8_paths)
+}
+
+/// Scans the collection, updating the stored log
+///
+/// Call it with the collection locked.
+fn scanned_log(state: &AppState, store: &FsStore) -> Result<SyncLog, Error> {
+    let file = sync_log_path(&state.base_path);
+    let mut log = SyncLog::load(&file)?;
+    if log.scan(store)? {
+        log.save(&file)?;
+    }
+    Ok(log)
+}
+
+/// Recipes that changed since a revision, with their content
+pub async fn pull(State(state): State<S>, Query(query): Query<PullQuery>) -> Response {
+    let res = tokio::task::block_in_place(|| {
+        let store = store(&state);
+        let _lock = CollectionLock::acquire(&state.base_path.join(COOK_DIR))?;
+        let log = scanned_log(&state, &store)?;
+        let mut changes = Vec::new();
+        for change in log.changes_since(query.since) {
+            let (hash, content) = match change.hash {
+                Some(_) => {
+                    let content = store.read(&store.base_path().join(&change.path))?;
+                    (Some(content.hash()), Some(content.into_text()))
+                }
+                None => (None, None),
+            };
+            changes.push(PulledRecipe {
+                path: change.path,
+                revision: change.revision,
+                hash: hash.map(|h| h.to_string()),
+                content,
+            });
+        }
+        Ok::<_, Error>(Pull {
+            revision: log.revision(),
+            changes,
+        })
+    });
+    match res {
+        Ok(pull) => Json(pull).into_response(),
+        Err(e) => (error_status(&e), e.to_string()).into_response(),
+    }
+}
+
+/// Applies the edits of a client, all of them or none
+///
+/// Only with `--allow-edits`. If a recipe changed since the client got it,
+/// answers `409` with the conflicts.
+pub async fn push(State(state): State<S>, Json(push): Json<Push>) -> Response {
+    if !state.allow_edits {
+        return edits_disabled();
+    }
+    let mut changes = Vec::with_capacity(push.changes.len());
+    for change in push.changes {
+        let base = match change.base.map(|h| h.parse::<ContentHash>()).transpose() {
+            Ok(base) => base,
+            Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        };
+        changes.push(PushChange {
+            path: change.path,
+            base,
+            content: change.content,
+        });
+    }
+
+    let res = tokio::task::block_in_place(|| {
+        let store = store(&state);
+        let _lock = CollectionLock::acquire(&state.base_path.join(COOK_DIR))?;
+        let mut log = scanned_log(&state, &store)?;
+        let operations = match log.plan_push(&changes) {
+            Ok(operations) => operations,
+            Err(conflicts) => return Ok(Err(conflicts)),
+        };
+        let mut transaction =
+            Transaction::new(&state.base_path, journal_file_path(&state.base_path))
+                .with_trash(Trash::new(&state.base_path, trash_path(&state.base_path)));
+        for op in operations {
+            transaction.push(op);
+        }
+        transaction.commit()?;
+        log.scan(&store)?;
+        log.save(&sync_log_path(&state.base_path))?;
+        Ok::<_, Error>(Ok(log.revision()))
+    });
+    let revision = match res {
+        Ok(Ok(revision)) => revision,
+        Ok(Err(conflicts)) => {
+            let conflicts = conflicts
+                .into_iter()
+                .map(|c| PushConflict {
+                    path: c.path,
+                    revision: c.revision,
+                    hash: c.hash.map(|h| h.to_string()),
+                })
+                .collect::<Vec<_>>();
+            return (
+                StatusCode::CONFLICT,
+                Json(serde_json::json!({ "conflicts": conflicts })),
+            )
+                .into_response();
+        }
+        Err(e) => return (error_status(&e), e.to_string()).into_response(),
+    };
+    tracing::info!(
+        "Synced {} changes, now at revision {revision}",
+        changes.len()
+    );
+
+    ok_status!(state.recipe_index.refresh().await);
+    Json(serde_json::json!({ "revision": revision })).into_response()
+}
@@ -1,12 +1,20 @@
 mod async_index;
+mod collections;
 mod handlers;
 mod locale;
+mod proxy;
+mod scheduler;
+mod share;
+mod systemd;
 
 use self::{
-    async_index::{AsyncFsIndex, Update},
+    async_index::{AsyncFsIndex, IndexMode, Update},
     locale::{make_locale_store, LocaleStore},
 };
-use crate::Context;
+use crate::{
+    config::{index_file_path, journal_file_path, share_key_path},
+    index_builder, Context,
+};
 use anyhow::{bail, Context as _, Result};
 use axum::{
     extract::Request,
@@ -25,6 +33,7 @@ use std::{
     collections::HashMap,
     net::SocketAddr,
     sync::{atomic::AtomicI32, Arc},
+    time::Duration,
 };
 use tokio::sync::broadcast;
 use tower::ServiceBuilder;
@@ -37,33 +46,159 @@ pub struct ServeArgs {
     host: bool,
 
     /// Set http server port
+    ///
+    /// With systemd socket activation, the passed socket is used instead.
     #[arg(long, default_value_t = 8080)]
     port: u16,
 
     /// Open browser on start
     #[arg(long, conflicts_with = "host", default_value_t = false)]
     open: bool,
+
+    /// Tune for slow network file systems (SMB, NFS...)
+    ///
+    /// Files are not watched for changes and the index is stored in the
+    /// collection. Send a `POST /refresh` request to update it.
+    #[arg(long)]
+    network: bool,
+
+    /// Serve from the stored index without walking the collection
+    ///
+    /// Implies `--network`. Create the index with `chef collection reindex`.
+    #[arg(long)]
+    offline: bool,
+
+    /// Show the archived recipes in the listings and the search
+    #[arg(long)]
+    include_archived: bool,
+
+    /// Compress the responses with gzip or brotli
+    ///
+    /// Useful when browsing from another device in a slow network.
+    #[arg(long)]
+    compress: bool,
+
+    /// Serve the web UI under this path, like `/recipes`
+    ///
+    /// For a reverse proxy that forwards the path as is. A proxy that strips
+    /// its prefix can send it in the `X-Forwarded-Prefix` header instead.
+    #[arg(long, value_name = "PATH", default_value = "")]
+    base_path: String,
+
+    /// Allow changing the recipes with `POST /api/batch` and `POST /api/sync`
+    ///
+    /// Anyone that can reach the server can change them.
+    #[arg(long)]
+    allow_edits: bool,
+
+    /// Accept recipes proposed by anyone at `/submit`
+    ///
+    /// They are checked with the parser and stored in the `inbox` dir to be
+    /// reviewed. The `submission` hook of the config runs for each one.
+    #[arg(long)]
+    submissions: bool,
+
+    /// Serve every collection of the global config, each under `/<name>`
+    ///
+    /// Add them to the `collections` table of the global config. The current
+    /// collection is not served unless it's there too.
+    #[arg(long)]
+    collections: bool,
+
+    /// Only serve links to these recipes, to send them to someone
+    ///
+    /// The links are printed at start. They are signed with a key stored in
+    /// the collection, so they can't be changed to see other recipes, and
+    /// expire after `--share-expires`. Nothing else of the collection is
+    /// served.
+    #[arg(
+        long,
+        value_name = "RECIPE",
+        conflicts_with_all = ["collections", "allow_edits", "submissions", "open"]
+    )]
+    share: Vec<String>,
+
+    /// How long the `--share` links work, like `30m`, `12h` or `7d`
+    #[arg(long, value_name = "DURATION", default_value = "24h", value_parser = share::parse_duration)]
+    share_expires: Duration,
 }
 
 #[tokio::main]
 pub async fn run(ctx: Context, args: ServeArgs) -> Result<()> {
-    if !ctx.is_collection {
+    if !ctx.is_collection && !args.collections {
         bail!("`serve` needs to run inside a collection");
     }
 
-    let state = build_state(ctx).context("failed to build web server")?;
-    let app = make_router(state);
-
-    let addr = if args.host {
-        SocketAddr::from(([0, 0, 0, 0], args.port))
+    let prefix = proxy::normalize_prefix(&args.base_path)?;
+    let tls = match &ctx.config.serve.tls {
+        Some(tls) => {
+            let config = axum_server::tls_rustls::RustlsConfig::from_pem_file(
+                ctx.base_path.join(&tls.cert),
+                ctx.base_path.join(&tls.key),
+            )
+            .await
+            .context("failed to load the TLS certificate and key")?;
+            Some(config)
+        }
+        None => None,
+    };
+    let mut share_links = Vec::new();
+    let mut app = if args.collections {
+        serve_collections(&ctx, &args, &prefix)?
+    } else if !args.share.is_empty() {
+        let state =
+            build_state(ctx, &args, prefix.clone()).context("failed to build web server")?;
+        let key = state.share_key.as_ref().expect("share key loaded");
+        share_links = state
+            .shared
+            .iter()
+            .map(|path| {
+                let path = handlers::clean_path(path, &state.base_path).with_extension("");
+                share::link(key, path.as_str(), args.share_expires)
+            })
+            .collect();
+        make_share_router(state)
     } else {
-        SocketAddr::from(([127, 0, 0, 1], args.port))
+        let state =
+            build_state(ctx, &args, prefix.clone()).context("failed to build web server")?;
+        make_router(state)
+    };
+    if args.compress {
+        use tower_http::compression::{
+            predicate::{NotForContentType, Predicate},
+            CompressionLayer, DefaultPredicate,
+        };
+        // videos are already compressed and requested by ranges
+        let predicate = DefaultPredicate::new().and(NotForContentType::const_new("video/"));
+        app = app.layer(CompressionLayer::new().compress_when(predicate));
+    }
+
+    let listener = match systemd::take_listener()? {
+        Some(listener) => listener,
+        None => {
+            let addr = if args.host {
+                SocketAddr::from(([0, 0, 0, 0], args.port))
+            } else {
+                SocketAddr::from(([127, 0, 0, 1], args.port))
+            };
+            std::net::TcpListener::bind(addr).with_context(|| format!("failed to bind {addr}"))?
+        }
     };
+    listener.set_nonblocking(true)?;
+    let addr = listener.local_addr()?;
 
     info!("Listening on {addr}");
+    if !share_links.is_empty() {
+        let scheme = if tls.is_some() { "https" } else { "http" };
+        info!("Share these links, replacing the address with the one others can reach:");
+        for link in &share_links {
+            println!("{scheme}://{addr}{prefix}{link}");
+        }
+    }
 
     if args.open {
-        let url = format!("http://{}:{}", addr.ip(), addr.port());
+        let scheme = if tls.is_some() { "https" } else { "http" };
+        let url = format!("{scheme}://{}:{}{prefix}/", addr.ip(), addr.port());
         info!("Serving web UI on {url}");
         tokio::task::spawn(async move {
             tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
@@ -73,26 +208,77 @@ pub async fn run(ctx: Context, args: ServeArgs) -> Result<()> {
         });
     }
 
-    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
     let app_service = app.into_make_service_with_connect_info::<SocketAddr>();
-    axum::serve(listener, app_service).await.unwrap();
+    match tls {
+        Some(config) => {
+            axum_server::from_tcp_rustls(listener, config)
+                .serve(app_service)
+                .await?
+        }
+        None => {
+            let listener = tokio::net::TcpListener::from_std(listener)?;
+            axum::serve(listener, app_service).await?
+        }
+    }
 
     info!("Server stopped");
 
     Ok(())
 }
 
+/// Router with every collection of the global config, see [`collections`]
+fn serve_collections(ctx: &Context, args: &ServeArgs, prefix: &str) -> Result<Router> {
+    let collections = &ctx.chef_config.collections;
+    if collections.is_empty() {
+        bail!("no collections in the global config, add them to the `collections` table");
+    }
+    let mut router = collections::index_router(prefix, collections.keys().cloned().collect());
+    for (name, path) in collections {
+        let url_prefix = format!("{prefix}{}", collections::collection_prefix(name)?);
+        let state = ctx
+            .for_collection(path)
+            .and_then(|ctx| {
+                if !ctx.is_collection {
+                    bail!("'{path}' is not a collection");
+                }
+                build_state(ctx, args, url_prefix)
+            })
+            .with_context(|| format!("failed to build the collection '{name}'"))?;
+        info!("Serving '{name}' from {path}");
+        router = router.merge(make_router(state));
+    }
+    Ok(router)
+}
+
 #[tracing::instrument(level = "debug", skip_all)]
 fn make_router(state: Arc<AppState>) -> Router {
-    Router::new()
+    let prefix = state.url_prefix.clone();
+    let router = Router::new()
         .route("/", get(handlers::index))
         .route("/d/*path", get(handlers::index))
         .route("/search", get(handlers::search))
+        .route(
+            "/api/recipes",
+            get(handlers::api::recipes).post(handlers::api::create),
+        )
+        .route("/api/batch", post(handlers::api::batch))
+        .route("/api/links/*path", get(handlers::api::links))
+        .route(
+            "/api/sync",
+            get(handlers::sync::pull).post(handlers::sync::push),
+        )
         .route("/about", get(handlers::about))
         .route("/r/*path", get(handlers::recipe))
         .route("/updates", get(handlers::sse_updates))
         .route("/open_editor/*path", get(handlers::open_editor))
         .route("/convert_modal", post(handlers::convert_popover))
+        .route("/refresh", post(handlers::refresh))
+        .route("/shopping-list", get(handlers::shopping_list))
+        .route("/shopping-list/:key", post(handlers::check_item))
+        .route(
+            "/submit",
+            get(handlers::submit::submit_form).post(handlers::submit::submit),
+        )
         .nest_service(
             "/src",
             ServiceBuilder::new()
@@ -101,7 +287,30 @@ fn make_router(state: Arc<AppState>) -> Router {
                 .service(tower_http::services::ServeDir::new(&state.base_path)),
         )
         .fallback(handlers::static_file)
-        .with_state(state)
+        .layer(middleware::from_fn(etag))
+        .with_state(state);
+    if prefix.is_empty() {
+        router
+    } else {
+        Router::new().nest(&prefix, router)
+    }
+}
+
+/// Router with only the recipes shared with `--share`
+fn make_share_router(state: Arc<AppState>) -> Router {
+    let prefix = state.url_prefix.clone();
+    let router = Router::new()
+        .route("/", get(|| async { StatusCode::NOT_FOUND }))
+        .route("/share/*path", get(handlers::share::share))
+        .route("/src/*path", get(handlers::share::share_src))
+        .fallback(handlers::static_file)
+        .layer(middleware::from_fn(etag))
+        .with_state(state);
+    if prefix.is_empty() {
+        router
+    } else {
+        Router::new().nest(&prefix, router)
+    }
 }
 
 pub struct AppState {
@@ -112,15 +321,56 @@ pub struct AppState {
     recipe_index: AsyncFsIndex,
     updates_stream: broadcast::Receiver<Update>,
     config: crate::config::Config,
+    links: crate::links::Links,
     editor_command: Option<Vec<String>>,
     editor_count: AtomicI32,
+    /// Serializes the changes to the shopping list checklists
+    checklist_lock: std::sync::Mutex<()>,
+    include_archived: bool,
+    allow_edits: bool,
+    submissions: bool,
+    /// Only with `--share`
+    share_key: Option<share::ShareKey>,
+    /// Recipes given to `--share`
+    shared: Vec<Utf8PathBuf>,
+    /// Normalized `--base-path`, see [`proxy`], followed by the collection
+    /// name with `--collections`
+    url_prefix: String,
 }
 
 type S = Arc<AppState>;
 
 #[tracing::instrument(level = "debug", skip_all)]
-fn build_state(ctx: Context) -> Result<S> {
+fn build_state(ctx: Context, args: &ServeArgs, url_prefix: String) -> Result<S> {
     ctx.parser()?;
+    let index_file = index_file_path(&ctx.base_path);
+    let stored_index = args
+        .offline
+        .then(|| ctx.load_stored_index())
+        .transpose()
+        .context("failed to load the stored index. Create it with `chef collection reindex`")?;
+    if args.allow_edits {
+        let _lock = ctx.lock_collection()?;
+        if cooklang_fs::transaction::recover(&journal_file_path(&ctx.base_path))? {
+            tracing::warn!("An interrupted batch of changes was undone");
+        }
+    }
+    let share_key = (!args.share.is_empty())
+        .then(|| share::ShareKey::load_or_create(&share_key_path(&ctx.base_path)))
+        .transpose()?;
+    let mut shared = Vec::new();
+    for recipe in &args.share {
+        let entry = ctx
+            .recipe_index
+            .resolve(recipe, None)
+            .with_context(|| format!("can't share '{recipe}'"))?;
+        shared.push(entry.path().to_owned());
+    }
+    let lock = if args.network && !args.offline {
+        ctx.lock_collection()?
+    } else {
+        None
+    };
     let Context {
         parser,
         recipe_index,
@@ -130,15 +380,35 @@ fn build_state(ctx: Context) -> Result<S> {
         ..
     } = ctx;
     let parser = parser.into_inner().unwrap();
-    let complete_index = recipe_index
-        .index_all()
-        .context("failed to index the recipes")?;
-    let (recipe_index, updates) = AsyncFsIndex::new(complete_index);
+    let complete_index = match stored_index {
+        Some(index) => index,
+        // the stored index is used if the collection did not change
+        None if lock.is_some() => index_builder(&base_path, &config)?
+            .batch_stats(true)
+            .load_or_build(&index_file)
+            .context("failed to index the recipes")?,
+        None => recipe_index
+            .index_all()
+            .context("failed to index the recipes")?,
+    };
+    let mode = if args.network || args.offline {
+        IndexMode::Manual {
+            store: Some(index_file),
+        }
+    } else {
+        IndexMode::Watch
+    };
+    drop(lock);
+    let links = match config.links(&base_path) {
+        Some(path) => crate::links::Links::load(&path)?,
+        None => Default::default(),
+    };
+    let (recipe_index, updates) = AsyncFsIndex::new(complete_index, mode, config.collation);
 
     let locales = make_locale_store();
     let templates = make_template_env(&locales);
 
-    Ok(Arc::new(AppState {
+    let state = Arc::new(AppState {
         templates,
         locales,
         parser,
@@ -146,9 +416,19 @@ fn build_state(ctx: Context) -> Result<S> {
         recipe_index,
         updates_stream: updates,
         config,
+        links,
         editor_command: chef_config.editor().ok(),
         editor_count: 0.into(),
-    }))
+        checklist_lock: Default::default(),
+        include_archived: args.include_archived,
+        allow_edits: args.allow_edits,
+        submissions: args.submissions,
+        share_key,
+        shared,
+        url_prefix,
+    });
+    scheduler::spawn(Arc::clone(&state));
+    Ok(state)
 }
 
 fn make_template_env(locales: &LocaleStore) -> Environment<'static> {
@@ -263,17 +543,75 @@ fn make_template_env(locales: &LocaleStore) -> Environment<'static> {
     env
 }
 
-/// filters static files to only expose images and cook files
+/// Videos of the recipes, `<recipe>.<ext>` next to the recipe file
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "webm", "m4v", "mov"];
+
+/// filters static files to only expose images, videos and cook files
+///
+/// They are streamed and support range requests, so videos can be scrubbed.
 async fn filter_files(req: Request, next: Next) -> impl axum::response::IntoResponse {
     let path = req.uri().path();
     let (_, ext) = path.rsplit_once('.').ok_or(StatusCode::NOT_FOUND)?;
-    if ext == "cook" || cooklang_fs::IMAGE_EXTENSIONS.contains(&ext) {
+    if ext == "cook"
+        || cooklang_fs::IMAGE_EXTENSIONS.contains(&ext)
+        || VIDEO_EXTENSIONS.contains(&ext)
+    {
         Ok(next.run(req).await)
     } else {
         Err(StatusCode::NOT_FOUND)
     }
 }
 
+/// Adds an ETag with the hash of the content to the GET responses that don't
+/// have a cache validator, answering `304 Not Modified` if the browser already
+/// has it
+///
+/// Files from the collection already have a `Last-Modified` and the streams
+/// are left alone.
+async fn etag(req: Request, next: Next) -> Response {
+    use axum::{
+        body::Body,
+        http::{
+            header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH, LAST_MODIFIED},
+            Method,
+        },
+        response::IntoResponse,
+    };
+
+    if req.method() != Method::GET {
+        return next.run(req).await;
+    }
+    let if_none_match = req.headers().get(IF_NONE_MATCH).cloned();
+    let res = next.run(req).await;
+    let is_stream = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .is_some_and(|t| t.as_bytes().starts_with(b"text/event-stream"));
+    if res.status() != StatusCode::OK
+        || is_stream
+        || res.headers().contains_key(ETAG)
+        || res.headers().contains_key(LAST_MODIFIED)
+    {
+        return res;
+    }
+
+    let (mut parts, body) = res.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let etag = format!("\"{}\"", cooklang_fs::ContentHash::of_bytes(&bytes));
+    let etag = HeaderValue::from_str(&etag).expect("hex etag");
+    if if_none_match.is_some_and(|v| v == etag) {
+        return (StatusCode::NOT_MODIFIED, [(ETAG, etag)]).into_response();
+    }
+    parts.headers.insert(ETAG, etag);
+    parts
+        .headers
+        .entry(CACHE_CONTROL)
+        .or_insert(HeaderValue::from_static("no-cache"));
+    Response::from_parts(parts, Body::from(bytes))
+}
+
 /// sets the mime type for .cook files based on extension
 async fn cook_mime_type(req: Request, next: Next) -> Response {
     let is_dot_cook = req.uri().path().ends_with(".cook");
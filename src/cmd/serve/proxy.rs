@@ -0,0 +1,119 @@
+//! Serving behind a reverse proxy
+//!
+//! The UI can live under a path with `--base-path`. A proxy that strips its
+//! own prefix before forwarding can tell it with `X-Forwarded-Prefix`. Links
+//! are made with both, see [`BasePath`].
+//!
+//! Connections from the same machine can be a proxy, so then the client
+//! address is taken from `X-Forwarded-For`, see [`client_ip`].
+
+use std::net::{IpAddr, SocketAddr};
+
+use anyhow::{bail, Result};
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{request::Parts, HeaderMap},
+};
+
+const FORWARDED_PREFIX: &str = "x-forwarded-prefix";
+const FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Prefix of the links for a request, empty or like `/recipes`
+pub struct BasePath(pub String);
+
+#[async_trait]
+impl FromRequestParts<super::S> for BasePath {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &super::S,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(Self(link_base(&state.url_prefix, &parts.headers)))
+    }
+}
+
+/// Normalizes the `--base-path` arg to empty or `/path` without a trailing `/`
+pub fn normalize_prefix(prefix: &str) -> Result<String> {
+    let trimmed = prefix.trim_matches('/');
+    if trimmed
+        .split('/')
+        .any(|part| matches!(part, "." | "..") || part.contains(['?', '#', '%']))
+    {
+        bail!("invalid base path: '{prefix}'");
+    }
+    if trimmed.is_empty() {
+        return Ok(String::new());
+    }
+    Ok(format!("/{trimmed}"))
+}
+
+/// The prefix of the proxy followed by our own
+fn link_base(prefix: &str, headers: &HeaderMap) -> String {
+    let forwarded = headers
+        .get(FORWARDED_PREFIX)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| normalize_prefix(v).ok())
+        .unwrap_or_default();
+    format!("{forwarded}{prefix}")
+}
+
+/// Address of the client
+///
+/// When the connection is from the same machine and there's an
+/// `X-Forwarded-For`, the last address in the header is used: the one the
+/// proxy added. An invalid header is treated as a remote client.
+pub fn client_ip(peer: SocketAddr, headers: &HeaderMap) -> IpAddr {
+    let peer = peer.ip();
+    if !peer.is_loopback() {
+        return peer;
+    }
+    let Some(forwarded) = headers.get(FORWARDED_FOR) else {
+        return peer;
+    };
+    forwarded
+        .to_str()
+        .ok()
+        .and_then(|v| v.rsplit(',').next())
+        .and_then(|last| last.trim().parse().ok())
+        .unwrap_or(IpAddr::from([0, 0, 0, 0]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn prefix() {
+        assert_eq!(normalize_prefix("").unwrap(), "");
+        assert_eq!(normalize_prefix("/").unwrap(), "");
+        assert_eq!(normalize_prefix("recipes/").unwrap(), "/recipes");
+        assert_eq!(normalize_prefix("/a/b").unwrap(), "/a/b");
+        assert!(normalize_prefix("/a/../b").is_err());
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(link_base("/recipes", &headers), "/recipes");
+        headers.insert(FORWARDED_PREFIX, HeaderValue::from_static("/home/"));
+        assert_eq!(link_base("/recipes", &headers), "/home/recipes");
+        assert_eq!(link_base("", &headers), "/home");
+    }
+
+    #[test]
+    fn forwarded_for() {
+        let local = SocketAddr::from(([127, 0, 0, 1], 1234));
+        let remote = SocketAddr::from(([10, 0, 0, 2], 1234));
+        let mut headers = HeaderMap::new();
+        assert!(client_ip(local, &headers).is_loopback());
+        headers.insert(
+            FORWARDED_FOR,
+            HeaderValue::from_static("127.0.0.1, 192.168.1.5"),
+        );
+        assert_eq!(client_ip(local, &headers), IpAddr::from([192, 168, 1, 5]));
+        // only trusted from a local proxy
+        assert_eq!(client_ip(remote, &headers), remote.ip());
+        headers.insert(FORWARDED_FOR, HeaderValue::from_static("garbage"));
+        assert!(!client_ip(local, &headers).is_loopback());
+    }
+}
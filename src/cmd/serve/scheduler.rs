@@ -0,0 +1,143 @@
+//! Runs the `[[schedule]]` tasks of the config while serving
+
+use std::{process::Stdio, sync::Arc, time::Duration};
+
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8Path;
+use tokio::task::block_in_place;
+
+use crate::{
+    archive::in_archive_dir,
+    hooks, index_builder,
+    schedule::{Task, Time},
+};
+
+use super::{share::unix_now, AppState, S};
+
+/// Checks the schedule every minute in the background
+pub fn spawn(state: S) {
+    if state.config.schedule.is_empty() {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut last = unix_now() / 60;
+        loop {
+            tokio::time::sleep(Duration::from_secs(60 - unix_now() % 60)).await;
+            let minute = unix_now() / 60;
+            if minute == last {
+                continue;
+            }
+            last = minute;
+            let time = Time::from_unix(minute * 60);
+            for entry in &state.config.schedule {
+                if entry.at.matches(&time) {
+                    let state = Arc::clone(&state);
+                    let task = entry.task.clone();
+                    tokio::spawn(async move { run(&state, &task).await });
+                }
+            }
+        }
+    });
+}
+
+async fn run(state: &AppState, task: &Task) {
+    tracing::info!("Running scheduled {}", task.name());
+    let res = match task {
+        Task::Reindex => state.recipe_index.refresh().await.map_err(Into::into),
+        Task::Orphans => block_in_place(|| orphans(state)),
+        Task::Export { dir } => block_in_place(|| export(state, &state.base_path.join(dir))),
+        Task::GitCommit { message } => block_in_place(|| git_commit(&state.base_path, message)),
+        Task::Command { command } => {
+            hooks::run("scheduled", command, &state.base_path, &[]);
+            Ok(())
+        }
+    };
+    if let Err(e) = res {
+        tracing::error!("Scheduled {} failed: {e:#}", task.name());
+    }
+}
+
+fn orphans(state: &AppState) -> Result<()> {
+    let stats = index_builder(&state.base_path, &state.config)?.stats()?;
+    for image in &stats.orphan_images {
+        let path = image.strip_prefix(&state.base_path).unwrap_or(image);
+        tracing::warn!("Orphan image: {path}");
+    }
+    tracing::info!("{} orphan images", stats.orphan_images.len());
+    Ok(())
+}
+
+/// Writes every recipe, except the archived ones, as Markdown
+fn export(state: &AppState, dir: &Utf8Path) -> Result<()> {
+    let config = &state.config;
+    let recipes = cooklang_fs::all_recipes_par(
+        &state.base_path,
+        config.max_depth,
+        config.markdown,
+        config.follow_links,
+    )?;
+    let mut count = 0;
+    for entry in recipes {
+        if in_archive_dir(&state.base_path, entry.path()) {
+            continue;
+        }
+        let relative = entry
+            .path()
+            .strip_prefix(&state.base_path)
+            .unwrap_or(entry.path());
+        let dest = dir.join(relative).with_extension("md");
+        if dest == entry.path() {
+            continue;
+        }
+        let content = entry.read()?;
+        let Some(recipe) = content.parse(&state.parser).into_output() else {
+            tracing::warn!("Not exporting '{relative}': it has errors");
+            continue;
+        };
+        let mut buf = Vec::new();
+        cooklang_to_md::print_md_with_options(
+            &recipe.default_scale(),
+            entry.name(),
+            &config.export.markdown,
+            state.parser.converter(),
+            &mut buf,
+        )?;
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        cooklang_fs::safe_write(&dest, buf, cooklang_fs::WriteOptions::default())?;
+        count += 1;
+    }
+    tracing::info!("Exported {count} recipes to {dir}");
+    Ok(())
+}
+
+fn git_commit(base_path: &Utf8Path, message: &str) -> Result<()> {
+    let git = |args: &[&str]| {
+        std::process::Command::new("git")
+            .args(args)
+            .current_dir(base_path)
+            .stdin(Stdio::null())
+            .output()
+            .context("Failed to run git, is it installed?")
+    };
+    let add = git(&["add", "-A"])?;
+    if !add.status.success() {
+        bail!(
+            "git add failed: {}",
+            String::from_utf8_lossy(&add.stderr).trim()
+        );
+    }
+    if git(&["diff", "--cached", "--quiet"])?.status.success() {
+        tracing::info!("Nothing to commit");
+        return Ok(());
+    }
+    let commit = git(&["commit", "--quiet", "--message", message])?;
+    if !commit.status.success() {
+        bail!(
+            "git commit failed: {}",
+            String::from_utf8_lossy(&commit.stderr).trim()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,205 @@
+//! Links to a single recipe, for `--share`
+//!
+//! A link has the path of the recipe, when it expires and a signature of
+//! both, a HMAC-SHA256 with a key stored in the collection. Without the key
+//! a link to another recipe or with a later expiry can't be made, so the
+//! server only serves the `/share/` routes and no listing. `/src` only has
+//! the images and videos of the shared recipes.
+
+use std::{
+    io::Write,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+const KEY_LEN: usize = 32;
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub struct ShareKey([u8; KEY_LEN]);
+
+impl ShareKey {
+    /// Reads the key, or creates a random one if the file doesn't exist
+    pub fn load_or_create(file: &Utf8Path) -> Result<Self> {
+        match std::fs::read(file) {
+            Ok(content) => {
+                let Ok(key) = content.try_into() else {
+                    bail!("invalid share key '{file}', remove it to create a new one");
+                };
+                return Ok(Self(key));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e).with_context(|| format!("failed to read '{file}'")),
+        }
+        let mut key = [0; KEY_LEN];
+        getrandom::getrandom(&mut key).context("failed to generate the share key")?;
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options
+            .open(file)
+            .and_then(|mut f| f.write_all(&key))
+            .with_context(|| format!("failed to write '{file}'"))?;
+        Ok(Self(key))
+    }
+
+    fn mac(&self, path: &str, expires: u64) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.0).expect("HMAC takes keys of any size");
+        mac.update(format!("{path}\n{expires}").as_bytes());
+        mac
+    }
+
+    /// Signature of a link, in hex
+    pub fn sign(&self, path: &str, expires: u64) -> String {
+        self.mac(path, expires)
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    /// Checks the signature of a link, not the expiry
+    pub fn verify(&self, path: &str, expires: u64, sig: &str) -> bool {
+        let Some(sig) = decode_hex(sig) else {
+            return false;
+        };
+        // constant time, the signature is a secret until it matches
+        self.mac(path, expires).verify_slice(&sig).is_ok()
+    }
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Checks if a file is an image or video of one of the shared recipes
+///
+/// The paths are like the ones of the index, starting with the base path.
+pub fn is_shared_file(recipes: &[Utf8PathBuf], file: &Utf8Path) -> bool {
+    recipes.iter().any(|recipe| {
+        cooklang_fs::recipe_images(recipe)
+            .iter()
+            .any(|img| img.path == file)
+            || super::VIDEO_EXTENSIONS
+                .iter()
+                .any(|ext| recipe.with_extension(ext) == file)
+    })
+}
+
+/// Seconds since the unix epoch
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time before unix epoch")
+        .as_secs()
+}
+
+/// Path and query of a link, `path` is relative to the collection without
+/// the extension
+pub fn link(key: &ShareKey, path: &str, valid_for: Duration) -> String {
+    let expires = unix_now() + valid_for.as_secs();
+    let sig = key.sign(path, expires);
+    format!("/share/{}?expires={expires}&sig={sig}", encode_path(path))
+}
+
+/// Percent encodes a path, keeping the `/`
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for b in path.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'/' | b'-' | b'_' | b'.' | b'~') {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{b:02X}"));
+        }
+    }
+    encoded
+}
+
+/// Parses durations like `30m`, `12h` or `7d`
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let err = || format!("invalid duration '{s}', use a number followed by m, h or d");
+    let unit = s.chars().last().ok_or_else(err)?;
+    let secs = match unit {
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        _ => return Err(err()),
+    };
+    let n: u64 = s[..s.len() - 1].parse().map_err(|_| err())?;
+    n.checked_mul(secs)
+        .filter(|&secs| secs > 0)
+        .map(Duration::from_secs)
+        .ok_or_else(err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signatures() {
+        let key = ShareKey([7; KEY_LEN]);
+        let sig = key.sign("Soups/Leek", 1000);
+        assert!(key.verify("Soups/Leek", 1000, &sig));
+        assert!(!key.verify("Soups/Leek", 2000, &sig));
+        assert!(!key.verify("Soups/Tomato", 1000, &sig));
+        assert!(!ShareKey([8; KEY_LEN]).verify("Soups/Leek", 1000, &sig));
+        assert!(!key.verify("Soups/Leek", 1000, &sig[..sig.len() - 2]));
+        assert!(!key.verify("Soups/Leek", 1000, "not hex"));
+        assert!(!key.verify("Soups/Leek", 1000, ""));
+    }
+
+    #[test]
+    fn shared_files() {
+        let dir = std::env::temp_dir().join(format!("chef-share-test-{}", std::process::id()));
+        let dir = Utf8PathBuf::from_path_buf(dir).unwrap();
+        std::fs::create_dir_all(&dir).unwrap();
+        for file in [
+            "Leek.cook",
+            "Leek.jpg",
+            "Leek.mp4",
+            "Tomato.cook",
+            "Tomato.jpg",
+        ] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+        let shared = [dir.join("Leek.cook")];
+        assert!(is_shared_file(&shared, &dir.join("Leek.jpg")));
+        assert!(is_shared_file(&shared, &dir.join("Leek.mp4")));
+        assert!(!is_shared_file(&shared, &dir.join("Leek.cook")));
+        assert!(!is_shared_file(&shared, &dir.join("Tomato.jpg")));
+        assert!(!is_shared_file(&shared, &dir.join("../Leek.jpg")));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("30m").unwrap(), Duration::from_secs(1800));
+        assert_eq!(parse_duration("7d").unwrap(), Duration::from_secs(604800));
+        assert!(parse_duration("0h").is_err());
+        assert!(parse_duration("12").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("").is_err());
+    }
+
+    #[test]
+    fn encoded_paths() {
+        assert_eq!(encode_path("Soups/Leek Soup"), "Soups/Leek%20Soup");
+        assert_eq!(encode_path("Crème"), "Cr%C3%A8me");
+    }
+}
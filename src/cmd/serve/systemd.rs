@@ -0,0 +1,75 @@
+//! systemd socket activation
+//!
+//! With a `.socket` unit, systemd opens the port and passes it to `serve`, so
+//! it can be started on demand and without permissions to bind it. See
+//! `sd_listen_fds(3)`.
+
+use anyhow::Result;
+
+/// First fd passed by systemd
+#[cfg(unix)]
+const LISTEN_FDS_START: std::os::fd::RawFd = 3;
+
+/// Takes the socket passed by systemd, if any
+///
+/// The env vars are removed so child processes, like the editor, don't use
+/// it too.
+pub fn take_listener() -> Result<Option<std::net::TcpListener>> {
+    let fds = passed_fds(
+        std::env::var("LISTEN_PID").ok().as_deref(),
+        std::env::var("LISTEN_FDS").ok().as_deref(),
+        std::process::id(),
+    );
+    std::env::remove_var("LISTEN_PID");
+    std::env::remove_var("LISTEN_FDS");
+    std::env::remove_var("LISTEN_FDNAMES");
+    if fds == 0 {
+        return Ok(None);
+    }
+    if fds > 1 {
+        tracing::warn!("{fds} sockets passed, only the first one is used");
+    }
+    listener()
+}
+
+#[cfg(unix)]
+fn listener() -> Result<Option<std::net::TcpListener>> {
+    use anyhow::Context as _;
+    use std::os::fd::FromRawFd;
+
+    // SAFETY: systemd passed this fd to this process and it's only taken once
+    let listener = unsafe { std::net::TcpListener::from_raw_fd(LISTEN_FDS_START) };
+    listener
+        .local_addr()
+        .context("the socket passed by systemd is not a TCP socket")?;
+    Ok(Some(listener))
+}
+
+#[cfg(not(unix))]
+fn listener() -> Result<Option<std::net::TcpListener>> {
+    anyhow::bail!("socket activation is only supported in unix")
+}
+
+/// Number of fds passed to the process with `own_pid`
+fn passed_fds(pid: Option<&str>, fds: Option<&str>, own_pid: u32) -> usize {
+    if pid.and_then(|p| p.parse::<u32>().ok()) != Some(own_pid) {
+        return 0;
+    }
+    fds.and_then(|n| n.parse().ok()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fds() {
+        assert_eq!(passed_fds(Some("42"), Some("1"), 42), 1);
+        assert_eq!(passed_fds(Some("42"), Some("2"), 42), 2);
+        // for another process
+        assert_eq!(passed_fds(Some("7"), Some("1"), 42), 0);
+        assert_eq!(passed_fds(None, Some("1"), 42), 0);
+        assert_eq!(passed_fds(Some("42"), None, 42), 0);
+        assert_eq!(passed_fds(Some("42"), Some("x"), 42), 0);
+    }
+}
@@ -1,15 +1,28 @@
 use anyhow::{bail, Context as _, Result};
 use camino::Utf8PathBuf;
 use clap::{Args, CommandFactory, ValueEnum};
+use std::collections::BTreeMap;
+
 use cooklang::{
     aisle::AisleConf,
-    ingredient_list::IngredientList,
-    quantity::{GroupedQuantity, Quantity, Value},
-    ScaledQuantity,
+    ingredient_list::{GroupedIngredient, IngredientList},
+    model::{Content, Item},
+    quantity::{GroupedQuantity, Quantity},
+    Converter, Ingredient, ScalableRecipe, ScaledQuantity, ScaledRecipe, Value,
 };
+use cooklang_fs::Collation;
 use serde::Serialize;
 
-use crate::{util::write_to_output, util::Input, Context};
+use crate::{
+    config::Config,
+    purchase::{Purchase, Purchases},
+    scaling::{check_text, TextQuantities},
+    send::{send, SendTarget},
+    util::resolve_recipe,
+    util::write_to_output,
+    util::Input,
+    Context,
+};
 
 #[derive(Debug, Args)]
 pub struct ShoppingListArgs {
@@ -38,6 +51,38 @@ pub struct ShoppingListArgs {
     /// Load aisle conf file
     #[arg(short, long)]
     aisle: Option<Utf8PathBuf>,
+
+    /// What to do with text quantities, like `a pinch`
+    ///
+    /// Overrides the `text_quantities` config option.
+    #[arg(long, value_enum)]
+    text_quantities: Option<TextQuantities>,
+
+    /// Interpolate the values per servings, like `{2|4|6}`, for servings they
+    /// don't have
+    #[arg(long)]
+    interpolate: bool,
+
+    /// Show which recipes and steps need each ingredient
+    #[arg(long)]
+    explain: bool,
+
+    /// Also list the staples, like salt, from the `staples` config option
+    #[arg(long)]
+    include_staples: bool,
+
+    /// Check off the items as you buy them
+    ///
+    /// The checked items are saved in the collection, so running the same
+    /// list again shows them checked.
+    #[arg(short, long, conflicts_with_all = ["output", "format", "explain"])]
+    interactive: bool,
+
+    /// Send the list instead of printing it
+    ///
+    /// Configure the targets in the `send` section of the config.
+    #[arg(long, value_enum, conflicts_with_all = ["output", "interactive"])]
+    send: Option<SendTarget>,
 }
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
@@ -78,6 +123,12 @@ pub fn run(ctx: &Context, mut args: ShoppingListArgs) -> Result<()> {
         args.plain = true;
     }
 
+    let converter = ctx.parser()?.converter();
+    let purchases = match ctx.config.purchase(&ctx.base_path) {
+        Some(path) => Purchases::load(&path, converter)?,
+        None => Purchases::empty(converter),
+    };
+
     let format = args.format.unwrap_or_else(|| match &args.output {
         Some(p) => match p.extension() {
             Some("json") => OutputFormat::Json,
@@ -87,19 +138,40 @@ pub fn run(ctx: &Context, mut args: ShoppingListArgs) -> Result<()> {
     });
 
     // retrieve, scale and merge ingredients
+    let policy = args.text_quantities.unwrap_or(ctx.config.text_quantities);
     let mut list = IngredientList::new();
+    let mut sources = Sources::new();
+    let mut recipes = Vec::new();
+    let is_skipped = skip_filter(&ctx.config, args.include_staples);
     for entry in args.recipes {
-        extract_ingredients(&entry, &mut list, ctx)?;
+        let recipe = extract_ingredients(
+            &entry,
+            &mut list,
+            &mut sources,
+            policy,
+            args.interpolate,
+            &is_skipped,
+            ctx,
+        )?;
+        recipes.push(recipe);
+    }
+    let sources = args.explain.then_some(&sources);
+    let collation = ctx.config.collation;
+
+    if args.interactive {
+        return interactive(list, &recipes, ctx);
     }
 
-    write_to_output(args.output.as_deref(), |mut w| {
+    let write = |w: &mut dyn std::io::Write| -> Result<()> {
         match format {
             OutputFormat::Human => {
-                let table = build_human_table(list, &aisle, args.plain);
+                let table =
+                    build_human_table(list, &aisle, args.plain, sources, &purchases, collation);
                 write!(w, "{table}")?;
             }
             OutputFormat::Json => {
-                let value = build_json_value(list, &aisle, args.plain);
+                let value =
+                    build_json_value(list, &aisle, args.plain, sources, &purchases, collation);
                 if args.pretty {
                     serde_json::to_writer_pretty(w, &value)?;
                 } else {
@@ -108,10 +180,29 @@ pub fn run(ctx: &Context, mut args: ShoppingListArgs) -> Result<()> {
             }
         }
         Ok(())
-    })
+    };
+
+    if let Some(target) = args.send {
+        let mut buf = Vec::new();
+        write(&mut buf)?;
+        let text = anstream::adapter::strip_str(std::str::from_utf8(&buf)?).to_string();
+        return send(&ctx.config.send, target, "Shopping list", &text);
+    }
+
+    write_to_output(args.output.as_deref(), |mut w| write(&mut w))
 }
 
-fn extract_ingredients(entry: &str, list: &mut IngredientList, ctx: &Context) -> Result<()> {
+/// Adds a recipe to the list, returning its path and servings for the
+/// [`list_key`](crate::checklist::list_key)
+fn extract_ingredients(
+    entry: &str,
+    list: &mut IngredientList,
+    sources: &mut Sources,
+    policy: TextQuantities,
+    interpolate: bool,
+    is_skipped: &dyn Fn(&GroupedIngredient) -> bool,
+    ctx: &Context,
+) -> Result<(Utf8PathBuf, Option<u32>)> {
     let converter = ctx.parser()?.converter();
 
     // split into name and servings
@@ -132,65 +223,348 @@ fn extract_ingredients(entry: &str, list: &mut IngredientList, ctx: &Context) ->
         .unwrap_or((entry, None));
 
     // Resolve and parse the recipe
-    let input = {
-        let entry = ctx.recipe_index.resolve(name, None)?;
-        Input::File {
-            entry,
-            override_name: None,
-        }
+    let entry = resolve_recipe(&ctx.recipe_index, name)?;
+    let path = entry.path().to_owned();
+    let input = Input::File {
+        entry,
+        override_name: None,
     };
     let recipe = input.parse(ctx)?;
+    let key_servings = default_servings(&recipe, servings);
+    let recipe = prepare_recipe(
+        recipe,
+        servings,
+        interpolate,
+        input.file_name(),
+        policy,
+        &ctx.config,
+        converter,
+    )?;
+
+    // Add ingredients to the list
+    add_recipe(
+        list,
+        sources,
+        input.name()?,
+        &recipe,
+        converter,
+        is_skipped,
+        ctx.config.normalize_ingredients,
+    );
+
+    Ok((path, key_servings))
+}
 
+/// The servings the recipe is scaled to, the default ones if not given
+pub(crate) fn default_servings(recipe: &ScalableRecipe, servings: Option<u32>) -> Option<u32> {
+    servings.or_else(|| recipe.metadata.servings().and_then(|s| s.first().copied()))
+}
+
+/// Scales a recipe and leaves it ready to [`add_recipe`]
+///
+/// The web UI lists use this too, so they have the same items as the CLI.
+pub(crate) fn prepare_recipe(
+    recipe: ScalableRecipe,
+    servings: Option<u32>,
+    interpolate: bool,
+    file_name: &str,
+    policy: TextQuantities,
+    config: &Config,
+    converter: &Converter,
+) -> Result<ScaledRecipe> {
     // Scale
-    let recipe = if let Some(servings) = servings {
-        recipe.scale(servings, converter)
+    let mut recipe = if let Some(servings) = servings {
+        crate::scaling::scale(recipe, servings, interpolate, converter)
     } else {
         recipe.default_scale()
     };
 
-    // Add ingredients to the list
-    list.add_recipe(&recipe, converter);
+    // Only the part of the sections that is used
+    crate::section_yield::apply(&mut recipe, converter);
+
+    // "To taste" is not a text quantity, it's no quantity
+    crate::to_taste::normalize(&mut recipe);
 
-    Ok(())
+    // "2 large onions, chopped" is the same as "onion"
+    if config.normalize_ingredients {
+        crate::ingredient_name::normalize(&mut recipe);
+    }
+
+    // Text quantities can't be added to others
+    check_text(&mut recipe, file_name, policy)?;
+    Ok(recipe)
+}
+
+/// Ingredients left out of the list: staples and, if configured, the ones
+/// without quantity
+pub(crate) fn skip_filter(
+    config: &Config,
+    include_staples: bool,
+) -> impl Fn(&GroupedIngredient) -> bool + '_ {
+    move |entry| {
+        let igr = entry.ingredient;
+        let is_staple = !include_staples
+            && (config.is_staple(&igr.name) || config.is_staple(&igr.display_name()));
+        is_staple || (!config.to_taste.shopping_list && entry.quantity.is_empty())
+    }
+}
+
+/// Where each ingredient of the list comes from, by ingredient name
+pub(crate) type Sources = BTreeMap<String, Vec<Source>>;
+
+#[derive(Serialize)]
+pub(crate) struct Source {
+    recipe: String,
+    /// Step numbers where the ingredient is used
+    steps: Vec<u32>,
+    quantity: Vec<ScaledQuantity>,
+}
+
+/// Like [`IngredientList::add_recipe`], but skipping some ingredients and
+/// recording the contribution of every listed one in `sources`
+///
+/// With `by_name` the ingredients are listed by their name instead of the
+/// display name, for normalized recipes.
+pub(crate) fn add_recipe(
+    list: &mut IngredientList,
+    sources: &mut Sources,
+    recipe_name: &str,
+    recipe: &ScaledRecipe,
+    converter: &Converter,
+    is_skipped: &dyn Fn(&GroupedIngredient) -> bool,
+    by_name: bool,
+) {
+    for entry in recipe.group_ingredients(converter) {
+        let igr = entry.ingredient;
+        if !igr.modifiers().should_be_listed() || is_skipped(&entry) {
+            continue;
+        }
+        let name = list_name(igr, by_name);
+        list.add_ingredient(name.clone(), &entry.quantity, converter);
+        sources.entry(name).or_default().push(Source {
+            recipe: recipe_name.to_string(),
+            steps: used_in_steps(recipe, entry.index),
+            quantity: entry.quantity.into_vec(),
+        });
+    }
+}
+
+/// Step numbers that use an ingredient or a reference to it
+fn used_in_steps(recipe: &ScaledRecipe, index: usize) -> Vec<u32> {
+    let indices = std::iter::once(index)
+        .chain(
+            recipe.ingredients[index]
+                .relation
+                .referenced_from()
+                .iter()
+                .copied(),
+        )
+        .collect::<Vec<_>>();
+    let mut steps = Vec::new();
+    for section in &recipe.sections {
+        for content in &section.content {
+            let Content::Step(step) = content else {
+                continue;
+            };
+            let uses = step
+                .items
+                .iter()
+                .any(|item| matches!(item, Item::Ingredient { index } if indices.contains(index)));
+            if uses {
+                steps.push(step.number);
+            }
+        }
+    }
+    steps
 }
 
-fn grouped_qty_fmt(qty: &GroupedQuantity, row: &mut tabular::Row) {
-    let content = qty
+fn list_name(igr: &Ingredient<Value>, by_name: bool) -> String {
+    if by_name {
+        igr.name.clone()
+    } else {
+        igr.display_name().into_owned()
+    }
+}
+
+fn source_fmt(source: &Source) -> String {
+    let mut text = source.recipe.clone();
+    if !source.steps.is_empty() {
+        let steps = source
+            .steps
+            .iter()
+            .map(u32::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let label = if source.steps.len() == 1 {
+            "step"
+        } else {
+            "steps"
+        };
+        text = format!("{text} ({label} {steps})");
+    }
+    text
+}
+
+fn add_source_rows(table: &mut tabular::Table, name: &str, sources: Option<&Sources>) {
+    use owo_colors::OwoColorize;
+
+    let Some(sources) = sources.and_then(|s| s.get(name)) else {
+        return;
+    };
+    for source in sources {
+        let mut row =
+            tabular::Row::new().with_ansi_cell(format!("  {}", source_fmt(source).dimmed()));
+        let content = source
+            .quantity
+            .iter()
+            .map(quantity_fmt)
+            .reduce(|s, q| format!("{s}, {q}"))
+            .unwrap_or_default();
+        row.add_ansi_cell(content.dimmed().to_string());
+        table.add_row(row);
+    }
+}
+
+#[cfg(feature = "interactive")]
+fn interactive(
+    list: IngredientList,
+    recipes: &[(Utf8PathBuf, Option<u32>)],
+    ctx: &Context,
+) -> Result<()> {
+    use crate::checklist::{list_key, Checklist};
+
+    let mut lines = list
+        .into_iter()
+        .map(|(igr, q)| {
+            let q = q
+                .iter()
+                .map(Quantity::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            (igr, q)
+        })
+        .collect::<Vec<_>>();
+    let key = list_key(
+        &ctx.base_path,
+        recipes
+            .iter()
+            .map(|(path, servings)| (path.as_path(), *servings)),
+    );
+    let checklist = Checklist::load(&ctx.base_path, &key)?;
+    ctx.config
+        .collation
+        .sort_by_name(&mut lines, |(i, _)| i.as_str());
+
+    let items = lines
+        .iter()
+        .map(|(i, q)| {
+            if q.is_empty() {
+                i.clone()
+            } else {
+                format!("{i}: {q}")
+            }
+        })
+        .collect::<Vec<_>>();
+    let checked = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(index, (i, _))| checklist.is_checked(i).then_some(index))
+        .collect::<Vec<_>>();
+
+    let selected = match inquire::MultiSelect::new("Shopping list", items)
+        .with_default(&checked)
+        .with_page_size(20)
+        .raw_prompt()
+    {
+        Ok(selected) => selected,
+        // keep the previous state
+        Err(inquire::InquireError::OperationCanceled) => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    // load it again, the web UI may have changed it while prompting
+    let _lock = ctx.lock_collection()?;
+    let mut checklist = Checklist::load(&ctx.base_path, &key)?;
+    for (index, (item, _)) in lines.iter().enumerate() {
+        checklist.set(item, selected.iter().any(|o| o.index == index));
+    }
+    checklist.save()
+}
+
+#[cfg(not(feature = "interactive"))]
+fn interactive(_: IngredientList, _: &[(Utf8PathBuf, Option<u32>)], _: &Context) -> Result<()> {
+    bail!("chef was built without interactive prompts (`interactive` feature)")
+}
+
+fn grouped_qty_fmt(
+    igr: &str,
+    qty: &GroupedQuantity,
+    purchases: &Purchases,
+    row: &mut tabular::Row,
+) {
+    use owo_colors::OwoColorize;
+
+    let mut content = qty
         .iter()
         .map(quantity_fmt)
         .reduce(|s, q| format!("{s}, {q}"))
         .unwrap_or_default();
+    if let Some(purchase) = purchases.get(igr, qty) {
+        content = format!("{content} → {}", purchase.bold());
+    }
     row.add_ansi_cell(content);
 }
 
 fn quantity_fmt(qty: &Quantity) -> String {
     use owo_colors::OwoColorize;
 
+    let f = cooklang_to_human::quantity_formatter();
     if let Some(unit) = qty.unit() {
-        format!("{} {}", qty.value, unit.text().italic())
+        format!(
+            "{}{}{}",
+            f.value(&qty.value),
+            f.unit_separator(),
+            unit.text().italic()
+        )
     } else {
-        format!("{}", qty.value)
+        f.value(&qty.value)
     }
 }
 
-fn build_human_table(list: IngredientList, aisle: &AisleConf, plain: bool) -> tabular::Table {
+/// Items of the list sorted by name
+fn sorted(list: IngredientList, collation: Collation) -> Vec<(String, GroupedQuantity)> {
+    let mut items = list.into_iter().collect::<Vec<_>>();
+    collation.sort_by_name(&mut items, |(igr, _)| igr.as_str());
+    items
+}
+
+fn build_human_table(
+    list: IngredientList,
+    aisle: &AisleConf,
+    plain: bool,
+    sources: Option<&Sources>,
+    purchases: &Purchases,
+    collation: Collation,
+) -> tabular::Table {
     use owo_colors::OwoColorize;
 
     let mut table = tabular::Table::new("{:<} {:<}");
     if plain {
-        for (igr, q) in list {
-            let mut row = tabular::Row::new().with_cell(igr);
-            grouped_qty_fmt(&q, &mut row);
+        for (igr, q) in sorted(list, collation) {
+            let mut row = tabular::Row::new().with_cell(&igr);
+            grouped_qty_fmt(&igr, &q, purchases, &mut row);
             table.add_row(row);
+            add_source_rows(&mut table, &igr, sources);
         }
     } else {
         let categories = list.categorize(aisle);
         for (cat, items) in categories {
             table.add_heading(format!("[{}]", cat.green()));
-            for (igr, q) in items {
-                let mut row = tabular::Row::new().with_cell(igr);
-                grouped_qty_fmt(&q, &mut row);
+            for (igr, q) in sorted(items, collation) {
+                let mut row = tabular::Row::new().with_cell(&igr);
+                grouped_qty_fmt(&igr, &q, purchases, &mut row);
                 table.add_row(row);
+                add_source_rows(&mut table, &igr, sources);
             }
         }
     }
@@ -201,32 +575,25 @@ fn build_json_value<'a>(
     list: IngredientList,
     aisle: &'a AisleConf<'a>,
     plain: bool,
+    sources: Option<&'a Sources>,
+    purchases: &Purchases,
+    collation: Collation,
 ) -> serde_json::Value {
     #[derive(Serialize)]
-    struct Quantity {
-        value: Value,
-        unit: Option<String>,
-    }
-    impl From<cooklang::quantity::Quantity> for Quantity {
-        fn from(qty: cooklang::quantity::Quantity) -> Self {
-            let unit = qty.unit_text().map(|s| s.to_owned());
-            let value = qty.value;
-            Self { value, unit }
-        }
-    }
-    #[derive(Serialize)]
-    struct Ingredient {
+    struct Ingredient<'a> {
         name: String,
         quantity: Vec<ScaledQuantity>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        purchase: Option<Purchase>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sources: Option<&'a [Source]>,
     }
-    impl From<(String, GroupedQuantity)> for Ingredient {
-        fn from((name, qty): (String, GroupedQuantity)) -> Self {
-            Ingredient {
-                name,
-                quantity: qty.into_vec(),
-            }
-        }
-    }
+    let ingredient = |(name, qty): (String, GroupedQuantity)| Ingredient {
+        sources: sources.map(|s| s.get(&name).map(Vec::as_slice).unwrap_or_default()),
+        purchase: purchases.get(&name, &qty),
+        name,
+        quantity: qty.into_vec(),
+    };
     #[derive(Serialize)]
     struct Category {
         category: String,
@@ -234,14 +601,23 @@ fn build_json_value<'a>(
     }
 
     if plain {
-        serde_json::to_value(list.into_iter().map(Ingredient::from).collect::<Vec<_>>()).unwrap()
+        serde_json::to_value(
+            sorted(list, collation)
+                .into_iter()
+                .map(ingredient)
+                .collect::<Vec<_>>(),
+        )
+        .unwrap()
     } else {
         serde_json::to_value(
             list.categorize(aisle)
                 .into_iter()
                 .map(|(category, items)| Category {
                     category,
-                    items: items.into_iter().map(Ingredient::from).collect(),
+                    items: sorted(items, collation)
+                        .into_iter()
+                        .map(ingredient)
+                        .collect(),
                 })
                 .collect::<Vec<_>>(),
         )
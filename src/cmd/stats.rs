@@ -0,0 +1,118 @@
+use anstream::println;
+use anyhow::{bail, Result};
+use clap::Args;
+use cooklang_fs::MetadataIndex;
+use owo_colors::OwoColorize;
+
+use crate::{config::metadata_cache_path, index_builder, Context};
+
+#[derive(Debug, Args)]
+pub struct StatsArgs {
+    /// Count the recipes with each tag
+    ///
+    /// Needs to read the metadata of every recipe.
+    #[arg(short, long)]
+    tags: bool,
+
+    /// List the images that don't belong to any recipe
+    #[arg(short, long)]
+    orphans: bool,
+
+    /// Output the stats as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Force to count recipes even outside a collection
+    #[arg(short, long)]
+    force: bool,
+}
+
+pub fn run(ctx: &Context, args: StatsArgs) -> Result<()> {
+    if !args.force && !ctx.is_collection {
+        bail!("`stats` needs to run inside a collection or pass `--force`");
+    }
+
+    let builder = index_builder(&ctx.base_path, &ctx.config)?;
+    let stats = if args.tags {
+        let cache_file = metadata_cache_path(&ctx.base_path);
+        let mut cache = if ctx.is_collection && ctx.config.metadata_cache {
+            MetadataIndex::load(&ctx.base_path, &cache_file)
+        } else {
+            MetadataIndex::new(&ctx.base_path)
+        };
+        let stats = builder.stats_with_tags(&mut cache, ctx.parser()?)?;
+        if ctx.is_collection && ctx.config.metadata_cache {
+            if let Err(e) = cache.save(&cache_file) {
+                tracing::warn!("Can't store the metadata cache: {e}");
+            }
+        }
+        stats
+    } else {
+        builder.stats()?
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+
+    use tabular::{row, Table};
+    let mut table = Table::new("{:<}  {:>}");
+    table.add_row(row!("recipes", stats.recipes));
+    table.add_row(row!("dirs", stats.dirs));
+    table.add_row(row!("images", stats.images));
+    table.add_row(row!("orphan images", stats.orphan_images.len()));
+    table.add_row(row!("size", format_size(stats.size)));
+    print!("{table}");
+
+    if args.tags {
+        println!("\n{}", "Tags".bold());
+        let mut tags = stats.tags.iter().collect::<Vec<_>>();
+        tags.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        let mut table = Table::new("{:<}  {:>}");
+        for (tag, count) in tags {
+            table.add_row(row!(tag, count));
+        }
+        table.add_row(row!("(untagged)", stats.untagged));
+        print!("{table}");
+    }
+
+    if args.orphans && !stats.orphan_images.is_empty() {
+        println!("\n{}", "Orphan images".bold());
+        for image in &stats.orphan_images {
+            let path = image.strip_prefix(&ctx.base_path).unwrap_or(image);
+            println!("{path}");
+        }
+    }
+    Ok(())
+}
+
+/// Size in bytes with a binary unit
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for next in &UNITS[1..] {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next;
+    }
+    format!("{value:.1} {unit}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MiB");
+    }
+}
@@ -0,0 +1,102 @@
+use anstream::{eprintln, println};
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8PathBuf;
+use clap::{Args, Subcommand};
+use cooklang_fs::Trash;
+use owo_colors::OwoColorize;
+
+use crate::{
+    config::trash_path,
+    util::{format_age, unix_now},
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct TrashArgs {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// List the deleted files, the last deleted first
+    #[command(visible_alias = "ls")]
+    List {
+        /// Output the files as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Put a deleted file back where it was
+    ///
+    /// If it was deleted more than once, the last one is restored.
+    Restore {
+        /// Path relative to the collection, the extension can be omitted
+        path: Utf8PathBuf,
+    },
+    /// Delete the files in the trash for good
+    Empty {
+        /// Only the files deleted more than this number of days ago
+        #[arg(long, value_name = "DAYS")]
+        older_than: Option<u64>,
+    },
+}
+
+pub fn run(ctx: &Context, args: TrashArgs) -> Result<()> {
+    if !ctx.is_collection {
+        bail!("`trash` needs to run inside a collection");
+    }
+    let trash = Trash::new(&ctx.base_path, trash_path(&ctx.base_path));
+    match args.command {
+        Command::List { json } => list(&trash, json),
+        Command::Restore { path } => restore(ctx, &trash, path),
+        Command::Empty { older_than } => empty(ctx, &trash, older_than),
+    }
+}
+
+fn list(trash: &Trash, json: bool) -> Result<()> {
+    let files = trash.list()?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&files)?);
+        return Ok(());
+    }
+    if files.is_empty() {
+        eprintln!("The trash is empty");
+        return Ok(());
+    }
+    let now = unix_now();
+    for file in &files {
+        println!(
+            "{:>14}  {}",
+            format_age(now.saturating_sub(file.deleted)).dimmed(),
+            file.path
+        );
+    }
+    Ok(())
+}
+
+fn restore(ctx: &Context, trash: &Trash, path: Utf8PathBuf) -> Result<()> {
+    let path = path.strip_prefix(&ctx.base_path).unwrap_or(&path);
+    let _lock = ctx.lock_collection()?;
+    let files = trash.list()?;
+    // the list has the last deleted first
+    let Some(file) = files
+        .iter()
+        .find(|f| f.path == path)
+        .or_else(|| files.iter().find(|f| f.path.with_extension("") == path))
+    else {
+        bail!("'{path}' is not in the trash");
+    };
+    let dest = trash
+        .restore(file)
+        .with_context(|| format!("Failed to restore '{}'", file.path))?;
+    eprintln!("Restored {dest}");
+    Ok(())
+}
+
+fn empty(ctx: &Context, trash: &Trash, older_than: Option<u64>) -> Result<()> {
+    let before = older_than.map(|days| unix_now().saturating_sub(days * 24 * 60 * 60));
+    let _lock = ctx.lock_collection()?;
+    let count = trash.empty(before)?;
+    eprintln!("Deleted {count} files");
+    Ok(())
+}
@@ -0,0 +1,199 @@
+//! Quantities written with more than one unit, like `1 lb 4 oz`
+
+use std::sync::Arc;
+
+use anyhow::{bail, Context as _, Result};
+use cooklang::{
+    quantity::{Number, ScalableValue, Value},
+    Converter, Quantity, ScalableRecipe,
+};
+
+/// Units that are written together, from the biggest to the smallest
+const COMPOUND_UNITS: &[&[&str]] = &[&["lb", "oz"], &["ft", "in"], &["cup", "tbsp", "tsp"]];
+
+/// Parses a quantity with one or more number and unit pairs
+///
+/// The result is in the first unit, so `1 lb 4 oz` is `1.25 lb`. All the
+/// units have to be of the same physical quantity, and units with an offset,
+/// like temperatures, can't be combined.
+pub fn parse(text: &str, converter: &Converter) -> Result<(f64, String)> {
+    let mut parts = Vec::new();
+    let mut tokens = text.split_whitespace();
+    while let Some(token) = tokens.next() {
+        // allow the unit glued to the number, like `4oz`
+        let split = token
+            .find(|c: char| !(c.is_ascii_digit() || matches!(c, '.' | '/' | '-')))
+            .unwrap_or(token.len());
+        let (number, unit) = token.split_at(split);
+        let value = parse_number(number).with_context(|| format!("Invalid number: '{token}'"))?;
+        let unit = if unit.is_empty() {
+            tokens.next().context("Missing unit")?
+        } else {
+            unit
+        };
+        parts.push((value, unit));
+    }
+
+    let Some(&(first_value, first_unit)) = parts.first() else {
+        bail!("Empty quantity");
+    };
+    let first = converter
+        .find_unit(first_unit)
+        .with_context(|| format!("Unknown unit: '{first_unit}'"))?;
+    if parts.len() == 1 {
+        return Ok((first_value, first_unit.to_string()));
+    }
+
+    let mut total = 0.0;
+    for &(value, unit) in &parts {
+        let u = converter
+            .find_unit(unit)
+            .with_context(|| format!("Unknown unit: '{unit}'"))?;
+        if u.physical_quantity != first.physical_quantity {
+            bail!(
+                "Can't combine {first_unit} ({}) with {unit} ({})",
+                first.physical_quantity,
+                u.physical_quantity
+            );
+        }
+        if u.difference != 0.0 {
+            bail!("Can't combine units with an offset, like {unit}");
+        }
+        total += value * u.ratio / first.ratio;
+    }
+    Ok((total, first_unit.to_string()))
+}
+
+fn parse_number(s: &str) -> Option<f64> {
+    match s.split_once('/') {
+        Some((num, den)) => {
+            let num = num.parse::<f64>().ok()?;
+            let den = den.parse::<f64>().ok()?;
+            (den != 0.0).then(|| num / den)
+        }
+        None => s.parse().ok(),
+    }
+}
+
+/// Writes a value with more than one unit, like `1 lb 4 oz`
+///
+/// Returns `None` if the unit is not in [`COMPOUND_UNITS`] or if a single
+/// unit is enough.
+pub fn render(value: f64, unit: &str, converter: &Converter) -> Option<String> {
+    let from = converter.find_unit(unit)?;
+    let chain = COMPOUND_UNITS.iter().find(|chain| {
+        chain.iter().any(|u| {
+            converter
+                .find_unit(u)
+                .is_some_and(|u| Arc::ptr_eq(&u, &from))
+        })
+    })?;
+    let units = chain
+        .iter()
+        .map(|u| converter.find_unit(u).map(|unit| (*u, unit)))
+        .collect::<Option<Vec<_>>>()?;
+
+    let negative = value < 0.0;
+    let mut rest = value.abs() * from.ratio;
+    let mut parts = Vec::new();
+    for (i, (symbol, unit)) in units.iter().enumerate() {
+        let amount = rest / unit.ratio;
+        let amount = if i == units.len() - 1 {
+            (amount * 100.0).round() / 100.0
+        } else {
+            // avoid `0.99999 lb` being `0 lb 16 oz`
+            (amount + 1e-9).floor()
+        };
+        if amount > 0.0 {
+            parts.push(format!("{amount} {symbol}"));
+            rest -= amount * unit.ratio;
+        }
+    }
+
+    if parts.len() < 2 {
+        return None;
+    }
+    let text = parts.join(" ");
+    Some(if negative { format!("-{text}") } else { text })
+}
+
+/// Normalizes the compound quantities of the ingredients to a single unit
+///
+/// The parser reads `@flour{1 lb 4 oz}` as `1` of the unit `lb 4 oz`, this
+/// turns it into `1.25 lb` so it can be scaled and converted.
+pub fn normalize(recipe: &mut ScalableRecipe, converter: &Converter) {
+    for igr in &mut recipe.ingredients {
+        let Some(q) = &mut igr.quantity else {
+            continue;
+        };
+        let Some(unit) = q.unit_text() else {
+            continue;
+        };
+        if converter.find_unit(unit).is_some() {
+            continue;
+        }
+        let number = match &q.value {
+            ScalableValue::Fixed(Value::Number(n)) | ScalableValue::Linear(Value::Number(n)) => {
+                n.value()
+            }
+            _ => continue,
+        };
+        let Ok((value, unit)) = parse(&format!("{number} {unit}"), converter) else {
+            continue;
+        };
+        let value = Value::Number(Number::Regular(value));
+        let value = match q.value {
+            ScalableValue::Fixed(_) => ScalableValue::Fixed(value),
+            _ => ScalableValue::Linear(value),
+        };
+        *q = Quantity::new(value, Some(unit));
+    }
+}
+
+/// Writes the ingredient quantities with more than one unit when possible
+pub fn compound_quantities(recipe: &mut cooklang::ScaledRecipe, converter: &Converter) {
+    for igr in &mut recipe.ingredients {
+        let Some(q) = &mut igr.quantity else {
+            continue;
+        };
+        let (Value::Number(n), Some(unit)) = (&q.value, q.unit_text()) else {
+            continue;
+        };
+        if let Some(text) = render(n.value(), unit, converter) {
+            *q = Quantity::new(Value::Text(text), None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_compound() {
+        let converter = Converter::default();
+        let (value, unit) = parse("1 lb 4 oz", &converter).unwrap();
+        assert_eq!(unit, "lb");
+        assert!((value - 1.25).abs() < 1e-6);
+
+        let (value, unit) = parse("5ft 6in", &converter).unwrap();
+        assert_eq!(unit, "ft");
+        assert!((value - 5.5).abs() < 1e-6);
+
+        assert_eq!(parse("3 cups", &converter).unwrap(), (3.0, "cups".into()));
+        assert!(parse("1 lb 4 ml", &converter).is_err());
+        assert!(parse("1 lb 4", &converter).is_err());
+        assert!(parse("10 C 5 F", &converter).is_err());
+    }
+
+    #[test]
+    fn render_compound() {
+        let converter = Converter::default();
+        assert_eq!(render(1.25, "lb", &converter).as_deref(), Some("1 lb 4 oz"));
+        assert_eq!(render(20.0, "oz", &converter).as_deref(), Some("1 lb 4 oz"));
+        assert_eq!(render(66.0, "in", &converter).as_deref(), Some("5 ft 6 in"));
+        assert_eq!(render(1.0, "lb", &converter), None);
+        assert_eq!(render(4.0, "oz", &converter), None);
+        assert_eq!(render(500.0, "g", &converter), None);
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     env,
     fs::{self, File},
     io::{self, Read},
@@ -9,20 +9,40 @@ use std::{
 use anyhow::{bail, Context, Result};
 use camino::{Utf8Path, Utf8PathBuf};
 use cooklang::Extensions;
+use cooklang_fs::{Collation, NameMatching, NonUtf8Policy};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use crate::{APP_NAME, COOK_DIR, UTF8_PATH_PANIC};
+use crate::{
+    hooks::HooksConfig, scaling::TextQuantities, schedule::ScheduleEntry, send::SendConfig,
+    to_taste::ToTaste, unit_preference::UnitPreferences, APP_NAME, COOK_DIR, UTF8_PATH_PANIC,
+};
+
+pub mod migrate;
+
+pub use migrate::CONFIG_VERSION;
 
 pub const CONFIG_FILE: &str = "config.toml";
 pub const AUTO_AISLE: &str = "aisle.conf";
 pub const AUTO_UNITS: &str = "units.toml";
+pub const AUTO_PURCHASE: &str = "purchase.toml";
+pub const AUTO_LINKS: &str = "links.toml";
 pub const DEFAULT_CONFIG_FILE: &str = "default-config.toml";
 pub const CHEF_CONFIG_FILE: &str = "chef-config.toml";
+pub const INDEX_FILE: &str = "index.json";
+pub const ALIASES_FILE: &str = "aliases.toml";
+pub const METADATA_CACHE_FILE: &str = "metadata.json";
+pub const JOURNAL_FILE: &str = "journal.json";
+pub const SYNC_LOG_FILE: &str = "sync.json";
+pub const SHARE_KEY_FILE: &str = "share.key";
+pub const TRASH_DIR: &str = "trash";
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct ChefConfig {
     pub default_collection: Option<Utf8PathBuf>,
     pub editor_command: Option<Vec<String>>,
+    /// Named collections, served together with `chef serve --collections`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub collections: BTreeMap<String, Utf8PathBuf>,
 }
 
 impl ChefConfig {
@@ -59,21 +79,48 @@ impl Default for ChefConfig {
         Self {
             default_collection: None,
             editor_command: None,
+            collections: BTreeMap::new(),
         }
     }
 }
 
 pub fn default_config() -> Result<Config> {
-    global_load(DEFAULT_CONFIG_FILE)
+    let path = global_file_path(DEFAULT_CONFIG_FILE)?;
+    if path.is_file() {
+        Config::read_file(&path)
+    } else {
+        global_load(DEFAULT_CONFIG_FILE)
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(default)]
 pub struct Config {
+    /// Layout version, see [`migrate`]
+    #[serde(default)]
+    pub version: u32,
     pub default_units: bool,
     pub warnings_as_errors: bool,
     pub recipe_ref_check: bool,
     pub max_depth: usize,
+    pub backup: bool,
+    pub non_utf8_paths: NonUtf8Policy,
+    /// Markdown files with a cooklang code block are recipes too
+    pub markdown: bool,
+    /// Follow symbolic links when looking for recipes
+    pub follow_links: bool,
+    pub text_quantities: TextQuantities,
+    pub staples: Vec<String>,
+    #[serde(skip_serializing_if = "is_default")]
+    pub to_taste: ToTaste,
+    /// Merge and search ingredients by their singular name without
+    /// descriptors, see [`crate::ingredient_name`]
+    pub normalize_ingredients: bool,
+    pub collation: Collation,
+    pub name_matching: NameMatching,
+    pub usage_log: bool,
+    /// Cache the metadata of the recipes for `list`
+    pub metadata_cache: bool,
     #[serde(with = "extensions_serde")]
     pub extensions: Extensions,
     #[serde(skip_serializing_if = "Load::is_empty")]
@@ -82,19 +129,57 @@ pub struct Config {
     pub ui: UiConfig,
     #[serde(skip_serializing_if = "is_default")]
     pub export: ExportConfig,
+    #[serde(skip_serializing_if = "is_default")]
+    pub format: cooklang_to_cooklang::Options,
+    /// Units to convert to, see [`crate::unit_preference`]
+    #[serde(skip_serializing_if = "UnitPreferences::is_empty")]
+    pub unit_preferences: UnitPreferences,
+    /// How quantities are written in the terminal
+    #[serde(skip_serializing_if = "is_default")]
+    pub quantities: cooklang_to_human::QuantityFormatter,
+    #[serde(skip_serializing_if = "is_default")]
+    pub send: SendConfig,
+    #[serde(skip_serializing_if = "is_default")]
+    pub serve: ServeConfig,
+    #[serde(skip_serializing_if = "is_default")]
+    pub hooks: HooksConfig,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub schedule: Vec<ScheduleEntry>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            version: CONFIG_VERSION,
             default_units: true,
             extensions: Extensions::all() ^ Extensions::MULTILINE_STEPS,
             warnings_as_errors: false,
             recipe_ref_check: true,
             max_depth: 10,
+            backup: false,
+            non_utf8_paths: NonUtf8Policy::Skip,
+            markdown: false,
+            follow_links: false,
+            text_quantities: TextQuantities::KeepText,
+            staples: ["salt", "pepper", "water", "oil"]
+                .map(String::from)
+                .to_vec(),
+            to_taste: Default::default(),
+            normalize_ingredients: false,
+            collation: Collation::Binary,
+            name_matching: NameMatching::IgnoreCase,
+            usage_log: false,
+            metadata_cache: false,
             load: Default::default(),
             ui: Default::default(),
             export: Default::default(),
+            format: Default::default(),
+            quantities: Default::default(),
+            unit_preferences: Default::default(),
+            send: Default::default(),
+            serve: Default::default(),
+            hooks: Default::default(),
+            schedule: Default::default(),
         }
     }
 }
@@ -106,11 +191,18 @@ pub struct Load {
     pub units: Vec<PathBuf>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub aisle: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purchase: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub links: Option<PathBuf>,
 }
 
 impl Load {
     fn is_empty(&self) -> bool {
-        self.units.is_empty() && self.aisle.is_none()
+        self.units.is_empty()
+            && self.aisle.is_none()
+            && self.purchase.is_none()
+            && self.links.is_none()
     }
 }
 
@@ -138,6 +230,22 @@ pub struct ExportConfig {
     pub markdown: cooklang_to_md::Options,
 }
 
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct ServeConfig {
+    /// Serve the web UI with HTTPS
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsConfig>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct TlsConfig {
+    /// PEM certificate chain, relative to the collection
+    pub cert: PathBuf,
+    /// PEM private key, relative to the collection
+    pub key: PathBuf,
+}
+
 fn is_default<T>(this: &T) -> bool
 where
     T: Default + PartialEq,
@@ -153,8 +261,23 @@ impl Config {
             return default_config().context("Error loading default global config file");
         }
         tracing::debug!("Loading local config from {local}");
-        let content = std::fs::read_to_string(&local)?;
-        let config = toml::from_str(&content)?;
+        Self::read_file(local)
+    }
+
+    /// Reads a config file, upgrading it in memory if it's from an older chef
+    pub fn read_file(path: &Utf8Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let mut table: toml::Table = toml::from_str(&content)?;
+        if migrate::version(&table)? != CONFIG_VERSION {
+            let notes = migrate::migrate(&mut table)
+                .with_context(|| format!("Can't load config file: {path}"))?;
+            if !notes.is_empty() {
+                tracing::warn!(
+                    "{path} is from an older chef, upgrade it with `chef config migrate`"
+                );
+            }
+        }
+        let config = toml::Value::Table(table).try_into()?;
         Ok(config)
     }
 
@@ -194,6 +317,13 @@ impl Config {
         }
     }
 
+    pub fn write_options(&self) -> cooklang_fs::WriteOptions {
+        cooklang_fs::WriteOptions {
+            backup: self.backup,
+            ..Default::default()
+        }
+    }
+
     pub fn aisle(&self, base_path: &Utf8Path) -> Option<Utf8PathBuf> {
         self.load
             .aisle
@@ -211,8 +341,42 @@ impl Config {
             })
     }
 
+    pub fn purchase(&self, base_path: &Utf8Path) -> Option<Utf8PathBuf> {
+        self.load
+            .purchase
+            .as_ref()
+            .map(|p| resolve_path(base_path, p))
+            .or_else(|| {
+                let auto = base_path.join(COOK_DIR).join(AUTO_PURCHASE);
+                tracing::trace!("checking auto purchase file: {auto}");
+                auto.is_file().then_some(auto)
+            })
+            .or_else(|| {
+                let global = global_file_path(AUTO_PURCHASE).ok()?;
+                tracing::trace!("checking global auto purchase file: {global}");
+                global.is_file().then_some(global)
+            })
+    }
+
+    pub fn links(&self, base_path: &Utf8Path) -> Option<Utf8PathBuf> {
+        self.load
+            .links
+            .as_ref()
+            .map(|p| resolve_path(base_path, p))
+            .or_else(|| {
+                let auto = base_path.join(COOK_DIR).join(AUTO_LINKS);
+                tracing::trace!("checking auto links file: {auto}");
+                auto.is_file().then_some(auto)
+            })
+            .or_else(|| {
+                let global = global_file_path(AUTO_LINKS).ok()?;
+                tracing::trace!("checking global links file: {global}");
+                global.is_file().then_some(global)
+            })
+    }
+
     pub fn units(&self, base_path: &Utf8Path) -> Vec<Utf8PathBuf> {
-        (!self.load.is_empty())
+        (!self.load.units.is_empty() || self.load.aisle.is_some())
             .then(|| {
                 self.load
                     .units
@@ -232,6 +396,12 @@ impl Config {
             })
             .unwrap_or(vec![])
     }
+
+    /// Ingredients that are always at home, left out of shopping lists
+    pub fn is_staple(&self, name: &str) -> bool {
+        let name = name.trim().to_lowercase();
+        self.staples.iter().any(|s| s.to_lowercase() == name)
+    }
 }
 
 pub fn resolve_path(base_path: &Utf8Path, path: &Path) -> Utf8PathBuf {
@@ -247,6 +417,56 @@ pub fn config_file_path(base_path: &Utf8Path) -> Utf8PathBuf {
     base_path.join(COOK_DIR).join(CONFIG_FILE)
 }
 
+pub fn index_file_path(base_path: &Utf8Path) -> Utf8PathBuf {
+    base_path.join(COOK_DIR).join(INDEX_FILE)
+}
+
+pub fn metadata_cache_path(base_path: &Utf8Path) -> Utf8PathBuf {
+    base_path.join(COOK_DIR).join(METADATA_CACHE_FILE)
+}
+
+pub fn journal_file_path(base_path: &Utf8Path) -> Utf8PathBuf {
+    base_path.join(COOK_DIR).join(JOURNAL_FILE)
+}
+
+pub fn sync_log_path(base_path: &Utf8Path) -> Utf8PathBuf {
+    base_path.join(COOK_DIR).join(SYNC_LOG_FILE)
+}
+
+pub fn share_key_path(base_path: &Utf8Path) -> Utf8PathBuf {
+    base_path.join(COOK_DIR).join(SHARE_KEY_FILE)
+}
+
+/// Where the deleted recipes and images go, see `chef trash`
+pub fn trash_path(base_path: &Utf8Path) -> Utf8PathBuf {
+    base_path.join(COOK_DIR).join(TRASH_DIR)
+}
+
+pub fn aliases_file_path(base_path: &Utf8Path) -> Utf8PathBuf {
+    base_path.join(COOK_DIR).join(ALIASES_FILE)
+}
+
+/// Loads the alternate names of the recipes in `.cooklang/aliases.toml`
+///
+/// The file maps names to paths relative to the base path:
+/// `"Grandma's sauce" = "Sauces/Tomato.cook"`.
+pub fn load_aliases(base_path: &Utf8Path) -> Result<BTreeMap<String, Utf8PathBuf>> {
+    let path = aliases_file_path(base_path);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => return Err(e).context("Failed to read aliases file"),
+    };
+    let aliases: BTreeMap<String, Utf8PathBuf> =
+        toml::from_str(&content).with_context(|| format!("Invalid aliases file: {path}"))?;
+    for (alias, target) in &aliases {
+        if !base_path.join(target).is_file() {
+            tracing::warn!("Alias '{alias}' points to a recipe that does not exist: '{target}'");
+        }
+    }
+    Ok(aliases)
+}
+
 pub fn global_file_path(name: &str) -> Result<Utf8PathBuf> {
     let dirs = directories::ProjectDirs::from("", "", APP_NAME)
         .context("Could not determine home directory path")?;
@@ -0,0 +1,105 @@
+//! Upgrades of old config files
+//!
+//! Every config has a `version`, missing in the ones before versions existed.
+//! The migrations change the TOML before it's deserialized, so a config
+//! written by an older chef can always be loaded.
+
+use anyhow::{bail, Result};
+use cooklang::Extensions;
+use toml::{Table, Value};
+
+/// Version of the configs this chef writes
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Migration from the version at the index to the next one
+///
+/// They return notes about what changed, that are shown to the user.
+const MIGRATIONS: &[fn(&mut Table) -> Vec<String>] = &[v0_to_v1];
+
+/// Version of a config, 0 if missing
+pub fn version(table: &Table) -> Result<u32> {
+    match table.get("version") {
+        None => Ok(0),
+        Some(Value::Integer(v)) => match u32::try_from(*v) {
+            Ok(v) => Ok(v),
+            Err(_) => bail!("Invalid config version: {v}"),
+        },
+        Some(other) => bail!("Invalid config version: {other}"),
+    }
+}
+
+/// Upgrades a config to [`CONFIG_VERSION`]
+///
+/// Returns the notes of the changes, empty if only the version changed.
+pub fn migrate(table: &mut Table) -> Result<Vec<String>> {
+    let from = version(table)?;
+    if from > CONFIG_VERSION {
+        bail!(
+            "The config is version {from}, this chef only understands up to {CONFIG_VERSION}. \
+            Upgrade chef."
+        );
+    }
+    let mut notes = Vec::new();
+    for migration in &MIGRATIONS[from as usize..] {
+        notes.extend(migration(table));
+    }
+    table.insert("version".into(), Value::Integer(CONFIG_VERSION.into()));
+    Ok(notes)
+}
+
+/// Removes the extensions the parser no longer has
+///
+/// The parser renames and removes extensions between versions, and an unknown
+/// name fails to load.
+fn v0_to_v1(table: &mut Table) -> Vec<String> {
+    let mut notes = Vec::new();
+    if let Some(Value::Table(extensions)) = table.get_mut("extensions") {
+        extensions.retain(|name, _| {
+            let known = Extensions::from_name(&name.replace(' ', "_").to_uppercase()).is_some();
+            if !known {
+                notes.push(format!("Removed unknown extension '{name}'"));
+            }
+            known
+        });
+    }
+    notes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrate_unversioned() {
+        let mut table: Table = toml::from_str(
+            r#"
+            max_depth = 3
+            [extensions]
+            multiline_steps = true
+            removed_long_ago = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(version(&table).unwrap(), 0);
+        let notes = migrate(&mut table).unwrap();
+        assert_eq!(notes, ["Removed unknown extension 'removed_long_ago'"]);
+        assert_eq!(version(&table).unwrap(), CONFIG_VERSION);
+        assert_eq!(table["max_depth"].as_integer(), Some(3));
+        assert!(table["extensions"].get("multiline_steps").is_some());
+    }
+
+    #[test]
+    fn migrate_current() {
+        let mut table: Table = toml::from_str("version = 1\nextensions = \"all\"").unwrap();
+        assert!(migrate(&mut table).unwrap().is_empty());
+        assert_eq!(table["extensions"].as_str(), Some("all"));
+    }
+
+    #[test]
+    fn newer_version() {
+        let mut table: Table = toml::from_str("version = 1000").unwrap();
+        assert!(migrate(&mut table).is_err());
+        let mut table: Table = toml::from_str("version = \"1\"").unwrap();
+        assert!(migrate(&mut table).is_err());
+    }
+}
@@ -0,0 +1,44 @@
+//! Commands run when something happens in the collection
+//!
+//! Configured in the `[hooks]` table of the config. Each hook is a program
+//! and its args, run in the base path with the details in `CHEF_*` env vars.
+//! Chef doesn't wait for them and only logs their errors.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct HooksConfig {
+    /// After a recipe is submitted to `chef serve --submissions`, with
+    /// `CHEF_RECIPE` set to its path
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub submission: Vec<String>,
+}
+
+/// Starts a hook, if there is a command for it
+#[cfg(feature = "serve")]
+pub fn run(name: &str, command: &[String], base_path: &camino::Utf8Path, env: &[(&str, &str)]) {
+    let Some((program, args)) = command.split_first() else {
+        return;
+    };
+    tracing::debug!("Running {name} hook: {program}");
+    let child = std::process::Command::new(program)
+        .args(args)
+        .current_dir(base_path)
+        .envs(env.iter().copied())
+        .stdin(std::process::Stdio::null())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            tracing::warn!("Can't run the {name} hook '{program}': {e}");
+            return;
+        }
+    };
+    let name = name.to_string();
+    std::thread::spawn(move || match child.wait() {
+        Ok(status) if !status.success() => tracing::warn!("The {name} hook failed: {status}"),
+        Ok(_) => {}
+        Err(e) => tracing::warn!("Can't wait for the {name} hook: {e}"),
+    });
+}
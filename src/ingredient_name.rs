@@ -0,0 +1,239 @@
+//! Ingredient names for merging and searching
+//!
+//! `2 large onions, finely chopped` and `@onion` are the same thing to buy.
+//! [`normalize_name`] strips the descriptors, like sizes and preparations, and
+//! makes the name singular. It's only a few English rules, so names it doesn't
+//! know are left as they are.
+
+use cooklang::ScaledRecipe;
+
+/// Words at the start of a name that describe the ingredient
+const DESCRIPTORS: &[&str] = &[
+    // sizes
+    "large",
+    "small",
+    "medium",
+    "big",
+    "extra-large",
+    "jumbo",
+    // how to prepare it
+    "chopped",
+    "diced",
+    "minced",
+    "sliced",
+    "grated",
+    "peeled",
+    "crushed",
+    "shredded",
+    "melted",
+    "softened",
+    "beaten",
+    "halved",
+    "quartered",
+    "cubed",
+    "julienned",
+    "trimmed",
+    "rinsed",
+    "drained",
+    "pitted",
+    "deseeded",
+    "cored",
+    "mashed",
+    "whisked",
+    "sifted",
+    // how much
+    "finely",
+    "roughly",
+    "coarsely",
+    "thinly",
+    "thickly",
+    "freshly",
+    "lightly",
+    "very",
+];
+
+/// Plurals that the rules get wrong, lowercase
+const IRREGULAR: &[(&str, &str)] = &[
+    ("leaves", "leaf"),
+    ("loaves", "loaf"),
+    ("halves", "half"),
+    ("knives", "knife"),
+    ("cookies", "cookie"),
+    ("brownies", "brownie"),
+    ("veggies", "veggie"),
+    ("smoothies", "smoothie"),
+    ("children", "child"),
+    ("geese", "goose"),
+    ("mice", "mouse"),
+];
+
+/// Names that end like a plural but are not, lowercase
+const INVARIANT: &[&str] = &[
+    "molasses",
+    "hummus",
+    "couscous",
+    "asparagus",
+    "citrus",
+    "oats",
+    "grits",
+    "greens",
+    "swiss",
+    "lemongrass",
+    "series",
+    "species",
+    "bitters",
+    "schnapps",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedName {
+    /// Singular name without the descriptors
+    pub name: String,
+    /// What was removed from the name, like `large, finely chopped`
+    pub descriptor: Option<String>,
+}
+
+/// Removes the descriptors and makes the name singular
+///
+/// Everything after a comma is a descriptor, like in `onion, finely chopped`,
+/// and so are the known [`DESCRIPTORS`] at the start. The last word of what
+/// is left is made singular. A name that would be left empty is not changed.
+pub fn normalize_name(name: &str) -> NormalizedName {
+    let (head, after_comma) = match name.split_once(',') {
+        Some((head, rest)) if !head.trim().is_empty() => (head, Some(rest.trim())),
+        _ => (name, None),
+    };
+
+    let words = head.split_whitespace().collect::<Vec<_>>();
+    let mut start = words.iter().take_while(|w| is_descriptor(w)).count();
+    if start == words.len() {
+        start = 0;
+    }
+    let (before, rest) = words.split_at(start);
+
+    let mut rest = rest.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+    if let Some(last) = rest.last_mut() {
+        *last = singular(last);
+    }
+
+    let descriptor = [
+        before.join(" "),
+        after_comma.unwrap_or_default().to_string(),
+    ]
+    .into_iter()
+    .filter(|d| !d.is_empty())
+    .collect::<Vec<_>>()
+    .join(", ");
+    NormalizedName {
+        name: rest.join(" "),
+        descriptor: (!descriptor.is_empty()).then_some(descriptor),
+    }
+}
+
+fn is_descriptor(word: &str) -> bool {
+    let word = word.to_lowercase();
+    DESCRIPTORS.contains(&word.as_str())
+}
+
+/// Singular of an English word
+pub fn singular(word: &str) -> String {
+    let lower = word.to_lowercase();
+    if let Some((_, singular)) = IRREGULAR.iter().find(|(plural, _)| *plural == lower) {
+        return singular.to_string();
+    }
+    if lower.chars().count() <= 3 || INVARIANT.contains(&lower.as_str()) {
+        return word.to_string();
+    }
+    // all the suffixes are ASCII, so the lengths are the same in `word`
+    if lower.ends_with("ies") {
+        return format!("{}y", &word[..word.len() - 3]);
+    }
+    let drop = if lower.ends_with("oes")
+        || ["ches", "shes", "sses", "xes", "zes"]
+            .iter()
+            .any(|s| lower.ends_with(s))
+    {
+        2
+    } else if lower.ends_with('s') && !["ss", "us", "is"].iter().any(|s| lower.ends_with(s)) {
+        1
+    } else {
+        0
+    };
+    word[..word.len() - drop].to_string()
+}
+
+/// Changes the ingredients to merge and search them by the normalized name
+///
+/// The `name` is the normalized display name of the ingredient, the original
+/// is kept in the `alias` to show it and the descriptor is added to the
+/// `note`.
+pub fn normalize(recipe: &mut ScaledRecipe) {
+    for igr in &mut recipe.ingredients {
+        let original = igr.display_name().into_owned();
+        let NormalizedName { name, descriptor } = normalize_name(&original);
+        if let Some(descriptor) = descriptor {
+            igr.note = Some(match igr.note.take() {
+                Some(note) => format!("{descriptor}, {note}"),
+                None => descriptor,
+            });
+        }
+        igr.alias = (name != original).then_some(original);
+        igr.name = name;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn n(name: &str) -> (String, Option<String>) {
+        let n = normalize_name(name);
+        (n.name, n.descriptor)
+    }
+
+    #[test]
+    fn descriptors() {
+        assert_eq!(n("onion"), ("onion".into(), None));
+        assert_eq!(
+            n("large onions, finely chopped"),
+            ("onion".into(), Some("large, finely chopped".into()))
+        );
+        assert_eq!(
+            n("Finely chopped red Onions"),
+            ("red Onion".into(), Some("Finely chopped".into()))
+        );
+        // nothing would be left
+        assert_eq!(n("large"), ("large".into(), None));
+        // only at the start
+        assert_eq!(
+            n("olive oil extra large"),
+            ("olive oil extra large".into(), None)
+        );
+    }
+
+    #[test]
+    fn plurals() {
+        let cases = [
+            ("onions", "onion"),
+            ("berries", "berry"),
+            ("tomatoes", "tomato"),
+            ("peaches", "peach"),
+            ("radishes", "radish"),
+            ("boxes", "box"),
+            ("cloves", "clove"),
+            ("leaves", "leaf"),
+            ("cookies", "cookie"),
+            ("Eggs", "Egg"),
+            ("molasses", "molasses"),
+            ("asparagus", "asparagus"),
+            ("oats", "oats"),
+            ("peas", "pea"),
+            ("gas", "gas"),
+            ("glass", "glass"),
+            ("flour", "flour"),
+        ];
+        for (plural, expected) in cases {
+            assert_eq!(singular(plural), expected, "{plural}");
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! The cooklang toolkit behind the `chef` CLI
+//!
+//! This crate re-exports the parser and the other cooklang crates in versions
+//! that work together, so a single dependency is enough. It also has the
+//! helpers chef builds on top of them.
+//!
+//! | Module             | Crate                    | Feature           |
+//! |--------------------|--------------------------|-------------------|
+//! | [`cooklang`]       | `cooklang`               | always            |
+//! | `fs`               | `cooklang-fs`            | `fs`              |
+//! | `render::human`    | `cooklang-to-human`      | `human`           |
+//! | `render::markdown` | `cooklang-to-md`         | `markdown`        |
+//! | `render::cooklang` | `cooklang-to-cooklang`   | `cooklang-output` |
+//! | [`shopping_list`]  | `cooklang`, [`purchase`] | always            |
+//!
+//! Without default features only the library is built. See the `examples`
+//! directory for complete workflows.
+
+pub use cooklang;
+pub use cooklang::{
+    convert::{Converter, ConverterBuilder},
+    CooklangParser, Extensions, ScalableRecipe, ScaledRecipe,
+};
+
+#[cfg(feature = "fs")]
+pub use cooklang_fs as fs;
+
+pub mod arithmetic;
+pub mod compound;
+pub mod ingredient_name;
+pub mod links;
+pub mod purchase;
+pub mod query;
+pub mod scaling;
+pub mod section_yield;
+pub mod temperature;
+pub mod timeline;
+pub mod to_taste;
+pub mod unit_preference;
+pub mod unit_system;
+pub mod variants;
+
+/// Renderers from a recipe to text
+pub mod render {
+    #[cfg(feature = "cooklang-output")]
+    pub use cooklang_to_cooklang as cooklang;
+    #[cfg(feature = "human")]
+    pub use cooklang_to_human as human;
+    #[cfg(feature = "markdown")]
+    pub use cooklang_to_md as markdown;
+}
+
+/// Merging the ingredients of recipes into a list to buy
+pub mod shopping_list {
+    pub use crate::purchase::{Purchase, Purchases};
+    pub use cooklang::aisle::{self, AisleConf};
+    pub use cooklang::ingredient_list::IngredientList;
+}
@@ -0,0 +1,126 @@
+//! Links of the ingredients to a product or another recipe
+//!
+//! The links of the collection are loaded from `.cooklang/links.toml`:
+//!
+//! ```toml
+//! "olive oil" = "https://shop.example.com/olive-oil"
+//! pesto = "Sauces/Pesto"
+//! ```
+//!
+//! A recipe can add or replace them with `link.<ingredient>` metadata keys,
+//! like `>> link.olive oil: https://shop.example.com/olive-oil`. A link that
+//! starts with `http://` or `https://` is a URL, anything else is the name of
+//! a recipe of the collection.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{Context as _, Result};
+use camino::Utf8Path;
+use cooklang::{Modifiers, ScaledRecipe};
+use serde::Serialize;
+
+const LINK_KEY: &str = "link.";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Link {
+    Url(String),
+    /// Name of a recipe, not resolved
+    Recipe(String),
+}
+
+impl Link {
+    /// `None` if it's empty
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        if s.is_empty() {
+            None
+        } else if s.starts_with("http://") || s.starts_with("https://") {
+            Some(Self::Url(s.to_string()))
+        } else {
+            Some(Self::Recipe(s.to_string()))
+        }
+    }
+}
+
+/// The links of the collection, by ingredient name
+#[derive(Debug, Default)]
+pub struct Links {
+    links: HashMap<String, Link>,
+}
+
+impl Links {
+    pub fn load(path: &Utf8Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).context("Failed to read links file")?;
+        Self::parse(&content).with_context(|| format!("Invalid links file: {path}"))
+    }
+
+    /// Parses the content of a links file
+    pub fn parse(content: &str) -> Result<Self> {
+        let links: HashMap<String, String> = toml::from_str(content)?;
+        let links = links
+            .into_iter()
+            .filter_map(|(name, link)| Some((name.to_lowercase(), Link::parse(&link)?)))
+            .collect();
+        Ok(Self { links })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Link> {
+        self.links.get(&name.to_lowercase())
+    }
+
+    /// Links of the ingredients of a recipe, by ingredient name
+    ///
+    /// The metadata of the recipe goes first. Recipe references already
+    /// link to their recipe, so they are left out.
+    pub fn for_recipe(&self, recipe: &ScaledRecipe) -> BTreeMap<String, Link> {
+        let in_metadata = recipe
+            .metadata
+            .map
+            .iter()
+            .filter_map(|(key, value)| {
+                let name = key.strip_prefix(LINK_KEY)?;
+                Some((name.trim().to_lowercase(), Link::parse(value)?))
+            })
+            .collect::<HashMap<_, _>>();
+
+        recipe
+            .ingredients
+            .iter()
+            .filter(|igr| !igr.modifiers().contains(Modifiers::RECIPE))
+            .filter_map(|igr| {
+                let link = in_metadata
+                    .get(&igr.name.to_lowercase())
+                    .or_else(|| self.get(&igr.name))?;
+                Some((igr.name.clone(), link.clone()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Link::parse(" https://example.com/oil "),
+            Some(Link::Url("https://example.com/oil".into()))
+        );
+        assert_eq!(
+            Link::parse("Sauces/Pesto"),
+            Some(Link::Recipe("Sauces/Pesto".into()))
+        );
+        assert_eq!(Link::parse(" "), None);
+
+        let links =
+            Links::parse("\"Olive oil\" = \"https://example.com/oil\"\nsalt = \"\"").unwrap();
+        assert_eq!(
+            links.get("olive OIL"),
+            Some(&Link::Url("https://example.com/oil".into()))
+        );
+        assert_eq!(links.get("salt"), None);
+        assert!(Links::parse("oil = 1").is_err());
+    }
+}
@@ -2,9 +2,13 @@ use anstream::ColorChoice;
 use anyhow::{bail, Context as _, Result};
 use args::{CliArgs, Command, GlobalArgs};
 use camino::{Utf8Path, Utf8PathBuf};
-use clap::Parser;
-use config::{global_load, ChefConfig, Config, CHEF_CONFIG_FILE};
+use clap::{CommandFactory, FromArgMatches};
+use config::{global_load, index_file_path, ChefConfig, Config, CHEF_CONFIG_FILE};
 use cooklang::{convert::ConverterBuilder, Converter, CooklangParser, ParseOptions};
+use cooklang_chef::{
+    compound, ingredient_name, links, purchase, query, scaling, section_yield, temperature,
+    timeline, to_taste, unit_preference, unit_system, variants,
+};
 use cooklang_fs::LazyFsIndex;
 use once_cell::sync::OnceCell;
 use util::metadata_validator;
@@ -13,8 +17,16 @@ use util::metadata_validator;
 mod cmd;
 
 // other modules
+mod archive;
 mod args;
+#[cfg(any(feature = "serve", feature = "interactive"))]
+mod checklist;
 mod config;
+mod hooks;
+mod metrics;
+mod schedule;
+mod send;
+mod usage;
 mod util;
 
 const COOK_DIR: &str = ".cooklang";
@@ -22,7 +34,8 @@ const APP_NAME: &str = "cooklang-chef";
 const UTF8_PATH_PANIC: &str = "chef only supports UTF-8 paths. If this is problem for you, file an issue in the cooklang-chef github repository";
 
 pub fn main() -> Result<()> {
-    let args = CliArgs::parse();
+    let matches = CliArgs::command().get_matches();
+    let args = CliArgs::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
     let color_ctx = init_color(args.global_args.color);
     if args.global_args.debug_trace {
@@ -44,20 +57,63 @@ pub fn main() -> Result<()> {
     }
 
     let ctx = configure_context(args.global_args, color_ctx)?;
+    cooklang_to_human::set_quantity_formatter(ctx.config.quantities.clone());
 
-    match args.command {
-        Command::Recipe(args) => cmd::recipe::run(&ctx, args),
-        Command::List(args) => cmd::list::run(&ctx, args),
+    let usage_file =
+        (ctx.config.usage_log && ctx.is_collection).then(|| usage::usage_file_path(&ctx.base_path));
+    let start = std::time::Instant::now();
+    let res = run_command(ctx, args.command);
+    if let Some(file) = usage_file {
+        let command = usage::command_name(&matches);
+        usage::record(&file, &command, start.elapsed(), res.is_ok());
+    }
+    res
+}
+
+fn run_command(ctx: Context, command: Command) -> Result<()> {
+    #[cfg(feature = "serve")]
+    if let Command::Serve(args) = command {
+        return cmd::serve::run(ctx, args);
+    }
+    run_with_context(&ctx, command)
+}
+
+/// Runs a command that only borrows the context, so `batch-exec` can run
+/// many with the same one
+fn run_with_context(ctx: &Context, command: Command) -> Result<()> {
+    match command {
+        Command::Recipe(args) => cmd::recipe::run(ctx, args),
+        Command::List(args) => cmd::list::run(ctx, args),
         #[cfg(feature = "serve")]
-        Command::Serve(args) => cmd::serve::run(ctx, args),
-        Command::ShoppingList(args) => cmd::shopping_list::run(&ctx, args),
+        Command::Serve(_) => bail!("`serve` can't run in a batch"),
+        Command::ShoppingList(args) => cmd::shopping_list::run(ctx, args),
         Command::Units(args) => cmd::units::run(ctx.parser()?.converter(), args),
-        Command::Convert(args) => cmd::convert::run(ctx.parser()?.converter(), args),
-        Command::Config(args) => cmd::config::run(&ctx, args),
-        Command::Collection(args) => cmd::collection::run(&ctx, args),
+        Command::Convert(args) => cmd::convert::run(
+            ctx.parser()?.converter(),
+            &ctx.config.unit_preferences,
+            args,
+        ),
+        Command::Config(args) => cmd::config::run(ctx, args),
+        Command::Init(args) => cmd::init::run(ctx, args),
+        Command::Collection(args) => cmd::collection::run(ctx, args),
         Command::GenerateCompletions(args) => cmd::generate_completions::run(args),
-        Command::New(args) => cmd::new::run(args, &ctx),
-        Command::Edit(args) => cmd::edit::run(args, &ctx),
+        Command::New(args) => cmd::new::run(args, ctx),
+        Command::Edit(args) => cmd::edit::run(args, ctx),
+        Command::Query(args) => cmd::query::run(ctx, args),
+        Command::Grep(args) => cmd::grep::run(ctx, args),
+        Command::Lint(args) => cmd::lint::run(ctx, args),
+        Command::Health(args) => cmd::health::run(ctx, args),
+        Command::Fmt(args) => cmd::fmt::run(ctx, args),
+        Command::Blame(args) => cmd::blame::run(ctx, args),
+        Command::Images(args) => cmd::images::run(ctx, args),
+        Command::Stats(args) => cmd::stats::run(ctx, args),
+        #[cfg(feature = "backup")]
+        Command::Backup(args) => cmd::backup::run(ctx, args),
+        Command::Trash(args) => cmd::trash::run(ctx, args),
+        Command::MergeDriver(args) => cmd::merge_driver::run(args),
+        Command::Doctor(args) => cmd::doctor::run(ctx, args),
+        Command::Debug(args) => cmd::debug::run(ctx, args),
+        Command::BatchExec(args) => cmd::batch_exec::run(ctx, args),
     }
 }
 
@@ -109,9 +165,7 @@ fn configure_context(args: GlobalArgs, color_ctx: ColorContext) -> Result<Contex
     };
     config.override_with_args(&args);
 
-    let recipe_index = cooklang_fs::new_index(base_path, config.max_depth)?
-        .config_dir(COOK_DIR.to_string())
-        .lazy();
+    let recipe_index = index_builder(base_path, &config)?.lazy();
 
     Ok(Context {
         is_collection: base_path.join(COOK_DIR).is_dir(),
@@ -125,7 +179,20 @@ fn configure_context(args: GlobalArgs, color_ctx: ColorContext) -> Result<Contex
     })
 }
 
-const RECIPE_REF_ERROR: &str = "The name must match exactly except lower and upper case.";
+fn index_builder(base_path: &Utf8Path, config: &Config) -> Result<cooklang_fs::FsIndexBuilder> {
+    let builder = cooklang_fs::new_index(base_path, config.max_depth)?
+        .config_dir(COOK_DIR.to_string())
+        .non_utf8(config.non_utf8_paths)
+        .markdown(config.markdown)
+        .follow_links(config.follow_links)
+        .name_matching(config.name_matching);
+    let aliases = config::load_aliases(base_path)?;
+    let builder = builder.aliases(aliases.iter().map(|(a, p)| (a.as_str(), p.as_path())));
+    Ok(builder)
+}
+
+const RECIPE_REF_ERROR: &str =
+    "The name must match exactly except lower and upper case, and accents with `name_matching = \"ignore_accents\"`.";
 
 impl Context {
     fn parser(&self) -> Result<&CooklangParser> {
@@ -136,20 +203,15 @@ impl Context {
     fn checker(
         &self,
         relative_to: Option<&Utf8Path>,
-    ) -> Option<cooklang::analysis::RecipeRefCheck> {
+    ) -> Option<cooklang::analysis::RecipeRefCheck<'_>> {
         if self.config.recipe_ref_check {
-            let relative_to = relative_to.map(|r| {
-                r.to_path_buf()
-                    .parent()
-                    .expect("no parent for recipe entry")
-                    .to_owned()
-            });
+            let relative_to = relative_to.map(Utf8Path::to_path_buf);
             Some(Box::new(move |name: &str| {
-                if self
-                    .recipe_index
-                    .resolve(name, relative_to.as_deref())
-                    .is_ok()
-                {
+                let res = match &relative_to {
+                    Some(recipe) => self.recipe_index.resolve_relative(recipe, name),
+                    None => self.recipe_index.resolve(name, None),
+                };
+                if res.is_ok() {
                     cooklang::analysis::CheckResult::Ok
                 } else {
                     cooklang::analysis::CheckResult::Warning(vec![RECIPE_REF_ERROR.into()])
@@ -160,12 +222,77 @@ impl Context {
         }
     }
 
-    fn parse_options(&self, relative_to: Option<&Utf8Path>) -> ParseOptions {
+    /// Locks the collection before modifying it
+    ///
+    /// Returns `None` when not running in a collection, there is nothing to
+    /// protect.
+    fn lock_collection(&self) -> Result<Option<cooklang_fs::CollectionLock>> {
+        if !self.is_collection {
+            return Ok(None);
+        }
+        let lock = cooklang_fs::CollectionLock::acquire(&self.base_path.join(COOK_DIR))?;
+        Ok(Some(lock))
+    }
+
+    /// Loads the stored index, see `collection reindex`
+    ///
+    /// If it's corrupted, it's rebuilt and stored again.
+    fn load_stored_index(&self) -> Result<cooklang_fs::FsIndex> {
+        let (index, rebuilt) = self.stored_index()?;
+        if rebuilt {
+            let _lock = self.lock_collection()?;
+            index.save(&index_file_path(&self.base_path))?;
+        }
+        Ok(index)
+    }
+
+    /// Adds a new recipe to the stored index, if there is one
+    ///
+    /// Call it with the collection locked, so the index and the recipes don't
+    /// go out of sync.
+    fn add_to_stored_index(&self, path: &Utf8Path) -> Result<()> {
+        let file = index_file_path(&self.base_path);
+        if !self.is_collection || !file.is_file() {
+            return Ok(());
+        }
+        let (mut index, _) = self.stored_index()?;
+        index.insert(path)?;
+        index.save(&file)?;
+        Ok(())
+    }
+
+    fn stored_index(&self) -> Result<(cooklang_fs::FsIndex, bool)> {
+        let file = index_file_path(&self.base_path);
+        match index_builder(&self.base_path, &self.config)?.load(&file) {
+            Err(cooklang_fs::Error::IndexFile(e)) => {
+                tracing::warn!("The stored index is corrupted, rebuilding it: {e}");
+                let index = index_builder(&self.base_path, &self.config)?.indexed()?;
+                Ok((index, true))
+            }
+            res => Ok((res?, false)),
+        }
+    }
+
+    fn parse_options(&self, relative_to: Option<&Utf8Path>) -> ParseOptions<'_> {
         ParseOptions {
             recipe_ref_check: self.checker(relative_to),
             metadata_validator: Some(Box::new(metadata_validator)),
         }
     }
+
+    /// Context of another collection with the same args
+    ///
+    /// The collection uses its own config, `--config-file` is ignored.
+    #[cfg(feature = "serve")]
+    fn for_collection(&self, path: &Utf8Path) -> Result<Context> {
+        let mut args = self.global_args.clone();
+        args.path = Some(path.to_owned());
+        args.config_file = None;
+        let color = ColorContext {
+            color_stderr: self.color.color_stderr,
+        };
+        configure_context(args, color)
+    }
 }
 
 #[tracing::instrument(level = "debug", skip_all)]
@@ -0,0 +1,139 @@
+//! Parse performance metrics
+//!
+//! Allocation counts are only available with the `alloc-metrics` feature,
+//! which installs a counting global allocator.
+
+use std::time::{Duration, Instant};
+
+use cooklang::{CooklangParser, ParseOptions, RecipeResult};
+use serde::Serialize;
+
+/// Metrics of a single parse
+#[derive(Debug, Clone, Serialize)]
+pub struct ParseMetrics {
+    /// Input length in bytes
+    pub input_len: usize,
+    /// Number of events generated by the parser
+    pub events: usize,
+    /// Tokenizing and parsing into events
+    ///
+    /// The lexer is driven by the parser, so both are measured together.
+    pub parse: Phase,
+    /// Analysis of the events into a recipe
+    pub analysis: Phase,
+    pub total: Phase,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Phase {
+    #[serde(rename = "duration_us", serialize_with = "micros")]
+    pub duration: Duration,
+    /// `None` without the `alloc-metrics` feature
+    pub allocations: Option<u64>,
+    pub allocated_bytes: Option<u64>,
+}
+
+fn micros<S: serde::Serializer>(d: &Duration, se: S) -> Result<S::Ok, S::Error> {
+    se.serialize_f64(d.as_secs_f64() * 1e6)
+}
+
+struct Mark {
+    instant: Instant,
+    alloc: Option<(u64, u64)>,
+}
+
+impl Mark {
+    fn now() -> Self {
+        Self {
+            alloc: alloc::snapshot(),
+            instant: Instant::now(),
+        }
+    }
+
+    fn phase(&self) -> Phase {
+        let duration = self.instant.elapsed();
+        let alloc = alloc::snapshot().zip(self.alloc).map(
+            |((count, bytes), (start_count, start_bytes))| {
+                (count - start_count, bytes - start_bytes)
+            },
+        );
+        Phase {
+            duration,
+            allocations: alloc.map(|a| a.0),
+            allocated_bytes: alloc.map(|a| a.1),
+        }
+    }
+}
+
+/// Parses a recipe like [`CooklangParser::parse_with_options`] measuring
+/// every phase
+pub fn measure_parse(
+    parser: &CooklangParser,
+    input: &str,
+    options: ParseOptions,
+) -> (RecipeResult, ParseMetrics) {
+    let total = Mark::now();
+
+    let mark = Mark::now();
+    let events = cooklang::parser::PullParser::new(input, parser.extensions()).collect::<Vec<_>>();
+    let parse = mark.phase();
+    let n_events = events.len();
+
+    let mark = Mark::now();
+    let result = cooklang::analysis::parse_events(
+        events.into_iter(),
+        input,
+        parser.extensions(),
+        parser.converter(),
+        options,
+    );
+    let analysis = mark.phase();
+
+    let metrics = ParseMetrics {
+        input_len: input.len(),
+        events: n_events,
+        parse,
+        analysis,
+        total: total.phase(),
+    };
+    (result, metrics)
+}
+
+#[cfg(feature = "alloc-metrics")]
+mod alloc {
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        sync::atomic::{AtomicU64, Ordering},
+    };
+
+    struct CountingAllocator;
+
+    static COUNT: AtomicU64 = AtomicU64::new(0);
+    static BYTES: AtomicU64 = AtomicU64::new(0);
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            COUNT.fetch_add(1, Ordering::Relaxed);
+            BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: CountingAllocator = CountingAllocator;
+
+    pub fn snapshot() -> Option<(u64, u64)> {
+        Some((COUNT.load(Ordering::Relaxed), BYTES.load(Ordering::Relaxed)))
+    }
+}
+
+#[cfg(not(feature = "alloc-metrics"))]
+mod alloc {
+    pub fn snapshot() -> Option<(u64, u64)> {
+        None
+    }
+}
@@ -0,0 +1,160 @@
+//! Units the ingredients are bought in, like a dozen eggs or a 250 g block of
+//! butter
+//!
+//! They are loaded from `.cooklang/purchase.toml`:
+//!
+//! ```toml
+//! eggs = { unit = "dozen", size = 12 }
+//! butter = { unit = "block", size = "250 g" }
+//! ```
+
+use std::collections::HashMap;
+
+use anyhow::{Context as _, Result};
+use camino::Utf8Path;
+use cooklang::{
+    quantity::{GroupedQuantity, Number, Value},
+    Converter,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::compound;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PurchaseUnit {
+    /// Name of the package
+    pub unit: String,
+    /// Plural of [`Self::unit`], by default with an `s` at the end
+    #[serde(default)]
+    pub plural: Option<String>,
+    pub size: PackageSize,
+}
+
+/// What a package has inside
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum PackageSize {
+    /// Number of items, for ingredients without unit
+    Count(f64),
+    /// Quantity with a unit, like `250 g`
+    Quantity(String),
+}
+
+/// The purchase units of the ingredients, by name
+pub struct Purchases<'c> {
+    units: HashMap<String, PurchaseUnit>,
+    converter: &'c Converter,
+}
+
+/// Packages to buy of an ingredient
+#[derive(Debug, Serialize)]
+pub struct Purchase {
+    pub count: u32,
+    pub unit: String,
+    /// What is not used of the last package
+    pub leftover: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leftover_unit: Option<String>,
+}
+
+impl<'c> Purchases<'c> {
+    pub fn empty(converter: &'c Converter) -> Self {
+        Self {
+            units: HashMap::new(),
+            converter,
+        }
+    }
+
+    pub fn load(path: &Utf8Path, converter: &'c Converter) -> Result<Self> {
+        let content = std::fs::read_to_string(path).context("Failed to read purchase file")?;
+        Self::parse(&content, converter).with_context(|| format!("Invalid purchase file: {path}"))
+    }
+
+    /// Parses the content of a purchase file
+    pub fn parse(content: &str, converter: &'c Converter) -> Result<Self> {
+        let units: HashMap<String, PurchaseUnit> = toml::from_str(content)?;
+        for (name, unit) in &units {
+            if let PackageSize::Quantity(size) = &unit.size {
+                compound::parse(size, converter)
+                    .with_context(|| format!("Invalid size for '{name}'"))?;
+            }
+        }
+        let units = units
+            .into_iter()
+            .map(|(name, unit)| (name.to_lowercase(), unit))
+            .collect();
+        Ok(Self { units, converter })
+    }
+
+    /// Packages needed for the quantity of an ingredient
+    ///
+    /// `None` if the ingredient has no purchase unit or some of the
+    /// quantities can't be converted to it.
+    pub fn get(&self, name: &str, quantity: &GroupedQuantity) -> Option<Purchase> {
+        let purchase = self.units.get(&name.to_lowercase())?;
+        let (size, size_unit) = match &purchase.size {
+            PackageSize::Count(n) => (*n, None),
+            PackageSize::Quantity(s) => {
+                let (value, unit) = compound::parse(s, self.converter).ok()?;
+                (value, Some(unit))
+            }
+        };
+        if size <= 0.0 {
+            return None;
+        }
+
+        let mut total = 0.0;
+        for q in quantity.iter() {
+            let Value::Number(n) = &q.value else {
+                return None;
+            };
+            match (q.unit_text(), &size_unit) {
+                (None, None) => total += n.value(),
+                (Some(unit), Some(size_unit)) => {
+                    let from = self.converter.find_unit(unit)?;
+                    let to = self.converter.find_unit(size_unit)?;
+                    if from.physical_quantity != to.physical_quantity {
+                        return None;
+                    }
+                    total += n.value() * from.ratio / to.ratio;
+                }
+                _ => return None,
+            }
+        }
+        if total <= 0.0 {
+            return None;
+        }
+
+        let count = (total / size).ceil();
+        let leftover = count * size - total;
+        let unit = if count == 1.0 {
+            purchase.unit.clone()
+        } else {
+            purchase
+                .plural
+                .clone()
+                .unwrap_or_else(|| format!("{}s", purchase.unit))
+        };
+        Some(Purchase {
+            count: count as u32,
+            unit,
+            leftover: (leftover * 100.0).round() / 100.0,
+            leftover_unit: size_unit,
+        })
+    }
+}
+
+impl std::fmt::Display for Purchase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.count, self.unit)?;
+        if self.leftover > 0.0 {
+            let leftover = Value::Number(Number::Regular(self.leftover));
+            match &self.leftover_unit {
+                Some(unit) => write!(f, ", {leftover} {unit} left over")?,
+                None => write!(f, ", {leftover} left over")?,
+            }
+        }
+        Ok(())
+    }
+}
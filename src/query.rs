@@ -0,0 +1,242 @@
+//! Small jq-like queries over JSON
+//!
+//! Only paths are supported:
+//! - `.` the whole value
+//! - `.name` or `."name with spaces"` a field of an object
+//! - `[]` every element of an array or value of an object
+//! - `[2]` an element of an array, negative from the end
+//!
+//! Like `.sections[].content[].value`.
+
+use anyhow::{bail, Result};
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Field(String),
+    Iterate,
+    Index(i64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query(Vec<Segment>);
+
+impl Query {
+    pub fn parse(expr: &str) -> Result<Self> {
+        let expr = expr.trim();
+        if !expr.starts_with('.') {
+            bail!("A query starts with '.'");
+        }
+        let mut segments = Vec::new();
+        let mut rest = expr;
+        while !rest.is_empty() {
+            let pos = expr.len() - rest.len();
+            if let Some(r) = rest.strip_prefix('[') {
+                let Some((inside, r)) = r.split_once(']') else {
+                    bail!("Unclosed '[' at {pos}");
+                };
+                let inside = inside.trim();
+                if inside.is_empty() {
+                    segments.push(Segment::Iterate);
+                } else {
+                    let Ok(index) = inside.parse() else {
+                        bail!("Invalid index '{inside}' at {pos}");
+                    };
+                    segments.push(Segment::Index(index));
+                }
+                rest = r;
+            } else if let Some(r) = rest.strip_prefix(".\"") {
+                let Some((field, r)) = r.split_once('"') else {
+                    bail!("Unclosed '\"' at {pos}");
+                };
+                segments.push(Segment::Field(field.to_string()));
+                rest = r;
+            } else if let Some(r) = rest.strip_prefix('.') {
+                let end = r
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .unwrap_or(r.len());
+                if end > 0 {
+                    segments.push(Segment::Field(r[..end].to_string()));
+                } else if !(r.is_empty() || r.starts_with('[')) {
+                    bail!("Expected a field name at {}", pos + 1);
+                }
+                rest = &r[end..];
+            } else {
+                bail!("Unexpected '{}' at {pos}", rest.chars().next().unwrap());
+            }
+        }
+        Ok(Self(segments))
+    }
+
+    /// Runs the query, it may give any number of values
+    pub fn run<'a>(&self, value: &'a Value) -> Result<Vec<&'a Value>> {
+        let mut values = vec![value];
+        for segment in &self.0 {
+            let mut next = Vec::new();
+            for v in values {
+                match (segment, v) {
+                    (Segment::Field(field), Value::Object(map)) => {
+                        next.push(map.get(field).unwrap_or(&Value::Null))
+                    }
+                    (Segment::Field(_), Value::Null) => next.push(&Value::Null),
+                    (Segment::Field(field), _) => {
+                        bail!("Can't get '{field}' of {}", type_name(v))
+                    }
+                    (Segment::Iterate, Value::Array(items)) => next.extend(items),
+                    (Segment::Iterate, Value::Object(map)) => next.extend(map.values()),
+                    (Segment::Iterate, _) => bail!("Can't iterate over {}", type_name(v)),
+                    (&Segment::Index(index), Value::Array(items)) => {
+                        let index = if index < 0 {
+                            items.len().checked_sub(index.unsigned_abs() as usize)
+                        } else {
+                            Some(index as usize)
+                        };
+                        next.push(index.and_then(|i| items.get(i)).unwrap_or(&Value::Null));
+                    }
+                    (Segment::Index(_), Value::Null) => next.push(&Value::Null),
+                    (Segment::Index(_), _) => bail!("Can't index {}", type_name(v)),
+                }
+            }
+            values = next;
+        }
+        Ok(values)
+    }
+}
+
+/// A [`Query`] to select values, like `.tags[] == "dinner"`
+///
+/// A value matches when any result of the query is equal to the one after
+/// `==`, which is JSON or else a plain string. Without it, when any result is
+/// not `null` or `false`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    query: Query,
+    value: Option<Value>,
+}
+
+impl Filter {
+    pub fn parse(expr: &str) -> Result<Self> {
+        let (query, value) = match split_eq(expr) {
+            Some((query, value)) => {
+                let value = value.trim();
+                let value = serde_json::from_str(value)
+                    .unwrap_or_else(|_| Value::String(value.to_string()));
+                (query, Some(value))
+            }
+            None => (expr, None),
+        };
+        Ok(Self {
+            query: Query::parse(query)?,
+            value,
+        })
+    }
+
+    /// Values the query can't run on don't match
+    pub fn matches(&self, value: &Value) -> bool {
+        let Ok(results) = self.query.run(value) else {
+            return false;
+        };
+        results.into_iter().any(|r| match &self.value {
+            Some(expected) => r == expected,
+            None => !matches!(r, Value::Null | Value::Bool(false)),
+        })
+    }
+}
+
+/// Splits at the first `==` not inside a quoted field
+fn split_eq(expr: &str) -> Option<(&str, &str)> {
+    let mut quoted = false;
+    for (i, c) in expr.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '=' if !quoted && expr[i..].starts_with("==") => {
+                return Some((&expr[..i], &expr[i + 2..]))
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse() {
+        use Segment::*;
+        assert_eq!(Query::parse(".").unwrap(), Query(vec![]));
+        assert_eq!(
+            Query::parse(".sections[].content[0].\"a b\"").unwrap(),
+            Query(vec![
+                Field("sections".into()),
+                Iterate,
+                Field("content".into()),
+                Index(0),
+                Field("a b".into()),
+            ])
+        );
+        assert_eq!(Query::parse(".[-1]").unwrap(), Query(vec![Index(-1)]));
+        assert!(Query::parse("name").is_err());
+        assert!(Query::parse(".a[").is_err());
+        assert!(Query::parse(".a[x]").is_err());
+        assert!(Query::parse(".a..b").is_err());
+    }
+
+    #[test]
+    fn run() {
+        let value = json!({
+            "name": "Bread",
+            "ingredients": [{ "name": "flour" }, { "name": "water" }],
+        });
+        let q = |e| Query::parse(e).unwrap().run(&value).unwrap();
+        assert_eq!(q(".name"), [&json!("Bread")]);
+        assert_eq!(q(".ingredients[].name"), [&json!("flour"), &json!("water")]);
+        assert_eq!(q(".ingredients[-1].name"), [&json!("water")]);
+        assert_eq!(q(".ingredients[5].name"), [&Value::Null]);
+        assert_eq!(q(".missing"), [&Value::Null]);
+        assert!(Query::parse(".name[]").unwrap().run(&value).is_err());
+    }
+
+    #[test]
+    fn filter() {
+        let value = json!({
+            "name": "Bread",
+            "tags": ["baking", "easy"],
+            "servings": [4, 8],
+            "emoji": null,
+            "error": false,
+            "a == b": "x",
+        });
+        let m = |e| Filter::parse(e).unwrap().matches(&value);
+        assert!(m(".tags[] == \"easy\""));
+        assert!(m(".tags[] == easy"));
+        assert!(m(" .tags[]  ==  easy "));
+        assert!(!m(".tags[] == dinner"));
+        assert!(m(".servings[] == 8"));
+        assert!(!m(".servings[] == \"8\""));
+        assert!(m(".error == false"));
+        assert!(m(".name"));
+        assert!(m(".tags[]"));
+        assert!(!m(".emoji"));
+        assert!(!m(".error"));
+        assert!(!m(".missing"));
+        assert!(m(".\"a == b\" == x"));
+        // can't index a string
+        assert!(!m(".name[0]"));
+        assert!(Filter::parse("tag:dinner").is_err());
+        assert!(Filter::parse(".tags[ == x").is_err());
+    }
+}
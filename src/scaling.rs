@@ -0,0 +1,422 @@
+use anyhow::{bail, Result};
+use cooklang::{
+    quantity::{Number, ScalableValue, Value},
+    scale::ScaleOutcome,
+    Converter, ScalableRecipe, ScaledRecipe,
+};
+use serde::{Deserialize, Serialize};
+
+/// What to do with quantities that are text, like `a pinch`, when they have
+/// to be scaled or added together
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum TextQuantities {
+    /// Fail
+    Error,
+    /// Keep the text as is
+    #[default]
+    KeepText,
+    /// Remove the quantity, keep the ingredient
+    Drop,
+}
+
+/// Scales the recipe to `target` servings
+///
+/// With `interpolate`, the values per servings are first interpolated for
+/// servings that they don't have, see [`interpolate_by_servings`].
+pub fn scale(
+    mut recipe: ScalableRecipe,
+    target: u32,
+    interpolate: bool,
+    converter: &Converter,
+) -> ScaledRecipe {
+    if interpolate {
+        interpolate_by_servings(&mut recipe, target);
+    }
+    recipe.scale(target, converter)
+}
+
+/// Gives the values per servings, like `{2|4|6}`, a value for `target`
+///
+/// When `target` is not one of the declared servings, the value is linear
+/// between the closest declared servings and it's fixed so it's not scaled
+/// again. Out of the declared servings it goes on from the two closest ones,
+/// or from zero if there is only one. Text values are left as they are.
+pub fn interpolate_by_servings(recipe: &mut ScalableRecipe, target: u32) {
+    let Some(servings) = recipe.metadata.servings().map(<[u32]>::to_vec) else {
+        return;
+    };
+    if servings.contains(&target) {
+        return;
+    }
+    let ingredients = recipe
+        .ingredients
+        .iter_mut()
+        .filter_map(|igr| igr.quantity.as_mut().map(|q| &mut q.value));
+    let cookware = recipe
+        .cookware
+        .iter_mut()
+        .filter_map(|cw| cw.quantity.as_mut());
+    for value in ingredients.chain(cookware) {
+        let ScalableValue::ByServings(values) = value else {
+            continue;
+        };
+        if let Some(interpolated) = interpolate_value(&servings, values, target) {
+            *value = ScalableValue::Fixed(interpolated);
+        }
+    }
+}
+
+fn interpolate_value(servings: &[u32], values: &[Value], target: u32) -> Option<Value> {
+    if servings.len() != values.len() {
+        return None;
+    }
+    let line = |get: fn(&Value) -> Option<f64>| {
+        let points = servings
+            .iter()
+            .zip(values)
+            .map(|(&s, v)| Some((s as f64, get(v)?)))
+            .collect::<Option<Vec<_>>>()?;
+        interpolate(&points, target as f64).map(Number::Regular)
+    };
+    match values.first()? {
+        Value::Number(_) => Some(Value::Number(line(|v| match v {
+            Value::Number(n) => Some(n.value()),
+            _ => None,
+        })?)),
+        Value::Range { .. } => Some(Value::Range {
+            start: line(|v| match v {
+                Value::Range { start, .. } => Some(start.value()),
+                _ => None,
+            })?,
+            end: line(|v| match v {
+                Value::Range { end, .. } => Some(end.value()),
+                _ => None,
+            })?,
+        }),
+        Value::Text(_) => None,
+    }
+}
+
+/// `y` at `x` on the line of the two closest points, never negative
+fn interpolate(points: &[(f64, f64)], x: f64) -> Option<f64> {
+    let mut points = points.to_vec();
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let (a, b) = match points.as_slice() {
+        [] => return None,
+        [(x0, y0)] if *x0 == 0.0 => return Some(*y0),
+        [(x0, y0)] => return Some(y0 * x / x0),
+        _ => {
+            let i = points
+                .partition_point(|p| p.0 < x)
+                .clamp(1, points.len() - 1);
+            (points[i - 1], points[i])
+        }
+    };
+    if a.0 == b.0 {
+        return Some(a.1);
+    }
+    Some((a.1 + (b.1 - a.1) * (x - a.0) / (b.0 - a.0)).max(0.0))
+}
+
+/// Applies the policy to the ingredients that could not be scaled
+///
+/// Also rounds up the scaled cookware counts, half a pan is still a pan.
+/// Does nothing if the recipe was not scaled.
+pub fn check_scaled(recipe: &mut ScaledRecipe, name: &str, policy: TextQuantities) -> Result<()> {
+    let Some(data) = recipe.scaled_data() else {
+        return Ok(());
+    };
+    let scaled_cookware = data
+        .cookware
+        .iter()
+        .enumerate()
+        .filter(|(_, outcome)| matches!(outcome, ScaleOutcome::Scaled))
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    let failed = data
+        .ingredients
+        .iter()
+        .enumerate()
+        .filter(|(_, outcome)| matches!(outcome, ScaleOutcome::Error(_)))
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    for index in scaled_cookware {
+        if let Some(value) = &mut recipe.cookware[index].quantity {
+            round_up(value);
+        }
+    }
+    apply(recipe, name, policy, &failed)
+}
+
+fn round_up(value: &mut Value) {
+    let ceil = |n: &mut Number| *n = Number::Regular(n.value().ceil());
+    match value {
+        Value::Number(n) => ceil(n),
+        Value::Range { start, end } => {
+            ceil(start);
+            ceil(end);
+        }
+        Value::Text(_) => {}
+    }
+}
+
+/// Applies the policy to the ingredients with a text quantity
+///
+/// Used before merging the ingredients of a recipe with others.
+pub fn check_text(recipe: &mut ScaledRecipe, name: &str, policy: TextQuantities) -> Result<()> {
+    let text = recipe
+        .ingredients
+        .iter()
+        .enumerate()
+        .filter(|(_, igr)| {
+            igr.quantity
+                .as_ref()
+                .is_some_and(|q| matches!(q.value, Value::Text(_)))
+        })
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    apply(recipe, name, policy, &text)
+}
+
+fn apply(
+    recipe: &mut ScaledRecipe,
+    name: &str,
+    policy: TextQuantities,
+    indices: &[usize],
+) -> Result<()> {
+    if indices.is_empty() {
+        return Ok(());
+    }
+    match policy {
+        TextQuantities::KeepText => {}
+        TextQuantities::Drop => {
+            for &index in indices {
+                recipe.ingredients[index].quantity = None;
+            }
+        }
+        TextQuantities::Error => {
+            let list = indices
+                .iter()
+                .map(|&i| {
+                    let igr = &recipe.ingredients[i];
+                    match &igr.quantity {
+                        Some(q) => format!("'{}' ({q})", igr.name),
+                        None => format!("'{}'", igr.name),
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            bail!(
+                "Text quantities in '{name}': {list}. Use the `text_quantities` option to keep or drop them"
+            );
+        }
+    }
+    Ok(())
+}
+
+/// How every ingredient and cookware item of a recipe was scaled
+#[derive(Debug, Serialize)]
+pub struct ScaleReport {
+    /// Servings declared in the recipe
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub servings: Option<Vec<u32>>,
+    pub target: u32,
+    pub ingredients: Vec<ItemScale>,
+    pub cookware: Vec<ItemScale>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ItemScale {
+    pub index: usize,
+    pub name: String,
+    pub outcome: ScaleKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScaleKind {
+    /// Multiplied by the scale factor
+    Linear,
+    /// Marked to not scale
+    Fixed,
+    /// Picked from a value per servings, like `{2|4|6}`
+    ByServings,
+    /// Interpolated from the values per servings
+    Interpolated,
+    /// Nothing to scale
+    NoQuantity,
+    /// Could not be scaled, the original quantity is kept
+    Error,
+}
+
+/// Items with a value per servings, see [`ScaleReport::by_servings`]
+#[derive(Debug, Default)]
+pub struct ByServings {
+    pub ingredients: Vec<bool>,
+    pub cookware: Vec<bool>,
+}
+
+impl ScaleReport {
+    /// Marks the ingredients and cookware with a value per servings
+    ///
+    /// Scaling loses this information, so it has to be called before.
+    pub fn by_servings(recipe: &ScalableRecipe) -> ByServings {
+        let is_by_servings =
+            |v: Option<&ScalableValue>| matches!(v, Some(ScalableValue::ByServings(_)));
+        ByServings {
+            ingredients: recipe
+                .ingredients
+                .iter()
+                .map(|igr| is_by_servings(igr.quantity.as_ref().map(|q| &q.value)))
+                .collect(),
+            cookware: recipe
+                .cookware
+                .iter()
+                .map(|cw| is_by_servings(cw.quantity.as_ref()))
+                .collect(),
+        }
+    }
+
+    /// Builds the report, `None` if the recipe was not scaled
+    pub fn new(recipe: &ScaledRecipe, by_servings: &ByServings) -> Option<Self> {
+        let data = recipe.scaled_data()?;
+        Some(Self {
+            servings: recipe.metadata.servings().map(<[u32]>::to_vec),
+            target: data.target.target_servings(),
+            ingredients: item_scales(&data.ingredients, &by_servings.ingredients, |i| {
+                recipe.ingredients[i].display_name().to_string()
+            }),
+            cookware: item_scales(&data.cookware, &by_servings.cookware, |i| {
+                recipe.cookware[i].display_name().to_string()
+            }),
+        })
+    }
+
+    /// Writes the report as footnotes for the human output
+    ///
+    /// Only the items that were not scaled linearly are listed.
+    pub fn write_human(&self, w: &mut impl std::io::Write) -> std::io::Result<()> {
+        use owo_colors::OwoColorize;
+
+        let from = match self.servings.as_deref() {
+            Some([base, ..]) => format!("{base} \u{2192} "),
+            _ => String::new(),
+        };
+        writeln!(w, "{} {from}{} servings", "Scaling:".bold(), self.target)?;
+        for item in self.ingredients.iter().chain(&self.cookware) {
+            let line = match item.outcome {
+                ScaleKind::Linear | ScaleKind::NoQuantity => continue,
+                ScaleKind::Fixed => format!("{}: fixed, not scaled", item.name)
+                    .yellow()
+                    .to_string(),
+                ScaleKind::ByServings => format!("{}: picked for the servings", item.name),
+                ScaleKind::Interpolated => format!("{}: interpolated for the servings", item.name)
+                    .yellow()
+                    .to_string(),
+                ScaleKind::Error => format!(
+                    "{}: not scaled, {}",
+                    item.name,
+                    item.error.as_deref().unwrap_or("error")
+                )
+                .red()
+                .to_string(),
+            };
+            writeln!(w, "  - {line}")?;
+        }
+        Ok(())
+    }
+}
+
+fn item_scales(
+    outcomes: &[ScaleOutcome],
+    by_servings: &[bool],
+    name: impl Fn(usize) -> String,
+) -> Vec<ItemScale> {
+    outcomes
+        .iter()
+        .enumerate()
+        .map(|(index, outcome)| {
+            let (outcome, error) = match outcome {
+                ScaleOutcome::Scaled if by_servings.get(index) == Some(&true) => {
+                    (ScaleKind::ByServings, None)
+                }
+                ScaleOutcome::Scaled => (ScaleKind::Linear, None),
+                // only an interpolated value per servings ends up fixed
+                ScaleOutcome::Fixed if by_servings.get(index) == Some(&true) => {
+                    (ScaleKind::Interpolated, None)
+                }
+                ScaleOutcome::Fixed => (ScaleKind::Fixed, None),
+                ScaleOutcome::NoQuantity => (ScaleKind::NoQuantity, None),
+                ScaleOutcome::Error(e) => (ScaleKind::Error, Some(e.to_string())),
+            };
+            ItemScale {
+                index,
+                name: name(index),
+                outcome,
+                error,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolation() {
+        let points = [(2.0, 100.0), (6.0, 250.0), (4.0, 200.0)];
+        assert_eq!(interpolate(&points, 5.0), Some(225.0));
+        assert_eq!(interpolate(&points, 8.0), Some(300.0));
+        assert_eq!(interpolate(&points, 1.0), Some(50.0));
+        assert_eq!(interpolate(&[(2.0, 100.0)], 3.0), Some(150.0));
+        assert_eq!(interpolate(&[(2.0, 100.0), (4.0, 20.0)], 6.0), Some(0.0));
+        assert_eq!(interpolate(&[], 3.0), None);
+
+        let n = |n| Value::Number(Number::Regular(n));
+        assert_eq!(
+            interpolate_value(&[2, 4], &[n(1.0), n(2.0)], 3),
+            Some(n(1.5))
+        );
+        let range = |start, end| Value::Range {
+            start: Number::Regular(start),
+            end: Number::Regular(end),
+        };
+        assert_eq!(
+            interpolate_value(&[2, 4], &[range(1.0, 2.0), range(3.0, 4.0)], 3),
+            Some(range(2.0, 3.0))
+        );
+        // mixed or text values are not interpolated
+        assert_eq!(
+            interpolate_value(&[2, 4], &[n(1.0), range(1.0, 2.0)], 3),
+            None
+        );
+        assert_eq!(
+            interpolate_value(&[2, 4], &[Value::Text("a".into()), n(1.0)], 3),
+            None
+        );
+        assert_eq!(interpolate_value(&[2, 4, 6], &[n(1.0), n(2.0)], 3), None);
+    }
+
+    #[test]
+    fn round_up_cookware() {
+        let mut v = Value::Number(Number::Regular(1.5));
+        round_up(&mut v);
+        assert_eq!(v, Value::Number(Number::Regular(2.0)));
+        let mut v = Value::Range {
+            start: Number::Regular(1.0),
+            end: Number::Regular(2.25),
+        };
+        round_up(&mut v);
+        assert_eq!(
+            v,
+            Value::Range {
+                start: Number::Regular(1.0),
+                end: Number::Regular(3.0)
+            }
+        );
+    }
+}
@@ -0,0 +1,270 @@
+//! Recurring tasks run by `chef serve`
+//!
+//! Configured in the `[[schedule]]` array of the config. Each entry has a
+//! cron expression in `at` and a `task`. Times are in UTC.
+
+// only `serve` runs the tasks
+#![cfg_attr(not(feature = "serve"), allow(dead_code))]
+
+use camino::Utf8PathBuf;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleEntry {
+    pub at: Cron,
+    #[serde(flatten)]
+    pub task: Task,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "task", rename_all = "kebab-case")]
+pub enum Task {
+    /// Walk the collection again, and store the index with `serve --network`
+    Reindex,
+    /// Log the images that don't belong to any recipe
+    Orphans,
+    /// Write every recipe as Markdown to a dir, relative to the collection
+    Export { dir: Utf8PathBuf },
+    /// Commit every change of the collection to git
+    GitCommit {
+        #[serde(default = "default_commit_message")]
+        message: String,
+    },
+    /// Any program and its args, run in the collection dir
+    Command { command: Vec<String> },
+}
+
+fn default_commit_message() -> String {
+    "Automatic commit by chef".to_string()
+}
+
+impl Task {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Task::Reindex => "reindex",
+            Task::Orphans => "orphans",
+            Task::Export { .. } => "export",
+            Task::GitCommit { .. } => "git-commit",
+            Task::Command { .. } => "command",
+        }
+    }
+}
+
+/// A cron expression: `minute hour day month weekday`
+///
+/// Each field can be `*`, a number, a range `a-b`, a step `*/n` or `a-b/n`,
+/// or a list of them separated by commas. Sunday is `0` or `7`. `@hourly`,
+/// `@daily`, `@weekly` and `@monthly` are also accepted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Cron {
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// When both are restricted, either of them has to match
+    any_day: bool,
+    any_weekday: bool,
+}
+
+/// A time to check a [`Cron`] against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Time {
+    pub minute: u32,
+    pub hour: u32,
+    /// 1-31
+    pub day: u32,
+    /// 1-12
+    pub month: u32,
+    /// 0 is Sunday
+    pub weekday: u32,
+}
+
+impl Time {
+    /// UTC time of seconds since the unix epoch
+    pub fn from_unix(secs: u64) -> Self {
+        let days = (secs / 86400) as i64;
+        let secs_of_day = secs % 86400;
+        // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        Self {
+            minute: (secs_of_day / 60 % 60) as u32,
+            hour: (secs_of_day / 3600) as u32,
+            day: day as u32,
+            month: month as u32,
+            // 1970-01-01 was a Thursday
+            weekday: ((days + 4) % 7) as u32,
+        }
+    }
+}
+
+impl Cron {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let expr = match s.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields = expr.split_whitespace().collect::<Vec<_>>();
+        let &[minutes, hours, days, months, weekdays] = fields.as_slice() else {
+            return Err(format!(
+                "'{s}' must have 5 fields: minute hour day month weekday"
+            ));
+        };
+        let mut weekday_mask = parse_field(weekdays, 0, 7)?;
+        // 7 is also sunday
+        if weekday_mask & (1 << 7) != 0 {
+            weekday_mask = (weekday_mask & !(1 << 7)) | 1;
+        }
+        Ok(Self {
+            source: s.to_string(),
+            minutes: parse_field(minutes, 0, 59)?,
+            hours: parse_field(hours, 0, 23)?,
+            days: parse_field(days, 1, 31)?,
+            months: parse_field(months, 1, 12)?,
+            weekdays: weekday_mask,
+            any_day: days.starts_with('*'),
+            any_weekday: weekdays.starts_with('*'),
+        })
+    }
+
+    pub fn matches(&self, time: &Time) -> bool {
+        let has = |mask: u64, value: u32| mask & (1 << value) != 0;
+        let day = has(self.days, time.day);
+        let weekday = has(self.weekdays, time.weekday);
+        let day_matches = if self.any_day || self.any_weekday {
+            day && weekday
+        } else {
+            day || weekday
+        };
+        has(self.minutes, time.minute)
+            && has(self.hours, time.hour)
+            && has(self.months, time.month)
+            && day_matches
+    }
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let num = |s: &str| {
+        s.parse::<u32>()
+            .map_err(|_| format!("invalid number '{s}' in cron field '{field}'"))
+    };
+    let mut mask = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match num(step)? {
+                0 => return Err(format!("step can't be 0 in cron field '{field}'")),
+                step => (range, step),
+            },
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (num(start)?, num(end)?)
+        } else {
+            let n = num(range)?;
+            // `5/10` is from 5 to the end
+            (n, if step > 1 { max } else { n })
+        };
+        if start < min || end > max || start > end {
+            return Err(format!(
+                "'{part}' is out of range {min}-{max} in cron field '{field}'"
+            ));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+impl TryFrom<String> for Cron {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::parse(&value)
+    }
+}
+
+impl From<Cron> for String {
+    fn from(value: Cron) -> Self {
+        value.source
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(hour: u32, minute: u32, day: u32, weekday: u32) -> Time {
+        Time {
+            minute,
+            hour,
+            day,
+            month: 6,
+            weekday,
+        }
+    }
+
+    #[test]
+    fn unix_time() {
+        assert_eq!(
+            Time::from_unix(0),
+            Time {
+                minute: 0,
+                hour: 0,
+                day: 1,
+                month: 1,
+                weekday: 4
+            }
+        );
+        // 2023-11-14 22:13:20, a tuesday
+        assert_eq!(
+            Time::from_unix(1_700_000_000),
+            Time {
+                minute: 13,
+                hour: 22,
+                day: 14,
+                month: 11,
+                weekday: 2
+            }
+        );
+    }
+
+    #[test]
+    fn cron() {
+        let every_15 = Cron::parse("*/15 * * * *").unwrap();
+        assert!(every_15.matches(&time(3, 30, 1, 0)));
+        assert!(!every_15.matches(&time(3, 31, 1, 0)));
+
+        let weekdays = Cron::parse("0 3 * * 1-5").unwrap();
+        assert!(weekdays.matches(&time(3, 0, 10, 1)));
+        assert!(!weekdays.matches(&time(3, 0, 10, 6)));
+
+        let sunday = Cron::parse("0 0 * * 7").unwrap();
+        assert!(sunday.matches(&time(0, 0, 10, 0)));
+
+        // either the day or the weekday
+        let both = Cron::parse("0 0 1 * 1").unwrap();
+        assert!(both.matches(&time(0, 0, 1, 3)));
+        assert!(both.matches(&time(0, 0, 8, 1)));
+        assert!(!both.matches(&time(0, 0, 8, 3)));
+
+        assert!(Cron::parse("@daily").unwrap().matches(&time(0, 0, 5, 5)));
+        assert!(Cron::parse("61 * * * *").is_err());
+        assert!(Cron::parse("* * *").is_err());
+        assert!(Cron::parse("*/0 * * * *").is_err());
+    }
+}
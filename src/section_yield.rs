@@ -0,0 +1,216 @@
+//! Sections that make more than what the recipe uses
+//!
+//! A section declares what it makes with a `yield.<section>` metadata key,
+//! by name or number, like `>> yield.Sauce: 500 ml`. When the rest of the
+//! recipe uses the section with a quantity, like `@&(=~1)sauce{200%ml}`, only
+//! that part is needed, so the shopping list takes 40% of the ingredients of
+//! the section.
+
+use cooklang::{
+    model::{Content, IngredientReferenceTarget, Item},
+    quantity::{Number, Value},
+    Converter, ScaledRecipe,
+};
+use serde::Serialize;
+
+const YIELD_KEY: &str = "yield.";
+
+/// How much of a section the recipe uses
+#[derive(Debug, Serialize)]
+pub struct SectionYield {
+    /// Section index
+    pub section: usize,
+    /// What the section makes, already scaled
+    pub amount: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    /// Used by the rest of the recipe, in the unit of the yield. `None` if
+    /// it's not known, so all of it is used.
+    pub used: Option<f64>,
+}
+
+impl SectionYield {
+    /// Part of the section ingredients that is needed
+    pub fn factor(&self) -> f64 {
+        match self.used {
+            Some(used) if self.amount > 0.0 => used / self.amount,
+            _ => 1.0,
+        }
+    }
+}
+
+/// Finds the sections with a yield and how much of them is used
+pub fn section_yields(recipe: &ScaledRecipe, converter: &Converter) -> Vec<SectionYield> {
+    let scale = recipe
+        .scaled_data()
+        .map_or(1.0, |data| data.target.factor());
+    let mut yields = Vec::new();
+    for (key, value) in &recipe.metadata.map {
+        let Some(section_key) = key.strip_prefix(YIELD_KEY) else {
+            continue;
+        };
+        let Some(section) = find_section(recipe, section_key) else {
+            tracing::warn!("Unknown section '{section_key}' in '{key}'");
+            continue;
+        };
+        let Some((amount, unit)) = parse_amount(value) else {
+            tracing::warn!("Invalid yield in '{key}': '{value}'");
+            continue;
+        };
+        let used = used(recipe, section, unit.as_deref(), converter);
+        yields.push(SectionYield {
+            section,
+            amount: amount * scale,
+            unit,
+            used,
+        });
+    }
+    yields
+}
+
+/// Reduces the ingredients of the sections to the part that is used
+///
+/// Only for adding the ingredients up, the steps will not match them anymore.
+pub fn apply(recipe: &mut ScaledRecipe, converter: &Converter) -> Vec<SectionYield> {
+    let yields = section_yields(recipe, converter);
+    for y in &yields {
+        let factor = y.factor();
+        if factor == 1.0 {
+            continue;
+        }
+        let indices = section_ingredients(recipe, y.section);
+        for index in indices {
+            if let Some(q) = &mut recipe.ingredients[index].quantity {
+                scale_value(&mut q.value, factor);
+            }
+        }
+    }
+    yields
+}
+
+/// Section by number, starting at 1, or by name ignoring case
+fn find_section(recipe: &ScaledRecipe, key: &str) -> Option<usize> {
+    if let Ok(number) = key.parse::<usize>() {
+        return number
+            .checked_sub(1)
+            .filter(|&index| index < recipe.sections.len());
+    }
+    recipe.sections.iter().position(|s| {
+        s.name
+            .as_deref()
+            .is_some_and(|name| name.eq_ignore_ascii_case(key.trim()))
+    })
+}
+
+/// Parses `500 ml`, `500%ml` or `2`
+fn parse_amount(text: &str) -> Option<(f64, Option<String>)> {
+    let text = text.trim();
+    let (number, unit) = match text.split_once(|c: char| c == '%' || c.is_whitespace()) {
+        Some((number, unit)) => (number, Some(unit.trim().to_string())),
+        None => (text, None),
+    };
+    let amount = number.trim().parse::<f64>().ok()?;
+    Some((amount, unit.filter(|u| !u.is_empty())))
+}
+
+/// Total of the references to a section, in `unit`
+fn used(
+    recipe: &ScaledRecipe,
+    section: usize,
+    unit: Option<&str>,
+    converter: &Converter,
+) -> Option<f64> {
+    let mut total = 0.0;
+    let mut found = false;
+    for igr in &recipe.ingredients {
+        let is_ref = matches!(
+            igr.relation.references_to(),
+            Some((target, IngredientReferenceTarget::Section)) if target == section
+        );
+        if !is_ref {
+            continue;
+        }
+        found = true;
+        let q = igr.quantity.as_ref()?;
+        let Value::Number(n) = &q.value else {
+            return None;
+        };
+        total += n.value() * ratio(q.unit_text(), unit, converter)?;
+    }
+    found.then_some(total)
+}
+
+/// Factor to go from one unit to another
+fn ratio(from: Option<&str>, to: Option<&str>, converter: &Converter) -> Option<f64> {
+    match (from, to) {
+        (None, None) => Some(1.0),
+        (Some(from), Some(to)) if from.eq_ignore_ascii_case(to) => Some(1.0),
+        (Some(from), Some(to)) => {
+            let from = converter.find_unit(from)?;
+            let to = converter.find_unit(to)?;
+            (from.physical_quantity == to.physical_quantity).then_some(from.ratio / to.ratio)
+        }
+        _ => None,
+    }
+}
+
+/// Ingredients used in the steps of a section, without the references to
+/// other sections
+fn section_ingredients(recipe: &ScaledRecipe, section: usize) -> Vec<usize> {
+    let mut indices = Vec::new();
+    for content in &recipe.sections[section].content {
+        let Content::Step(step) = content else {
+            continue;
+        };
+        for item in &step.items {
+            if let &Item::Ingredient { index } = item {
+                let is_section_ref = matches!(
+                    recipe.ingredients[index].relation.references_to(),
+                    Some((_, IngredientReferenceTarget::Section))
+                );
+                if !is_section_ref {
+                    indices.push(index);
+                }
+            }
+        }
+    }
+    indices
+}
+
+fn scale_value(value: &mut Value, factor: f64) {
+    let scale = |n: &mut Number| *n = Number::Regular(n.value() * factor);
+    match value {
+        Value::Number(n) => scale(n),
+        Value::Range { start, end } => {
+            scale(start);
+            scale(end);
+        }
+        Value::Text(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amounts() {
+        assert_eq!(parse_amount("500 ml"), Some((500.0, Some("ml".into()))));
+        assert_eq!(parse_amount("0.5%l"), Some((0.5, Some("l".into()))));
+        assert_eq!(parse_amount(" 2 "), Some((2.0, None)));
+        assert_eq!(parse_amount("a lot"), None);
+    }
+
+    #[test]
+    fn factor() {
+        let y = |used| SectionYield {
+            section: 0,
+            amount: 500.0,
+            unit: Some("ml".into()),
+            used,
+        };
+        assert_eq!(y(Some(200.0)).factor(), 0.4);
+        assert_eq!(y(Some(1000.0)).factor(), 2.0);
+        assert_eq!(y(None).factor(), 1.0);
+    }
+}
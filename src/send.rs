@@ -0,0 +1,127 @@
+use anyhow::{bail, Context as _, Result};
+use serde::{Deserialize, Serialize};
+
+/// Where to send a rendered document
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum SendTarget {
+    /// Email it with the configured SMTP server
+    Email,
+    /// Print it with the configured command
+    Print,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct SendConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<EmailConfig>,
+    pub print: PrintConfig,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct EmailConfig {
+    /// SMTP server host
+    pub server: String,
+    /// SMTP server port. Defaults to the submission port with STARTTLS
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    /// Env var with the password, so it's not stored in the config file
+    #[serde(default = "default_password_env")]
+    pub password_env: String,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+fn default_port() -> u16 {
+    587
+}
+
+fn default_password_env() -> String {
+    "CHEF_SMTP_PASSWORD".to_string()
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct PrintConfig {
+    /// Command that reads the document from stdin and prints it
+    pub command: Vec<String>,
+}
+
+impl Default for PrintConfig {
+    fn default() -> Self {
+        Self {
+            command: vec!["lp".to_string()],
+        }
+    }
+}
+
+/// Sends a plain text document
+pub fn send(config: &SendConfig, target: SendTarget, subject: &str, text: &str) -> Result<()> {
+    match target {
+        SendTarget::Email => {
+            let Some(email) = &config.email else {
+                bail!("No email configured. Add a `send.email` section to the config");
+            };
+            send_email(email, subject, text)
+        }
+        SendTarget::Print => print(&config.print, text),
+    }
+}
+
+#[cfg(feature = "send-email")]
+fn send_email(config: &EmailConfig, subject: &str, text: &str) -> Result<()> {
+    use lettre::{transport::smtp::authentication::Credentials, Message, SmtpTransport, Transport};
+
+    let mut message = Message::builder()
+        .from(config.from.parse().context("Invalid `from` address")?)
+        .subject(subject);
+    for to in &config.to {
+        message = message.to(to
+            .parse()
+            .with_context(|| format!("Invalid `to` address: {to}"))?);
+    }
+    let message = message.body(text.to_string())?;
+
+    let mut transport = SmtpTransport::starttls_relay(&config.server)?.port(config.port);
+    if let Some(username) = &config.username {
+        let password = std::env::var(&config.password_env)
+            .with_context(|| format!("Missing SMTP password in `{}`", config.password_env))?;
+        transport = transport.credentials(Credentials::new(username.clone(), password));
+    }
+    transport
+        .build()
+        .send(&message)
+        .context("Failed to send the email")?;
+    tracing::info!("Sent to {}", config.to.join(", "));
+    Ok(())
+}
+
+#[cfg(not(feature = "send-email"))]
+fn send_email(_: &EmailConfig, _: &str, _: &str) -> Result<()> {
+    bail!("chef was built without email support (`send-email` feature)")
+}
+
+fn print(config: &PrintConfig, text: &str) -> Result<()> {
+    use std::io::Write;
+
+    let Some((program, args)) = config.command.split_first() else {
+        bail!("Empty print command in the config");
+    };
+    let mut child = std::process::Command::new(program)
+        .args(args)
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run print command: {program}"))?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(text.as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("Print command failed: {status}");
+    }
+    Ok(())
+}
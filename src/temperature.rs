@@ -0,0 +1,120 @@
+//! Temperatures of the steps
+//!
+//! With the `TEMPERATURE` extension, temperatures in the text of a step,
+//! like `Bake at 180 °C`, are quantities. This lists them apart, with what is
+//! heated when the step says it, so outputs can show `Oven: 180 °C` first.
+//! They are converted with the rest of the recipe.
+
+use cooklang::{
+    convert::PhysicalQuantity,
+    model::{Content, Item},
+    Converter, Quantity, ScaledRecipe, Value,
+};
+use serde::Serialize;
+
+/// Words of a step and what they heat, the first found is used
+const HEATED: &[(&str, &str)] = &[
+    ("oven", "Oven"),
+    ("preheat", "Oven"),
+    ("bake", "Oven"),
+    ("roast", "Oven"),
+    ("grill", "Grill"),
+    ("fryer", "Fryer"),
+    ("oil", "Oil"),
+    ("water", "Water"),
+    ("pan", "Pan"),
+    ("skillet", "Pan"),
+];
+
+#[derive(Debug, Serialize)]
+pub struct Temperature {
+    /// Section index
+    pub section: usize,
+    /// Step number in the section
+    pub step: u32,
+    /// What is heated, like `Oven`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heated: Option<&'static str>,
+    pub quantity: Quantity<Value>,
+}
+
+/// Inline quantities that are temperatures, in order
+pub fn temperatures(recipe: &ScaledRecipe, converter: &Converter) -> Vec<Temperature> {
+    let mut found = Vec::new();
+    for (section_index, section) in recipe.sections.iter().enumerate() {
+        for content in &section.content {
+            let Content::Step(step) = content else {
+                continue;
+            };
+            let text = step
+                .items
+                .iter()
+                .filter_map(|item| match item {
+                    Item::Text { value } => Some(value.as_str()),
+                    _ => None,
+                })
+                .collect::<String>();
+            for item in &step.items {
+                let &Item::InlineQuantity { index } = item else {
+                    continue;
+                };
+                let quantity = &recipe.inline_quantities[index];
+                let is_temperature = quantity
+                    .unit_text()
+                    .and_then(|u| converter.find_unit(u))
+                    .is_some_and(|u| u.physical_quantity == PhysicalQuantity::Temperature);
+                if is_temperature {
+                    found.push(Temperature {
+                        section: section_index,
+                        step: step.number,
+                        heated: heated(&text),
+                        quantity: quantity.clone(),
+                    });
+                }
+            }
+        }
+    }
+    found
+}
+
+fn heated(text: &str) -> Option<&'static str> {
+    let text = text.to_lowercase();
+    let words = text.split(|c: char| !c.is_alphabetic()).collect::<Vec<_>>();
+    HEATED
+        .iter()
+        .find(|(word, _)| words.contains(word))
+        .map(|&(_, heated)| heated)
+}
+
+/// Writes the temperatures for the human output
+pub fn write_human(
+    temperatures: &[Temperature],
+    w: &mut impl std::io::Write,
+) -> std::io::Result<()> {
+    use owo_colors::OwoColorize;
+
+    writeln!(w, "{}", "Temperatures:".bold())?;
+    for t in temperatures {
+        writeln!(
+            w,
+            "  {}: {} {}",
+            t.heated.unwrap_or("Step"),
+            t.quantity.bright_red(),
+            format!("(section {} step {})", t.section + 1, t.step).dimmed()
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heated_from_text() {
+        assert_eq!(heated("Preheat the oven to "), Some("Oven"));
+        assert_eq!(heated("Heat the oil in a pan to "), Some("Oil"));
+        assert_eq!(heated("Cook in a skillet at "), Some("Pan"));
+        assert_eq!(heated("Panko goes at "), None);
+    }
+}
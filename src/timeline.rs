@@ -0,0 +1,215 @@
+//! Time of the steps from their timers, split in active and passive
+//!
+//! Active time needs the cook, passive time is waiting: resting, baking,
+//! chilling... A timer is passive if it's named `passive`, like
+//! `~passive{1%hour}`, or if its step has a word like `bake` or `rest`. Name
+//! it `active` to override that.
+
+use cooklang::{
+    convert::PhysicalQuantity,
+    model::{Content, Item},
+    quantity::Value,
+    Converter, ScaledRecipe,
+};
+use serde::Serialize;
+
+/// Words of steps where the time is spent waiting
+const PASSIVE_WORDS: &[&str] = &[
+    "bake",
+    "bakes",
+    "baking",
+    "braise",
+    "braising",
+    "chill",
+    "chilling",
+    "cool",
+    "cooling",
+    "ferment",
+    "fermenting",
+    "freeze",
+    "marinate",
+    "marinating",
+    "proof",
+    "proofing",
+    "refrigerate",
+    "rest",
+    "resting",
+    "rests",
+    "rise",
+    "rising",
+    "roast",
+    "roasting",
+    "simmer",
+    "simmering",
+    "soak",
+    "soaking",
+    "steep",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeKind {
+    Active,
+    Passive,
+}
+
+/// Time of a timer in a step
+#[derive(Debug, Serialize)]
+pub struct StepTime {
+    /// Section index
+    pub section: usize,
+    /// Step number in the section
+    pub step: u32,
+    pub kind: TimeKind,
+    pub minutes: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Timeline {
+    pub steps: Vec<StepTime>,
+    pub active_minutes: f64,
+    pub passive_minutes: f64,
+    pub total_minutes: f64,
+}
+
+impl Timeline {
+    /// Timers without a time quantity are ignored
+    pub fn new(recipe: &ScaledRecipe, converter: &Converter) -> Self {
+        let mut steps = Vec::new();
+        for (section_index, section) in recipe.sections.iter().enumerate() {
+            for content in &section.content {
+                let Content::Step(step) = content else {
+                    continue;
+                };
+                let passive_step = step.items.iter().any(|item| match item {
+                    Item::Text { value } => has_passive_word(value),
+                    _ => false,
+                });
+                for item in &step.items {
+                    let &Item::Timer { index } = item else {
+                        continue;
+                    };
+                    let timer = &recipe.timers[index];
+                    let Some(minutes) = timer
+                        .quantity
+                        .as_ref()
+                        .and_then(|q| minutes(&q.value, q.unit_text()?, converter))
+                    else {
+                        continue;
+                    };
+                    let name = timer.name.as_deref().map(str::to_lowercase);
+                    let kind = match name.as_deref() {
+                        Some("passive") => TimeKind::Passive,
+                        Some("active") => TimeKind::Active,
+                        Some(name) if has_passive_word(name) => TimeKind::Passive,
+                        _ if passive_step => TimeKind::Passive,
+                        _ => TimeKind::Active,
+                    };
+                    steps.push(StepTime {
+                        section: section_index,
+                        step: step.number,
+                        kind,
+                        minutes,
+                    });
+                }
+            }
+        }
+        let sum = |kind| {
+            steps
+                .iter()
+                .filter(|s| s.kind == kind)
+                .map(|s| s.minutes)
+                .sum::<f64>()
+        };
+        let active_minutes = sum(TimeKind::Active);
+        let passive_minutes = sum(TimeKind::Passive);
+        Self {
+            steps,
+            active_minutes,
+            passive_minutes,
+            total_minutes: active_minutes + passive_minutes,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Writes the totals and the time of each step
+    pub fn write_human(&self, w: &mut impl std::io::Write) -> std::io::Result<()> {
+        use owo_colors::OwoColorize;
+
+        writeln!(
+            w,
+            "{} {} active, {} passive, {} total",
+            "Time:".bold(),
+            format_minutes(self.active_minutes),
+            format_minutes(self.passive_minutes),
+            format_minutes(self.total_minutes)
+        )?;
+        for s in &self.steps {
+            let kind = match s.kind {
+                TimeKind::Active => "active".green().to_string(),
+                TimeKind::Passive => "passive".cyan().to_string(),
+            };
+            writeln!(
+                w,
+                "  section {} step {}: {} {kind}",
+                s.section + 1,
+                s.step,
+                format_minutes(s.minutes)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn has_passive_word(text: &str) -> bool {
+    text.split(|c: char| !c.is_alphabetic())
+        .any(|word| PASSIVE_WORDS.contains(&word.to_lowercase().as_str()))
+}
+
+/// Minutes of a time quantity, the end of a range
+fn minutes(value: &Value, unit: &str, converter: &Converter) -> Option<f64> {
+    let n = match value {
+        Value::Number(n) => n,
+        Value::Range { end, .. } => end,
+        Value::Text(_) => return None,
+    };
+    let unit = converter.find_unit(unit)?;
+    let minute = converter.find_unit("min")?;
+    if unit.physical_quantity != PhysicalQuantity::Time {
+        return None;
+    }
+    Some(n.value() * unit.ratio / minute.ratio)
+}
+
+pub fn format_minutes(minutes: f64) -> String {
+    let minutes = minutes.round() as u64;
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{m} min"),
+        (h, 0) => format!("{h} h"),
+        (h, m) => format!("{h} h {m} min"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passive_words() {
+        assert!(has_passive_word("Let it rest."));
+        assert!(has_passive_word("BAKE until golden"));
+        assert!(!has_passive_word("Stir the restaurant-style sauce"));
+        assert!(!has_passive_word("Chop the onions"));
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!(format_minutes(0.0), "0 min");
+        assert_eq!(format_minutes(45.0), "45 min");
+        assert_eq!(format_minutes(60.0), "1 h");
+        assert_eq!(format_minutes(95.4), "1 h 35 min");
+    }
+}
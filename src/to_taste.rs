@@ -0,0 +1,70 @@
+//! Ingredients without a quantity or "to taste"
+//!
+//! `@salt{}` and `@salt{to taste}` mean the same: there's no amount to buy or
+//! add up. The [`ToTaste`] policy decides if they go to shopping lists and how
+//! the ingredient list shows them.
+
+use cooklang::{quantity::Value, Quantity, ScaledRecipe};
+use serde::{Deserialize, Serialize};
+
+/// Text quantities that mean there's no quantity, compared ignoring case
+const PHRASES: &[&str] = &["to taste", "as needed", "as required", "as desired"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToTaste {
+    /// List them in shopping lists, without a quantity
+    pub shopping_list: bool,
+    /// Shown instead of the missing quantity in ingredient lists
+    pub label: String,
+}
+
+impl Default for ToTaste {
+    fn default() -> Self {
+        Self {
+            shopping_list: true,
+            label: String::new(),
+        }
+    }
+}
+
+/// Checks if a quantity is missing or a "to taste" text
+pub fn is_to_taste(quantity: Option<&Quantity<Value>>) -> bool {
+    match quantity.map(|q| &q.value) {
+        None => true,
+        Some(Value::Text(text)) => {
+            let text = text.trim();
+            PHRASES.iter().any(|p| p.eq_ignore_ascii_case(text))
+        }
+        Some(_) => false,
+    }
+}
+
+/// Removes the "to taste" text quantities of the ingredients
+///
+/// Then they are like the ones without a quantity, so they don't break adding
+/// them up or the `text_quantities` policy.
+pub fn normalize(recipe: &mut ScaledRecipe) {
+    for igr in &mut recipe.ingredients {
+        if igr.quantity.is_some() && is_to_taste(igr.quantity.as_ref()) {
+            igr.quantity = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cooklang::quantity::Number;
+
+    #[test]
+    fn phrases() {
+        let text = |t: &str| Quantity::new(Value::Text(t.into()), None);
+        assert!(is_to_taste(None));
+        assert!(is_to_taste(Some(&text("To taste"))));
+        assert!(is_to_taste(Some(&text(" as needed "))));
+        assert!(!is_to_taste(Some(&text("a pinch"))));
+        let grams = Quantity::new(Value::Number(Number::Regular(5.0)), Some("g".into()));
+        assert!(!is_to_taste(Some(&grams)));
+    }
+}
@@ -0,0 +1,197 @@
+//! Units to convert to
+//!
+//! When converting to a system, or with `fit`, the converter picks the best
+//! unit by itself. [`UnitPreferences`] changes that pick for the units people
+//! actually cook with:
+//!
+//! ```toml
+//! [unit_preferences]
+//! avoid = ["dl", "stone"]
+//! prefer = [
+//!     { unit = "g", max = 1000 },
+//!     { unit = "ml", max = 1000 },
+//! ]
+//! ```
+//!
+//! The preferred units are checked in order and the first one where the value
+//! is between `min` and `max` is used. A quantity that is still in an avoided
+//! unit goes to another unit of the same system.
+
+use cooklang::{
+    convert::{ConvertTo, ConvertUnit, Unit},
+    quantity::Value,
+    Converter, Quantity, ScaledRecipe,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UnitPreferences {
+    /// Units that are never chosen
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub avoid: Vec<String>,
+    /// Units chosen first, in order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub prefer: Vec<PreferredUnit>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PreferredUnit {
+    pub unit: String,
+    /// Smallest value in this unit, included
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    /// Largest value in this unit, not included
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+}
+
+impl PreferredUnit {
+    fn fits(&self, value: f64) -> bool {
+        self.min.unwrap_or(f64::NEG_INFINITY) <= value && value < self.max.unwrap_or(f64::INFINITY)
+    }
+}
+
+impl UnitPreferences {
+    pub fn is_empty(&self) -> bool {
+        self.avoid.is_empty() && self.prefer.is_empty()
+    }
+
+    /// Changes a quantity to the preferred unit, if any fits
+    ///
+    /// Only the unit changes, the value is the same amount. Call it after
+    /// converting, it never changes the unit system.
+    pub fn apply(&self, qty: &mut Quantity<Value>, converter: &Converter) {
+        let Some(current) = qty.unit_text().and_then(|u| converter.find_unit(u)) else {
+            return;
+        };
+        let Some(value) = magnitude(&qty.value) else {
+            return;
+        };
+
+        for pref in &self.prefer {
+            let Some(unit) = converter.find_unit(&pref.unit) else {
+                tracing::warn!("Unknown preferred unit '{}'", pref.unit);
+                continue;
+            };
+            if !compatible(&current, &unit) || self.is_avoided(&unit, converter) {
+                continue;
+            }
+            if let Some(converted) = convert(qty, &pref.unit, converter) {
+                if magnitude(&converted.value).is_some_and(|v| pref.fits(v)) {
+                    *qty = converted;
+                    return;
+                }
+            }
+        }
+
+        if self.is_avoided(&current, converter) {
+            let Some(key) = self.replacement(&current, value, converter) else {
+                return;
+            };
+            if let Some(converted) = convert(qty, &key, converter) {
+                *qty = converted;
+            }
+        }
+    }
+
+    /// [`Self::apply`] to the ingredients and inline quantities of a recipe
+    pub fn apply_recipe(&self, recipe: &mut ScaledRecipe, converter: &Converter) {
+        if self.is_empty() {
+            return;
+        }
+        let ingredients = recipe
+            .ingredients
+            .iter_mut()
+            .filter_map(|igr| igr.quantity.as_mut());
+        for qty in ingredients.chain(recipe.inline_quantities.iter_mut()) {
+            self.apply(qty, converter);
+        }
+    }
+
+    fn is_avoided(&self, unit: &Unit, converter: &Converter) -> bool {
+        self.avoid
+            .iter()
+            .filter_map(|name| converter.find_unit(name))
+            .any(|avoided| std::ptr::eq(&*avoided, unit))
+    }
+
+    /// The largest unit where the value is at least 1, or else the smallest
+    fn replacement(&self, current: &Unit, value: f64, converter: &Converter) -> Option<String> {
+        let mut candidates = converter
+            .all_units()
+            .filter(|u| {
+                converter.is_best_unit(u)
+                    && compatible(current, u)
+                    && !self.is_avoided(u, converter)
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_by(|a, b| a.ratio.total_cmp(&b.ratio));
+        let unit = candidates
+            .iter()
+            .rev()
+            .find(|u| value * current.ratio / u.ratio >= 1.0)
+            .or(candidates.first())?;
+        unit.symbols
+            .first()
+            .or(unit.names.first())
+            .map(|key| key.to_string())
+    }
+}
+
+/// Same physical quantity and system, units without a system go with any
+fn compatible(a: &Unit, b: &Unit) -> bool {
+    a.physical_quantity == b.physical_quantity
+        && (a.system == b.system || a.system.is_none() || b.system.is_none())
+}
+
+fn convert(qty: &Quantity<Value>, unit: &str, converter: &Converter) -> Option<Quantity<Value>> {
+    let mut converted = qty.clone();
+    converted
+        .convert(ConvertTo::Unit(ConvertUnit::Key(unit)), converter)
+        .ok()?;
+    Some(converted)
+}
+
+/// The largest number of a value
+fn magnitude(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => Some(n.value()),
+        Value::Range { end, .. } => Some(end.value()),
+        Value::Text(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preferred_range() {
+        let pref = |min, max| PreferredUnit {
+            unit: "g".into(),
+            min,
+            max,
+        };
+        assert!(pref(None, None).fits(5000.0));
+        assert!(pref(None, Some(1000.0)).fits(999.5));
+        assert!(!pref(None, Some(1000.0)).fits(1000.0));
+        assert!(pref(Some(1.0), None).fits(1.0));
+        assert!(!pref(Some(1.0), Some(10.0)).fits(0.5));
+    }
+
+    #[test]
+    fn config() {
+        let prefs: UnitPreferences = toml::from_str(
+            r#"
+            avoid = ["dl"]
+            prefer = [{ unit = "g", max = 1000 }, { unit = "kg" }]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(prefs.avoid, ["dl"]);
+        assert_eq!(prefs.prefer[0].max, Some(1000.0));
+        assert_eq!(prefs.prefer[1].min, None);
+        assert!(UnitPreferences::default().is_empty());
+    }
+}
@@ -0,0 +1,71 @@
+//! Unit system used in a recipe
+//!
+//! Units that are not from a system, like `tbsp` or `pinch`, don't count.
+
+use cooklang::{convert::System, quantity::QuantityValue, Converter, Metadata, Recipe};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitSystem {
+    Metric,
+    Imperial,
+}
+
+impl From<UnitSystem> for System {
+    fn from(value: UnitSystem) -> Self {
+        match value {
+            UnitSystem::Metric => System::Metric,
+            UnitSystem::Imperial => System::Imperial,
+        }
+    }
+}
+
+/// How many ingredient quantities are in each system
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct UnitUsage {
+    /// The system with more quantities, `None` if there are no units or there
+    /// is the same amount of each
+    pub system: Option<UnitSystem>,
+    pub metric: u32,
+    pub imperial: u32,
+}
+
+impl UnitUsage {
+    pub fn new<D, V: QuantityValue>(recipe: &Recipe<D, V>, converter: &Converter) -> Self {
+        let mut usage = Self::default();
+        for q in recipe
+            .ingredients
+            .iter()
+            .filter_map(|i| i.quantity.as_ref())
+        {
+            let system = q
+                .unit_text()
+                .and_then(|u| converter.find_unit(u))
+                .and_then(|u| u.system);
+            match system {
+                Some(System::Metric) => usage.metric += 1,
+                Some(System::Imperial) => usage.imperial += 1,
+                None => {}
+            }
+        }
+        usage.system = match usage.metric.cmp(&usage.imperial) {
+            std::cmp::Ordering::Greater => Some(UnitSystem::Metric),
+            std::cmp::Ordering::Less => Some(UnitSystem::Imperial),
+            std::cmp::Ordering::Equal => None,
+        };
+        usage
+    }
+
+    /// Uses units of both systems
+    pub fn is_mixed(&self) -> bool {
+        self.metric > 0 && self.imperial > 0
+    }
+}
+
+/// Checks the `mixed_units` metadata key, to allow mixing systems on purpose
+pub fn mixed_in_metadata(meta: &Metadata) -> bool {
+    meta.map
+        .get("mixed_units")
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case("true"))
+}
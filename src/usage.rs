@@ -0,0 +1,162 @@
+//! Local log of how long the commands take
+//!
+//! Only written when `usage_log` is enabled in the config. It never leaves
+//! the collection, `chef debug slowest` reads it.
+
+use std::{
+    fs,
+    io::Write,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Result;
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::COOK_DIR;
+
+pub const USAGE_FILE: &str = "usage.jsonl";
+
+/// Commands that wait for the user, their time says nothing
+const WAITS_FOR_USER: &[&str] = &["serve", "edit", "new", "config setup"];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UsageEntry {
+    /// Command with its subcommands, without args
+    pub command: String,
+    pub duration_ms: f64,
+    pub ok: bool,
+    /// Unix time in seconds
+    pub timestamp: u64,
+}
+
+pub fn usage_file_path(base_path: &Utf8Path) -> Utf8PathBuf {
+    base_path.join(COOK_DIR).join(USAGE_FILE)
+}
+
+/// Name of the command that runs, like `debug parse`
+pub fn command_name(matches: &clap::ArgMatches) -> String {
+    let mut names = Vec::new();
+    let mut current = matches;
+    while let Some((name, sub)) = current.subcommand() {
+        names.push(name);
+        current = sub;
+    }
+    names.join(" ")
+}
+
+/// Adds an entry to the log
+///
+/// Errors are only traced, the log is not worth failing a command.
+pub fn record(path: &Utf8Path, command: &str, duration: Duration, ok: bool) {
+    if command.is_empty() || WAITS_FOR_USER.contains(&command) {
+        return;
+    }
+    let entry = UsageEntry {
+        command: command.to_string(),
+        duration_ms: duration.as_secs_f64() * 1e3,
+        ok,
+        timestamp: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+    };
+    let res = (|| -> Result<()> {
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    })();
+    if let Err(e) = res {
+        tracing::debug!("Can't write usage log: {e}");
+    }
+}
+
+/// Entries of the log, skipping the invalid lines
+pub fn read(path: &Utf8Path) -> Result<Vec<UsageEntry>> {
+    let content = fs::read_to_string(path)?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Timings of a command
+#[derive(Debug, Serialize)]
+pub struct CommandStats {
+    pub command: String,
+    pub runs: usize,
+    pub mean_ms: f64,
+    pub median_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Stats of every command, the slowest first by median
+pub fn slowest(entries: &[UsageEntry]) -> Vec<CommandStats> {
+    let mut by_command = std::collections::BTreeMap::<&str, Vec<f64>>::new();
+    for entry in entries.iter().filter(|e| e.ok) {
+        by_command
+            .entry(&entry.command)
+            .or_default()
+            .push(entry.duration_ms);
+    }
+    let mut stats = by_command
+        .into_iter()
+        .map(|(command, mut times)| {
+            times.sort_by(f64::total_cmp);
+            let runs = times.len();
+            let median = if runs % 2 == 0 {
+                (times[runs / 2 - 1] + times[runs / 2]) / 2.0
+            } else {
+                times[runs / 2]
+            };
+            CommandStats {
+                command: command.to_string(),
+                runs,
+                mean_ms: times.iter().sum::<f64>() / runs as f64,
+                median_ms: median,
+                max_ms: times[runs - 1],
+            }
+        })
+        .collect::<Vec<_>>();
+    stats.sort_by(|a, b| b.median_ms.total_cmp(&a.median_ms));
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(command: &str, duration_ms: f64, ok: bool) -> UsageEntry {
+        UsageEntry {
+            command: command.into(),
+            duration_ms,
+            ok,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn slowest_by_median() {
+        let entries = [
+            entry("list", 10.0, true),
+            entry("list", 30.0, true),
+            entry("list", 1000.0, false),
+            entry("shopping-list", 50.0, true),
+            entry("shopping-list", 20.0, true),
+            entry("shopping-list", 60.0, true),
+        ];
+        let stats = slowest(&entries);
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].command, "shopping-list");
+        assert_eq!(stats[0].median_ms, 50.0);
+        assert_eq!(stats[0].max_ms, 60.0);
+        assert_eq!(stats[1].command, "list");
+        assert_eq!(stats[1].runs, 2);
+        assert_eq!(stats[1].median_ms, 20.0);
+    }
+}
@@ -2,11 +2,11 @@ use std::borrow::Cow;
 
 use anyhow::{bail, Context as _, Result};
 
-use camino::Utf8Path;
+use camino::{Utf8Path, Utf8PathBuf};
 use cooklang::{analysis::CheckResult, Metadata};
-use cooklang_fs::{RecipeContent, RecipeEntry};
+use cooklang_fs::{LazyFsIndex, RecipeContent, RecipeEntry};
 
-use crate::Context;
+use crate::{archive::in_archive_dir, Context};
 
 /// Utility to create lazy regex
 /// from <https://docs.rs/once_cell/latest/once_cell/#lazily-compiled-regex>
@@ -49,8 +49,11 @@ pub enum Input {
 
 impl Input {
     pub fn parse(&self, ctx: &Context) -> Result<cooklang::ScalableRecipe> {
-        self.parse_result(ctx)
-            .and_then(|r| unwrap_recipe(r, self.file_name(), self.text()?.as_ref(), ctx))
+        let mut recipe = self
+            .parse_result(ctx)
+            .and_then(|r| unwrap_recipe(r, self.file_name(), self.text()?.as_ref(), ctx))?;
+        crate::compound::normalize(&mut recipe, ctx.parser()?.converter());
+        Ok(recipe)
     }
 
     pub fn parse_result(&self, ctx: &Context) -> Result<cooklang::RecipeResult> {
@@ -83,7 +86,7 @@ impl Input {
         }
     }
 
-    pub fn text(&self) -> Result<Cow<str>> {
+    pub fn text(&self) -> Result<Cow<'_, str>> {
         Ok(match self {
             Input::File { entry, .. } => entry.read()?.into_text().into(),
             Input::Stdin { text, .. } => text.as_str().into(),
@@ -120,6 +123,80 @@ pub fn unwrap_recipe(
     }
 }
 
+/// The given recipes, or all of the collection if none are given
+///
+/// `cmd` is only used for the error when not in a collection.
+pub fn recipe_entries(
+    ctx: &Context,
+    recipes: &[Utf8PathBuf],
+    include_archived: bool,
+    cmd: &str,
+) -> Result<Vec<RecipeEntry>> {
+    if recipes.is_empty() {
+        if !ctx.is_collection {
+            bail!("`{cmd}` needs to run inside a collection or be given the recipes");
+        }
+        let entries = cooklang_fs::all_recipes(
+            &ctx.base_path,
+            ctx.config.max_depth,
+            ctx.config.markdown,
+            ctx.config.follow_links,
+        )?
+        .filter(|e| include_archived || !in_archive_dir(&ctx.base_path, e.path()))
+        .collect();
+        return Ok(entries);
+    }
+    recipes
+        .iter()
+        .map(|r| {
+            if r.extension().is_some_and(|e| e == "cook") && r.is_file() {
+                Ok(RecipeEntry::new(r))
+            } else {
+                resolve_recipe(&ctx.recipe_index, r.as_str())
+            }
+        })
+        .collect()
+}
+
+/// Resolves a recipe, suggesting similar names if it's not found
+pub fn resolve_recipe(index: &LazyFsIndex, query: &str) -> Result<RecipeEntry> {
+    match index.resolve(query, None) {
+        Err(cooklang_fs::Error::NotFound(_)) => {
+            let similar = index
+                .search(query)
+                .unwrap_or_default()
+                .iter()
+                .take(3)
+                .map(|e| {
+                    let path = e.path().strip_prefix(index.base_path()).unwrap_or(e.path());
+                    format!("'{}'", path.with_extension(""))
+                })
+                .collect::<Vec<_>>();
+            if similar.is_empty() {
+                bail!("Recipe not found: '{query}'");
+            }
+            bail!(
+                "Recipe not found: '{query}'. Did you mean {}?",
+                similar.join(", ")
+            )
+        }
+        Err(cooklang_fs::Error::Ambiguous { candidates, .. }) => {
+            let candidates = candidates
+                .iter()
+                .map(|p| {
+                    let path = p.strip_prefix(index.base_path()).unwrap_or(p);
+                    format!("  - {}", path.with_extension(""))
+                })
+                .collect::<Vec<_>>();
+            bail!(
+                "Many recipes match '{query}', use one of:\n{}",
+                candidates.join("\n")
+            )
+        }
+        res => Ok(res?),
+    }
+}
+
 pub fn meta_name(meta: &cooklang::Metadata) -> Option<&str> {
     ["name", "title"]
         .iter()
@@ -230,6 +307,38 @@ pub fn is_valid_tag(tag: &str) -> bool {
     tag_len.contains(&tag.chars().count()) && re.is_match(tag)
 }
 
+/// Seconds since the unix epoch
+pub fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Width to wrap text in the terminal, up to 80
+#[cfg(feature = "terminal")]
+pub fn term_width() -> usize {
+    textwrap::termwidth().min(80)
+}
+
+/// Width to wrap text in the terminal, up to 80
+#[cfg(not(feature = "terminal"))]
+pub fn term_width() -> usize {
+    80
+}
+
+/// Short human description of a number of seconds in the past
+pub fn format_age(secs: u64) -> String {
+    let (n, unit) = match secs {
+        0..=59 => return "just now".to_string(),
+        60..=3599 => (secs / 60, "minute"),
+        3600..=86399 => (secs / 3600, "hour"),
+        _ => (secs / 86400, "day"),
+    };
+    let s = if n == 1 { "" } else { "s" };
+    format!("{n} {unit}{s} ago")
+}
+
 const IS_VALID_TAG_MSG: &str =
     "The tag should only have lower case letters and numbers separated by a single hyphen ('-')";
 
@@ -239,6 +348,14 @@ const TAG_TOO_LONG_MSG: &str = "The tag is too long";
 mod tests {
     use super::*;
 
+    #[test]
+    fn age() {
+        assert_eq!(format_age(5), "just now");
+        assert_eq!(format_age(60), "1 minute ago");
+        assert_eq!(format_age(2 * 3600 + 5), "2 hours ago");
+        assert_eq!(format_age(3 * 86400), "3 days ago");
+    }
+
     #[test]
     fn test_is_valid_tag() {
         assert!(is_valid_tag("uwu"));
@@ -0,0 +1,186 @@
+//! Step text that depends on the servings
+//!
+//! Like the by-servings quantities, `{one pan|two pans}` in the text of a
+//! recipe with `servings: 2|4` is `one pan` for 2 servings and `two pans`
+//! for 4. Other servings use the variant of the closest listed servings.
+
+use cooklang::{
+    model::{Content, Item},
+    Recipe, ScaledRecipe,
+};
+
+/// Part of a text with variants
+#[derive(Debug, PartialEq)]
+enum Segment<'a> {
+    Text(&'a str),
+    Variants(Vec<&'a str>),
+}
+
+/// Splits a text in the literal parts and the `{a|b}` variants
+///
+/// Braces without a `|` inside are literal text.
+fn segments(text: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        let inside = &rest[start + 1..start + len];
+        if !inside.contains('|') || inside.contains('{') {
+            segments.push(Segment::Text(&rest[..start + 1]));
+            rest = &rest[start + 1..];
+            continue;
+        }
+        if start > 0 {
+            segments.push(Segment::Text(&rest[..start]));
+        }
+        segments.push(Segment::Variants(
+            inside.split('|').map(str::trim).collect(),
+        ));
+        rest = &rest[start + len + 1..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest));
+    }
+    segments
+}
+
+/// The text with the variant at `index`, `None` if it has no variants
+fn resolve_text(text: &str, index: usize) -> Option<String> {
+    let segments = segments(text);
+    if !segments.iter().any(|s| matches!(s, Segment::Variants(_))) {
+        return None;
+    }
+    let mut out = String::with_capacity(text.len());
+    for segment in segments {
+        match segment {
+            Segment::Text(t) => out.push_str(t),
+            Segment::Variants(v) => out.push_str(v[index.min(v.len() - 1)]),
+        }
+    }
+    Some(out)
+}
+
+/// Index of the variant used for the servings the recipe is scaled to
+fn variant_index(recipe: &ScaledRecipe) -> usize {
+    let Some(servings) = recipe.metadata.servings() else {
+        return 0;
+    };
+    let Some(target) = recipe
+        .scaled_data()
+        .and_then(|data| data.target.target_servings())
+    else {
+        return 0;
+    };
+    servings
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, &s)| s.abs_diff(target))
+        .map(|(index, _)| index)
+        .unwrap_or(0)
+}
+
+/// Replaces the variants in the text of the recipe with the one for its
+/// servings
+pub fn resolve(recipe: &mut ScaledRecipe) {
+    let index = variant_index(recipe);
+    for section in &mut recipe.sections {
+        for content in &mut section.content {
+            match content {
+                Content::Step(step) => {
+                    for item in &mut step.items {
+                        if let Item::Text { value } = item {
+                            if let Some(resolved) = resolve_text(value, index) {
+                                *value = resolved;
+                            }
+                        }
+                    }
+                }
+                Content::Text(text) => {
+                    if let Some(resolved) = resolve_text(text, index) {
+                        *text = resolved;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Text variants that don't have one variant per servings
+#[derive(Debug, PartialEq)]
+pub struct Mismatch {
+    /// The variants, like `{one pan|two pans}`
+    pub text: String,
+    pub variants: usize,
+    /// Number of servings, 0 if the recipe has none
+    pub servings: usize,
+}
+
+/// Finds the variants whose count does not match the servings
+pub fn mismatches<D, V: cooklang::quantity::QuantityValue>(recipe: &Recipe<D, V>) -> Vec<Mismatch> {
+    let servings = recipe.metadata.servings().map_or(0, <[u32]>::len);
+    let mut found = Vec::new();
+    let mut check = |text: &str| {
+        for segment in segments(text) {
+            if let Segment::Variants(v) = segment {
+                if v.len() != servings {
+                    found.push(Mismatch {
+                        text: format!("{{{}}}", v.join("|")),
+                        variants: v.len(),
+                        servings,
+                    });
+                }
+            }
+        }
+    };
+    for section in &recipe.sections {
+        for content in &section.content {
+            match content {
+                Content::Step(step) => {
+                    for item in &step.items {
+                        if let Item::Text { value } = item {
+                            check(value);
+                        }
+                    }
+                }
+                Content::Text(text) => check(text),
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_segments() {
+        use Segment::*;
+        assert_eq!(
+            segments("use {one pan|two pans} for it"),
+            [
+                Text("use "),
+                Variants(vec!["one pan", "two pans"]),
+                Text(" for it")
+            ]
+        );
+        assert_eq!(
+            segments("no {variants} here"),
+            [Text("no {"), Text("variants} here")]
+        );
+        assert_eq!(segments("{a|b}"), [Variants(vec!["a", "b"])]);
+        assert_eq!(segments("open {a|b"), [Text("open {a|b")]);
+    }
+
+    #[test]
+    fn resolve_variants() {
+        let text = "use {one pan|two pans} and {a|b|c}";
+        assert_eq!(resolve_text(text, 0).unwrap(), "use one pan and a");
+        assert_eq!(resolve_text(text, 1).unwrap(), "use two pans and b");
+        // short lists use the last variant
+        assert_eq!(resolve_text(text, 2).unwrap(), "use two pans and c");
+        assert_eq!(resolve_text("plain {text}", 1), None);
+    }
+}
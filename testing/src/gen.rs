@@ -0,0 +1,321 @@
+//! Grammar based random recipe generator
+//!
+//! The output is (mostly) valid cooklang, so it goes past the lexer and
+//! exercises the parser and analysis. [`Gen::noisy_recipe`] adds random
+//! syntax characters to also reach the error paths.
+
+use std::fmt::Write;
+
+/// Small deterministic random number generator
+///
+/// When created with [`Rng::from_bytes`] it first returns the given bytes, so
+/// a fuzzer can guide the generator, and then continues pseudo-randomly.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    bytes: Vec<u8>,
+    pos: usize,
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            bytes: Vec::new(),
+            pos: 0,
+            state: seed,
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let seed = bytes.iter().fold(0xcbf29ce484222325, |h, &b| {
+            (h ^ b as u64).wrapping_mul(0x100000001b3)
+        });
+        Self {
+            bytes: bytes.to_vec(),
+            pos: 0,
+            state: seed,
+        }
+    }
+
+    // splitmix64
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Random number in `0..n`
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0);
+        if let Some(&b) = self.bytes.get(self.pos) {
+            self.pos += 1;
+            return b as usize % n;
+        }
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Random number in `range`
+    pub fn range(&mut self, range: std::ops::RangeInclusive<usize>) -> usize {
+        range.start() + self.below(range.end() - range.start() + 1)
+    }
+
+    /// `true` with a probability of `percent`%
+    pub fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+const WORDS: &[&str] = &[
+    "add", "mix", "the", "and", "until", "golden", "brown", "then", "slowly", "stir", "into", "a",
+    "with", "cook", "for", "minutes", "añade", "crème", "über", "½", "2", "10", "bake",
+];
+
+const INGREDIENTS: &[&str] = &[
+    "flour",
+    "water",
+    "salt",
+    "eggs",
+    "butter",
+    "sugar",
+    "milk",
+    "olive oil",
+    "black pepper",
+    "garlic",
+    "crème fraîche",
+    "cumin seeds",
+];
+
+const COOKWARE: &[&str] = &[
+    "pan",
+    "pot",
+    "bowl",
+    "oven",
+    "frying pan",
+    "baking tray",
+    "whisk",
+];
+
+const UNITS: &[&str] = &[
+    "g", "kg", "ml", "l", "cup", "cups", "tbsp", "tsp", "oz", "lb", "fl oz", "pinch", "°C", "°F",
+];
+
+const TIME_UNITS: &[&str] = &["s", "min", "minutes", "h", "hours"];
+
+const SYNTAX: &[char] = &[
+    '@', '#', '~', '{', '}', '%', '|', '(', ')', '=', '-', '&', '?', '*', '>', ':', '[', ']', '\n',
+    ' ', '/', '.',
+];
+
+/// Random recipe generator
+pub struct Gen {
+    rng: Rng,
+    ingredients: Vec<String>,
+    servings: Option<Vec<u32>>,
+    buf: String,
+}
+
+impl Gen {
+    pub fn new(rng: Rng) -> Self {
+        Self {
+            rng,
+            ingredients: Vec::new(),
+            servings: None,
+            buf: String::new(),
+        }
+    }
+
+    /// Generates a recipe
+    pub fn recipe(&mut self) -> String {
+        self.ingredients.clear();
+        self.servings = None;
+        self.buf.clear();
+
+        self.metadata();
+        for index in 0..self.rng.range(1..=3) {
+            self.section(index);
+        }
+        std::mem::take(&mut self.buf)
+    }
+
+    /// Generates a recipe and inserts random syntax characters in it
+    pub fn noisy_recipe(&mut self) -> String {
+        let mut recipe = self.recipe();
+        for _ in 0..self.rng.range(1..=8) {
+            let mut pos = self.rng.below(recipe.len() + 1);
+            while !recipe.is_char_boundary(pos) {
+                pos -= 1;
+            }
+            let c = *self.rng.pick(SYNTAX);
+            recipe.insert(pos, c);
+        }
+        recipe
+    }
+
+    fn metadata(&mut self) {
+        if self.rng.chance(50) {
+            let servings = (0..self.rng.range(1..=3))
+                .map(|i| (i as u32 + 1) * 2)
+                .collect::<Vec<_>>();
+            let list = servings
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join("|");
+            writeln!(self.buf, ">> servings: {list}").unwrap();
+            self.servings = Some(servings);
+        }
+        if self.rng.chance(50) {
+            self.buf.push_str(">> title: ");
+            self.words(1..=4);
+            self.buf.push('\n');
+        }
+        if self.rng.chance(30) {
+            writeln!(self.buf, ">> tags: dinner, quick").unwrap();
+        }
+        if self.rng.chance(20) {
+            writeln!(self.buf, ">> time: {} min", self.rng.range(1..=120)).unwrap();
+        }
+        self.buf.push('\n');
+    }
+
+    fn section(&mut self, index: usize) {
+        if index > 0 || self.rng.chance(20) {
+            if self.rng.chance(70) {
+                self.buf.push_str("== ");
+                self.words(1..=3);
+                self.buf.push_str(" ==\n\n");
+            } else {
+                self.buf.push_str("====\n\n");
+            }
+        }
+        for _ in 0..self.rng.range(1..=4) {
+            if self.rng.chance(15) {
+                self.buf.push_str("> ");
+                self.words(1..=10);
+            } else {
+                self.step();
+            }
+            self.buf.push_str("\n\n");
+        }
+    }
+
+    fn step(&mut self) {
+        for i in 0..self.rng.range(1..=8) {
+            if i > 0 {
+                self.buf.push(' ');
+            }
+            match self.rng.below(10) {
+                0..=2 => self.ingredient(),
+                3 => self.cookware(),
+                4 => self.timer(),
+                5 => self.buf.push('\n'),
+                _ => self.words(1..=5),
+            }
+        }
+    }
+
+    fn words(&mut self, count: std::ops::RangeInclusive<usize>) {
+        for i in 0..self.rng.range(count) {
+            if i > 0 {
+                self.buf.push(' ');
+            }
+            let w = *self.rng.pick(WORDS);
+            self.buf.push_str(w);
+        }
+    }
+
+    fn ingredient(&mut self) {
+        self.buf.push('@');
+        let reference = !self.ingredients.is_empty() && self.rng.chance(20);
+        let name = if reference {
+            self.buf.push('&');
+            self.rng.pick(&self.ingredients).clone()
+        } else {
+            match self.rng.below(10) {
+                0 => self.buf.push('?'),
+                1 => self.buf.push('-'),
+                _ => {}
+            }
+            let name = self.rng.pick(INGREDIENTS).to_string();
+            self.ingredients.push(name.clone());
+            name
+        };
+        self.buf.push_str(&name);
+        let multi_word = name.contains(' ');
+        if self.rng.chance(10) {
+            self.buf.push_str("|alias");
+            self.buf.push_str("{}");
+        } else if self.rng.chance(70) {
+            self.quantity();
+        } else if multi_word {
+            self.buf.push_str("{}");
+        }
+        if self.rng.chance(15) {
+            self.buf.push_str("(chopped)");
+        }
+    }
+
+    fn cookware(&mut self) {
+        self.buf.push('#');
+        let name = *self.rng.pick(COOKWARE);
+        self.buf.push_str(name);
+        if self.rng.chance(20) {
+            write!(self.buf, "{{{}}}", self.rng.range(1..=3)).unwrap();
+        } else if name.contains(' ') {
+            self.buf.push_str("{}");
+        }
+    }
+
+    fn timer(&mut self) {
+        self.buf.push('~');
+        if self.rng.chance(40) {
+            self.buf.push_str("rest");
+        }
+        let unit = *self.rng.pick(TIME_UNITS);
+        write!(self.buf, "{{{}%{unit}}}", self.rng.range(1..=90)).unwrap();
+    }
+
+    fn quantity(&mut self) {
+        self.buf.push('{');
+        match self.rng.below(10) {
+            0 => {
+                self.buf.push('}');
+                return;
+            }
+            1 => {
+                let n = self.servings.as_ref().map_or(2, |s| s.len());
+                let values = (0..n)
+                    .map(|i| ((i + 1) * 100).to_string())
+                    .collect::<Vec<_>>();
+                self.buf.push_str(&values.join("|"));
+            }
+            2 => write!(
+                self.buf,
+                "{}-{}",
+                self.rng.range(1..=3),
+                self.rng.range(4..=6)
+            )
+            .unwrap(),
+            3 => write!(self.buf, "1/{}", self.rng.range(2..=4)).unwrap(),
+            4 => self.buf.push_str("some"),
+            _ => {
+                write!(self.buf, "{}", self.rng.range(1..=500)).unwrap();
+                if self.rng.chance(50) {
+                    self.buf.push('*');
+                }
+            }
+        }
+        if self.rng.chance(80) {
+            self.buf.push('%');
+            let unit = *self.rng.pick(UNITS);
+            self.buf.push_str(unit);
+        }
+        self.buf.push('}');
+    }
+}
@@ -0,0 +1,13 @@
+//! Testing utilities for the chef crates.
+//!
+//! - [`gen`] generates random recipes following the cooklang grammar.
+//! - [`props`] has properties every recipe should hold. They are checked by
+//!   the tests of this crate and by the fuzz targets in `/fuzz`.
+//! - [`snapshot`] renders a suite of recipes and compares the output with
+//!   golden files, for the built-in formats or custom templates.
+
+pub mod gen;
+pub mod props;
+pub mod snapshot;
+
+pub use gen::{Gen, Rng};
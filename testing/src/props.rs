@@ -0,0 +1,143 @@
+//! Properties of parsed recipes
+//!
+//! Every check takes the input text, so they can be run with generated
+//! recipes or arbitrary input. Inputs that don't parse are accepted, only
+//! panics and broken properties of valid recipes are failures.
+
+use cooklang::{
+    convert::{ConvertTo, ConvertUnit, PhysicalQuantity, System},
+    quantity::{ScalableValue, Value},
+    CooklangParser, ScalableRecipe,
+};
+
+fn parse(parser: &CooklangParser, input: &str) -> Option<ScalableRecipe> {
+    let (recipe, _warnings) = parser.parse(input).into_result().ok()?;
+    Some(recipe)
+}
+
+fn format(recipe: &ScalableRecipe) -> String {
+    let mut buf = Vec::new();
+    cooklang_to_cooklang::print_cooklang(recipe, &mut buf).expect("write to vec");
+    String::from_utf8(buf).expect("formatter output is not UTF-8")
+}
+
+/// Parsing never panics
+pub fn parse_no_panic(parser: &CooklangParser, input: &str) {
+    let _ = parser.parse(input);
+}
+
+/// Formatting a recipe back to cooklang keeps its content, and formatting it
+/// again gives the same text, ignoring whitespace
+pub fn roundtrip_fmt(parser: &CooklangParser, input: &str) -> Result<(), String> {
+    let Some(recipe) = parse(parser, input) else {
+        return Ok(());
+    };
+    let formatted = format(&recipe);
+    let Some(reparsed) = parse(parser, &formatted) else {
+        return Err(format!("formatted recipe does not parse:\n{formatted}"));
+    };
+
+    let names = |r: &ScalableRecipe| {
+        (
+            r.ingredients
+                .iter()
+                .map(|i| i.name.clone())
+                .collect::<Vec<_>>(),
+            r.cookware
+                .iter()
+                .map(|c| c.name.clone())
+                .collect::<Vec<_>>(),
+            r.timers.len(),
+            r.sections.len(),
+        )
+    };
+    if names(&recipe) != names(&reparsed) {
+        return Err(format!(
+            "components changed:\n{:?}\n{:?}\nformatted:\n{formatted}",
+            names(&recipe),
+            names(&reparsed)
+        ));
+    }
+
+    // steps are wrapped, and the parser joins the lines with a single space,
+    // so the whitespace may change
+    let again = format(&reparsed);
+    let words = |s: &str| s.split_whitespace().collect::<Vec<_>>().join(" ");
+    if words(&formatted) != words(&again) {
+        return Err(format!("format is not stable:\n{formatted}\n---\n{again}"));
+    }
+    Ok(())
+}
+
+/// Scaling and then converting gives the same quantities as converting and
+/// then scaling
+///
+/// Only linear numeric quantities are checked.
+pub fn scale_convert_commute(
+    parser: &CooklangParser,
+    input: &str,
+    target: u32,
+    system: System,
+) -> Result<(), String> {
+    let Some(recipe) = parse(parser, input) else {
+        return Ok(());
+    };
+    let converter = parser.converter();
+    let base = recipe
+        .metadata
+        .servings()
+        .and_then(|s| s.first().copied())
+        .unwrap_or(1);
+    let factor = target as f64 / base as f64;
+
+    let linear = recipe
+        .ingredients
+        .iter()
+        .map(|i| {
+            matches!(
+                i.quantity.as_ref().map(|q| &q.value),
+                Some(ScalableValue::Linear(Value::Number(_)))
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut scaled_first = recipe.clone().scale(target, converter);
+    scaled_first.convert(system, converter);
+    let mut converted_first = recipe.default_scale();
+    converted_first.convert(system, converter);
+
+    for (index, linear) in linear.into_iter().enumerate() {
+        if !linear {
+            continue;
+        }
+        let a = scaled_first.ingredients[index].quantity.as_ref().unwrap();
+        // temperatures are not proportional to 0, so they don't commute
+        let is_temperature = a
+            .unit_text()
+            .and_then(|u| converter.find_unit(u))
+            .is_some_and(|u| u.physical_quantity == PhysicalQuantity::Temperature);
+        if is_temperature {
+            continue;
+        }
+        let mut b = converted_first.ingredients[index].quantity.clone().unwrap();
+        if let Some(unit) = a.unit_text() {
+            if b.convert(ConvertTo::Unit(ConvertUnit::Key(unit)), converter)
+                .is_err()
+            {
+                continue;
+            }
+        }
+        let (Value::Number(a_n), Value::Number(b_n)) = (&a.value, &b.value) else {
+            continue;
+        };
+        let expected = b_n.value() * factor;
+        let got = a_n.value();
+        if (got - expected).abs() > expected.abs() * 1e-3 {
+            return Err(format!(
+                "ingredient {index}: scaled then converted {got} {unit:?}, converted then scaled {expected}",
+                unit = a.unit_text()
+            ));
+        }
+    }
+    Ok(())
+}
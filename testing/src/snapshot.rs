@@ -0,0 +1,410 @@
+//! Golden file tests for the recipe renderers
+//!
+//! A [`Harness`] renders a suite of recipes with a [`Renderer`] and compares
+//! the output with the files stored in a snapshot directory. When the output
+//! changes the diff is shown, so format changes can be reviewed like any
+//! other change.
+//!
+//! Set the `UPDATE_SNAPSHOTS` env var to write the new output instead of
+//! failing. A missing snapshot is a failure too, so new recipes or renderers
+//! need it to create their snapshots.
+//!
+//! The built-in renderers are [`Markdown`], [`Human`] and [`Cooklang`].
+//! HTML and LaTeX are not covered, the web UI HTML comes from the `chef`
+//! templates and there is no LaTeX renderer. Other formats can be checked
+//! implementing [`Renderer`] or with [`FnRenderer`]:
+//!
+//! ```no_run
+//! use cooklang_testing::snapshot::{FnRenderer, Harness};
+//!
+//! let summary = FnRenderer::new("summary", "txt", |recipe, name, _converter| {
+//!     Ok(format!("{name}: {} ingredients\n", recipe.ingredients.len()))
+//! });
+//! Harness::new("tests/snapshots").check(&summary).unwrap();
+//! ```
+
+use std::{
+    fmt::{self, Write as _},
+    path::{Path, PathBuf},
+};
+
+use cooklang::{convert::Converter, CooklangParser, ScaledRecipe};
+
+/// Width used to wrap the text in every renderer
+pub const WRAP_WIDTH: usize = 80;
+
+/// Recipes used by default in a [`Harness`]
+///
+/// `(name, content)`. They try to cover every element of the language at
+/// least once.
+pub const RECIPES: &[(&str, &str)] = &[
+    ("Basic", include_str!("../recipes/Basic.cook")),
+    ("Sections", include_str!("../recipes/Sections.cook")),
+    ("Servings", include_str!("../recipes/Servings.cook")),
+    ("References", include_str!("../recipes/References.cook")),
+    ("Timers", include_str!("../recipes/Timers.cook")),
+    (
+        "Text Quantities",
+        include_str!("../recipes/Text Quantities.cook"),
+    ),
+];
+
+/// Output format to snapshot
+pub trait Renderer {
+    /// Name of the renderer, used as the snapshot subdirectory
+    fn name(&self) -> &str;
+    /// Extension of the snapshot files
+    fn extension(&self) -> &str;
+    /// Renders the recipe
+    fn render(
+        &self,
+        recipe: &ScaledRecipe,
+        name: &str,
+        converter: &Converter,
+    ) -> Result<String, String>;
+}
+
+/// Markdown with the default options
+pub struct Markdown;
+
+impl Renderer for Markdown {
+    fn name(&self) -> &str {
+        "markdown"
+    }
+
+    fn extension(&self) -> &str {
+        "md"
+    }
+
+    fn render(
+        &self,
+        recipe: &ScaledRecipe,
+        name: &str,
+        converter: &Converter,
+    ) -> Result<String, String> {
+        let mut buf = Vec::new();
+        cooklang_to_md::print_md(recipe, name, converter, &mut buf).map_err(|e| e.to_string())?;
+        String::from_utf8(buf).map_err(|e| e.to_string())
+    }
+}
+
+/// Terminal output, without the colours
+pub struct Human;
+
+impl Renderer for Human {
+    fn name(&self) -> &str {
+        "human"
+    }
+
+    fn extension(&self) -> &str {
+        "txt"
+    }
+
+    fn render(
+        &self,
+        recipe: &ScaledRecipe,
+        name: &str,
+        converter: &Converter,
+    ) -> Result<String, String> {
+        let mut buf = Vec::new();
+        cooklang_to_human::print_human(recipe, name, converter, &mut buf)
+            .map_err(|e| e.to_string())?;
+        let text = String::from_utf8(buf).map_err(|e| e.to_string())?;
+        Ok(strip_ansi(&text))
+    }
+}
+
+/// Cooklang, from the formatter
+pub struct Cooklang;
+
+impl Renderer for Cooklang {
+    fn name(&self) -> &str {
+        "cooklang"
+    }
+
+    fn extension(&self) -> &str {
+        "cook"
+    }
+
+    fn render(
+        &self,
+        recipe: &ScaledRecipe,
+        _name: &str,
+        _converter: &Converter,
+    ) -> Result<String, String> {
+        let mut buf = Vec::new();
+        cooklang_to_cooklang::print_cooklang(recipe, &mut buf).map_err(|e| e.to_string())?;
+        String::from_utf8(buf).map_err(|e| e.to_string())
+    }
+}
+
+type RenderFn = dyn Fn(&ScaledRecipe, &str, &Converter) -> Result<String, String>;
+
+/// [`Renderer`] from a closure
+pub struct FnRenderer {
+    name: String,
+    extension: String,
+    f: Box<RenderFn>,
+}
+
+impl FnRenderer {
+    pub fn new(
+        name: impl Into<String>,
+        extension: impl Into<String>,
+        f: impl Fn(&ScaledRecipe, &str, &Converter) -> Result<String, String> + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            extension: extension.into(),
+            f: Box::new(f),
+        }
+    }
+}
+
+impl Renderer for FnRenderer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn extension(&self) -> &str {
+        &self.extension
+    }
+
+    fn render(
+        &self,
+        recipe: &ScaledRecipe,
+        name: &str,
+        converter: &Converter,
+    ) -> Result<String, String> {
+        (self.f)(recipe, name, converter)
+    }
+}
+
+/// Renders recipes and compares them with the stored snapshots
+pub struct Harness {
+    dir: PathBuf,
+    recipes: Vec<(String, String)>,
+    scales: Vec<u32>,
+    update: bool,
+}
+
+/// Snapshots that didn't match
+#[derive(Debug, Default)]
+pub struct Failures(pub Vec<Failure>);
+
+#[derive(Debug)]
+pub struct Failure {
+    pub path: PathBuf,
+    pub kind: FailureKind,
+}
+
+#[derive(Debug)]
+pub enum FailureKind {
+    /// The recipe does not parse or the renderer failed
+    Render(String),
+    /// There is no snapshot stored
+    Missing,
+    /// The output is different, with a line diff
+    Changed(String),
+    Io(String),
+}
+
+impl Harness {
+    /// New harness that stores the snapshots in `dir`
+    ///
+    /// It renders [`RECIPES`] at their default scale. The renderers wrap the
+    /// text at [`WRAP_WIDTH`].
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        cooklang_to_md::set_wrap_width(WRAP_WIDTH);
+        cooklang_to_human::set_wrap_width(WRAP_WIDTH);
+        Self {
+            dir: dir.into(),
+            recipes: RECIPES
+                .iter()
+                .map(|(n, c)| (n.to_string(), c.to_string()))
+                .collect(),
+            scales: Vec::new(),
+            update: std::env::var_os("UPDATE_SNAPSHOTS").is_some(),
+        }
+    }
+
+    /// Adds a recipe to the suite
+    pub fn recipe(mut self, name: impl Into<String>, content: impl Into<String>) -> Self {
+        self.recipes.push((name.into(), content.into()));
+        self
+    }
+
+    /// Adds every `.cook` file in `dir` to the suite
+    pub fn recipes_dir(mut self, dir: impl AsRef<Path>) -> std::io::Result<Self> {
+        let mut entries = std::fs::read_dir(dir)?
+            .map(|e| e.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort();
+        for path in entries {
+            if path.extension().is_some_and(|e| e == "cook") {
+                let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+                let content = std::fs::read_to_string(&path)?;
+                self.recipes.push((name, content));
+            }
+        }
+        Ok(self)
+    }
+
+    /// Also render every recipe scaled to `servings`
+    pub fn scale(mut self, servings: u32) -> Self {
+        self.scales.push(servings);
+        self
+    }
+
+    /// Overwrite the snapshots with the new output instead of failing
+    pub fn update(mut self, update: bool) -> Self {
+        self.update = update;
+        self
+    }
+
+    /// Renders every recipe and compares it with its snapshot
+    pub fn check(&self, renderer: &dyn Renderer) -> Result<(), Failures> {
+        let parser = CooklangParser::extended();
+        let dir = self.dir.join(renderer.name());
+        let mut failures = Vec::new();
+
+        for (name, content) in &self.recipes {
+            let scales = std::iter::once(None).chain(self.scales.iter().copied().map(Some));
+            for scale in scales {
+                let file_name = match scale {
+                    Some(s) => format!("{name}@{s}.{}", renderer.extension()),
+                    None => format!("{name}.{}", renderer.extension()),
+                };
+                let path = dir.join(file_name);
+                let kind = match render(&parser, renderer, name, content, scale) {
+                    Ok(output) => self.compare(&path, &output),
+                    Err(err) => Some(FailureKind::Render(err)),
+                };
+                if let Some(kind) = kind {
+                    failures.push(Failure { path, kind });
+                }
+            }
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(Failures(failures))
+        }
+    }
+
+    fn compare(&self, path: &Path, output: &str) -> Option<FailureKind> {
+        let stored = match std::fs::read_to_string(path) {
+            Ok(s) => Some(s),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Some(FailureKind::Io(e.to_string())),
+        };
+        if stored.as_deref() == Some(output) {
+            return None;
+        }
+        if self.update {
+            let res = path
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|_| std::fs::write(path, output));
+            return res.err().map(|e| FailureKind::Io(e.to_string()));
+        }
+        Some(match stored {
+            Some(stored) => FailureKind::Changed(diff(&stored, output)),
+            None => FailureKind::Missing,
+        })
+    }
+}
+
+fn render(
+    parser: &CooklangParser,
+    renderer: &dyn Renderer,
+    name: &str,
+    content: &str,
+    scale: Option<u32>,
+) -> Result<String, String> {
+    let (recipe, _warnings) = parser.parse(content).into_result().map_err(|report| {
+        let mut buf = Vec::new();
+        let _ = report.write(name, content, false, &mut buf);
+        String::from_utf8_lossy(&buf).into_owned()
+    })?;
+    let converter = parser.converter();
+    let recipe = match scale {
+        Some(target) => recipe.scale(target, converter),
+        None => recipe.default_scale(),
+    };
+    renderer.render(&recipe, name, converter)
+}
+
+/// Very simple line diff, enough to spot what changed in a snapshot
+fn diff(old: &str, new: &str) -> String {
+    let old = old.lines().collect::<Vec<_>>();
+    let new = new.lines().collect::<Vec<_>>();
+    // longest common subsequence
+    let mut lcs = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            writeln!(out, "  {}", old[i]).unwrap();
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            writeln!(out, "+ {}", new[j]).unwrap();
+            j += 1;
+        } else {
+            writeln!(out, "- {}", old[i]).unwrap();
+            i += 1;
+        }
+    }
+    out
+}
+
+/// Removes ANSI escape sequences
+pub fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+impl fmt::Display for Failures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for failure in &self.0 {
+            let path = failure.path.display();
+            match &failure.kind {
+                FailureKind::Render(err) => writeln!(f, "{path}: render failed: {err}")?,
+                FailureKind::Missing => writeln!(
+                    f,
+                    "{path}: missing snapshot, create it with UPDATE_SNAPSHOTS=1"
+                )?,
+                FailureKind::Io(err) => writeln!(f, "{path}: {err}")?,
+                FailureKind::Changed(diff) => writeln!(f, "{path}: changed\n{diff}")?,
+            }
+        }
+        write!(f, "set UPDATE_SNAPSHOTS=1 to accept the new output")
+    }
+}
+
+impl std::error::Error for Failures {}
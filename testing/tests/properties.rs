@@ -0,0 +1,37 @@
+use cooklang::{convert::System, CooklangParser};
+use cooklang_testing::{props, Gen, Rng};
+
+const CASES: u64 = 500;
+
+fn check(f: impl Fn(&CooklangParser, &str) -> Result<(), String>) {
+    let parser = CooklangParser::extended();
+    for seed in 0..CASES {
+        let input = Gen::new(Rng::new(seed)).recipe();
+        if let Err(err) = f(&parser, &input) {
+            panic!("seed {seed} failed: {err}\ninput:\n{input}");
+        }
+    }
+}
+
+#[test]
+fn noisy_input_does_not_panic() {
+    let parser = CooklangParser::extended();
+    for seed in 0..CASES {
+        let input = Gen::new(Rng::new(seed)).noisy_recipe();
+        props::parse_no_panic(&parser, &input);
+    }
+}
+
+#[test]
+fn roundtrip_fmt() {
+    check(props::roundtrip_fmt);
+}
+
+#[test]
+fn scale_convert_commute() {
+    for system in [System::Metric, System::Imperial] {
+        for target in [1, 3, 4] {
+            check(|p, i| props::scale_convert_commute(p, i, target, system));
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use cooklang_testing::snapshot::{Cooklang, Harness, Human, Markdown, Renderer};
+
+fn check(renderer: &dyn Renderer) {
+    let harness = Harness::new(concat!(env!("CARGO_MANIFEST_DIR"), "/snapshots")).scale(6);
+    if let Err(failures) = harness.check(renderer) {
+        panic!("{failures}");
+    }
+}
+
+#[test]
+fn markdown() {
+    check(&Markdown);
+}
+
+#[test]
+fn human() {
+    check(&Human);
+}
+
+#[test]
+fn cooklang() {
+    check(&Cooklang);
+}
@@ -45,15 +45,21 @@ function triggerHotReload() {
 let hrEventSource = null;
 function hrConnect() {
   if (hrEventSource !== null) hrEventSource.close();
-  hrEventSource = new EventSource("/updates");
+  hrEventSource = new EventSource(basePath + "/updates");
 
-  function isIndex() {
+  // path without the base path
+  function appPath() {
     const path = window.location.pathname;
+    return path.startsWith(basePath) ? path.slice(basePath.length) || "/" : path;
+  }
+
+  function isIndex() {
+    const path = appPath();
     return path == "/" || path.startsWith("/d/") || path.startsWith("/search");
   }
 
   function isCurrentRecipe(triggered) {
-    let path = window.location.pathname;
+    let path = appPath();
     if (!path.startsWith("/r/")) {
       return false;
     }
@@ -70,7 +76,7 @@ function hrConnect() {
     if (isIndex()) {
       triggerHotReload();
     } else if (isCurrentRecipe(ev.data)) {
-      window.location = "/?deleted=" + ev.data.replace(/\.cook$/, "");
+      window.location = basePath + "/?deleted=" + ev.data.replace(/\.cook$/, "");
     }
   });
   hrEventSource.addEventListener("added", () => {
@@ -85,7 +91,7 @@ function hrConnect() {
     let data = JSON.parse(ev.data);
     if (isCurrentRecipe(data.from)) {
       console.log("renamed current recipe");
-      let url = "/r/" + data.to.replace(/\.cook$/, "") + window.location.search;
+      let url = basePath + "/r/" + data.to.replace(/\.cook$/, "") + window.location.search;
       window.location.replace(url);
     }
   });